@@ -0,0 +1,24 @@
+//! Records the target, profile and compiler for `BuildInfo::runtime`.
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    let vars = [("GAMEPACK_BUILD_TARGET", "TARGET"), ("GAMEPACK_BUILD_PROFILE", "PROFILE")];
+    for (name, var) in vars {
+        let value = env::var(var).unwrap_or_else(|_| "unknown".to_string());
+        println!("cargo:rustc-env={}={}", name, value);
+    }
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|version| version.trim().to_string())
+        .filter(|version| !version.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GAMEPACK_BUILD_RUSTC={}", version);
+}
@@ -278,7 +278,7 @@ interface GameDefinition {
 interface GameEvent {
     /** Event type key (e.g., "ChampionKill", "TurretDestroyed") */
     eventKey: string;
-    /** When the event occurred (game time in seconds) */
+    /** When the event occurred (game time in seconds; negative before match start) */
     eventTime: number;
     /** Additional event data (game-specific) */
     eventData?: Record<string, unknown>;
@@ -286,6 +286,24 @@ interface GameEvent {
     preSecs?: number;
     /** Seconds to capture after the event */
     postSecs?: number;
+    /** Events sharing a clip group are merged into one clip */
+    clipGroup?: string;
+    /** Authoritative server time in seconds (eventTime drives clip timing) */
+    serverTimeSecs?: number;
+    /** Detection confidence from 0 to 1; events without one are certain */
+    confidence?: number;
+}
+/**
+ * In-game identity of the tracked player. Ids are opaque tokens; compare
+ * them for equality only.
+ */
+interface PlayerIdentity {
+    /** Name shown in game (e.g., summoner name or Riot ID) */
+    displayName?: string;
+    /** Stable account id issued by the game (e.g., PUUID) */
+    gamePlayerId?: string;
+    /** Game server region or shard (e.g., "euw1") */
+    region?: string;
 }
 /**
  * Current game connection/status.
@@ -297,6 +315,10 @@ interface GameStatus {
     phase: string;
     /** Whether actively in a game */
     isInGame: boolean;
+    /** Version or patch of the running game (game-defined format, e.g., "14.3") */
+    gameVersion?: string;
+    /** Player signed in to the game client, once known */
+    player?: PlayerIdentity;
 }
 /**
  * Match data returned when a game session ends.
@@ -308,8 +330,12 @@ interface MatchData {
     gameSlug: string;
     /** Game ID (for backwards compatibility) */
     gameId: number;
-    /** Match result */
-    result: "win" | "loss" | "remake" | "unknown";
+    /** Normalized match result (for filtering) */
+    result: "win" | "loss" | "draw" | "remake" | "unknown";
+    /** Raw game-specific outcome for display (e.g., "surrender_at_15") */
+    outcomeDetail?: string;
+    /** Player the match was played as */
+    player?: PlayerIdentity;
     /** Match duration in seconds */
     duration: number;
     /** Game-specific match details */
@@ -448,4 +474,4 @@ declare function getSandboxedPackContext(): PackContext;
  */
 declare function useSandboxedCache(): PackCacheAPI;
 
-export { type BaseMatch, type BridgeState, type CoreMatchData, type GameDefinition, type GameEvent, type GamePack, type GamePackResources, type GamePackUtilities, type GameStatus, type HostInitMessage, type HostRenderMessage, type HostResponseMessage, type HostToPackMessage, type LiveMatchCardProps, type MatchCardProps, type MatchData, type MatchWithDetails, type PackCacheAPI, type PackCacheClearMessage, type PackCacheExistsMessage, type PackCacheGetSizeMessage, type PackCacheReadMessage, type PackCacheWriteMessage, type PackContext, PackContextReact, type PackReadyMessage, type PackToHostMessage, type PendingRequest, type ResourceState, type RuntimeGamePack, getSandboxedCacheAPI, getSandboxedPackContext, initSandboxRuntime, usePackCache, usePackContext, useSandboxedCache };
+export { type BaseMatch, type BridgeState, type CoreMatchData, type GameDefinition, type GameEvent, type GamePack, type GamePackResources, type GamePackUtilities, type GameStatus, type HostInitMessage, type HostRenderMessage, type HostResponseMessage, type HostToPackMessage, type LiveMatchCardProps, type MatchCardProps, type MatchData, type MatchWithDetails, type PackCacheAPI, type PackCacheClearMessage, type PackCacheExistsMessage, type PackCacheGetSizeMessage, type PackCacheReadMessage, type PackCacheWriteMessage, type PackContext, PackContextReact, type PackReadyMessage, type PackToHostMessage, type PendingRequest, type PlayerIdentity, type ResourceState, type RuntimeGamePack, getSandboxedCacheAPI, getSandboxedPackContext, initSandboxRuntime, usePackCache, usePackContext, useSandboxedCache };
@@ -0,0 +1,320 @@
+//! Non-blocking emission through a bounded queue.
+//!
+//! Writing to stdout blocks when the daemon reads slowly. A poll loop that
+//! emits through a plain [`StdoutSink`](crate::StdoutSink) then stalls with
+//! it. [`ChannelEmitter`] puts a bounded queue and a writer thread between
+//! the [`Emitter`](crate::Emitter) and the real sink, and reports how close
+//! that queue gets to full.
+
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use crate::emitter::EmitSink;
+use crate::responses::GamepackResponse;
+use crate::types::MatchDataMessage;
+
+type HighWaterCallback = Box<dyn FnOnce(usize) + Send>;
+
+/// Sink that hands messages to a writer thread through a bounded queue.
+///
+/// Which messages may be dropped when the queue is full:
+///
+/// - `WriteStatistics` and `Heartbeat` are sent without blocking and
+///   dropped when the queue is full. The emit returns an
+///   [`io::ErrorKind::WouldBlock`] error and the drop is counted in
+///   [`dropped_total`](Self::dropped_total). The next heartbeat supersedes a
+///   dropped one, but statistics are partial (see
+///   [`MatchDataMessage::WriteStatistics`]): a key only the dropped write
+///   carried stays stale until a later write repeats it. Resend on the
+///   error when that matters.
+/// - Everything else, in particular `SetComplete`, events and moments, uses
+///   a blocking send and waits for room. Losing those would lose data.
+///   `LiveDataPatch` waits too: each patch applies on top of the previous
+///   one, so dropping one would corrupt every later one.
+///
+/// Wrap it in an [`Emitter`](crate::Emitter); clones share the same queue:
+///
+/// ```rust
+/// use gamepack_runtime::{ChannelEmitter, Emitter, StdoutSink};
+///
+/// let channel = ChannelEmitter::new(StdoutSink, 256)
+///     .on_high_water(192, |depth| eprintln!("emit queue at {}", depth));
+/// let emitter = Emitter::new(channel.clone());
+/// ```
+#[derive(Clone)]
+pub struct ChannelEmitter {
+    sender: SyncSender<(GamepackResponse, String)>,
+    shared: Arc<Shared>,
+}
+
+/// State shared between the emitting side and the writer thread.
+struct Shared {
+    /// Messages accepted but not yet written (queued or being written)
+    depth: Mutex<usize>,
+    /// Signalled whenever the writer finishes a message
+    drained: Condvar,
+    high_water_mark: AtomicU64,
+    dropped_total: AtomicU64,
+    /// Threshold and callback; taken when it fires
+    alarm: Mutex<Option<(usize, HighWaterCallback)>>,
+}
+
+impl std::fmt::Debug for ChannelEmitter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChannelEmitter")
+            .field("queued", &self.queued())
+            .field("high_water_mark", &self.high_water_mark())
+            .field("dropped_total", &self.dropped_total())
+            .finish()
+    }
+}
+
+impl ChannelEmitter {
+    /// Start a writer thread draining a queue of `capacity` messages into
+    /// `inner`.
+    ///
+    /// The thread exits once every clone of the returned value is dropped.
+    pub fn new(inner: impl EmitSink + 'static, capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<(GamepackResponse, String)>(capacity);
+        let shared = Arc::new(Shared {
+            depth: Mutex::new(0),
+            drained: Condvar::new(),
+            high_water_mark: AtomicU64::new(0),
+            dropped_total: AtomicU64::new(0),
+            alarm: Mutex::new(None),
+        });
+
+        let writer = Arc::clone(&shared);
+        thread::spawn(move || {
+            for (response, line) in receiver {
+                if inner.emit(&response, &line).is_err() {
+                    writer.dropped_total.fetch_add(1, Ordering::Relaxed);
+                }
+                *writer.depth.lock().unwrap() -= 1;
+                writer.drained.notify_all();
+            }
+            let _ = inner.flush();
+        });
+
+        Self { sender, shared }
+    }
+
+    /// Call `callback` once, with the queue depth, the first time more than
+    /// `threshold` messages are waiting. Use it to log a warning.
+    pub fn on_high_water(
+        self,
+        threshold: usize,
+        callback: impl FnOnce(usize) + Send + 'static,
+    ) -> Self {
+        *self.shared.alarm.lock().unwrap() = Some((threshold, Box::new(callback)));
+        self
+    }
+
+    /// Messages accepted but not yet written.
+    pub fn queued(&self) -> usize {
+        *self.shared.depth.lock().unwrap()
+    }
+
+    /// Most messages ever waiting at once.
+    pub fn high_water_mark(&self) -> u64 {
+        self.shared.high_water_mark.load(Ordering::Relaxed)
+    }
+
+    /// Messages dropped because the queue was full or the inner sink failed.
+    pub fn dropped_total(&self) -> u64 {
+        self.shared.dropped_total.load(Ordering::Relaxed)
+    }
+
+    /// Count a message in before sending it, returning the new depth.
+    fn enter(&self) -> usize {
+        let mut depth = self.shared.depth.lock().unwrap();
+        *depth += 1;
+        *depth
+    }
+
+    /// Update the high-water mark and maybe fire the alarm for a message
+    /// that made it into the queue at `depth`.
+    fn queued_at(&self, depth: usize) {
+        self.shared
+            .high_water_mark
+            .fetch_max(depth as u64, Ordering::Relaxed);
+
+        let mut alarm = self.shared.alarm.lock().unwrap();
+        if alarm.as_ref().is_some_and(|(threshold, _)| depth > *threshold) {
+            if let Some((_, callback)) = alarm.take() {
+                drop(alarm);
+                callback(depth);
+            }
+        }
+    }
+
+    /// Undo [`enter`](Self::enter) for a message that never made it in;
+    /// it does not count toward the high-water mark.
+    fn leave(&self) {
+        *self.shared.depth.lock().unwrap() -= 1;
+        self.shared.drained.notify_all();
+    }
+}
+
+/// Whether a message may be dropped instead of waiting for room.
+fn droppable(response: &GamepackResponse) -> bool {
+    matches!(
+        response,
+        GamepackResponse::Heartbeat { .. }
+            | GamepackResponse::WriteMatchData {
+                message: MatchDataMessage::WriteStatistics { .. },
+                ..
+            }
+    )
+}
+
+impl EmitSink for ChannelEmitter {
+    fn emit(&self, response: &GamepackResponse, line: &str) -> io::Result<()> {
+        let depth = self.enter();
+        let item = (response.clone(), line.to_string());
+        let result = if droppable(response) {
+            self.sender.try_send(item).map_err(|e| match e {
+                TrySendError::Full(_) => {
+                    self.shared.dropped_total.fetch_add(1, Ordering::Relaxed);
+                    io::Error::new(io::ErrorKind::WouldBlock, "emit queue is full")
+                }
+                TrySendError::Disconnected(_) => writer_gone(),
+            })
+        } else {
+            self.sender.send(item).map_err(|_| writer_gone())
+        };
+        match result {
+            Ok(()) => self.queued_at(depth),
+            Err(_) => self.leave(),
+        }
+        result
+    }
+
+    /// Wait until everything queued so far has been written.
+    fn flush(&self) -> io::Result<()> {
+        let depth = self.shared.depth.lock().unwrap();
+        let _empty = self
+            .shared
+            .drained
+            .wait_while(depth, |depth| *depth > 0)
+            .unwrap();
+        Ok(())
+    }
+}
+
+fn writer_gone() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "emit writer thread stopped")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CapturingSink;
+    use crate::types::SummarySource;
+    use crate::Emitter;
+    use std::collections::HashMap;
+    use std::sync::mpsc::Receiver;
+    use std::time::Duration;
+
+    /// Sink that announces each write, then waits for the test's gate.
+    struct SlowSink {
+        gate: Arc<Mutex<()>>,
+        entered: Mutex<mpsc::Sender<()>>,
+        inner: CapturingSink,
+    }
+
+    impl EmitSink for SlowSink {
+        fn emit(&self, response: &GamepackResponse, line: &str) -> io::Result<()> {
+            let _ = self.entered.lock().unwrap().send(());
+            let _open = self.gate.lock().unwrap();
+            self.inner.emit(response, line)
+        }
+    }
+
+    type SlowChannel = (ChannelEmitter, Arc<Mutex<()>>, Receiver<()>, CapturingSink);
+
+    fn slow_channel(capacity: usize) -> SlowChannel {
+        let gate = Arc::new(Mutex::new(()));
+        let (entered, entered_rx) = mpsc::channel();
+        let inner = CapturingSink::new();
+        let sink = SlowSink {
+            gate: Arc::clone(&gate),
+            entered: Mutex::new(entered),
+            inner: inner.clone(),
+        };
+        (ChannelEmitter::new(sink, capacity), gate, entered_rx, inner)
+    }
+
+    #[test]
+    fn full_queue_drops_stats_and_fires_high_water_once() {
+        let (channel, gate, entered, inner) = slow_channel(2);
+        let alarms = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&alarms);
+        let channel = channel.on_high_water(2, move |depth| recorded.lock().unwrap().push(depth));
+        let emitter = Emitter::new(channel.clone());
+
+        let closed = gate.lock().unwrap();
+        emitter.emit_statistics(0, "m1", 1.0, HashMap::new()).unwrap();
+        // The writer is now stuck on the first message; fill the queue
+        entered.recv().unwrap();
+        emitter.emit_statistics(0, "m1", 2.0, HashMap::new()).unwrap();
+        emitter.emit_statistics(0, "m1", 3.0, HashMap::new()).unwrap();
+
+        let dropped = emitter.emit_statistics(0, "m1", 4.0, HashMap::new());
+        let also_dropped = emitter.emit_statistics(0, "m1", 5.0, HashMap::new());
+
+        assert!(dropped.is_err() && also_dropped.is_err());
+        assert_eq!(channel.dropped_total(), 2);
+        // One being written and two queued; the drops never waited
+        assert_eq!(channel.high_water_mark(), 3);
+        assert_eq!(*alarms.lock().unwrap(), vec![3]);
+
+        drop(closed);
+        channel.flush().unwrap();
+        assert_eq!(inner.messages().len(), 3);
+        assert_eq!(channel.queued(), 0);
+    }
+
+    #[test]
+    fn set_complete_waits_for_room_instead_of_dropping() {
+        let (channel, gate, entered, inner) = slow_channel(1);
+        let emitter = Emitter::new(channel.clone());
+
+        let closed = gate.lock().unwrap();
+        emitter.emit_statistics(0, "m1", 1.0, HashMap::new()).unwrap();
+        entered.recv().unwrap();
+        emitter.emit_statistics(0, "m1", 2.0, HashMap::new()).unwrap();
+
+        let background = emitter.clone();
+        let completing = thread::spawn(move || {
+            background.emit_match_data(MatchDataMessage::set_complete(
+                0,
+                "m1",
+                SummarySource::LiveFallback,
+            ))
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(!completing.is_finished(), "SetComplete should block on a full queue");
+
+        drop(closed);
+        completing.join().unwrap().unwrap();
+        channel.flush().unwrap();
+
+        assert_eq!(channel.dropped_total(), 0);
+        assert!(matches!(
+            inner.messages().last(),
+            Some(MatchDataMessage::SetComplete { .. })
+        ));
+    }
+
+    #[test]
+    fn live_data_patches_are_never_dropped() {
+        assert!(!droppable(&GamepackResponse::LiveDataPatch {
+            request_id: String::new(),
+            ops: vec![],
+        }));
+    }
+}
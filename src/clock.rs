@@ -0,0 +1,66 @@
+//! Injectable wall-clock time.
+//!
+//! Code that stamps wall-clock times (e.g. `captured_at` on timeline entries)
+//! takes a [`Clock`] so tests can pin the time with a [`FixedClock`].
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use crate::types::Timestamp;
+
+/// Source of wall-clock timestamps.
+pub trait Clock: Send + Sync {
+    /// The current time.
+    fn now(&self) -> Timestamp;
+}
+
+/// The real system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+/// A clock that only moves when told to. For tests.
+#[derive(Debug, Default)]
+pub struct FixedClock {
+    millis: AtomicI64,
+}
+
+impl FixedClock {
+    /// Create a clock stopped at the given milliseconds since the Unix epoch.
+    pub fn at_unix_millis(millis: i64) -> Self {
+        Self {
+            millis: AtomicI64::new(millis),
+        }
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, by: Duration) {
+        self.millis.fetch_add(by.as_millis() as i64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::from_unix_millis(self.millis.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_clock_only_moves_when_advanced() {
+        let clock = FixedClock::at_unix_millis(1_705_314_600_000);
+        assert_eq!(clock.now().as_str(), "2024-01-15T10:30:00.000Z");
+        assert_eq!(clock.now(), clock.now());
+
+        clock.advance(Duration::from_millis(1_500));
+        assert_eq!(clock.now().as_str(), "2024-01-15T10:30:01.500Z");
+    }
+}
@@ -2,15 +2,37 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::types::{count, TriggerConfig};
+
 /// Commands sent from the main daemon to a gamepack.
 ///
 /// Each command includes a `request_id` for correlating responses.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, strum::IntoStaticStr)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum GamepackCommand {
     /// Initialize the integration.
+    /// Only the first `Init` reaches the handler; a repeat (e.g. after the
+    /// daemon reconnects) is answered with the same `Initialized` unless
+    /// `force` is set.
     /// Expected response: `Initialized`
-    Init { request_id: String },
+    Init {
+        request_id: String,
+        /// Highest protocol version the daemon supports. When set, the
+        /// session uses the lower of this and the pack's version; older
+        /// daemons omit it and get the pack's version.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        protocol_version: Option<u32>,
+        /// Optional protocol features the daemon supports (see
+        /// [`SUPPORTED_FEATURES`](crate::version::SUPPORTED_FEATURES)).
+        /// The runner enables the ones it also supports and lists them in
+        /// `Initialized { features }`.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        daemon_features: Vec<String>,
+        /// Call the handler's `init` again even if it already succeeded,
+        /// renegotiating the protocol version and features
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        force: Option<bool>,
+    },
 
     /// Check if the game client/process is running.
     /// Expected response: `RunningStatus`
@@ -20,6 +42,16 @@ pub enum GamepackCommand {
     /// Expected response: `GameStatus`
     GetStatus { request_id: String },
 
+    /// Get the running game's version or patch, e.g. for analytics and
+    /// compatibility checks.
+    /// Expected response: `GameVersion`
+    GetGameVersion { request_id: String },
+
+    /// Get how the pack binary was built (target, profile, features,
+    /// compiler), for support logs. Informational only.
+    /// Expected response: `BuildInfo`
+    GetBuildInfo { request_id: String },
+
     /// Poll for new game events (triggers for clip capture).
     /// Expected response: `Events`
     PollEvents { request_id: String },
@@ -28,6 +60,19 @@ pub enum GamepackCommand {
     /// Expected response: `LiveData`
     GetLiveData { request_id: String },
 
+    /// Get the full current live data, e.g. after a daemon restart.
+    /// The pull-path override for packs that push live data: always answered
+    /// with a complete `LiveData` (never a `LiveDataPatch`), and the result
+    /// becomes the base for later patches.
+    /// Expected response: `LiveData`
+    RefreshLiveData { request_id: String },
+
+    /// Get live data for every match the gamepack is tracking, for
+    /// spectator/arena modes that run several matches at once.
+    /// Never patched; answered with an empty list while paused.
+    /// Expected response: `LiveDataAll`
+    GetLiveDataAll { request_id: String },
+
     /// Notification that a game session has started.
     /// Expected response: `SessionStarted`
     SessionStart { request_id: String },
@@ -41,9 +86,23 @@ pub enum GamepackCommand {
     },
 
     /// Request graceful shutdown.
+    /// `ShutdownComplete` is the last line the gamepack writes: commands sent
+    /// after `Shutdown` are never read or answered, and emits from
+    /// background threads are dropped.
     /// Expected response: `ShutdownComplete`
     Shutdown { request_id: String },
 
+    /// Ask the gamepack to stop polling its game API (e.g. clipping disabled).
+    /// While paused, `PollEvents`, `GetLiveData`, `RefreshLiveData` and
+    /// `GetLiveDataAll` are answered with empty results without calling the
+    /// handler, unless the pack sets `RunnerOptions::poll_while_paused`.
+    /// Expected response: `Ack`
+    Pause { request_id: String },
+
+    /// Resume normal operation after `Pause`.
+    /// Expected response: `Ack`
+    Resume { request_id: String },
+
     /// Request an icon URL for an event type.
     /// Used for discovered events that don't have icons in the seed data.
     /// Expected response: `EventIconResolved`
@@ -68,6 +127,25 @@ pub enum GamepackCommand {
         external_match_id: String,
     },
 
+    /// Check whether the pack knows a match at all.
+    /// A cheap existence check for recovery, unlike `IsMatchInProgress`
+    /// (which asks whether the game is still running) or `GetMatchTimeline`
+    /// (which returns the data).
+    /// Expected response: `MatchExistsResult`
+    MatchExists {
+        request_id: String,
+        /// Subpack index (0 = default)
+        subpack: u8,
+        /// Game's native match ID
+        external_match_id: String,
+    },
+
+    /// Get the matches the pack currently considers active, e.g. to sync
+    /// the UI. The pack's own view, asked proactively; `IsMatchInProgress`
+    /// instead checks one match the daemon already knows, during recovery.
+    /// Expected response: `ActiveMatches`
+    GetActiveMatches { request_id: String },
+
     /// Request match timeline data.
     /// Used for recovery when a gamepack needs to reconstruct match state.
     /// Expected response: `MatchTimeline`
@@ -80,11 +158,35 @@ pub enum GamepackCommand {
         /// Filter by entry types (None = all types)
         #[serde(skip_serializing_if = "Option::is_none")]
         entry_types: Option<Vec<String>>,
-        /// Max entries to return (latest N)
+        /// Max entries to return (latest N, applied after the time range)
         #[serde(skip_serializing_if = "Option::is_none")]
         limit: Option<u32>,
+        /// Only entries at or after this game time (seconds)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        start_secs: Option<f64>,
+        /// Only entries at or before this game time (seconds)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        end_secs: Option<f64>,
+    },
+
+    /// Request summaries of the most recently finished matches.
+    /// Used to backfill history the daemon missed (e.g. while it was down).
+    /// Expected response: `RecentMatches`
+    GetRecentMatches {
+        request_id: String,
+        /// Subpack index (0 = default)
+        subpack: u8,
+        /// Max matches to return, newest first (None = all the pack retains)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit: Option<u32>,
     },
 
+    /// Re-run the pack's own stale match recovery, e.g. after the pack was
+    /// reloaded. Unlike `IsMatchInProgress`, the pack decides which matches
+    /// are stale; the messages it returns are written before the response.
+    /// Expected response: `RecoveryComplete`
+    RunRecovery { request_id: String },
+
     // ========================================================================
     // DEBUG / PREVIEW
     // ========================================================================
@@ -97,24 +199,594 @@ pub enum GamepackCommand {
         /// Subpack index (0 = default/main game mode)
         subpack: u8,
     },
+
+    /// Request the schema a subpack declares in code, if any.
+    /// Optional: `config.json` stays authoritative where both describe a field.
+    /// Expected response: `Schema`
+    GetSchema {
+        request_id: String,
+        /// Subpack index (0 = default/main game mode)
+        subpack: u8,
+    },
+
+    /// Diagnostic: emit a synthetic moment to exercise the whole
+    /// moment -> trigger -> recording path without playing the game.
+    /// The runner writes a `WriteMoments` with `moment_id` and data
+    /// `{"test": true}` before answering.
+    /// Expected response: `Ack`
+    EmitTestMoment {
+        request_id: String,
+        /// Subpack index (0 = default/main game mode)
+        subpack: u8,
+        /// Moment to emit, as declared in config.json
+        moment_id: String,
+    },
+
+    // ========================================================================
+    // DERIVED STATS
+    // ========================================================================
+
+    /// Request the pack's derived stat declarations.
+    /// Expected response: `DerivedStats`
+    GetDerivedStats { request_id: String },
+
+    // ========================================================================
+    // HEALTH
+    // ========================================================================
+
+    /// Request the most recent error the gamepack encountered.
+    /// Answered by the runner; the handler is not involved.
+    /// Expected response: `LastError`
+    GetLastError { request_id: String },
+
+    /// One round of clock synchronization, answered by the runner.
+    /// Expected response: `TimeSyncResult`
+    ///
+    /// The daemon sends its clock reading `daemon_send_ns` (t0) and notes
+    /// when the answer arrives (t3). The answer echoes t0 and adds the pack
+    /// clock when the command was handled (t1, `pack_recv_ns`) and when the
+    /// answer was built (t2, `pack_send_ns`). As in NTP:
+    ///
+    /// - round trip = (t3 - t0) - (t2 - t1)
+    /// - pack clock offset = ((t1 - t0) + (t2 - t3)) / 2
+    ///
+    /// The offset is exact when both directions take equally long; take the
+    /// round with the smallest round trip out of a few. Pack readings are
+    /// nanoseconds since the Unix epoch, advanced by a monotonic clock, so
+    /// t1 <= t2 and readings never go backwards within one process.
+    TimeSync {
+        request_id: String,
+        daemon_send_ns: u64,
+    },
+
+    // ========================================================================
+    // STORAGE
+    // ========================================================================
+
+    /// Daemon-pushed clip storage status (advisory).
+    /// Sent when storage conditions change so packs can throttle
+    /// low-priority moments.
+    /// Expected response: `Ack`
+    StorageStatus {
+        request_id: String,
+        /// Free space available for clips, in bytes
+        free_bytes: u64,
+        /// Number of clips currently stored
+        clip_count: u32,
+        /// Whether the clip budget is used up
+        budget_exhausted: bool,
+    },
+
+    // ========================================================================
+    // SUBPACKS
+    // ========================================================================
+
+    /// The user selected a game mode; report to this subpack from now on.
+    /// Sent before a match so multi-mode packs know where its data belongs.
+    /// Expected response: `Ack`
+    SetActiveSubpack {
+        request_id: String,
+        /// Subpack index (0 = default)
+        subpack: u8,
+    },
+
+    // ========================================================================
+    // MATCH MUTING
+    // ========================================================================
+
+    /// Stop recording one match (e.g. the user turned recording off
+    /// mid-game) while the pack keeps running. The runner drops the match's
+    /// stats, events, moments and heartbeats from then on; `SetComplete` and
+    /// `SetAbandoned` still go out.
+    /// Expected response: `Ack`
+    MuteMatch {
+        request_id: String,
+        /// Subpack index (0 = default)
+        subpack: u8,
+        /// Game's native match ID
+        external_match_id: String,
+    },
+
+    /// Resume recording a match muted with `MuteMatch`.
+    /// Expected response: `Ack`
+    UnmuteMatch {
+        request_id: String,
+        /// Subpack index (0 = default)
+        subpack: u8,
+        /// Game's native match ID
+        external_match_id: String,
+    },
+
+    // ========================================================================
+    // SETTINGS
+    // ========================================================================
+
+    /// The user changed the pack's settings.
+    /// The pack validates and applies them; invalid settings are rejected
+    /// with an `Error` carrying the reason.
+    /// Expected response: `Ack` or `Error`
+    UpdateSettings {
+        request_id: String,
+        /// Changed settings as a JSON object (may be partial)
+        settings: serde_json::Value,
+    },
+
+    // ========================================================================
+    // TRIGGERS
+    // ========================================================================
+
+    /// Check a trigger configuration before the daemon saves it.
+    /// The daemon shows each returned issue next to the trigger it names and
+    /// refuses to save until the list is empty.
+    /// Expected response: `TriggerConfigValidation`
+    ValidateTriggerConfig {
+        request_id: String,
+        /// The proposed triggers, as the user configured them
+        triggers: Vec<TriggerConfig>,
+    },
+
+    /// Ask whether to record a moment that fired an enabled trigger.
+    /// Only sent to packs declaring `record_decisions`; the daemon's trigger
+    /// configuration is checked first, and the pack can then veto the
+    /// recording or replace its capture window.
+    /// Expected response: `RecordDecision`
+    ShouldRecord {
+        request_id: String,
+        /// Moment that fired, as declared in config.json
+        moment_id: String,
+        /// The moment's data
+        data: serde_json::Value,
+    },
+
+    /// Request the pack's rules for turning events into moments, for UI
+    /// that explains what creates a clip.
+    /// Expected response: `TriggerRules`
+    GetTriggerRules { request_id: String, subpack: u8 },
+
+    // ========================================================================
+    // UPDATES
+    // ========================================================================
+    //
+    // Auto-update choreography:
+    // 1. Daemon sends `PrepareUpdate`; the pack checkpoints its in-memory
+    //    state and answers `UpdatePrepared { state }`
+    // 2. Daemon sends `Shutdown`, replaces the binary and starts the new one
+    // 3. After `Init`, daemon sends `RestoreState { state }` with the
+    //    checkpoint (skipped if the old pack returned no state)
+
+    /// Ask the gamepack to checkpoint its state before its binary is replaced.
+    /// Expected response: `UpdatePrepared`
+    PrepareUpdate { request_id: String },
+
+    /// Hand a checkpoint from `UpdatePrepared` back to the restarted pack.
+    /// Expected response: `Ack`
+    RestoreState {
+        request_id: String,
+        /// State returned by the previous pack instance
+        state: serde_json::Value,
+    },
 }
 
 impl GamepackCommand {
     /// Get the request_id from any command variant.
     pub fn request_id(&self) -> &str {
         match self {
-            Self::Init { request_id } => request_id,
+            Self::Init { request_id, .. } => request_id,
             Self::DetectRunning { request_id } => request_id,
             Self::GetStatus { request_id } => request_id,
+            Self::GetGameVersion { request_id } => request_id,
+            Self::GetBuildInfo { request_id } => request_id,
             Self::PollEvents { request_id } => request_id,
             Self::GetLiveData { request_id } => request_id,
+            Self::RefreshLiveData { request_id } => request_id,
+            Self::GetLiveDataAll { request_id } => request_id,
             Self::SessionStart { request_id } => request_id,
             Self::SessionEnd { request_id, .. } => request_id,
             Self::Shutdown { request_id } => request_id,
+            Self::Pause { request_id } => request_id,
+            Self::Resume { request_id } => request_id,
             Self::ResolveEventIcon { request_id, .. } => request_id,
             Self::IsMatchInProgress { request_id, .. } => request_id,
+            Self::MatchExists { request_id, .. } => request_id,
+            Self::GetActiveMatches { request_id } => request_id,
+            Self::RunRecovery { request_id } => request_id,
             Self::GetMatchTimeline { request_id, .. } => request_id,
+            Self::GetRecentMatches { request_id, .. } => request_id,
             Self::GetSampleMatchData { request_id, .. } => request_id,
+            Self::GetSchema { request_id, .. } => request_id,
+            Self::EmitTestMoment { request_id, .. } => request_id,
+            Self::GetDerivedStats { request_id } => request_id,
+            Self::GetLastError { request_id } => request_id,
+            Self::TimeSync { request_id, .. } => request_id,
+            Self::StorageStatus { request_id, .. } => request_id,
+            Self::SetActiveSubpack { request_id, .. } => request_id,
+            Self::MuteMatch { request_id, .. } => request_id,
+            Self::UnmuteMatch { request_id, .. } => request_id,
+            Self::UpdateSettings { request_id, .. } => request_id,
+            Self::ValidateTriggerConfig { request_id, .. } => request_id,
+            Self::ShouldRecord { request_id, .. } => request_id,
+            Self::GetTriggerRules { request_id, .. } => request_id,
+            Self::PrepareUpdate { request_id } => request_id,
+            Self::RestoreState { request_id, .. } => request_id,
         }
     }
+
+    /// Serialize with sorted keys, for golden files and hashing; not the
+    /// wire format. See [`debug::canonical_json`](crate::debug::canonical_json).
+    pub fn to_canonical_json(&self) -> String {
+        crate::debug::canonical_json(self)
+    }
+
+    /// One-line human-readable summary for logs.
+    ///
+    /// For example `"IsMatchInProgress[id=req_1, subpack=0, match=abc]"`.
+    /// This is for diagnostics only and does not affect the wire format.
+    pub fn describe(&self) -> String {
+        let name: &'static str = self.into();
+        let id = self.request_id();
+        match self {
+            Self::ResolveEventIcon { event_key, .. } => {
+                format!("{}[id={}, event={}]", name, id, event_key)
+            }
+            Self::IsMatchInProgress {
+                subpack,
+                external_match_id,
+                ..
+            }
+            | Self::MatchExists {
+                subpack,
+                external_match_id,
+                ..
+            }
+            | Self::MuteMatch {
+                subpack,
+                external_match_id,
+                ..
+            }
+            | Self::UnmuteMatch {
+                subpack,
+                external_match_id,
+                ..
+            } => format!(
+                "{}[id={}, subpack={}, match={}]",
+                name, id, subpack, external_match_id
+            ),
+            Self::GetMatchTimeline {
+                subpack,
+                external_match_id,
+                entry_types,
+                limit,
+                start_secs,
+                end_secs,
+                ..
+            } => {
+                let mut details =
+                    format!("id={}, subpack={}, match={}", id, subpack, external_match_id);
+                if let Some(types) = entry_types {
+                    details.push_str(&format!(", types={}", types.join(",")));
+                }
+                if start_secs.is_some() || end_secs.is_some() {
+                    let bound = |b: &Option<f64>| b.map(|s| s.to_string()).unwrap_or_default();
+                    let range = format!("{}..{}", bound(start_secs), bound(end_secs));
+                    details.push_str(&format!(", range={}", range));
+                }
+                if let Some(limit) = limit {
+                    details.push_str(&format!(", limit={}", limit));
+                }
+                format!("{}[{}]", name, details)
+            }
+            Self::GetRecentMatches { subpack, limit, .. } => match limit {
+                Some(limit) => format!("{}[id={}, subpack={}, limit={}]", name, id, subpack, limit),
+                None => format!("{}[id={}, subpack={}]", name, id, subpack),
+            },
+            Self::GetSampleMatchData { subpack, .. }
+            | Self::GetSchema { subpack, .. }
+            | Self::SetActiveSubpack { subpack, .. }
+            | Self::GetTriggerRules { subpack, .. } => {
+                format!("{}[id={}, subpack={}]", name, id, subpack)
+            }
+            Self::EmitTestMoment {
+                subpack, moment_id, ..
+            } => format!("{}[id={}, subpack={}, moment={}]", name, id, subpack, moment_id),
+            Self::StorageStatus {
+                free_bytes,
+                clip_count,
+                budget_exhausted,
+                ..
+            } => format!(
+                "{}[id={}, free={}B, {}{}]",
+                name,
+                id,
+                free_bytes,
+                count(*clip_count as usize, "clip", "clips"),
+                if *budget_exhausted { ", exhausted" } else { "" }
+            ),
+            Self::ValidateTriggerConfig { triggers, .. } => format!(
+                "{}[id={}, {}]",
+                name,
+                id,
+                count(triggers.len(), "trigger", "triggers")
+            ),
+            Self::ShouldRecord { moment_id, .. } => {
+                format!("{}[id={}, moment={}]", name, id, moment_id)
+            }
+            Self::Init { .. }
+            | Self::DetectRunning { .. }
+            | Self::GetStatus { .. }
+            | Self::GetGameVersion { .. }
+            | Self::GetBuildInfo { .. }
+            | Self::PollEvents { .. }
+            | Self::GetLiveData { .. }
+            | Self::RefreshLiveData { .. }
+            | Self::GetLiveDataAll { .. }
+            | Self::SessionStart { .. }
+            | Self::SessionEnd { .. }
+            | Self::Shutdown { .. }
+            | Self::Pause { .. }
+            | Self::Resume { .. }
+            | Self::GetActiveMatches { .. }
+            | Self::RunRecovery { .. }
+            | Self::GetDerivedStats { .. }
+            | Self::GetLastError { .. }
+            | Self::TimeSync { .. }
+            | Self::UpdateSettings { .. }
+            | Self::PrepareUpdate { .. }
+            | Self::RestoreState { .. } => format!("{}[id={}]", name, id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_simple_commands() {
+        let cases = [
+            (
+                GamepackCommand::Init {
+                    request_id: "r".into(),
+                    protocol_version: None,
+                    daemon_features: vec![],
+                    force: None,
+                },
+                "Init[id=r]",
+            ),
+            (GamepackCommand::DetectRunning { request_id: "r".into() }, "DetectRunning[id=r]"),
+            (GamepackCommand::GetStatus { request_id: "r".into() }, "GetStatus[id=r]"),
+            (GamepackCommand::GetGameVersion { request_id: "r".into() }, "GetGameVersion[id=r]"),
+            (GamepackCommand::GetBuildInfo { request_id: "r".into() }, "GetBuildInfo[id=r]"),
+            (GamepackCommand::PollEvents { request_id: "r".into() }, "PollEvents[id=r]"),
+            (GamepackCommand::GetLiveData { request_id: "r".into() }, "GetLiveData[id=r]"),
+            (
+                GamepackCommand::RefreshLiveData { request_id: "r".into() },
+                "RefreshLiveData[id=r]",
+            ),
+            (GamepackCommand::GetLiveDataAll { request_id: "r".into() }, "GetLiveDataAll[id=r]"),
+            (GamepackCommand::SessionStart { request_id: "r".into() }, "SessionStart[id=r]"),
+            (
+                GamepackCommand::SessionEnd {
+                    request_id: "r".into(),
+                    context: serde_json::json!({"big": "context"}),
+                },
+                "SessionEnd[id=r]",
+            ),
+            (GamepackCommand::Shutdown { request_id: "r".into() }, "Shutdown[id=r]"),
+            (GamepackCommand::Pause { request_id: "r".into() }, "Pause[id=r]"),
+            (GamepackCommand::Resume { request_id: "r".into() }, "Resume[id=r]"),
+            (
+                GamepackCommand::GetActiveMatches { request_id: "r".into() },
+                "GetActiveMatches[id=r]",
+            ),
+            (GamepackCommand::RunRecovery { request_id: "r".into() }, "RunRecovery[id=r]"),
+            (GamepackCommand::GetDerivedStats { request_id: "r".into() }, "GetDerivedStats[id=r]"),
+            (GamepackCommand::GetLastError { request_id: "r".into() }, "GetLastError[id=r]"),
+            (
+                GamepackCommand::TimeSync {
+                    request_id: "r".into(),
+                    daemon_send_ns: 1_000,
+                },
+                "TimeSync[id=r]",
+            ),
+            (
+                GamepackCommand::UpdateSettings {
+                    request_id: "r".into(),
+                    settings: serde_json::json!({}),
+                },
+                "UpdateSettings[id=r]",
+            ),
+            (GamepackCommand::PrepareUpdate { request_id: "r".into() }, "PrepareUpdate[id=r]"),
+            (
+                GamepackCommand::RestoreState {
+                    request_id: "r".into(),
+                    state: serde_json::json!({"cursor": 3}),
+                },
+                "RestoreState[id=r]",
+            ),
+        ];
+
+        for (cmd, expected) in cases {
+            assert_eq!(cmd.describe(), expected);
+        }
+    }
+
+    #[test]
+    fn describe_commands_with_fields() {
+        assert_eq!(
+            GamepackCommand::StorageStatus {
+                request_id: "r".into(),
+                free_bytes: 1024,
+                clip_count: 12,
+                budget_exhausted: true,
+            }
+            .describe(),
+            "StorageStatus[id=r, free=1024B, 12 clips, exhausted]"
+        );
+        assert_eq!(
+            GamepackCommand::ResolveEventIcon {
+                request_id: "r".into(),
+                event_key: "DragonKill".into(),
+            }
+            .describe(),
+            "ResolveEventIcon[id=r, event=DragonKill]"
+        );
+        assert_eq!(
+            GamepackCommand::IsMatchInProgress {
+                request_id: "r".into(),
+                subpack: 0,
+                external_match_id: "abc".into(),
+            }
+            .describe(),
+            "IsMatchInProgress[id=r, subpack=0, match=abc]"
+        );
+        assert_eq!(
+            GamepackCommand::MatchExists {
+                request_id: "r".into(),
+                subpack: 1,
+                external_match_id: "abc".into(),
+            }
+            .describe(),
+            "MatchExists[id=r, subpack=1, match=abc]"
+        );
+        assert_eq!(
+            GamepackCommand::ValidateTriggerConfig {
+                request_id: "r".into(),
+                triggers: vec![TriggerConfig::new("pentakill")],
+            }
+            .describe(),
+            "ValidateTriggerConfig[id=r, 1 trigger]"
+        );
+        assert_eq!(
+            GamepackCommand::GetMatchTimeline {
+                request_id: "r".into(),
+                subpack: 1,
+                external_match_id: "abc".into(),
+                entry_types: Some(vec!["event".into(), "moment".into()]),
+                limit: Some(10),
+                start_secs: Some(60.0),
+                end_secs: None,
+            }
+            .describe(),
+            "GetMatchTimeline[id=r, subpack=1, match=abc, types=event,moment, range=60.., limit=10]"
+        );
+        assert_eq!(
+            GamepackCommand::GetMatchTimeline {
+                request_id: "r".into(),
+                subpack: 0,
+                external_match_id: "abc".into(),
+                entry_types: None,
+                limit: None,
+                start_secs: None,
+                end_secs: None,
+            }
+            .describe(),
+            "GetMatchTimeline[id=r, subpack=0, match=abc]"
+        );
+        assert_eq!(
+            GamepackCommand::GetSampleMatchData {
+                request_id: "r".into(),
+                subpack: 2,
+            }
+            .describe(),
+            "GetSampleMatchData[id=r, subpack=2]"
+        );
+        assert_eq!(
+            GamepackCommand::GetSchema {
+                request_id: "r".into(),
+                subpack: 1,
+            }
+            .describe(),
+            "GetSchema[id=r, subpack=1]"
+        );
+        assert_eq!(
+            GamepackCommand::SetActiveSubpack {
+                request_id: "r".into(),
+                subpack: 3,
+            }
+            .describe(),
+            "SetActiveSubpack[id=r, subpack=3]"
+        );
+        assert_eq!(
+            GamepackCommand::GetTriggerRules {
+                request_id: "r".into(),
+                subpack: 0,
+            }
+            .describe(),
+            "GetTriggerRules[id=r, subpack=0]"
+        );
+        assert_eq!(
+            GamepackCommand::MuteMatch {
+                request_id: "r".into(),
+                subpack: 0,
+                external_match_id: "abc".into(),
+            }
+            .describe(),
+            "MuteMatch[id=r, subpack=0, match=abc]"
+        );
+        assert_eq!(
+            GamepackCommand::UnmuteMatch {
+                request_id: "r".into(),
+                subpack: 0,
+                external_match_id: "abc".into(),
+            }
+            .describe(),
+            "UnmuteMatch[id=r, subpack=0, match=abc]"
+        );
+        assert_eq!(
+            GamepackCommand::GetRecentMatches {
+                request_id: "r".into(),
+                subpack: 1,
+                limit: Some(5),
+            }
+            .describe(),
+            "GetRecentMatches[id=r, subpack=1, limit=5]"
+        );
+        assert_eq!(
+            GamepackCommand::GetRecentMatches {
+                request_id: "r".into(),
+                subpack: 1,
+                limit: None,
+            }
+            .describe(),
+            "GetRecentMatches[id=r, subpack=1]"
+        );
+        assert_eq!(
+            GamepackCommand::EmitTestMoment {
+                request_id: "r".into(),
+                subpack: 0,
+                moment_id: "pentakill".into(),
+            }
+            .describe(),
+            "EmitTestMoment[id=r, subpack=0, moment=pentakill]"
+        );
+        assert_eq!(
+            GamepackCommand::ShouldRecord {
+                request_id: "r".into(),
+                moment_id: "pentakill".into(),
+                data: serde_json::json!({"kills": 5}),
+            }
+            .describe(),
+            "ShouldRecord[id=r, moment=pentakill]"
+        );
+    }
 }
@@ -0,0 +1,267 @@
+//! Debounced connection-state tracking for `get_status`.
+//!
+//! Game clients and their APIs come and go noisily: a process scan misses a
+//! beat, a websocket reconnects, a lobby briefly reports "no game". Reporting
+//! every raw observation makes `GameStatus` flap. [`ConnectionStateMachine`]
+//! takes the raw observations and only reports a new state once it has held
+//! for a configurable time.
+//!
+//! # State diagram
+//!
+//! ```text
+//!                client_found               game_start
+//!  Disconnected ─────────────► Connected ─────────────► InGame
+//!    ▲   │                      │   ▲                    │  │
+//!    │   │                      │   └────── game_end ────┘  │
+//!    │   └──────────────── game_start ──────────────────►───┘
+//!    │                          │                           │
+//!    └───────── client_lost ────┴────── client_lost ────────┘
+//! ```
+//!
+//! Observations that don't apply to the current state (e.g. `game_end` while
+//! disconnected) are ignored. While a change away from `Disconnected` is
+//! waiting out the hold time, the reported state is `Connecting`; other
+//! pending changes keep reporting the previous stable state.
+
+use std::time::{Duration, Instant};
+
+use crate::types::GameStatus;
+
+/// Stable connection state reported by [`ConnectionStateMachine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// No game client found
+    Disconnected,
+    /// Client found, waiting for the hold time before reporting `Connected`
+    Connecting,
+    /// Connected to the game client, not in a game
+    Connected,
+    /// Connected and in a game
+    InGame,
+}
+
+/// A raw observation fed to [`ConnectionStateMachine::observe_at`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    ClientFound,
+    ClientLost,
+    GameStart,
+    GameEnd,
+}
+
+impl ConnectionState {
+    /// Raw transition for an observation (see the module-level diagram).
+    fn next(self, event: ConnectionEvent) -> Self {
+        match (self, event) {
+            (_, ConnectionEvent::ClientLost) => Self::Disconnected,
+            (_, ConnectionEvent::GameStart) => Self::InGame,
+            (Self::Disconnected, ConnectionEvent::ClientFound) => Self::Connected,
+            (Self::InGame, ConnectionEvent::GameEnd) => Self::Connected,
+            (state, _) => state,
+        }
+    }
+}
+
+/// Turns noisy connection observations into a stable [`GameStatus`].
+///
+/// ```rust
+/// use std::time::Duration;
+/// use gamepack_runtime::{ConnectionState, ConnectionStateMachine};
+///
+/// let mut connection = ConnectionStateMachine::new(Duration::from_secs(2));
+/// connection.on_client_found();
+/// // Not stable yet
+/// assert_eq!(connection.state(), ConnectionState::Connecting);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectionStateMachine {
+    hold: Duration,
+    stable: ConnectionState,
+    /// Raw state that differs from `stable`, and when it was first observed
+    pending: Option<(ConnectionState, Instant)>,
+}
+
+impl ConnectionStateMachine {
+    /// Start disconnected. A new state is reported once it has held for
+    /// `hold`; `Duration::ZERO` reports every change immediately.
+    pub fn new(hold: Duration) -> Self {
+        Self {
+            hold,
+            stable: ConnectionState::Disconnected,
+            pending: None,
+        }
+    }
+
+    /// The game client was found (process running, API reachable).
+    pub fn on_client_found(&mut self) {
+        self.observe_at(ConnectionEvent::ClientFound, Instant::now());
+    }
+
+    /// The game client is gone.
+    pub fn on_client_lost(&mut self) {
+        self.observe_at(ConnectionEvent::ClientLost, Instant::now());
+    }
+
+    /// A game started.
+    pub fn on_game_start(&mut self) {
+        self.observe_at(ConnectionEvent::GameStart, Instant::now());
+    }
+
+    /// The game ended (back in lobby/menus).
+    pub fn on_game_end(&mut self) {
+        self.observe_at(ConnectionEvent::GameEnd, Instant::now());
+    }
+
+    /// Feed an observation made at `at`.
+    ///
+    /// The `on_*` methods call this with the current time; tests can pass
+    /// their own instants.
+    pub fn observe_at(&mut self, event: ConnectionEvent, at: Instant) {
+        self.settle(at);
+        let raw = self.pending.map_or(self.stable, |(state, _)| state);
+        let next = raw.next(event);
+        if next == self.stable {
+            // Flapped back before the hold elapsed
+            self.pending = None;
+        } else if next != raw {
+            self.pending = Some((next, at));
+        }
+    }
+
+    /// The stable state now.
+    pub fn state(&self) -> ConnectionState {
+        self.state_at(Instant::now())
+    }
+
+    /// The stable state at `at`.
+    pub fn state_at(&self, at: Instant) -> ConnectionState {
+        match self.pending {
+            Some((state, since)) if at.saturating_duration_since(since) >= self.hold => state,
+            Some(_) if self.stable == ConnectionState::Disconnected => ConnectionState::Connecting,
+            _ => self.stable,
+        }
+    }
+
+    /// The stable state as a [`GameStatus`], ready to return from `get_status`.
+    pub fn current_status(&self) -> GameStatus {
+        self.status_at(Instant::now())
+    }
+
+    /// The stable state at `at` as a [`GameStatus`].
+    pub fn status_at(&self, at: Instant) -> GameStatus {
+        match self.state_at(at) {
+            ConnectionState::Disconnected => GameStatus::disconnected(),
+            ConnectionState::Connecting => GameStatus {
+                connection_status: "Connecting".to_string(),
+                ..GameStatus::disconnected()
+            },
+            ConnectionState::Connected => GameStatus::connected("Connected"),
+            ConnectionState::InGame => GameStatus::connected("In game").in_game(true),
+        }
+    }
+
+    /// Promote the pending state if it has held long enough.
+    fn settle(&mut self, at: Instant) {
+        if let Some((state, since)) = self.pending {
+            if at.saturating_duration_since(since) >= self.hold {
+                self.stable = state;
+                self.pending = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ConnectionEvent::*;
+
+    const HOLD: Duration = Duration::from_secs(2);
+
+    fn ms(base: Instant, millis: u64) -> Instant {
+        base + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn change_is_reported_after_hold() {
+        let t0 = Instant::now();
+        let mut machine = ConnectionStateMachine::new(HOLD);
+
+        machine.observe_at(ClientFound, t0);
+        assert_eq!(machine.state_at(ms(t0, 1_999)), ConnectionState::Connecting);
+        assert_eq!(machine.state_at(ms(t0, 2_000)), ConnectionState::Connected);
+
+        machine.observe_at(GameStart, ms(t0, 3_000));
+        assert_eq!(machine.state_at(ms(t0, 4_000)), ConnectionState::Connected);
+        assert_eq!(machine.state_at(ms(t0, 5_000)), ConnectionState::InGame);
+    }
+
+    #[test]
+    fn client_flaps_are_suppressed() {
+        let t0 = Instant::now();
+        let mut machine = ConnectionStateMachine::new(HOLD);
+        machine.observe_at(ClientFound, t0);
+        machine.observe_at(GameStart, ms(t0, 2_000));
+
+        // Process scan misses the client for a moment mid-game
+        machine.observe_at(ClientLost, ms(t0, 5_000));
+        machine.observe_at(ClientFound, ms(t0, 5_500));
+        machine.observe_at(GameStart, ms(t0, 5_600));
+
+        for at in [5_000, 5_500, 6_000, 9_000] {
+            assert_eq!(machine.state_at(ms(t0, at)), ConnectionState::InGame, "at {}ms", at);
+        }
+    }
+
+    #[test]
+    fn game_end_flap_keeps_in_game() {
+        let t0 = Instant::now();
+        let mut machine = ConnectionStateMachine::new(HOLD);
+        machine.observe_at(GameStart, t0);
+        machine.observe_at(GameEnd, ms(t0, 4_000));
+        machine.observe_at(GameStart, ms(t0, 4_100));
+
+        assert_eq!(machine.state_at(ms(t0, 10_000)), ConnectionState::InGame);
+    }
+
+    #[test]
+    fn loss_that_holds_is_reported() {
+        let t0 = Instant::now();
+        let mut machine = ConnectionStateMachine::new(HOLD);
+        machine.observe_at(ClientFound, t0);
+        machine.observe_at(ClientLost, ms(t0, 3_000));
+
+        assert_eq!(machine.state_at(ms(t0, 4_000)), ConnectionState::Connected);
+        assert_eq!(machine.state_at(ms(t0, 5_000)), ConnectionState::Disconnected);
+    }
+
+    #[test]
+    fn zero_hold_reports_immediately_and_ignores_inapplicable_events() {
+        let t0 = Instant::now();
+        let mut machine = ConnectionStateMachine::new(Duration::ZERO);
+
+        machine.observe_at(GameEnd, t0);
+        assert_eq!(machine.state_at(t0), ConnectionState::Disconnected);
+        machine.observe_at(ClientFound, t0);
+        assert_eq!(machine.state_at(t0), ConnectionState::Connected);
+    }
+
+    #[test]
+    fn status_reflects_stable_state() {
+        let t0 = Instant::now();
+        let mut machine = ConnectionStateMachine::new(HOLD);
+
+        let status = machine.status_at(t0);
+        assert!(!status.connected);
+
+        machine.observe_at(ClientFound, t0);
+        let status = machine.status_at(t0);
+        assert!(!status.connected);
+        assert_eq!(status.connection_status, "Connecting");
+
+        machine.observe_at(GameStart, t0);
+        let status = machine.status_at(ms(t0, 2_000));
+        assert!(status.connected);
+        assert!(status.is_in_game);
+    }
+}
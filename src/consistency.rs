@@ -0,0 +1,158 @@
+//! Catching stat keys that drift mid-match.
+//!
+//! The daemon keys a match's timeline by stat name, so a pack that emits
+//! `"gold"` one poll and `"goldTotal"` the next splits one stat into two
+//! half-empty ones. [`KeyConsistencyChecker`] remembers the keys each match
+//! has emitted and reports a key that first shows up after the set settled.
+//!
+//! It is a diagnostic aid, not enforcement: nothing is rejected, and a stat
+//! that legitimately appears late (e.g. one only reported after the first
+//! objective) is reported once too. Feed it what you emit and warn about
+//! what it finds:
+//!
+//! ```rust
+//! use gamepack_runtime::consistency::KeyConsistencyChecker;
+//! use serde_json::json;
+//! use std::collections::HashMap;
+//!
+//! let mut checker = KeyConsistencyChecker::new().with_grace_polls(1);
+//! let poll = |key: &str| HashMap::from([(key.to_string(), json!(100))]);
+//!
+//! assert!(checker.observe(0, "m1", &poll("gold")).is_empty());
+//! assert!(checker.observe(0, "m1", &poll("gold")).is_empty());
+//! let drifted = checker.observe(0, "m1", &poll("goldTotal"));
+//! assert_eq!(drifted, vec!["goldTotal"]);
+//! // In a handler: gamepack_runtime::warn(format!("stat keys drifted: {:?}", drifted));
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+/// Default for [`KeyConsistencyChecker::with_grace_polls`].
+pub const DEFAULT_GRACE_POLLS: usize = 3;
+
+/// Keys seen so far in one match.
+#[derive(Debug, Default)]
+struct MatchKeys {
+    keys: HashSet<String>,
+    /// Observations in a row that added no key
+    quiet: usize,
+}
+
+/// Per-match record of emitted stat keys; see the
+/// [module documentation](self).
+///
+/// A match's key set counts as settled once
+/// [`grace_polls`](Self::with_grace_polls) observations in a row brought no
+/// new key. Until then new keys are simply recorded, since a pack's first
+/// polls often fill in gradually.
+#[derive(Debug)]
+pub struct KeyConsistencyChecker {
+    grace_polls: usize,
+    matches: HashMap<(u8, String), MatchKeys>,
+}
+
+impl Default for KeyConsistencyChecker {
+    fn default() -> Self {
+        Self {
+            grace_polls: DEFAULT_GRACE_POLLS,
+            matches: HashMap::new(),
+        }
+    }
+}
+
+impl KeyConsistencyChecker {
+    /// Create a checker with the default grace period.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consider a match's keys settled after `polls` observations in a row
+    /// without a new key.
+    pub fn with_grace_polls(mut self, polls: usize) -> Self {
+        self.grace_polls = polls;
+        self
+    }
+
+    /// Record the keys of one statistics emit and return, sorted, the ones
+    /// that are new although the match's keys had settled.
+    ///
+    /// Each drifting key is reported once; afterwards it is part of the
+    /// match's known keys.
+    pub fn observe(
+        &mut self,
+        subpack: u8,
+        external_match_id: &str,
+        stats: &HashMap<String, Value>,
+    ) -> Vec<String> {
+        let seen = self
+            .matches
+            .entry((subpack, external_match_id.to_string()))
+            .or_default();
+        let mut new: Vec<String> = stats
+            .keys()
+            .filter(|key| !seen.keys.contains(*key))
+            .cloned()
+            .collect();
+        if new.is_empty() {
+            seen.quiet += 1;
+            return new;
+        }
+
+        seen.keys.extend(new.iter().cloned());
+        if seen.quiet < self.grace_polls {
+            seen.quiet = 0;
+            return vec![];
+        }
+        new.sort();
+        new
+    }
+
+    /// Forget a finished match.
+    pub fn end_match(&mut self, subpack: u8, external_match_id: &str) {
+        self.matches.remove(&(subpack, external_match_id.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stats(keys: &[&str]) -> HashMap<String, Value> {
+        keys.iter().map(|key| (key.to_string(), json!(1))).collect()
+    }
+
+    #[test]
+    fn consistent_stream_reports_nothing() {
+        let mut checker = KeyConsistencyChecker::new();
+
+        // Keys filling in over the first polls are part of settling
+        for keys in [&["kills"][..], &["kills", "gold"], &["kills", "gold", "cs"]] {
+            assert!(checker.observe(0, "m1", &stats(keys)).is_empty());
+        }
+        for _ in 0..20 {
+            assert!(checker.observe(0, "m1", &stats(&["kills", "gold", "cs"])).is_empty());
+        }
+        // Fewer keys than usual is not drift
+        assert!(checker.observe(0, "m1", &stats(&["kills"])).is_empty());
+    }
+
+    #[test]
+    fn drifting_key_is_reported_once_per_match() {
+        let mut checker = KeyConsistencyChecker::new().with_grace_polls(2);
+        for _ in 0..3 {
+            checker.observe(0, "m1", &stats(&["kills", "gold"]));
+        }
+
+        let drifted = checker.observe(0, "m1", &stats(&["kills", "goldTotal", "cs"]));
+        assert_eq!(drifted, vec!["cs", "goldTotal"]);
+        assert!(checker.observe(0, "m1", &stats(&["kills", "goldTotal"])).is_empty());
+
+        // Other matches and subpacks settle on their own
+        assert!(checker.observe(1, "m1", &stats(&["goldTotal"])).is_empty());
+        checker.end_match(0, "m1");
+        assert!(checker.observe(0, "m1", &stats(&["kills", "gold"])).is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+//! Debugging helpers for inspecting the protocol stream.
+//!
+//! The wire format is strictly single-line NDJSON. These helpers are for
+//! external tooling (log viewers, support scripts) and are never used on the
+//! write path.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::commands::GamepackCommand;
+use crate::responses::GamepackResponse;
+
+/// Pretty-print a single protocol line for humans.
+///
+/// The line is parsed as a [`GamepackCommand`], then as a
+/// [`GamepackResponse`], and re-serialized as indented JSON. Lines that are
+/// valid JSON but not a known message are pretty-printed as-is; anything else
+/// is returned unchanged so a viewer never loses input.
+pub fn pretty(line: &str) -> String {
+    let line = line.trim_end_matches(['\r', '\n']);
+
+    let rendered = if let Ok(cmd) = serde_json::from_str::<GamepackCommand>(line) {
+        serde_json::to_string_pretty(&cmd)
+    } else if let Ok(response) = serde_json::from_str::<GamepackResponse>(line) {
+        serde_json::to_string_pretty(&response)
+    } else if let Ok(value) = serde_json::from_str::<serde_json::Value>(line) {
+        serde_json::to_string_pretty(&value)
+    } else {
+        return line.to_string();
+    };
+
+    rendered.unwrap_or_else(|_| line.to_string())
+}
+
+/// Serialize `value` as compact JSON with every object's keys sorted,
+/// recursively.
+///
+/// Two logically equal values always produce the same string, whatever order
+/// their `HashMap`s iterate in, so the result is suitable for golden files,
+/// cross-language fixtures and hashing. It is not the wire format: the
+/// runner and emitter write plain `serde_json` output.
+///
+/// Backs `to_canonical_json` on [`GamepackCommand`], [`GamepackResponse`]
+/// and [`MatchDataMessage`](crate::MatchDataMessage).
+pub fn canonical_json<T: Serialize + ?Sized>(value: &T) -> String {
+    let value = serde_json::to_value(value).expect("protocol types serialize to JSON");
+    sort_keys(value).to_string()
+}
+
+/// Rebuild objects in key order, so the result is sorted even if
+/// `serde_json`'s `preserve_order` feature is enabled somewhere in the build.
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MatchDataMessage, SummarySource};
+    use std::collections::HashMap;
+
+    fn assert_same_json(a: &str, b: &str) {
+        let a: Value = serde_json::from_str(a).unwrap();
+        let b: Value = serde_json::from_str(b).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn pretty_prints_command_line() {
+        let line = r#"{"type":"is_match_in_progress","request_id":"r1","subpack":0,"external_match_id":"abc"}"#;
+        let out = pretty(line);
+
+        assert!(out.contains('\n'));
+        assert!(out.contains("  \"type\": \"is_match_in_progress\""));
+        assert_same_json(line, &out);
+    }
+
+    #[test]
+    fn pretty_prints_response_line() {
+        let line = r#"{"type":"write_match_data","message":{"type":"set_complete","subpack":0,"external_match_id":"abc","summary_source":"api"}}"#;
+        let out = pretty(line);
+
+        assert!(out.contains('\n'));
+        assert_same_json(line, &out);
+    }
+
+    #[test]
+    fn pretty_prints_unknown_json_as_is() {
+        let line = r#"{"type":"from_the_future","x":1}"#;
+        let out = pretty(line);
+
+        assert!(out.contains('\n'));
+        assert_same_json(line, &out);
+    }
+
+    #[test]
+    fn pretty_returns_non_json_unchanged() {
+        assert_eq!(pretty("not json at all\n"), "not json at all");
+    }
+
+    fn stats(keys: &[&str]) -> HashMap<String, Value> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| (key.to_string(), serde_json::json!({"z": i, "a": [i]})))
+            .collect()
+    }
+
+    #[test]
+    fn canonical_json_ignores_insertion_order() {
+        let forward = stats(&["kills", "deaths", "assists", "cs", "gold"]);
+        let mut backward = HashMap::new();
+        for key in ["gold", "cs", "assists", "deaths", "kills"] {
+            backward.insert(key.to_string(), forward[key].clone());
+        }
+
+        let a = MatchDataMessage::set_complete_with_stats(0, "m1", SummarySource::Api, forward);
+        let b = MatchDataMessage::set_complete_with_stats(0, "m1", SummarySource::Api, backward);
+
+        assert_eq!(a.to_canonical_json(), b.to_canonical_json());
+        let response = GamepackResponse::WriteMatchData {
+            message: a,
+            source: None,
+            connection_id: None,
+        };
+        assert_eq!(
+            response.to_canonical_json(),
+            GamepackResponse::WriteMatchData {
+                message: b,
+                source: None,
+                connection_id: None,
+            }
+            .to_canonical_json()
+        );
+    }
+
+    #[test]
+    fn canonical_json_sorts_keys_recursively() {
+        let cmd = GamepackCommand::RestoreState {
+            request_id: "r".into(),
+            state: serde_json::json!({"b": {"y": 1, "x": 2}, "a": null}),
+        };
+
+        assert_eq!(
+            cmd.to_canonical_json(),
+            r#"{"request_id":"r","state":{"a":null,"b":{"x":2,"y":1}},"type":"restore_state"}"#
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Derived (computed) statistics.
+//!
+//! Some stats are pure functions of others, e.g. KDA = `(kills + assists) /
+//! deaths`. Rather than storing them redundantly, a pack declares them as
+//! [`DerivedStatSpec`]s (returned from
+//! [`GamepackHandler::derived_stats`](crate::GamepackHandler::derived_stats))
+//! and the daemon computes them from the base stats it already has. Packs
+//! that prefer to precompute can run the same evaluator with
+//! [`eval_derived`].
+//!
+//! # Expressions
+//!
+//! Expressions are a small arithmetic language over base stat keys:
+//!
+//! - numbers: `2`, `0.5`
+//! - stat keys: identifiers made of ASCII letters, digits and `_`, not
+//!   starting with a digit (`kills`, `cs_total`)
+//! - `+`, `-`, `*`, `/` with the usual precedence, left-associative
+//! - unary `-` and parentheses
+//!
+//! A derived stat is undefined (JSON `null`) when a referenced key is missing
+//! or not numeric, when it divides by zero, or when its expression does not
+//! parse. Derived stats cannot reference each other.
+
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::handler::{GamepackError, GamepackResult};
+
+/// Declaration of a stat computed from base stats.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DerivedStatSpec {
+    /// Stat key the result is stored under (e.g. `"kda"`)
+    pub name: String,
+    /// Formula over base stat keys (e.g. `"(kills + assists) / deaths"`)
+    pub expression: String,
+}
+
+impl DerivedStatSpec {
+    /// Declare a derived stat.
+    pub fn new(name: impl Into<String>, expression: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            expression: expression.into(),
+        }
+    }
+
+    /// Check that the expression parses.
+    ///
+    /// Fails with code `"invalid_expression"`. Worth calling from a test so a
+    /// typo is caught before the daemon silently stores `null`.
+    pub fn validate(&self) -> GamepackResult<()> {
+        parse(&self.expression).map(|_| ())
+    }
+
+    /// Evaluate against `base_stats`, or `None` if the result is undefined.
+    pub fn evaluate(&self, base_stats: &HashMap<String, Value>) -> Option<f64> {
+        parse(&self.expression).ok()?.eval(base_stats)
+    }
+}
+
+/// Evaluate every spec against `base_stats`.
+///
+/// Every spec name appears in the result; undefined results are
+/// `Value::Null`. Results are always floats, so `kills / 1` yields `3.0`.
+pub fn eval_derived(
+    specs: &[DerivedStatSpec],
+    base_stats: &HashMap<String, Value>,
+) -> HashMap<String, Value> {
+    specs
+        .iter()
+        .map(|spec| {
+            let value = spec
+                .evaluate(base_stats)
+                .and_then(serde_json::Number::from_f64)
+                .map_or(Value::Null, Value::Number);
+            (spec.name.clone(), value)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Num(f64),
+    Key(String),
+    Neg(Box<Expr>),
+    Bin(BinOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, base_stats: &HashMap<String, Value>) -> Option<f64> {
+        match self {
+            Self::Num(n) => Some(*n),
+            Self::Key(key) => base_stats.get(key)?.as_f64(),
+            Self::Neg(inner) => inner.eval(base_stats).map(|n| -n),
+            Self::Bin(op, lhs, rhs) => {
+                let (a, b) = (lhs.eval(base_stats)?, rhs.eval(base_stats)?);
+                let result = match op {
+                    BinOp::Add => a + b,
+                    BinOp::Sub => a - b,
+                    BinOp::Mul => a * b,
+                    BinOp::Div if b == 0.0 => return None,
+                    BinOp::Div => a / b,
+                };
+                result.is_finite().then_some(result)
+            }
+        }
+    }
+}
+
+fn parse(source: &str) -> GamepackResult<Expr> {
+    let mut parser = Parser {
+        source,
+        chars: source.char_indices().peekable(),
+    };
+    let expr = parser.sum()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some((at, c)) => Err(parser.error(at, &format!("unexpected '{}'", c))),
+    }
+}
+
+/// Recursive-descent parser; one method per precedence level.
+struct Parser<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    /// Next non-whitespace character, without consuming it.
+    fn peek(&mut self) -> Option<(usize, char)> {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        self.chars.peek().copied()
+    }
+
+    fn sum(&mut self) -> GamepackResult<Expr> {
+        let mut expr = self.product()?;
+        while let Some((_, c @ ('+' | '-'))) = self.peek() {
+            self.chars.next();
+            let op = if c == '+' { BinOp::Add } else { BinOp::Sub };
+            expr = Expr::Bin(op, Box::new(expr), Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> GamepackResult<Expr> {
+        let mut expr = self.unary()?;
+        while let Some((_, c @ ('*' | '/'))) = self.peek() {
+            self.chars.next();
+            let op = if c == '*' { BinOp::Mul } else { BinOp::Div };
+            expr = Expr::Bin(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> GamepackResult<Expr> {
+        if let Some((_, '-')) = self.peek() {
+            self.chars.next();
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> GamepackResult<Expr> {
+        match self.peek() {
+            Some((_, '(')) => {
+                self.chars.next();
+                let expr = self.sum()?;
+                match self.peek() {
+                    Some((_, ')')) => {
+                        self.chars.next();
+                        Ok(expr)
+                    }
+                    Some((at, _)) => Err(self.error(at, "expected ')'")),
+                    None => Err(self.error(self.source.len(), "expected ')'")),
+                }
+            }
+            Some((start, c)) if c.is_ascii_digit() || c == '.' => {
+                let text = self.take_while(start, |c| c.is_ascii_digit() || c == '.');
+                text.parse()
+                    .map(Expr::Num)
+                    .map_err(|_| self.error(start, "invalid number"))
+            }
+            Some((start, c)) if c.is_ascii_alphabetic() || c == '_' => {
+                let text = self.take_while(start, |c| c.is_ascii_alphanumeric() || c == '_');
+                Ok(Expr::Key(text.to_string()))
+            }
+            Some((at, c)) => Err(self.error(at, &format!("unexpected '{}'", c))),
+            None => Err(self.error(self.source.len(), "unexpected end of expression")),
+        }
+    }
+
+    /// Consume characters matching `pred` and return the slice from `start`.
+    fn take_while(&mut self, start: usize, pred: impl Fn(char) -> bool) -> &str {
+        let mut end = start;
+        while let Some((at, c)) = self.chars.next_if(|(_, c)| pred(*c)) {
+            end = at + c.len_utf8();
+        }
+        &self.source[start..end]
+    }
+
+    fn error(&self, at: usize, reason: &str) -> GamepackError {
+        GamepackError::with_code(
+            format!("Invalid expression '{}' at {}: {}", self.source, at, reason),
+            "invalid_expression",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stats(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn evaluates_formulas_with_precedence() {
+        let base = stats(json!({"kills": 6, "deaths": 2, "assists": 4, "cs": 150, "mins": 10.0}));
+        let cases = [
+            ("(kills + assists) / deaths", 5.0),
+            ("kills + assists / deaths", 8.0),
+            ("cs / mins", 15.0),
+            ("-kills * 2 - -1", -11.0),
+            ("0.5 * (kills - deaths)", 2.0),
+        ];
+
+        for (expression, expected) in cases {
+            let spec = DerivedStatSpec::new("x", expression);
+            assert_eq!(spec.evaluate(&base), Some(expected), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn division_by_zero_is_null() {
+        let base = stats(json!({"kills": 3, "assists": 1, "deaths": 0}));
+        let specs = [
+            DerivedStatSpec::new("kda", "(kills + assists) / deaths"),
+            DerivedStatSpec::new("kills_per_life", "kills / (deaths + 1)"),
+        ];
+
+        let derived = eval_derived(&specs, &base);
+
+        assert_eq!(derived["kda"], Value::Null);
+        assert_eq!(derived["kills_per_life"], json!(3.0));
+    }
+
+    #[test]
+    fn missing_or_non_numeric_keys_are_null() {
+        let base = stats(json!({"kills": 3, "champion": "Ahri"}));
+        let specs = [
+            DerivedStatSpec::new("a", "kills / deaths"),
+            DerivedStatSpec::new("b", "champion * 2"),
+            DerivedStatSpec::new("c", "kills +"),
+        ];
+
+        let derived = eval_derived(&specs, &base);
+
+        assert_eq!(derived.len(), 3);
+        assert!(derived.values().all(Value::is_null));
+    }
+
+    #[test]
+    fn validate_rejects_malformed_expressions() {
+        for expression in ["", "kills +", "(kills", "kills)", "1.2.3", "kills % 2"] {
+            let err = DerivedStatSpec::new("x", expression).validate().unwrap_err();
+            assert_eq!(err.code.as_deref(), Some("invalid_expression"), "{}", expression);
+        }
+        assert!(DerivedStatSpec::new("x", " ( a_1 + _b ) ").validate().is_ok());
+    }
+}
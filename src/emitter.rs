@@ -0,0 +1,2164 @@
+//! Emission of unsolicited messages from gamepacks to the daemon.
+//!
+//! An [`Emitter`] decides *what* gets written (serialization, framing) while
+//! an [`EmitSink`] decides *where* it goes. In production the sink is stdout;
+//! in tests a [`CapturingSink`](crate::testing::CapturingSink) records every
+//! message so packs can assert on their emission logic without real IO.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::clock::Clock;
+use crate::metrics::Metrics;
+use crate::responses::GamepackResponse;
+use crate::stats::StatsAccumulator;
+use crate::types::{GameEvent, MatchDataMessage, Moment, SummarySource, Timestamp};
+
+/// Global stdout lock for thread-safe message emission.
+/// This is used by [`StdoutSink`] to send unsolicited messages.
+static STDOUT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Process-wide emitter used by the free `emit_*` functions.
+static GLOBAL_EMITTER: OnceLock<Emitter> = OnceLock::new();
+
+/// Environment variable that enables source tagging on the global emitter.
+pub const SOURCE_TAGS_ENV: &str = "GAMEPACK_DEBUG_SOURCES";
+
+thread_local! {
+    /// Source label for messages emitted from the current thread.
+    static THREAD_SOURCE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Error returned when a message could not be emitted.
+#[derive(Debug, thiserror::Error)]
+pub enum EmitError {
+    /// The message could not be serialized to JSON.
+    #[error("failed to serialize message: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    /// The sink failed to write the message.
+    #[error("failed to write message: {0}")]
+    Io(#[from] io::Error),
+
+    /// The emitter was closed (the pack is shutting down).
+    #[error("emitter is closed")]
+    Closed,
+
+    /// The message needs a protocol feature the daemon did not negotiate.
+    #[error("protocol feature not negotiated: {0}")]
+    NotNegotiated(&'static str),
+
+    /// `SetComplete` for a match that never had a write through this emitter.
+    ///
+    /// Only returned when the [complete guard](Emitter::with_complete_guard)
+    /// is enabled.
+    #[error("match {external_match_id} (subpack {subpack}) completed without any write")]
+    CompleteWithoutWrite {
+        /// Subpack index
+        subpack: u8,
+        /// Match that was never written
+        external_match_id: String,
+    },
+
+    /// A low-priority message was dropped by the
+    /// [rate limit](Emitter::with_max_emit_rate).
+    #[error("emit rate limit exceeded")]
+    RateLimited,
+}
+
+/// Destination for emitted messages.
+///
+/// Implementations receive both the structured response and its serialized
+/// NDJSON line (without the trailing newline), and must be safe to call from
+/// any thread.
+pub trait EmitSink: Send + Sync {
+    /// Write a single message.
+    fn emit(&self, response: &GamepackResponse, line: &str) -> io::Result<()>;
+
+    /// Push out anything buffered. Called by the runner before it exits.
+    ///
+    /// Default implementation does nothing, for sinks that write through.
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sink that writes each message as a line to stdout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutSink;
+
+impl EmitSink for StdoutSink {
+    fn emit(&self, _response: &GamepackResponse, line: &str) -> io::Result<()> {
+        let _lock = STDOUT_LOCK.lock();
+        let mut stdout = std::io::stdout();
+        writeln!(stdout, "{}", line)?;
+        stdout.flush()
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let _lock = STDOUT_LOCK.lock();
+        std::io::stdout().flush()
+    }
+}
+
+/// Counters describing everything an [`Emitter`] has sent since creation.
+///
+/// Returned by [`Emitter::stats`]. Useful for self-monitoring, e.g. logging
+/// "emitted 10k stat messages this match" or detecting runaway emission.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmitStats {
+    /// `WriteStatistics` messages written
+    pub statistics_emitted: u64,
+    /// Individual game events written (across all `WriteGameEvents` messages)
+    pub events_emitted: u64,
+    /// Individual moments written (across all `WriteMoments` messages)
+    pub moments_emitted: u64,
+    /// Bytes handed to the sink, including each line's trailing newline
+    pub bytes_written: u64,
+    /// Messages that failed to serialize or write, or were emitted after close
+    pub dropped: u64,
+    /// Messages discarded because their match is [muted](Emitter::is_muted)
+    pub muted: u64,
+    /// Messages dropped by the [rate limit](Emitter::with_max_emit_rate)
+    pub rate_limited: u64,
+}
+
+/// Lock-free counters shared by all clones of an emitter.
+#[derive(Debug, Default)]
+struct EmitCounters {
+    statistics_emitted: AtomicU64,
+    events_emitted: AtomicU64,
+    moments_emitted: AtomicU64,
+    bytes_written: AtomicU64,
+    dropped: AtomicU64,
+    muted: AtomicU64,
+    rate_limited: AtomicU64,
+}
+
+impl EmitCounters {
+    fn record_written(&self, response: &GamepackResponse, bytes: usize) {
+        if let GamepackResponse::WriteMatchData { message, .. } = response {
+            match message {
+                MatchDataMessage::WriteStatistics { .. } => {
+                    self.statistics_emitted.fetch_add(1, Ordering::Relaxed);
+                }
+                MatchDataMessage::WriteGameEvents { events, .. } => {
+                    self.events_emitted.fetch_add(events.len() as u64, Ordering::Relaxed);
+                }
+                MatchDataMessage::WriteMoments { moments, .. } => {
+                    self.moments_emitted.fetch_add(moments.len() as u64, Ordering::Relaxed);
+                }
+                MatchDataMessage::SetComplete { .. } | MatchDataMessage::SetAbandoned { .. } => {}
+            }
+        }
+        self.bytes_written.fetch_add(bytes as u64 + 1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> EmitStats {
+        EmitStats {
+            statistics_emitted: self.statistics_emitted.load(Ordering::Relaxed),
+            events_emitted: self.events_emitted.load(Ordering::Relaxed),
+            moments_emitted: self.moments_emitted.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            muted: self.muted.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A subpack's match changed without the old one being completed.
+///
+/// Reported by [`Emitter::take_match_switches`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchSwitch {
+    /// Subpack index
+    pub subpack: u8,
+    /// Match that was still in progress
+    pub old_external_match_id: String,
+    /// Match that replaced it
+    pub new_external_match_id: String,
+}
+
+/// `(subpack, external_match_id)`
+type MatchKey = (u8, String);
+
+/// Most match switches queued for [`Emitter::take_match_switches`]; older
+/// ones are dropped.
+const MAX_PENDING_SWITCHES: usize = 64;
+
+/// Per-subpack match tracking for switch detection.
+#[derive(Debug, Default)]
+struct MatchTracker {
+    /// Match currently being written to, per subpack
+    active: HashMap<u8, String>,
+    /// Match last completed (explicitly or by a detected switch), per
+    /// subpack
+    completed: HashMap<u8, String>,
+    /// Switches not yet taken by the runner, oldest first
+    switches: VecDeque<MatchSwitch>,
+}
+
+impl MatchTracker {
+    /// Record a message about to be written. Returns the `SetComplete` to
+    /// emit first if it starts a new match while another is still active.
+    fn observe(&mut self, message: &MatchDataMessage) -> Option<MatchDataMessage> {
+        let subpack = message.subpack();
+        let id = message.external_match_id();
+
+        if message.ends_match() {
+            if self.active.get(&subpack).is_some_and(|active| active == id) {
+                self.active.remove(&subpack);
+            }
+            self.completed.insert(subpack, id.to_string());
+            return None;
+        }
+
+        // Late writes to a finished match are not a switch back to it
+        if self.completed.get(&subpack).is_some_and(|completed| completed == id) {
+            return None;
+        }
+
+        match self.active.insert(subpack, id.to_string()) {
+            Some(old) if old != id => {
+                self.completed.insert(subpack, old.clone());
+                if self.switches.len() == MAX_PENDING_SWITCHES {
+                    self.switches.pop_front();
+                }
+                self.switches.push_back(MatchSwitch {
+                    subpack,
+                    old_external_match_id: old.clone(),
+                    new_external_match_id: id.to_string(),
+                });
+                Some(MatchDataMessage::set_complete(subpack, old, SummarySource::LiveFallback))
+            }
+            _ => None,
+        }
+    }
+
+    /// Forget one match: it is neither active nor completed afterwards.
+    fn forget(&mut self, key: &MatchKey) {
+        if self.active.get(&key.0) == Some(&key.1) {
+            self.active.remove(&key.0);
+        }
+        if self.completed.get(&key.0) == Some(&key.1) {
+            self.completed.remove(&key.0);
+        }
+    }
+}
+
+/// Handle for emitting unsolicited messages to the daemon.
+///
+/// Cheap to clone; clones share the same sink, counters and closed state.
+/// Give one to your handler (or to background threads) and call the `emit_*`
+/// methods instead of the free functions when you want the emission to be
+/// testable:
+///
+/// ```rust,ignore
+/// use gamepack_runtime::{Emitter, Moment};
+///
+/// struct MyGame { emitter: Emitter }
+///
+/// // Production
+/// let game = MyGame { emitter: Emitter::stdout() };
+///
+/// // Tests
+/// let (emitter, sink) = gamepack_runtime::testing::capturing_emitter();
+/// let game = MyGame { emitter };
+/// ```
+#[derive(Clone)]
+pub struct Emitter {
+    sink: Arc<dyn EmitSink>,
+    counters: Arc<EmitCounters>,
+    /// Emits hold the read lock while writing, so `close` waits for them
+    closed: Arc<RwLock<bool>>,
+    /// What the runner learned from the daemon
+    session: Arc<SessionState>,
+    tag_sources: bool,
+    tag_connection: bool,
+    /// Present when match switch detection is enabled
+    matches: Option<Arc<Mutex<MatchTracker>>>,
+    /// Matches written so far; present when the complete guard is enabled
+    written: Option<Arc<Mutex<HashSet<MatchKey>>>>,
+    /// Present when the `played_at` guard is enabled
+    played_at: Option<Arc<PlayedAtGuard>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    on_serialize_error: Option<Arc<SerializeErrorCallback>>,
+    normalize_numbers: bool,
+    /// Present when a coalesce window is set
+    coalescer: Option<Arc<Coalescer>>,
+    /// Present when the null stat check is enabled
+    null_stats: Option<Arc<NullStatCheck>>,
+}
+
+type SerializeErrorCallback = dyn Fn(&EmitError) + Send + Sync;
+
+/// State of the [`played_at` guard](Emitter::with_played_at_guard).
+struct PlayedAtGuard {
+    clock: Box<dyn Clock>,
+    /// Matches whose first `WriteStatistics` has been written
+    started: Mutex<HashSet<MatchKey>>,
+}
+
+/// Session state the runner records on its emitter, shared by all clones.
+#[derive(Debug, Default)]
+struct SessionState {
+    /// Whether `live_data_patch` was negotiated; set at init
+    live_data_patches: AtomicBool,
+    /// Live data the daemon last received, the base for the next patch.
+    /// Shared by the runner's answers and pushed patches
+    live_data_base: Mutex<Option<serde_json::Value>>,
+    /// Session protocol version; set at init
+    negotiated_version: AtomicU32,
+    /// Subpack selected by `SetActiveSubpack`
+    active_subpack: AtomicU8,
+    /// Matches muted by `MuteMatch`
+    muted: Mutex<HashSet<MatchKey>>,
+    /// Id of the current run; set when the runner starts
+    connection_id: Mutex<Option<String>>,
+    /// Present when a maximum emit rate is set
+    rate_limit: Mutex<Option<TokenBucket>>,
+    /// Whether maps are written in sorted key order
+    stable_map_order: AtomicBool,
+}
+
+/// State of the [null stat check](Emitter::with_null_stat_check).
+#[derive(Debug, Default)]
+struct NullStatCheck {
+    /// Stats written with a value so far, per match
+    set: Mutex<HashMap<MatchKey, HashSet<String>>>,
+}
+
+impl NullStatCheck {
+    /// Record a message about to be written. Returns the stats it clears
+    /// although they were never set for the match.
+    fn observe(&self, message: &MatchDataMessage) -> Vec<String> {
+        let key = (message.subpack(), message.external_match_id().to_string());
+        let mut set = self.set.lock().unwrap();
+        if message.ends_match() {
+            set.remove(&key);
+            return vec![];
+        }
+        let MatchDataMessage::WriteStatistics { stats, .. } = message else {
+            return vec![];
+        };
+        let known = set.entry(key).or_default();
+        let unset: Vec<String> = message
+            .cleared_stats()
+            .into_iter()
+            .filter(|stat| !known.contains(*stat))
+            .map(str::to_string)
+            .collect();
+        for (stat, value) in stats {
+            if value.is_null() {
+                known.remove(stat);
+            } else {
+                known.insert(stat.clone());
+            }
+        }
+        unset
+    }
+}
+
+/// Statistics held back by [`Emitter::with_coalesce_window`].
+#[derive(Debug)]
+struct Coalescer {
+    window: Duration,
+    /// Pending writes in the order their first part was emitted, with when
+    /// that was
+    pending: Mutex<Vec<(Instant, MatchDataMessage)>>,
+}
+
+/// What statistics must share to be merged: subpack, match, player slot
+/// and game time.
+type CoalesceKey<'a> = (u8, &'a str, u8, u64);
+
+fn coalesce_key(message: &MatchDataMessage) -> Option<CoalesceKey<'_>> {
+    match message {
+        MatchDataMessage::WriteStatistics { game_time_secs, .. } => Some((
+            message.subpack(),
+            message.external_match_id(),
+            message.player_slot(),
+            game_time_secs.to_bits(),
+        )),
+        _ => None,
+    }
+}
+
+impl Coalescer {
+    /// Take `message` in and return what to write now, in order: writes
+    /// that are due, pending writes `message` must not overtake, and
+    /// `message` itself unless it was held back.
+    fn offer(&self, message: MatchDataMessage, now: Instant) -> Vec<MatchDataMessage> {
+        let mut pending = self.pending.lock().unwrap();
+        let game_time = |message: &MatchDataMessage| coalesce_key(message).map(|key| key.3);
+        let same_match = |held: &MatchDataMessage| {
+            held.subpack() == message.subpack()
+                && held.external_match_id() == message.external_match_id()
+        };
+        let release = |(since, held): &(Instant, MatchDataMessage)| {
+            now.duration_since(*since) >= self.window
+                || (same_match(held) && game_time(held) != game_time(&message))
+        };
+        let (mut out, kept): (Vec<_>, Vec<_>) = pending.drain(..).partition(release);
+        *pending = kept;
+
+        let Some(key) = coalesce_key(&message) else {
+            let mut out: Vec<_> = out.into_iter().map(|(_, held)| held).collect();
+            out.push(message);
+            return out;
+        };
+        match pending.iter_mut().find(|(_, held)| coalesce_key(held) == Some(key)) {
+            Some((_, held)) => merge_statistics(held, message),
+            None if self.window.is_zero() => out.push((now, message)),
+            None => pending.push((now, message)),
+        }
+        out.into_iter().map(|(_, held)| held).collect()
+    }
+
+    /// Remove pending writes, all of them or only those due at `now`.
+    fn take(&self, due_at: Option<Instant>) -> Vec<MatchDataMessage> {
+        let mut pending = self.pending.lock().unwrap();
+        let (out, kept): (Vec<_>, Vec<_>) = pending
+            .drain(..)
+            .partition(|(since, _)| {
+                due_at.is_none_or(|now| now.duration_since(*since) >= self.window)
+            });
+        *pending = kept;
+        out.into_iter().map(|(_, held)| held).collect()
+    }
+
+    /// Drop pending writes of one match, or of every match for `None`.
+    fn discard(&self, key: Option<&MatchKey>) {
+        self.pending.lock().unwrap().retain(|(_, held)| {
+            key.is_some_and(|(subpack, external_match_id)| {
+                held.subpack() != *subpack || held.external_match_id() != external_match_id
+            })
+        });
+    }
+}
+
+/// Merge a later part of a tick's statistics into the pending write: the
+/// later value wins for keys both contain, as do a later `played_at` and
+/// server time.
+fn merge_statistics(held: &mut MatchDataMessage, later: MatchDataMessage) {
+    if let (
+        MatchDataMessage::WriteStatistics {
+            played_at,
+            server_time_secs,
+            stats,
+            ..
+        },
+        MatchDataMessage::WriteStatistics {
+            played_at: later_played_at,
+            server_time_secs: later_server_time,
+            stats: later_stats,
+            ..
+        },
+    ) = (held, later)
+    {
+        stats.extend(later_stats);
+        if later_played_at.is_some() {
+            *played_at = later_played_at;
+        }
+        if later_server_time.is_some() {
+            *server_time_secs = later_server_time;
+        }
+    }
+}
+
+/// Token bucket behind [`Emitter::with_max_emit_rate`]. Holds up to one
+/// second's worth of tokens, so short bursts pass unthrottled.
+#[derive(Debug)]
+struct TokenBucket {
+    per_sec: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_sec: f64) -> Self {
+        Self {
+            per_sec,
+            tokens: per_sec.max(1.0),
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Take a token, or say how long until one is available.
+    fn take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_sec).min(self.per_sec.max(1.0));
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.per_sec))
+        }
+    }
+}
+
+/// How a message is treated once the emit rate limit is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Priority {
+    /// Superseded by the next one of its kind; dropped
+    Low,
+    /// Data that must not be lost; waits for the rate to allow it
+    Normal,
+    /// Never limited
+    High,
+}
+
+impl Priority {
+    fn of(response: &GamepackResponse) -> Self {
+        match response {
+            // Late answers to commands, e.g. from a background session end
+            _ if !response.request_id().is_empty() => Self::High,
+            GamepackResponse::Heartbeat { .. }
+            | GamepackResponse::WriteMatchData {
+                message: MatchDataMessage::WriteStatistics { .. },
+                ..
+            } => Self::Low,
+            GamepackResponse::Error { .. }
+            | GamepackResponse::Warning { .. }
+            | GamepackResponse::WriteMatchData {
+                message:
+                    MatchDataMessage::SetComplete { .. } | MatchDataMessage::SetAbandoned { .. },
+                ..
+            } => Self::High,
+            _ => Self::Normal,
+        }
+    }
+}
+
+impl std::fmt::Debug for Emitter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Emitter").finish_non_exhaustive()
+    }
+}
+
+impl Emitter {
+    /// Create an emitter writing to the given sink.
+    pub fn new(sink: impl EmitSink + 'static) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            counters: Arc::default(),
+            closed: Arc::default(),
+            session: Arc::new(SessionState {
+                negotiated_version: AtomicU32::new(crate::PROTOCOL_VERSION),
+                ..SessionState::default()
+            }),
+            tag_sources: false,
+            tag_connection: false,
+            matches: None,
+            written: None,
+            played_at: None,
+            metrics: None,
+            on_serialize_error: None,
+            normalize_numbers: false,
+            coalescer: None,
+            null_stats: None,
+        }
+    }
+
+    /// Reject `SetComplete` (or `SetAbandoned`) for matches this emitter
+    /// never wrote to.
+    ///
+    /// Catches pack bugs that would otherwise create an empty completed
+    /// match: such a `SetComplete` is dropped and reported as
+    /// [`EmitError::CompleteWithoutWrite`]. Advisory and off by default,
+    /// since a pack may legitimately complete a match written before a
+    /// restart (e.g. from `is_match_in_progress` during recovery).
+    pub fn with_complete_guard(mut self, enabled: bool) -> Self {
+        self.written = enabled.then(Arc::default);
+        self
+    }
+
+    /// Make sure the first `WriteStatistics` of each match carries
+    /// `played_at`, stamping it from `clock` when the pack left it out.
+    ///
+    /// The daemon creates the match row from that first write, and a
+    /// missing `played_at` leaves it without a start time. With the guard
+    /// on, a first write without one gets `clock.now()` filled in and a
+    /// warning on stderr; later writes for the match are passed through
+    /// unchanged. A first write that sets `played_at` itself is left alone.
+    ///
+    /// Opt-in: off by default, since it rewrites what the pack emitted. Pass
+    /// [`SystemClock`](crate::SystemClock) in production. Muted or failed
+    /// writes don't count as the first.
+    pub fn with_played_at_guard(mut self, clock: impl Clock + 'static) -> Self {
+        self.played_at = Some(Arc::new(PlayedAtGuard {
+            clock: Box::new(clock),
+            started: Mutex::default(),
+        }));
+        self
+    }
+
+    /// Warn on stderr when a `WriteStatistics` clears a stat (sets it to
+    /// `null`) that was never set for the match.
+    ///
+    /// Statistics are partial: an absent key leaves a field unchanged while
+    /// `null` clears it (see [`MatchDataMessage::WriteStatistics`]). Clearing
+    /// a field that holds nothing usually means the pack used `null` for
+    /// "unknown yet", where it should leave the key out. The message is
+    /// still written unchanged. Off by default; worth enabling in
+    /// development builds.
+    pub fn with_null_stat_check(mut self, enabled: bool) -> Self {
+        self.null_stats = enabled.then(Arc::default);
+        self
+    }
+
+    /// Detect matches that end without a `SetComplete`.
+    ///
+    /// Detection contract, per subpack: the first write (statistics, events
+    /// or moments) for a new `external_match_id` while another match is still
+    /// active counts as a switch. The emitter then writes
+    /// `SetComplete { summary_source: LiveFallback }` for the old match before
+    /// the new write, and queues a [`MatchSwitch`] for the runner, which
+    /// calls [`GamepackHandler::on_match_switch`](crate::GamepackHandler::on_match_switch).
+    ///
+    /// A match stops being active once it is completed; later writes to it
+    /// are passed through without triggering another switch. Only the last
+    /// completed match of each subpack is remembered: a write to an earlier
+    /// one starts it again.
+    ///
+    /// The runner only drains the [installed](Self::install_global) global
+    /// emitter, so enable detection on the emitter you install. On any other
+    /// emitter the handler is never told; drain the switches with
+    /// [`take_match_switches`](Self::take_match_switches). Only the latest
+    /// 64 are kept until then.
+    pub fn with_match_switch_detection(mut self, enabled: bool) -> Self {
+        self.matches = enabled.then(Arc::default);
+        self
+    }
+
+    /// Drain the match switches detected since the last call.
+    ///
+    /// Always empty unless match switch detection is enabled.
+    pub fn take_match_switches(&self) -> Vec<MatchSwitch> {
+        match &self.matches {
+            Some(matches) => std::mem::take(&mut matches.lock().unwrap().switches).into(),
+            None => vec![],
+        }
+    }
+
+    /// Matches written to and not yet completed, as
+    /// `(subpack, external_match_id)` sorted; at most one per subpack.
+    ///
+    /// Always empty unless match switch detection is enabled.
+    pub fn active_matches(&self) -> Vec<(u8, String)> {
+        let Some(matches) = &self.matches else {
+            return vec![];
+        };
+        let mut active: Vec<(u8, String)> = matches
+            .lock()
+            .unwrap()
+            .active
+            .iter()
+            .map(|(subpack, id)| (*subpack, id.clone()))
+            .collect();
+        active.sort();
+        active
+    }
+
+    /// Forget the per-match state this emitter keeps for one match.
+    ///
+    /// Afterwards the next write for the match is treated as its first:
+    /// the [complete guard](Self::with_complete_guard) no longer counts
+    /// earlier writes, the [`played_at` guard](Self::with_played_at_guard)
+    /// stamps the next statistics again, and
+    /// [match switch detection](Self::with_match_switch_detection) no longer
+    /// considers it active or completed. Switches already queued
+    /// for [`take_match_switches`](Self::take_match_switches) are kept.
+    /// Statistics still held by the
+    /// [coalesce window](Self::with_coalesce_window) are dropped, so they
+    /// cannot land on a reused id.
+    ///
+    /// Matches that end with a `SetComplete` or `SetAbandoned` need no
+    /// reset. Call this when a pack tracking several matches drops one
+    /// without ending it, or before writing a match again under a reused
+    /// `external_match_id` (e.g. a custom game restarted with the same id).
+    pub fn reset_match(&self, subpack: u8, external_match_id: &str) {
+        let key = (subpack, external_match_id.to_string());
+        if let Some(written) = &self.written {
+            written.lock().unwrap().remove(&key);
+        }
+        if let Some(guard) = &self.played_at {
+            guard.started.lock().unwrap().remove(&key);
+        }
+        if let Some(matches) = &self.matches {
+            matches.lock().unwrap().forget(&key);
+        }
+        if let Some(null_stats) = &self.null_stats {
+            null_stats.set.lock().unwrap().remove(&key);
+        }
+        if let Some(coalescer) = &self.coalescer {
+            coalescer.discard(Some(&key));
+        }
+    }
+
+    /// [`reset_match`](Self::reset_match) for every match, e.g. when the
+    /// game client restarts and all in-flight matches are lost.
+    pub fn reset_all(&self) {
+        if let Some(written) = &self.written {
+            written.lock().unwrap().clear();
+        }
+        if let Some(guard) = &self.played_at {
+            guard.started.lock().unwrap().clear();
+        }
+        if let Some(matches) = &self.matches {
+            let mut matches = matches.lock().unwrap();
+            matches.active.clear();
+            matches.completed.clear();
+        }
+        if let Some(null_stats) = &self.null_stats {
+            null_stats.set.lock().unwrap().clear();
+        }
+        if let Some(coalescer) = &self.coalescer {
+            coalescer.discard(None);
+        }
+    }
+
+    /// Attach the current thread's [source label](Self::set_source) to every
+    /// `WriteMatchData` this emitter writes.
+    ///
+    /// Off by default, so production output never carries the label.
+    pub fn with_source_tags(mut self, enabled: bool) -> Self {
+        self.tag_sources = enabled;
+        self
+    }
+
+    /// Attach the run's [connection id](crate::run_gamepack#connection-id) to
+    /// every `WriteMatchData` this emitter writes, so the daemon can discard
+    /// lines left over from a previous run.
+    ///
+    /// Off by default. Messages emitted before the runner starts carry no id.
+    pub fn with_connection_tags(mut self, enabled: bool) -> Self {
+        self.tag_connection = enabled;
+        self
+    }
+
+    /// Connection id of the run using this emitter, once the runner started.
+    pub fn connection_id(&self) -> Option<String> {
+        self.session.connection_id.lock().unwrap().clone()
+    }
+
+    /// Start the session of a run with id `connection_id`: reopen the
+    /// emitter if an earlier run [closed](Self::close) it, and forget that
+    /// run's patch base, active subpack and mutes. Features and protocol
+    /// version are negotiated afresh at `Init`; the rate limit and map order
+    /// are kept.
+    pub(crate) fn start_session(&self, connection_id: String) {
+        *self.closed.write().unwrap() = false;
+        let session = &self.session;
+        *session.live_data_base.lock().unwrap() = None;
+        session.active_subpack.store(0, Ordering::SeqCst);
+        session.muted.lock().unwrap().clear();
+        *session.connection_id.lock().unwrap() = Some(connection_id);
+    }
+
+    /// Report the serialized size of every message this emitter writes, and
+    /// of every response the runner writes when this is its emitter (the
+    /// [global](Self::install_global) one), to `metrics`.
+    pub fn with_metrics(mut self, metrics: impl Metrics + 'static) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// Emit at most `per_sec` messages per second on average, in bursts of
+    /// up to one second's worth, so a runaway poll loop cannot flood the
+    /// daemon. Applies to every clone; the runner sets it from
+    /// [`RunnerOptions::max_emit_rate`](crate::RunnerOptions::max_emit_rate).
+    ///
+    /// Once the rate is reached, messages are treated by priority:
+    ///
+    /// - `WriteStatistics` and `Heartbeat` are dropped. The emit returns
+    ///   [`EmitError::RateLimited`] and the drop is counted in
+    ///   [`EmitStats::rate_limited`]. The next heartbeat supersedes a dropped
+    ///   one, but statistics are partial (see
+    ///   [`MatchDataMessage::WriteStatistics`]): a key only the dropped write
+    ///   carried stays stale until a later write repeats it. Resend on the
+    ///   error when that matters.
+    /// - Events, moments, pushed `Events` and `LiveDataPatch` wait until the
+    ///   rate allows them, blocking the emitting thread. Losing them would
+    ///   lose data; a patch applies on top of the previous one. A push
+    ///   waits before it is diffed, so it uses up the rate even when
+    ///   nothing changed.
+    /// - `SetComplete`, `SetAbandoned`, `Error` and `Warning` are never
+    ///   limited and do not count against the rate. Neither are answers to
+    ///   commands written through the emitter, such as a `SessionEnded`
+    ///   from a background session end and its progress.
+    ///
+    /// A `per_sec` that is not a positive number turns the limit off.
+    pub fn with_max_emit_rate(self, per_sec: f64) -> Self {
+        self.set_max_emit_rate(per_sec);
+        self
+    }
+
+    /// Replace the rate limit; see [`with_max_emit_rate`](Self::with_max_emit_rate).
+    pub(crate) fn set_max_emit_rate(&self, per_sec: f64) {
+        let bucket = (per_sec > 0.0 && per_sec.is_finite()).then(|| TokenBucket::new(per_sec));
+        *self.session.rate_limit.lock().unwrap() = bucket;
+    }
+
+    /// Write statistics and final stats maps in sorted key order, so
+    /// logically equal messages produce byte-identical lines. Applies to
+    /// every clone; the runner sets it from
+    /// [`RunnerOptions::stable_map_order`](crate::RunnerOptions::stable_map_order).
+    /// See [`GamepackResponse::to_stable_line`].
+    pub fn with_stable_map_order(self) -> Self {
+        self.set_stable_map_order();
+        self
+    }
+
+    /// Merge `WriteStatistics` for the same match and game time, emitted
+    /// within `window` of each other, into one write.
+    ///
+    /// Meant for packs whose subsystems (say an economy and a combat
+    /// tracker) each emit part of a tick's stats. Emits are merged when they
+    /// share subpack, match, player slot and `game_time_secs`: the later
+    /// value wins for keys both contain (last writer wins), as do a later
+    /// `played_at` and server time.
+    ///
+    /// A pending write goes out, in the order its first part was emitted:
+    ///
+    /// - once `window` has passed since its first part, with the next emit
+    ///   from any clone or the next command the runner handles;
+    /// - when statistics for another game time of the same match arrive;
+    /// - before any other message for the same match, so events and
+    ///   `SetComplete` never overtake it;
+    /// - on [`flush`](Self::flush) and [`close`](Self::close).
+    ///
+    /// An emit that is held back returns `Ok`. If the later write fails, the
+    /// emit or flush that released it returns the error, and it is counted
+    /// in [`EmitStats::dropped`]. Clones share the pending writes. A zero
+    /// window writes everything at once, as without coalescing.
+    pub fn with_coalesce_window(mut self, window: Duration) -> Self {
+        self.coalescer = Some(Arc::new(Coalescer {
+            window,
+            pending: Mutex::default(),
+        }));
+        self
+    }
+
+    /// Write coalesced statistics whose window has passed; see
+    /// [`with_coalesce_window`](Self::with_coalesce_window).
+    pub(crate) fn emit_due_statistics(&self) -> Result<(), EmitError> {
+        match &self.coalescer {
+            Some(coalescer) => self.write_all(coalescer.take(Some(Instant::now()))),
+            None => Ok(()),
+        }
+    }
+
+    /// Write `messages` in order, returning the first error.
+    fn write_all(&self, messages: Vec<MatchDataMessage>) -> Result<(), EmitError> {
+        messages
+            .into_iter()
+            .map(|message| self.write_match_data(message))
+            .fold(Ok(()), Result::and)
+    }
+
+    /// Turn on sorted map order; see
+    /// [`with_stable_map_order`](Self::with_stable_map_order).
+    pub(crate) fn set_stable_map_order(&self) {
+        self.session.stable_map_order.store(true, Ordering::Relaxed);
+    }
+
+    /// Wait for, or refuse, a message under the rate limit.
+    fn throttle(&self, priority: Priority) -> Result<(), EmitError> {
+        if priority == Priority::High {
+            return Ok(());
+        }
+        loop {
+            let wait = match self.session.rate_limit.lock().unwrap().as_mut() {
+                None => return Ok(()),
+                Some(bucket) => match bucket.take() {
+                    Ok(()) => return Ok(()),
+                    Err(wait) => wait,
+                },
+            };
+            if priority == Priority::Low {
+                self.counters.rate_limited.fetch_add(1, Ordering::Relaxed);
+                return Err(EmitError::RateLimited);
+            }
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Call `callback` whenever a message fails to serialize, e.g. to log it.
+    ///
+    /// The emit still returns [`EmitError::Serialize`], but the free
+    /// functions ([`emit_statistics`](crate::emit_statistics) and friends)
+    /// discard it, and a runner response that fails has no caller to return
+    /// to. Without a callback these failures are written to stderr.
+    ///
+    /// Causes are values JSON cannot represent, mainly NaN or infinite
+    /// times (see [`GamepackResponse::to_line`]), and custom `Serialize`
+    /// impls in `data` payloads that return errors.
+    pub fn on_serialize_error(
+        mut self,
+        callback: impl Fn(&EmitError) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_serialize_error = Some(Arc::new(callback));
+        self
+    }
+
+    /// Normalize numbers in match data payloads before writing them, so
+    /// integers beyond 2^53 reach the daemon as the `f64` it would read them
+    /// as anyway, whichever serde_json features either side was built with.
+    /// See [`normalize_numbers`](crate::types::normalize_numbers).
+    ///
+    /// Off by default. Worth enabling when the pack (or a dependency)
+    /// enables serde_json's `arbitrary_precision` feature.
+    pub fn with_number_normalization(mut self, enabled: bool) -> Self {
+        self.normalize_numbers = enabled;
+        self
+    }
+
+    /// Report a serialization failure to the callback, or stderr.
+    pub(crate) fn report_serialize_error(&self, error: &EmitError) {
+        match &self.on_serialize_error {
+            Some(callback) => callback(error),
+            None => eprintln!("gamepack-runtime: {}", error),
+        }
+    }
+
+    /// Pass a serialized message's size to the metrics hook, if any.
+    pub(crate) fn record_bytes(&self, response: &GamepackResponse, bytes: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_bytes(crate::metrics::message_type(response), bytes);
+        }
+    }
+
+    /// Label messages emitted from the current thread, e.g. `"stats-poller"`.
+    ///
+    /// Only has a visible effect on emitters with source tagging enabled.
+    /// The label is per-thread, so each background worker sets its own.
+    pub fn set_source(name: impl Into<String>) {
+        THREAD_SOURCE.with(|source| *source.borrow_mut() = Some(name.into()));
+    }
+
+    /// Remove the current thread's source label.
+    pub fn clear_source() {
+        THREAD_SOURCE.with(|source| *source.borrow_mut() = None);
+    }
+
+    /// Create an emitter writing to stdout.
+    pub fn stdout() -> Self {
+        Self::new(StdoutSink)
+    }
+
+    /// Use this emitter as the process-wide [`global`](Self::global) one.
+    ///
+    /// Must be called before [`run_gamepack`](crate::run_gamepack) or any
+    /// free `emit_*` function. Returns the emitter back if the global one
+    /// was already initialized.
+    pub fn install_global(self) -> Result<(), Emitter> {
+        GLOBAL_EMITTER.set(self)
+    }
+
+    /// The process-wide stdout emitter used by the free `emit_*` functions.
+    ///
+    /// Source tagging is enabled when the [`SOURCE_TAGS_ENV`] environment
+    /// variable is set.
+    pub fn global() -> &'static Emitter {
+        GLOBAL_EMITTER.get_or_init(|| {
+            Emitter::stdout().with_source_tags(std::env::var_os(SOURCE_TAGS_ENV).is_some())
+        })
+    }
+
+    /// Counters accumulated since this emitter (or the one it was cloned
+    /// from) was created.
+    pub fn stats(&self) -> EmitStats {
+        self.counters.snapshot()
+    }
+
+    /// Stop emitting. Every later emit (from this emitter or any clone) is
+    /// dropped and returns [`EmitError::Closed`].
+    ///
+    /// Emits already being written when `close` is called finish first;
+    /// `close` returns once they have, so nothing from this emitter can be
+    /// written after it returns.
+    ///
+    /// The runner closes its emitter during `Shutdown`, so background threads
+    /// that outlive the handler cannot write to a pipe the daemon has closed,
+    /// or after `ShutdownComplete`. A later run in the same process reopens
+    /// it.
+    pub fn close(&self) {
+        if let Some(coalescer) = &self.coalescer {
+            let _ = self.write_all(coalescer.take(None));
+        }
+        *self.closed.write().unwrap() = true;
+    }
+
+    /// Write any [coalesced](Self::with_coalesce_window) statistics still
+    /// pending, then flush the underlying sink.
+    pub fn flush(&self) -> Result<(), EmitError> {
+        let pending = match &self.coalescer {
+            Some(coalescer) => self.write_all(coalescer.take(None)),
+            None => Ok(()),
+        };
+        self.sink.flush()?;
+        pending
+    }
+
+    /// Whether [`close`](Self::close) has been called.
+    pub fn is_closed(&self) -> bool {
+        *self.closed.read().unwrap()
+    }
+
+    /// Emit a match data message (unsolicited `WriteMatchData`).
+    pub fn emit_match_data(&self, mut message: MatchDataMessage) -> Result<(), EmitError> {
+        if self.normalize_numbers {
+            message.normalize_numbers();
+        }
+        match &self.coalescer {
+            Some(coalescer) if !self.is_closed() => {
+                self.write_all(coalescer.offer(message, Instant::now()))
+            }
+            _ => self.write_match_data(message),
+        }
+    }
+
+    /// Write a match data message now, past any coalescing.
+    fn write_match_data(&self, mut message: MatchDataMessage) -> Result<(), EmitError> {
+        let key = (message.subpack(), message.external_match_id().to_string());
+        let is_complete = message.ends_match();
+        let muted = self.session.muted.lock().unwrap().contains(&key);
+        if let Some(written) = &self.written {
+            // A muted match may legitimately have had every write dropped
+            if is_complete && !muted && !written.lock().unwrap().contains(&key) {
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                return Err(EmitError::CompleteWithoutWrite {
+                    subpack: key.0,
+                    external_match_id: key.1,
+                });
+            }
+        }
+
+        if let Some(matches) = &self.matches {
+            let complete_old = matches.lock().unwrap().observe(&message);
+            if let Some(complete_old) = complete_old {
+                self.emit_match_data(complete_old)?;
+            }
+        }
+
+        if muted && !is_complete {
+            self.counters.muted.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        if let Some(null_stats) = &self.null_stats {
+            let unset = null_stats.observe(&message);
+            if !unset.is_empty() {
+                eprintln!(
+                    "gamepack-runtime: statistics for match {} (subpack {}) clear {:?}, which \
+                     were never set; leave a key out to keep it unchanged",
+                    key.1, key.0, unset
+                );
+            }
+        }
+
+        let first_statistics = match (&self.played_at, &mut message) {
+            (Some(guard), MatchDataMessage::WriteStatistics { played_at, .. })
+                if !guard.started.lock().unwrap().contains(&key) =>
+            {
+                if played_at.is_none() {
+                    let now = guard.clock.now();
+                    eprintln!(
+                        "gamepack-runtime: first statistics for match {} (subpack {}) had no \
+                         played_at, using {}",
+                        key.1, key.0, now
+                    );
+                    *played_at = Some(now.to_string());
+                }
+                true
+            }
+            _ => false,
+        };
+
+        let source = if self.tag_sources {
+            THREAD_SOURCE.with(|source| source.borrow().clone())
+        } else {
+            None
+        };
+        let connection_id = if self.tag_connection {
+            self.connection_id()
+        } else {
+            None
+        };
+        self.emit_response(GamepackResponse::WriteMatchData {
+            message,
+            source,
+            connection_id,
+        })?;
+
+        if let Some(guard) = &self.played_at {
+            if first_statistics {
+                guard.started.lock().unwrap().insert(key.clone());
+            }
+        }
+        if let Some(written) = &self.written {
+            if !is_complete {
+                written.lock().unwrap().insert(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Protocol version negotiated at `Init`.
+    ///
+    /// Before negotiation (or for an emitter the runner doesn't use) this is
+    /// [`PROTOCOL_VERSION`](crate::PROTOCOL_VERSION).
+    pub fn negotiated_version(&self) -> u32 {
+        self.session.negotiated_version.load(Ordering::SeqCst)
+    }
+
+    /// Subpack the daemon last selected with `SetActiveSubpack` (0 until
+    /// then).
+    ///
+    /// Use it as the subpack for emits when the pack doesn't track the game
+    /// mode itself. It is only a default: every emit still names its subpack
+    /// explicitly, and that explicit value is what gets sent, even when it
+    /// differs from the active one.
+    pub fn active_subpack(&self) -> u8 {
+        self.session.active_subpack.load(Ordering::SeqCst)
+    }
+
+    /// Record the subpack selected by the daemon.
+    pub(crate) fn set_active_subpack(&self, subpack: u8) {
+        self.session.active_subpack.store(subpack, Ordering::SeqCst);
+    }
+
+    /// Whether the daemon muted a match with `MuteMatch`.
+    ///
+    /// While a match is muted, [`emit_match_data`](Self::emit_match_data)
+    /// discards its statistics, events and moments: the emit returns `Ok`
+    /// and counts the message in [`EmitStats::muted`], so poll loops need
+    /// no special casing. `SetComplete` and `SetAbandoned` are the exception
+    /// and are always written, so the daemon can still close the match. Packs may check
+    /// this to skip the work of building messages that would be dropped.
+    pub fn is_muted(&self, subpack: u8, external_match_id: &str) -> bool {
+        let key = (subpack, external_match_id.to_string());
+        self.session.muted.lock().unwrap().contains(&key)
+    }
+
+    /// Mute or unmute a match, as requested by the daemon.
+    pub(crate) fn set_muted(&self, subpack: u8, external_match_id: &str, muted: bool) {
+        let key = (subpack, external_match_id.to_string());
+        let mut set = self.session.muted.lock().unwrap();
+        if muted {
+            set.insert(key);
+        } else {
+            set.remove(&key);
+        }
+    }
+
+    /// Record the protocol version resolved at init.
+    pub(crate) fn set_negotiated_version(&self, version: u32) {
+        self.session.negotiated_version.store(version, Ordering::SeqCst);
+    }
+
+    /// Record whether the daemon accepted `live_data_patch` at init.
+    pub(crate) fn set_live_data_patches(&self, enabled: bool) {
+        self.session.live_data_patches.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Replace the live data the daemon holds, returning the previous one.
+    pub(crate) fn swap_live_data_base(
+        &self,
+        data: Option<serde_json::Value>,
+    ) -> Option<serde_json::Value> {
+        std::mem::replace(&mut *self.lock_live_data_base(), data)
+    }
+
+    /// Lock the live data the daemon holds. No patch is pushed until the
+    /// guard is dropped, so the runner holds it until its answer is written.
+    pub(crate) fn lock_live_data_base(&self) -> MutexGuard<'_, Option<serde_json::Value>> {
+        self.session.live_data_base.lock().unwrap()
+    }
+
+    /// Push changed live data as an unsolicited patch.
+    ///
+    /// `data` is the full new live data. It is diffed against the live data
+    /// the daemon holds (from the last `GetLiveData` answer or push), only
+    /// the difference is sent, and `data` becomes the base the runner's
+    /// next `GetLiveData` answer is diffed against. Nothing is sent if
+    /// nothing changed, if `data` is `null`, or while the daemon holds no
+    /// live data yet; the next `GetLiveData` then sends it in full.
+    ///
+    /// Fails with [`EmitError::NotNegotiated`] unless the daemon offered
+    /// `live_data_patch` at init; let `GetLiveData` carry the change then.
+    pub fn emit_live_data_patch(&self, data: &serde_json::Value) -> Result<(), EmitError> {
+        if !self.session.live_data_patches.load(Ordering::SeqCst) {
+            return Err(EmitError::NotNegotiated(crate::version::FEATURE_LIVE_DATA_PATCH));
+        }
+        // Wait for the rate limit first: the runner's live data answers
+        // need the base, so never sleep holding it
+        self.throttle(Priority::Normal)?;
+        // Held until written, so the runner's next answer is diffed against
+        // this push
+        let mut base = self.lock_live_data_base();
+        let Some(base) = base.as_mut().filter(|_| !data.is_null()) else {
+            return Ok(());
+        };
+        let ops = crate::patch::diff(base, data);
+        if ops.is_empty() {
+            return Ok(());
+        }
+        self.write_response(GamepackResponse::LiveDataPatch {
+            request_id: String::new(),
+            ops,
+        })?;
+        *base = data.clone();
+        Ok(())
+    }
+
+    /// Confirm that a match is still running and where its game clock is.
+    ///
+    /// Writes a `Heartbeat` stamped with the current wall-clock time. Send
+    /// one every 5 to 10 seconds of wall time while a match is in
+    /// progress, whether or not anything else was emitted. The daemon
+    /// compares consecutive heartbeats: if `game_time_secs` stops advancing
+    /// while wall time does, it may flag the match as frozen (a hung client,
+    /// or a pack reading stale data). Packs for games that can be paused
+    /// should keep sending heartbeats while paused; the daemon then sees a
+    /// frozen clock with live heartbeats, not a silent pack.
+    ///
+    /// Heartbeats for a [muted](Self::is_muted) match are dropped and
+    /// counted in [`EmitStats::muted`].
+    pub fn emit_heartbeat(
+        &self,
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        game_time_secs: f64,
+    ) -> Result<(), EmitError> {
+        let external_match_id = external_match_id.into();
+        if self.is_muted(subpack, &external_match_id) {
+            self.counters.muted.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        self.emit_response(GamepackResponse::Heartbeat {
+            subpack,
+            external_match_id,
+            game_time_secs,
+            wall_clock: Timestamp::now(),
+        })
+    }
+
+    /// Push game events as they happen instead of waiting for the next
+    /// `PollEvents`.
+    ///
+    /// Writes an unsolicited `Events` message with an empty `request_id`
+    /// that names the match, so it is safe to call from a background thread
+    /// watching the game. Pushing and polling can be mixed: the daemon
+    /// dedupes events by identity (type, timestamp and data), but a pack
+    /// that wants to keep the wire quiet can route both paths through a
+    /// [`PushedEvents`](crate::push::PushedEvents) so the next poll skips
+    /// what was already pushed.
+    ///
+    /// Events for a [muted](Self::is_muted) match are dropped and counted
+    /// in [`EmitStats::muted`].
+    pub fn emit_events(
+        &self,
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        events: Vec<GameEvent>,
+    ) -> Result<(), EmitError> {
+        let external_match_id = external_match_id.into();
+        if self.is_muted(subpack, &external_match_id) {
+            self.counters.muted.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        self.emit_response(GamepackResponse::Events {
+            request_id: String::new(),
+            events,
+            warnings: vec![],
+            subpack: Some(subpack),
+            external_match_id: Some(external_match_id),
+        })
+    }
+
+    /// Tell the daemon about a non-fatal problem outside of any command,
+    /// e.g. a game API that started returning partial data mid-match.
+    ///
+    /// The warning goes out with an empty `request_id`. Prefer
+    /// [`warn`](crate::warn) inside a handler method so the warning is tied
+    /// to the command being answered. Nothing is failed by a warning; use
+    /// an `Err` from the handler for that.
+    pub fn emit_warning(
+        &self,
+        message: impl Into<String>,
+        code: Option<String>,
+    ) -> Result<(), EmitError> {
+        self.emit_response(GamepackResponse::Warning {
+            request_id: String::new(),
+            message: message.into(),
+            code,
+        })
+    }
+
+    /// Emit statistics for a match. See [`emit_statistics`](crate::emit_statistics).
+    pub fn emit_statistics(
+        &self,
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        game_time_secs: f64,
+        stats: HashMap<String, serde_json::Value>,
+    ) -> Result<(), EmitError> {
+        self.emit_match_data(MatchDataMessage::write_statistics(
+            subpack,
+            external_match_id,
+            game_time_secs,
+            stats,
+        ))
+    }
+
+    /// Emit a [`StatsAccumulator`]'s full stat map if it changed since the
+    /// last emit, and remember it as emitted. Returns whether anything was
+    /// written. See the [`stats`](crate::stats) module.
+    pub fn emit_accumulated(
+        &self,
+        stats: &mut StatsAccumulator,
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        game_time_secs: f64,
+    ) -> Result<bool, EmitError> {
+        if !stats.has_changes() {
+            return Ok(false);
+        }
+        self.emit_match_data(MatchDataMessage::write_typed_statistics(
+            subpack,
+            external_match_id,
+            game_time_secs,
+            stats.current().clone(),
+        ))?;
+        stats.mark_emitted();
+        Ok(true)
+    }
+
+    /// Mark a match abandoned. See [`emit_abandoned`](crate::emit_abandoned).
+    pub fn emit_abandoned(
+        &self,
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        at_game_time_secs: f64,
+    ) -> Result<(), EmitError> {
+        self.emit_match_data(MatchDataMessage::set_abandoned(
+            subpack,
+            external_match_id,
+            at_game_time_secs,
+        ))
+    }
+
+    /// Emit game events for a match. See [`emit_game_events`](crate::emit_game_events).
+    pub fn emit_game_events(
+        &self,
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        events: Vec<GameEvent>,
+    ) -> Result<(), EmitError> {
+        self.emit_match_data(MatchDataMessage::write_game_events(
+            subpack,
+            external_match_id,
+            events,
+        ))
+    }
+
+    /// Emit moments for a match. See [`emit_moments`](crate::emit_moments).
+    pub fn emit_moments(
+        &self,
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        moments: Vec<Moment>,
+    ) -> Result<(), EmitError> {
+        self.emit_match_data(MatchDataMessage::write_moments(
+            subpack,
+            external_match_id,
+            moments,
+        ))
+    }
+
+    /// Write the late response to a command the runner answered off the
+    /// main loop.
+    pub(crate) fn emit_deferred_response(
+        &self,
+        response: GamepackResponse,
+    ) -> Result<(), EmitError> {
+        self.emit_response(response)
+    }
+
+    /// Serialize and write an arbitrary response through the sink.
+    fn emit_response(&self, response: GamepackResponse) -> Result<(), EmitError> {
+        // Before taking the lock, so a throttled emit does not hold up `close`
+        self.throttle(Priority::of(&response))?;
+        self.write_response(response)
+    }
+
+    /// [`emit_response`](Self::emit_response) for a response that already
+    /// passed the rate limit.
+    fn write_response(&self, response: GamepackResponse) -> Result<(), EmitError> {
+        // Held until the line is written so `close` cannot overtake us
+        let closed = self.closed.read().unwrap();
+        if *closed {
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+            return Err(EmitError::Closed);
+        }
+
+        let line = if self.session.stable_map_order.load(Ordering::Relaxed) {
+            response.to_stable_line()
+        } else {
+            response.to_line()
+        };
+        let result = line
+            .map_err(EmitError::from)
+            .and_then(|line| {
+                self.record_bytes(&response, line.len());
+                self.sink.emit(&response, &line)?;
+                Ok(line.len())
+            });
+
+        match result {
+            Ok(bytes) => {
+                self.counters.record_written(&response, bytes);
+                Ok(())
+            }
+            Err(e) => {
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                if matches!(e, EmitError::Serialize(_)) {
+                    self.report_serialize_error(&e);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patch::JsonPatchOp;
+    use crate::testing::capturing_emitter;
+    use crate::types::SummarySource;
+    use serde_json::json;
+
+    /// Sink whose writes always fail.
+    struct BrokenSink;
+
+    impl EmitSink for BrokenSink {
+        fn emit(&self, _response: &GamepackResponse, _line: &str) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+    }
+
+    #[test]
+    fn stats_count_each_message_kind() {
+        let (emitter, sink) = capturing_emitter();
+
+        emitter
+            .emit_statistics(0, "m1", 1.0, [("kills".to_string(), json!(1))].into())
+            .unwrap();
+        emitter
+            .emit_statistics(0, "m1", 2.0, [("kills".to_string(), json!(2))].into())
+            .unwrap();
+        emitter
+            .emit_game_events(
+                0,
+                "m1",
+                vec![
+                    GameEvent::new("Kill", 1.0, json!({})),
+                    GameEvent::new("Kill", 2.0, json!({})),
+                ],
+            )
+            .unwrap();
+        emitter
+            .emit_moments(0, "m1", vec![Moment::new("double_kill", 2.0, json!({}))])
+            .unwrap();
+        emitter
+            .emit_match_data(MatchDataMessage::set_complete(0, "m1", SummarySource::Api))
+            .unwrap();
+
+        let expected_bytes: usize = sink
+            .responses()
+            .iter()
+            .map(|r| serde_json::to_string(r).unwrap().len() + 1)
+            .sum();
+
+        assert_eq!(
+            emitter.stats(),
+            EmitStats {
+                statistics_emitted: 2,
+                events_emitted: 2,
+                moments_emitted: 1,
+                bytes_written: expected_bytes as u64,
+                dropped: 0,
+                muted: 0,
+                rate_limited: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn stats_are_shared_between_clones() {
+        let (emitter, _sink) = capturing_emitter();
+        let clone = emitter.clone();
+
+        clone.emit_moments(0, "m1", vec![]).unwrap();
+
+        assert_eq!(emitter.stats().bytes_written, clone.stats().bytes_written);
+        assert!(emitter.stats().bytes_written > 0);
+    }
+
+    #[test]
+    fn failed_writes_count_as_dropped() {
+        let emitter = Emitter::new(BrokenSink);
+
+        let result = emitter.emit_statistics(0, "m1", 1.0, HashMap::new());
+
+        assert!(matches!(result, Err(EmitError::Io(_))));
+        let stats = emitter.stats();
+        assert_eq!(stats.dropped, 1);
+        assert_eq!(stats.statistics_emitted, 0);
+        assert_eq!(stats.bytes_written, 0);
+    }
+
+    #[test]
+    fn closed_emitter_drops_messages_from_all_clones() {
+        let (emitter, sink) = capturing_emitter();
+        let background = emitter.clone();
+
+        emitter.close();
+        let result = background.emit_moments(0, "m1", vec![]);
+
+        assert!(matches!(result, Err(EmitError::Closed)));
+        assert!(background.is_closed());
+        assert!(sink.responses().is_empty());
+        assert_eq!(emitter.stats().dropped, 1);
+    }
+
+    fn sources(sink: &crate::testing::CapturingSink) -> Vec<Option<String>> {
+        sink.responses()
+            .into_iter()
+            .map(|response| match response {
+                GamepackResponse::WriteMatchData { source, .. } => source,
+                other => panic!("Expected WriteMatchData, got {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn source_tags_label_messages_per_thread() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_source_tags(true);
+
+        let worker = emitter.clone();
+        std::thread::spawn(move || {
+            Emitter::set_source("stats-poller");
+            worker.emit_moments(0, "m1", vec![]).unwrap();
+        })
+        .join()
+        .unwrap();
+        // The worker's label does not leak into this thread
+        emitter.emit_moments(0, "m1", vec![]).unwrap();
+
+        assert_eq!(sources(&sink), vec![Some("stats-poller".to_string()), None]);
+    }
+
+    #[test]
+    fn source_tags_are_omitted_by_default() {
+        let (emitter, sink) = capturing_emitter();
+
+        Emitter::set_source("stats-poller");
+        emitter.emit_moments(0, "m1", vec![]).unwrap();
+        Emitter::clear_source();
+
+        assert_eq!(sources(&sink), vec![None]);
+        let line = serde_json::to_string(&sink.responses()[0]).unwrap();
+        assert!(!line.contains("source"));
+    }
+
+    #[test]
+    fn match_switch_completes_old_match_first() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_match_switch_detection(true);
+
+        emitter.emit_statistics(0, "m1", 10.0, HashMap::new()).unwrap();
+        emitter.emit_statistics(0, "m2", 1.0, HashMap::new()).unwrap();
+
+        let messages = sink.messages();
+        assert_eq!(messages.len(), 3);
+        match &messages[1] {
+            MatchDataMessage::SetComplete {
+                external_match_id,
+                summary_source,
+                ..
+            } => {
+                assert_eq!(external_match_id, "m1");
+                assert_eq!(*summary_source, SummarySource::LiveFallback);
+            }
+            other => panic!("Expected SetComplete, got {:?}", other),
+        }
+        assert_eq!(messages[2].external_match_id(), "m2");
+        assert_eq!(
+            emitter.take_match_switches(),
+            vec![MatchSwitch {
+                subpack: 0,
+                old_external_match_id: "m1".to_string(),
+                new_external_match_id: "m2".to_string(),
+            }]
+        );
+        assert!(emitter.take_match_switches().is_empty());
+    }
+
+    #[test]
+    fn match_switch_ignores_clean_ends_and_other_subpacks() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_match_switch_detection(true);
+
+        emitter.emit_statistics(0, "m1", 10.0, HashMap::new()).unwrap();
+        emitter.emit_statistics(1, "other", 10.0, HashMap::new()).unwrap();
+        emitter
+            .emit_match_data(MatchDataMessage::set_complete(0, "m1", SummarySource::Api))
+            .unwrap();
+        emitter.emit_statistics(0, "m2", 1.0, HashMap::new()).unwrap();
+        // Late write for the finished match is not a switch back
+        emitter.emit_moments(0, "m1", vec![]).unwrap();
+        // Abandoning ends a match as well
+        emitter.emit_abandoned(0, "m2", 5.0).unwrap();
+        emitter.emit_statistics(0, "m3", 1.0, HashMap::new()).unwrap();
+
+        assert_eq!(sink.messages().len(), 7);
+        assert!(emitter.take_match_switches().is_empty());
+    }
+
+    #[test]
+    fn undrained_match_switch_state_stays_bounded() {
+        let (emitter, _sink) = capturing_emitter();
+        let emitter = emitter.with_match_switch_detection(true);
+        for i in 0..100 {
+            emitter.emit_statistics(0, format!("m{}", i), 1.0, HashMap::new()).unwrap();
+        }
+
+        let switches = emitter.take_match_switches();
+        assert_eq!(switches.len(), MAX_PENDING_SWITCHES);
+        assert_eq!(switches[0].old_external_match_id, "m35");
+        let matches = emitter.matches.as_ref().unwrap().lock().unwrap();
+        assert_eq!(matches.completed.len(), 1);
+    }
+
+    #[test]
+    fn complete_guard_allows_complete_after_write() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_complete_guard(true);
+
+        emitter.emit_statistics(0, "m1", 10.0, HashMap::new()).unwrap();
+        emitter
+            .emit_match_data(MatchDataMessage::set_complete(0, "m1", SummarySource::Api))
+            .unwrap();
+
+        assert_eq!(sink.messages().len(), 2);
+    }
+
+    #[test]
+    fn complete_guard_rejects_complete_without_write() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_complete_guard(true);
+
+        // A write to another subpack does not count for this one
+        emitter.emit_statistics(1, "m1", 10.0, HashMap::new()).unwrap();
+        let result =
+            emitter.emit_match_data(MatchDataMessage::set_complete(0, "m1", SummarySource::Api));
+
+        match result {
+            Err(EmitError::CompleteWithoutWrite {
+                subpack,
+                external_match_id,
+            }) => {
+                assert_eq!(subpack, 0);
+                assert_eq!(external_match_id, "m1");
+            }
+            other => panic!("Expected CompleteWithoutWrite, got {:?}", other),
+        }
+        assert_eq!(sink.messages().len(), 1);
+        assert_eq!(emitter.stats().dropped, 1);
+    }
+
+    #[test]
+    fn complete_guard_is_off_by_default() {
+        let (emitter, sink) = capturing_emitter();
+
+        emitter
+            .emit_match_data(MatchDataMessage::set_complete(0, "m1", SummarySource::Api))
+            .unwrap();
+
+        assert_eq!(sink.messages().len(), 1);
+    }
+
+    #[test]
+    fn muted_match_drops_everything_but_set_complete() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_complete_guard(true);
+        emitter.set_muted(0, "m1", true);
+
+        emitter.emit_statistics(0, "m1", 10.0, HashMap::new()).unwrap();
+        emitter.emit_game_events(0, "m1", vec![GameEvent::new("Kill", 10.0, json!({}))]).unwrap();
+        emitter.emit_moments(0, "m1", vec![]).unwrap();
+        assert!(sink.messages().is_empty());
+
+        // Not rejected by the guard although every write was dropped
+        emitter
+            .emit_match_data(MatchDataMessage::set_complete(0, "m1", SummarySource::Api))
+            .unwrap();
+
+        assert!(matches!(sink.messages()[..], [MatchDataMessage::SetComplete { .. }]));
+        let stats = emitter.stats();
+        assert_eq!((stats.muted, stats.dropped, stats.statistics_emitted), (3, 0, 0));
+    }
+
+    #[test]
+    fn reset_match_makes_next_write_a_first_write() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter
+            .with_complete_guard(true)
+            .with_match_switch_detection(true);
+        emitter.emit_statistics(0, "m1", 10.0, HashMap::new()).unwrap();
+
+        emitter.reset_match(0, "m1");
+
+        // The earlier write no longer counts for the guard...
+        let result =
+            emitter.emit_match_data(MatchDataMessage::set_complete(0, "m1", SummarySource::Api));
+        assert!(matches!(result, Err(EmitError::CompleteWithoutWrite { .. })));
+        // ...and m1 is no longer active, so m2 starts cleanly
+        emitter.emit_statistics(0, "m2", 1.0, HashMap::new()).unwrap();
+        assert!(emitter.take_match_switches().is_empty());
+        assert_eq!(sink.messages().len(), 2);
+
+        // A completed match written again after a reset is active again
+        emitter
+            .emit_match_data(MatchDataMessage::set_complete(0, "m2", SummarySource::Api))
+            .unwrap();
+        emitter.reset_match(0, "m2");
+        emitter.emit_statistics(0, "m2", 1.0, HashMap::new()).unwrap();
+        emitter.emit_statistics(0, "m3", 1.0, HashMap::new()).unwrap();
+        assert_eq!(emitter.take_match_switches().len(), 1);
+    }
+
+    #[test]
+    fn reset_all_forgets_every_match() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter
+            .with_complete_guard(true)
+            .with_match_switch_detection(true);
+        emitter.emit_statistics(0, "a", 1.0, HashMap::new()).unwrap();
+        emitter.emit_statistics(1, "b", 1.0, HashMap::new()).unwrap();
+
+        emitter.reset_all();
+
+        for (subpack, id) in [(0, "a"), (1, "b")] {
+            let complete = MatchDataMessage::set_complete(subpack, id, SummarySource::Api);
+            assert!(emitter.emit_match_data(complete).is_err());
+        }
+        emitter.emit_statistics(0, "c", 1.0, HashMap::new()).unwrap();
+        assert!(emitter.take_match_switches().is_empty());
+        assert_eq!(sink.messages().len(), 3);
+    }
+
+    #[test]
+    fn live_data_patch_requires_negotiation() {
+        let (emitter, sink) = capturing_emitter();
+        let data = json!({"gold": 650});
+
+        let result = emitter.emit_live_data_patch(&data);
+        assert!(matches!(result, Err(EmitError::NotNegotiated("live_data_patch"))));
+
+        emitter.set_live_data_patches(true);
+        // No base yet: left to the next GetLiveData
+        emitter.emit_live_data_patch(&data).unwrap();
+        assert!(sink.responses().is_empty());
+
+        emitter.swap_live_data_base(Some(json!({"gold": 500})));
+        emitter.emit_live_data_patch(&data).unwrap();
+        emitter.emit_live_data_patch(&data).unwrap();
+        let responses = sink.responses();
+        let [GamepackResponse::LiveDataPatch { ops, .. }] = responses.as_slice() else {
+            panic!("Expected one LiveDataPatch, got {:?}", responses);
+        };
+        assert_eq!(
+            ops,
+            &vec![JsonPatchOp::Replace {
+                path: "/gold".into(),
+                value: json!(650),
+            }]
+        );
+        assert_eq!(emitter.swap_live_data_base(None), Some(data));
+    }
+
+    #[test]
+    fn emit_warning_writes_unsolicited_warning() {
+        let (emitter, sink) = capturing_emitter();
+
+        emitter
+            .emit_warning("match history API returned 503", Some("api_unavailable".into()))
+            .unwrap();
+
+        let line = sink.responses()[0].to_line().unwrap();
+        assert_eq!(
+            line,
+            concat!(
+                r#"{"type":"warning","request_id":"","#,
+                r#""message":"match history API returned 503","code":"api_unavailable"}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn non_finite_time_fails_instead_of_writing_null() {
+        let reported = Arc::new(Mutex::new(0));
+        let count = Arc::clone(&reported);
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.on_serialize_error(move |_| *count.lock().unwrap() += 1);
+
+        let result = emitter.emit_statistics(0, "m1", f64::INFINITY, HashMap::new());
+        let moment = Moment::new("pentakill", f64::NAN, json!({}));
+        let also = emitter.emit_moments(0, "m1", vec![moment]);
+
+        assert!(matches!(result, Err(EmitError::Serialize(_))), "{:?}", result);
+        assert!(matches!(also, Err(EmitError::Serialize(_))), "{:?}", also);
+        assert_eq!(*reported.lock().unwrap(), 2);
+        assert!(sink.responses().is_empty());
+        assert_eq!(emitter.stats().dropped, 2);
+    }
+
+    #[test]
+    fn number_normalization_rounds_unsafe_integers() {
+        let (emitter, sink) = capturing_emitter();
+        let stats: HashMap<String, serde_json::Value> =
+            [("damage".to_string(), json!(u64::MAX)), ("kills".to_string(), json!(3))].into();
+
+        emitter.emit_statistics(0, "m1", 1.0, stats.clone()).unwrap();
+        let emitter = emitter.with_number_normalization(true);
+        emitter.emit_statistics(0, "m1", 2.0, stats).unwrap();
+
+        let damage: Vec<serde_json::Value> = sink
+            .messages()
+            .iter()
+            .map(|message| match message {
+                MatchDataMessage::WriteStatistics { stats, .. } => stats["damage"].clone(),
+                other => panic!("Expected WriteStatistics, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(damage, vec![json!(u64::MAX), json!(18446744073709551615.0)]);
+    }
+
+    #[test]
+    fn played_at_guard_stamps_only_the_first_statistics() {
+        let (emitter, sink) = capturing_emitter();
+        let clock = crate::FixedClock::at_unix_millis(1_705_314_600_000);
+        let emitter = emitter.with_played_at_guard(clock);
+
+        emitter.emit_statistics(0, "m1", 1.0, HashMap::new()).unwrap();
+        emitter.emit_statistics(0, "m1", 2.0, HashMap::new()).unwrap();
+        emitter
+            .emit_match_data(MatchDataMessage::write_statistics_with_time(
+                0,
+                "m2",
+                "2024-01-15T09:00:00.000Z",
+                1.0,
+                HashMap::new(),
+            ))
+            .unwrap();
+        emitter.emit_statistics(0, "m2", 2.0, HashMap::new()).unwrap();
+
+        let played_at: Vec<_> = sink
+            .messages()
+            .into_iter()
+            .map(|message| match message {
+                MatchDataMessage::WriteStatistics { played_at, .. } => played_at,
+                other => panic!("Expected WriteStatistics, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            played_at,
+            [
+                Some("2024-01-15T10:30:00.000Z".to_string()),
+                None,
+                Some("2024-01-15T09:00:00.000Z".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn emit_heartbeat_writes_game_clock() {
+        let (emitter, sink) = capturing_emitter();
+
+        emitter.emit_heartbeat(1, "m1", 1834.25).unwrap();
+
+        let line = sink.responses()[0].to_line().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        let wall_clock = json["wall_clock"].as_str().unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "type": "heartbeat",
+                "subpack": 1,
+                "external_match_id": "m1",
+                "game_time_secs": 1834.25,
+                "wall_clock": wall_clock,
+            })
+        );
+        assert!(wall_clock.ends_with('Z') && wall_clock.len() == 24, "{}", wall_clock);
+        assert_eq!(sink.responses()[0].request_id(), "");
+
+        emitter.set_muted(1, "m1", true);
+        emitter.emit_heartbeat(1, "m1", 1840.0).unwrap();
+        assert_eq!(sink.responses().len(), 1);
+
+        assert!(emitter.emit_heartbeat(1, "m2", f64::NAN).is_err());
+    }
+
+    #[test]
+    fn pushed_events_are_unsolicited_and_name_their_match() {
+        let (emitter, sink) = capturing_emitter();
+        emitter.set_muted(0, "muted", true);
+
+        let events = vec![GameEvent::new("Kill", 10.0, json!({}))];
+        emitter.emit_events(1, "m1", events).unwrap();
+        emitter.emit_events(0, "muted", vec![GameEvent::new("Kill", 1.0, json!({}))]).unwrap();
+
+        let responses = sink.responses();
+        assert_eq!(responses.len(), 1);
+        let GamepackResponse::Events {
+            request_id,
+            events,
+            subpack,
+            external_match_id,
+            ..
+        } = &responses[0]
+        else {
+            panic!("Expected Events, got {:?}", responses[0]);
+        };
+        assert_eq!(request_id, "");
+        assert_eq!(events.len(), 1);
+        assert_eq!((*subpack, external_match_id.as_deref()), (Some(1), Some("m1")));
+        assert_eq!(emitter.stats().muted, 1);
+    }
+
+    #[test]
+    fn rate_limit_drops_stats_but_lets_set_complete_through() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_max_emit_rate(50.0);
+
+        let started = Instant::now();
+        let mut written = 0;
+        while started.elapsed() < Duration::from_millis(200) {
+            if emitter.emit_statistics(0, "m1", 1.0, HashMap::new()).is_ok() {
+                written += 1;
+            }
+        }
+        // A one-second burst plus the refill over the loop, with slack
+        assert!((50..=65).contains(&written), "{} stats written", written);
+        let stats = emitter.stats();
+        assert!(stats.rate_limited > 0);
+        assert_eq!(stats.statistics_emitted, written);
+
+        emitter
+            .emit_match_data(MatchDataMessage::set_complete(0, "m1", SummarySource::Api))
+            .unwrap();
+        emitter.emit_warning("still here", None).unwrap();
+        assert!(matches!(
+            sink.messages().last(),
+            Some(MatchDataMessage::SetComplete { .. })
+        ));
+    }
+
+    #[test]
+    fn rate_limit_delays_events_instead_of_dropping_them() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_max_emit_rate(100.0);
+
+        let started = Instant::now();
+        for i in 0..110 {
+            let events = vec![GameEvent::new("Kill", i as f64, json!({}))];
+            emitter.emit_game_events(0, "m1", events).unwrap();
+        }
+
+        assert!(started.elapsed() >= Duration::from_millis(80));
+        assert_eq!(sink.messages().len(), 110);
+
+        // Patches build on each other: waited for like events
+        emitter.set_live_data_patches(true);
+        emitter.swap_live_data_base(Some(json!({"gold": 500})));
+        emitter.emit_live_data_patch(&json!({"gold": 650})).unwrap();
+        assert!(matches!(
+            sink.responses().last(),
+            Some(GamepackResponse::LiveDataPatch { .. })
+        ));
+        assert_eq!(emitter.stats().rate_limited, 0);
+    }
+
+    #[test]
+    fn throttled_patch_does_not_hold_the_live_data_base() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_max_emit_rate(2.0);
+        emitter.set_live_data_patches(true);
+        emitter.swap_live_data_base(Some(json!({"gold": 500})));
+        for _ in 0..2 {
+            emitter.emit_game_events(0, "m1", vec![]).unwrap();
+        }
+
+        // The rate is used up: this push waits about half a second
+        let pushing = {
+            let emitter = emitter.clone();
+            std::thread::spawn(move || emitter.emit_live_data_patch(&json!({"gold": 650})))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        let started = Instant::now();
+        assert_eq!(emitter.swap_live_data_base(None), Some(json!({"gold": 500})));
+        assert!(started.elapsed() < Duration::from_millis(250));
+
+        // No base by the time it got its turn: nothing to patch
+        pushing.join().unwrap().unwrap();
+        assert_eq!(sink.responses().len(), 2);
+    }
+
+    /// Sink that keeps the serialized lines.
+    #[derive(Clone, Default)]
+    struct LineSink(Arc<Mutex<Vec<String>>>);
+
+    impl EmitSink for LineSink {
+        fn emit(&self, _response: &GamepackResponse, line: &str) -> io::Result<()> {
+            self.0.lock().unwrap().push(line.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stable_map_order_writes_equal_stats_identically() {
+        let sink = LineSink::default();
+        let emitter = Emitter::new(sink.clone()).with_stable_map_order();
+        let keys: Vec<String> = (0..32).map(|i| format!("stat_{}", i)).collect();
+        let stat = |key: &String| (key.clone(), json!(key.len()));
+        let forward: HashMap<_, _> = keys.iter().map(stat).collect();
+        let backward: HashMap<_, _> = keys.iter().rev().map(stat).collect();
+
+        emitter.emit_statistics(0, "m1", 5.0, forward.clone()).unwrap();
+        emitter.emit_statistics(0, "m1", 5.0, backward.clone()).unwrap();
+        let complete = |stats| {
+            MatchDataMessage::set_complete_with_stats(0, "m1", SummarySource::LiveFallback, stats)
+        };
+        emitter.emit_match_data(complete(forward)).unwrap();
+        emitter.emit_match_data(complete(backward)).unwrap();
+
+        let lines = sink.0.lock().unwrap().clone();
+        assert_eq!(lines[0], lines[1]);
+        assert_eq!(lines[2], lines[3]);
+        assert!(lines[0].contains(r#"{"stat_0":6,"stat_1":6,"stat_10":7,"#), "{}", lines[0]);
+    }
+
+    #[test]
+    fn coalesce_window_merges_partial_stats_of_one_tick() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_coalesce_window(Duration::from_secs(60));
+        let stats = |pairs: &[(&str, i64)]| -> HashMap<String, serde_json::Value> {
+            pairs.iter().map(|(key, value)| (key.to_string(), json!(value))).collect()
+        };
+
+        emitter.emit_statistics(0, "m1", 10.0, stats(&[("gold", 500), ("kills", 1)])).unwrap();
+        emitter.emit_statistics(0, "m1", 10.0, stats(&[("kills", 2), ("deaths", 0)])).unwrap();
+        assert!(sink.messages().is_empty());
+
+        // The next tick releases the merged one
+        emitter.emit_statistics(0, "m1", 11.0, stats(&[("gold", 550)])).unwrap();
+        let written = sink.messages();
+        let [MatchDataMessage::WriteStatistics {
+            game_time_secs,
+            stats: merged,
+            ..
+        }] = &written[..]
+        else {
+            panic!("Expected one WriteStatistics, got {:?}", written);
+        };
+        assert_eq!(*game_time_secs, 10.0);
+        assert_eq!(merged, &stats(&[("gold", 500), ("kills", 2), ("deaths", 0)]));
+
+        // Nothing overtakes pending stats of its match
+        emitter
+            .emit_match_data(MatchDataMessage::set_complete(0, "m1", SummarySource::Api))
+            .unwrap();
+        let kinds: Vec<&str> = sink.messages().iter().map(<&str>::from).collect();
+        assert_eq!(kinds, ["WriteStatistics", "WriteStatistics", "SetComplete"]);
+    }
+
+    #[test]
+    fn coalesced_stats_go_out_when_due_or_flushed() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_coalesce_window(Duration::from_millis(5));
+
+        emitter.emit_statistics(0, "m1", 1.0, HashMap::new()).unwrap();
+        emitter.emit_statistics(1, "m2", 1.0, HashMap::new()).unwrap();
+        assert!(sink.messages().is_empty());
+        std::thread::sleep(Duration::from_millis(10));
+        emitter.emit_due_statistics().unwrap();
+        assert_eq!(sink.messages().len(), 2);
+
+        emitter.emit_statistics(0, "m1", 2.0, HashMap::new()).unwrap();
+        emitter.flush().unwrap();
+        assert_eq!(sink.messages().len(), 3);
+    }
+
+    #[test]
+    fn reset_drops_coalesced_stats_of_the_old_match() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_coalesce_window(Duration::from_secs(60));
+
+        emitter.emit_statistics(0, "m1", 1.0, HashMap::new()).unwrap();
+        emitter.emit_statistics(0, "m2", 1.0, HashMap::new()).unwrap();
+        emitter.reset_match(0, "m1");
+        emitter.flush().unwrap();
+        let matches: Vec<String> = sink
+            .messages()
+            .iter()
+            .map(|message| message.external_match_id().to_string())
+            .collect();
+        assert_eq!(matches, ["m2"]);
+
+        emitter.emit_statistics(0, "m1", 2.0, HashMap::new()).unwrap();
+        emitter.reset_all();
+        emitter.flush().unwrap();
+        assert_eq!(sink.messages().len(), 1);
+    }
+
+    #[test]
+    fn null_stat_check_flags_clearing_what_was_never_set() {
+        let (emitter, sink) = capturing_emitter();
+        let emitter = emitter.with_null_stat_check(true);
+        let check = emitter.null_stats.clone().unwrap();
+        let stats = |pairs: &[(&str, serde_json::Value)]| -> HashMap<String, serde_json::Value> {
+            pairs.iter().map(|(key, value)| (key.to_string(), value.clone())).collect()
+        };
+        let write = |pairs: &[(&str, serde_json::Value)]| {
+            MatchDataMessage::write_statistics(0, "m1", 1.0, stats(pairs))
+        };
+
+        assert!(check.observe(&write(&[("respawn_secs", json!(12))])).is_empty());
+        // Clearing a set stat is the intended use; clearing it twice is not
+        assert!(check.observe(&write(&[("respawn_secs", json!(null))])).is_empty());
+        let twice = check.observe(&write(&[("respawn_secs", json!(null)), ("gold", json!(1))]));
+        assert_eq!(twice, ["respawn_secs"]);
+        assert_eq!(check.observe(&write(&[("rank", json!(null))])), ["rank"]);
+
+        // The message itself goes out as emitted: cleared present, untouched absent
+        emitter.emit_statistics(0, "m1", 2.0, stats(&[("gold", json!(null))])).unwrap();
+        let line = serde_json::to_value(&sink.messages()[0]).unwrap();
+        assert_eq!(line["stats"], json!({"gold": null}));
+        assert!(line["stats"].get("respawn_secs").is_none());
+    }
+}
@@ -0,0 +1,265 @@
+//! Splitting the command stream into lines.
+//!
+//! A single `read` from stdin may return part of a line, several lines, or
+//! a line and a half; a command written by the daemon in two syscalls can
+//! arrive in two reads. [`LineReader`] keeps the unfinished tail between
+//! reads and only yields a line once its newline has arrived. It also
+//! enforces an optional size limit without buffering the oversized line.
+
+use std::io::{self, Read};
+
+/// Bytes requested from the underlying reader per `read` call.
+const CHUNK: usize = 8 * 1024;
+
+/// One unit of input produced by [`LineReader::next_frame`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    /// A complete line, without its `\n` (or `\r\n`)
+    Line(String),
+    /// A line longer than the limit; its contents were discarded
+    TooLong {
+        /// Length of the discarded line in bytes
+        bytes: usize,
+    },
+    /// A line that is not valid UTF-8
+    InvalidUtf8 {
+        /// The line decoded lossily, with U+FFFD for invalid sequences
+        lossy: String,
+        /// Byte offset of the first invalid sequence
+        valid_up_to: usize,
+    },
+}
+
+/// A frame that is not a usable line, with the error code the runner
+/// answers it with.
+///
+/// Transports report these as [`io::ErrorKind::InvalidData`] errors
+/// wrapping this type; see [`Transport`](crate::transport::Transport).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message}")]
+pub struct FrameError {
+    /// Error code, e.g. `"line_too_long"`
+    pub code: &'static str,
+    /// Human-readable description
+    pub message: String,
+}
+
+impl Frame {
+    /// The line, or why there is none.
+    pub fn into_line(self) -> Result<String, FrameError> {
+        match self {
+            Self::Line(line) => Ok(line),
+            Self::TooLong { bytes } => Err(FrameError {
+                code: "line_too_long",
+                message: format!("Command line too long: {} bytes", bytes),
+            }),
+            Self::InvalidUtf8 { valid_up_to, .. } => Err(FrameError {
+                code: "invalid_utf8",
+                message: format!("Invalid UTF-8 in command line at byte {}", valid_up_to),
+            }),
+        }
+    }
+}
+
+/// Newline-delimited reader that accumulates partial reads.
+///
+/// Like [`BufRead::lines`](std::io::BufRead::lines), a final line without a
+/// trailing newline is still yielded at end of input. Unlike it, a line that
+/// is not valid UTF-8 is not an I/O error: it is yielded as
+/// [`Frame::InvalidUtf8`] and reading continues with the next line, so one
+/// bad byte cannot end the stream.
+///
+/// ```rust
+/// use gamepack_runtime::framing::{Frame, LineReader};
+///
+/// let mut reader = LineReader::new(&b"{\"a\":1}\nxxxxxxxx\n"[..]).with_max_line_bytes(7);
+/// assert_eq!(reader.next_frame().unwrap(), Some(Frame::Line("{\"a\":1}".into())));
+/// assert_eq!(reader.next_frame().unwrap(), Some(Frame::TooLong { bytes: 8 }));
+/// assert_eq!(reader.next_frame().unwrap(), None);
+/// ```
+#[derive(Debug)]
+pub struct LineReader<R> {
+    inner: R,
+    /// Bytes read but not yet yielded; never holds a complete line on return
+    buf: Vec<u8>,
+    /// Bytes of `buf` already known to contain no newline
+    scanned: usize,
+    max_line_bytes: Option<usize>,
+    /// Bytes dropped so far from a line that exceeded the limit
+    discarding: Option<usize>,
+}
+
+impl<R: Read> LineReader<R> {
+    /// Read lines from `inner` with no size limit.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+            scanned: 0,
+            max_line_bytes: None,
+            discarding: None,
+        }
+    }
+
+    /// Report lines longer than `max` bytes (excluding the newline) as
+    /// [`Frame::TooLong`] instead of buffering them.
+    pub fn with_max_line_bytes(mut self, max: usize) -> Self {
+        self.max_line_bytes = Some(max);
+        self
+    }
+
+    /// Whether a complete line is already buffered, so the next
+    /// [`next_frame`](Self::next_frame) returns without reading.
+    pub fn has_complete_line(&self) -> bool {
+        self.buf[self.scanned..].contains(&b'\n')
+    }
+
+    /// Next line, or `None` at end of input.
+    pub fn next_frame(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            if let Some(at) = self.buf[self.scanned..].iter().position(|&b| b == b'\n') {
+                let end = self.scanned + at;
+                let mut line: Vec<u8> = self.buf.drain(..=end).collect();
+                line.pop();
+                self.scanned = 0;
+                return Ok(Some(self.finish(line)));
+            }
+            self.scanned = self.buf.len();
+
+            if let Some(max) = self.max_line_bytes {
+                // Allow one extra byte for a `\r` that may precede the newline
+                if self.buf.len() > max + 1 {
+                    *self.discarding.get_or_insert(0) += self.buf.len();
+                    self.buf.clear();
+                    self.scanned = 0;
+                }
+            }
+
+            let start = self.buf.len();
+            self.buf.resize(start + CHUNK, 0);
+            let result = self.inner.read(&mut self.buf[start..]);
+            self.buf.truncate(start + result.as_ref().map_or(0, |n| *n));
+            match result {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // End of input: yield an unterminated last line, if any
+        if self.buf.is_empty() && self.discarding.is_none() {
+            return Ok(None);
+        }
+        let line = std::mem::take(&mut self.buf);
+        self.scanned = 0;
+        Ok(Some(self.finish(line)))
+    }
+
+    /// Turn a line's bytes (newline removed) into a frame.
+    fn finish(&mut self, mut line: Vec<u8>) -> Frame {
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if let Some(dropped) = self.discarding.take() {
+            return Frame::TooLong {
+                bytes: dropped + line.len(),
+            };
+        }
+        if self.max_line_bytes.is_some_and(|max| line.len() > max) {
+            return Frame::TooLong { bytes: line.len() };
+        }
+        match String::from_utf8(line) {
+            Ok(line) => Frame::Line(line),
+            Err(e) => Frame::InvalidUtf8 {
+                valid_up_to: e.utf8_error().valid_up_to(),
+                lossy: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+            },
+        }
+    }
+}
+
+impl<R: Read> Iterator for LineReader<R> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::GamepackCommand;
+
+    /// Reader that hands out one byte per `read`, like a daemon writing a
+    /// command in many small syscalls.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&byte, rest)) if !out.is_empty() => {
+                    out[0] = byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn frames(reader: LineReader<impl Read>) -> Vec<Frame> {
+        reader.map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn byte_by_byte_input_parses_as_one_command() {
+        let input = b"{\"type\":\"poll_events\",\"request_id\":\"p1\"}\n";
+        let frames = frames(LineReader::new(Trickle(input)));
+
+        assert_eq!(frames.len(), 1);
+        let Frame::Line(line) = &frames[0] else {
+            panic!("Expected a line, got {:?}", frames[0]);
+        };
+        let cmd: GamepackCommand = serde_json::from_str(line).unwrap();
+        assert_eq!(cmd.request_id(), "p1");
+    }
+
+    #[test]
+    fn crlf_and_unterminated_last_line() {
+        let frames = frames(LineReader::new(Trickle(b"a\r\n\nb")));
+
+        assert_eq!(
+            frames,
+            vec![Frame::Line("a".into()), Frame::Line("".into()), Frame::Line("b".into())]
+        );
+    }
+
+    #[test]
+    fn oversized_line_is_skipped_across_reads() {
+        let mut input = vec![b'x'; 40];
+        input.extend_from_slice(b"\nok\n");
+        let mut reader = LineReader::new(Trickle(&input)).with_max_line_bytes(8);
+
+        assert_eq!(reader.next_frame().unwrap(), Some(Frame::TooLong { bytes: 40 }));
+        assert_eq!(reader.next_frame().unwrap(), Some(Frame::Line("ok".into())));
+        assert_eq!(reader.next_frame().unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_utf8_is_reported_and_skipped() {
+        let frames = frames(LineReader::new(&b"ab\xffc\nok\n"[..]));
+
+        assert_eq!(
+            frames,
+            vec![
+                Frame::InvalidUtf8 {
+                    lossy: "ab\u{FFFD}c".into(),
+                    valid_up_to: 2,
+                },
+                Frame::Line("ok".into()),
+            ]
+        );
+    }
+}
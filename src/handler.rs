@@ -1,10 +1,26 @@
 //! Trait for implementing gamepack handlers.
 
-use crate::types::{GameEvent, GameStatus, InitResponse, IsMatchInProgressResponse, MatchData};
+use crate::derived::DerivedStatSpec;
+use std::collections::HashSet;
+
+use crate::types::{
+    BuildInfo, Capability, GameEvent, GameStatus, GetMatchTimelineRequest,
+    GetMatchTimelineResponse, InitResponse, IsMatchInProgressResponse, MatchData,
+    MatchDataMessage, MatchSummary, RecordDecision, StorageStatus, TriggerConfig, TriggerIssue,
+    TriggerRule,
+};
 
 /// Result type for gamepack operations.
 pub type GamepackResult<T> = Result<T, GamepackError>;
 
+/// Session-start work run off the main loop; see
+/// [`GamepackHandler::background_session_start`].
+pub type SessionStartTask = Box<dyn FnOnce() -> Option<serde_json::Value> + Send>;
+
+/// Session-end work run off the main loop; see
+/// [`GamepackHandler::background_session_end`].
+pub type SessionEndTask = Box<dyn FnOnce() -> Option<MatchData> + Send>;
+
 /// Error type for gamepack operations.
 #[derive(Debug, Clone)]
 pub struct GamepackError {
@@ -75,6 +91,9 @@ impl From<&str> for GamepackError {
 ///             game_id: 1,
 ///             slug: "my-game".to_string(),
 ///             protocol_version: 1,
+///             pack_version: gamepack_runtime::pack_version!(),
+///             game_version: None,
+///             player: None,
 ///         })
 ///     }
 ///
@@ -113,6 +132,17 @@ pub trait GamepackHandler {
     /// this game integration.
     fn init(&mut self) -> GamepackResult<InitResponse>;
 
+    /// Called right after `init` once the session's protocol version is
+    /// resolved (the lower of the pack's and the daemon's versions).
+    ///
+    /// Use it to avoid fields an older daemon doesn't understand. The version
+    /// is also available later via
+    /// [`Emitter::negotiated_version`](crate::Emitter::negotiated_version);
+    /// emits made before negotiation assume [`PROTOCOL_VERSION`](crate::PROTOCOL_VERSION).
+    ///
+    /// Default implementation does nothing.
+    fn on_negotiated(&mut self, _version: u32) {}
+
     /// Check if the game client/process is running.
     ///
     /// Called periodically by the daemon to detect when the game launches
@@ -125,6 +155,46 @@ pub trait GamepackHandler {
     /// game phase, whether in an active match, etc.).
     fn get_status(&self) -> GameStatus;
 
+    /// Version or patch of the running game (e.g. `"14.3"`), if known.
+    ///
+    /// The format is game-defined; the daemon stores and compares it as an
+    /// opaque string. Packs that also report it in
+    /// [`GameStatus::game_version`] should return the same value here.
+    ///
+    /// Default implementation returns `None`.
+    fn get_game_version(&self) -> Option<String> {
+        None
+    }
+
+    /// Describe how the pack was built, for the daemon's support logs.
+    ///
+    /// Default implementation returns [`BuildInfo::runtime`]; the runner
+    /// adds the `pack_version` from `init`. Override to report the pack's
+    /// own cargo features with [`BuildInfo::with_features`].
+    fn get_build_info(&self) -> BuildInfo {
+        BuildInfo::runtime()
+    }
+
+    /// Features of this pack, sent in the `Initialized` response.
+    ///
+    /// The runtime cannot tell which default methods a pack overrides, so
+    /// packs declare it here: add a [`Capability`] for each optional method
+    /// you implement, e.g.
+    ///
+    /// ```rust,ignore
+    /// fn capabilities(&self) -> HashSet<Capability> {
+    ///     let mut caps = Capability::always();
+    ///     caps.extend([Capability::MatchTimeline, Capability::RecentMatches]);
+    ///     caps
+    /// }
+    /// ```
+    ///
+    /// The required capabilities ([`Capability::ALWAYS`]) are reported even
+    /// if left out. Default implementation returns only those.
+    fn capabilities(&self) -> HashSet<Capability> {
+        Capability::always()
+    }
+
     /// Poll for new game events.
     ///
     /// Called frequently (every ~500ms) during active games. Return any
@@ -135,8 +205,30 @@ pub trait GamepackHandler {
     ///
     /// Return current in-game statistics for display in the UI (KDA, gold,
     /// objectives, etc.). Return `None` if not in a game.
+    ///
+    /// Return `Some(Value::Null)` to clear the live data, e.g. once the
+    /// match ends: the response is flagged `cleared` and the daemon hides
+    /// the live panel, where `None` leaves it showing the last data.
     fn get_live_data(&self) -> Option<serde_json::Value>;
 
+    /// Get live data for every match being tracked.
+    ///
+    /// Spectator and arena modes can follow several matches at once. Return
+    /// one `(external_match_id, data)` entry per match with data, in the
+    /// order the UI should show them; leave out matches with nothing to show.
+    /// The ids must be the same ones used in `WriteMatchData` for those
+    /// matches.
+    ///
+    /// Default implementation wraps [`get_live_data`](Self::get_live_data)
+    /// as a single entry keyed `"active"`, or an empty list when it returns
+    /// `None` or cleared data.
+    fn get_live_data_all(&self) -> Vec<(String, serde_json::Value)> {
+        self.get_live_data()
+            .filter(|data| !data.is_null())
+            .map(|data| vec![("active".to_string(), data)])
+            .unwrap_or_default()
+    }
+
     /// Called when a game session starts.
     ///
     /// The daemon calls this when transitioning to an in-game state.
@@ -149,11 +241,74 @@ pub trait GamepackHandler {
     /// The `context` parameter contains data returned from `on_session_start`.
     fn on_session_end(&mut self, context: serde_json::Value) -> Option<MatchData>;
 
+    /// Run slow session-end work, e.g. fetching post-game stats from an
+    /// API, on a background thread instead of blocking the main loop.
+    ///
+    /// Return a task to replace [`on_session_end`](Self::on_session_end)
+    /// for this session. The runner runs it on its own thread and keeps
+    /// answering other commands (status, live data, ...) meanwhile. When it
+    /// finishes, `SessionEnded` is written through the emitter with the
+    /// `SessionEnd` command's `request_id`, so it may arrive after responses
+    /// to later commands; the daemon matches it by `request_id`. A panic in
+    /// the task is answered with an `Error` (code `"handler_panic"`).
+    /// `Shutdown`, and stdin closing, wait for unfinished tasks so their
+    /// responses are not lost.
+    ///
+    /// The task has no access to the handler: move what it needs (the
+    /// context, an API client) into the closure.
+    ///
+    /// Default implementation returns `None`: `on_session_end` is called on
+    /// the main loop as usual.
+    fn background_session_end(&mut self, _context: &serde_json::Value) -> Option<SessionEndTask> {
+        None
+    }
+
+    /// Run slow session-start work on a background thread; the
+    /// `SessionStarted` counterpart of
+    /// [`background_session_end`](Self::background_session_end), with the
+    /// same ordering. Replaces [`on_session_start`](Self::on_session_start)
+    /// when it returns a task.
+    ///
+    /// Default implementation returns `None`.
+    fn background_session_start(&mut self) -> Option<SessionStartTask> {
+        None
+    }
+
     /// Called on graceful shutdown.
     ///
     /// Clean up any resources before the process exits.
     fn shutdown(&mut self);
 
+    /// Called when the daemon pauses the gamepack.
+    ///
+    /// Stop background polling of the game API here to save resources.
+    /// Only called on the transition from running to paused; a repeated
+    /// `Pause` is acknowledged without calling this again.
+    ///
+    /// Default implementation does nothing.
+    fn on_pause(&mut self) {}
+
+    /// Called when the daemon resumes a paused gamepack.
+    ///
+    /// Only called on the transition from paused to running.
+    ///
+    /// Default implementation does nothing.
+    fn on_resume(&mut self) {}
+
+    /// Called after the emitter detected that a subpack's match changed
+    /// without a `SetComplete` for the old one (e.g. the player left a custom
+    /// game and joined another).
+    ///
+    /// By the time this runs, the old match has already been completed with
+    /// [`SummarySource::LiveFallback`](crate::SummarySource::LiveFallback).
+    /// Reset any per-match state here. Only called when the runner's emitter
+    /// has [match switch detection](crate::Emitter::with_match_switch_detection)
+    /// enabled; switches are delivered after the command during which they
+    /// were detected.
+    ///
+    /// Default implementation does nothing.
+    fn on_match_switch(&mut self, _old_external_id: &str, _new_external_id: &str) {}
+
     /// Resolve an icon URL for an event type.
     ///
     /// Called when the UI needs an icon for a discovered event type that
@@ -183,14 +338,253 @@ pub trait GamepackHandler {
         IsMatchInProgressResponse::ended()
     }
 
+    /// Check whether the gamepack knows a match, finished or not.
+    ///
+    /// Called during recovery as a cheap alternative to `get_match_timeline`.
+    /// Unlike [`is_match_in_progress`](Self::is_match_in_progress), this
+    /// answers "have you seen this match?", not "is it still being played?".
+    /// Packs holding a [`TimelineBuffer`](crate::TimelineBuffer) can answer
+    /// with [`TimelineBuffer::contains`](crate::TimelineBuffer::contains).
+    ///
+    /// Default implementation reports the match as unknown.
+    fn match_exists(&self, _subpack: u8, _external_match_id: &str) -> bool {
+        false
+    }
+
+    /// Matches the gamepack currently considers active, as
+    /// `(subpack, external_match_id)`.
+    ///
+    /// Called on `GetActiveMatches`, which the daemon sends proactively to
+    /// sync its UI with the pack's view. Unlike
+    /// [`is_match_in_progress`](Self::is_match_in_progress), which checks one
+    /// match the daemon names, this lists what the pack itself is tracking.
+    /// The runner adds the matches its emitter has seen written and not yet
+    /// completed when [match switch
+    /// detection](crate::Emitter::with_match_switch_detection) is on, so
+    /// packs using it need not override this.
+    ///
+    /// Default implementation returns no matches.
+    fn get_active_matches(&self) -> Vec<(u8, String)> {
+        vec![]
+    }
+
+    /// Return timeline data the gamepack holds for a match.
+    ///
+    /// Packs that keep a [`TimelineBuffer`](crate::timeline::TimelineBuffer)
+    /// can answer with `buffer.query(request)`, which honors the request's
+    /// entry type, time range, and limit filters.
+    ///
+    /// Default implementation reports the match as not found.
+    fn get_match_timeline(&self, _request: &GetMatchTimelineRequest) -> GetMatchTimelineResponse {
+        GetMatchTimelineResponse {
+            found: false,
+            entries: vec![],
+        }
+    }
+
+    /// Return summaries of the most recently finished matches, newest first.
+    ///
+    /// How many matches to retain is up to the pack; the daemon treats the
+    /// answer as best-effort backfill, not a complete history. Packs can
+    /// keep summaries in a [`MatchSummaryBuffer`](crate::MatchSummaryBuffer),
+    /// which bounds retention and answers with
+    /// [`MatchSummaryBuffer::recent`](crate::MatchSummaryBuffer::recent).
+    /// The runner truncates the result to `limit` if the pack returns more.
+    ///
+    /// Default implementation returns no matches.
+    fn get_recent_matches(&self, _subpack: u8, _limit: Option<u32>) -> Vec<MatchSummary> {
+        vec![]
+    }
+
+    /// Re-run stale match recovery on demand (`RunRecovery`).
+    ///
+    /// Called when the daemon wants the pack to look for matches it knows
+    /// are stale, typically after a pack reload, rather than asking about
+    /// each match with `IsMatchInProgress`. Return the messages to flush
+    /// for them, usually a `SetComplete` per stale match, possibly preceded
+    /// by final statistics. The runner writes them in order through the
+    /// emitter, then answers with `RecoveryComplete`. Declare
+    /// [`Capability::Recovery`] when overriding this.
+    ///
+    /// Default implementation recovers nothing.
+    fn on_run_recovery(&mut self) -> Vec<MatchDataMessage> {
+        vec![]
+    }
+
     /// Generate sample match data for UI preview/testing.
     ///
     /// Called by debug tools to get randomized but valid match data for
     /// previewing the MatchCard component. The returned JSON should match
     /// the schema expected by the pack's MatchCard component.
     ///
+    /// With a [`LiveDataSchema`](crate::LiveDataSchema) describing that data,
+    /// this can be a one-liner:
+    /// `Some(SampleGenerator::from_schema(schema).generate(seed))`
+    /// (see [`SampleGenerator`](crate::SampleGenerator)).
+    ///
+    /// Default implementation generates a sample (seed 0) from
+    /// [`schema`](Self::schema), or returns `None` without one.
+    fn get_sample_match_data(&self, subpack: u8) -> Option<serde_json::Value> {
+        let schema = self.schema(subpack)?;
+        Some(crate::SampleGenerator::from_schema(schema).generate(0))
+    }
+
+    /// Shape of a subpack's match data, declared in code.
+    ///
+    /// Optional: packs describe their columns in `config.json`, and where
+    /// both describe a field the daemon uses `config.json`. A code schema
+    /// lets the daemon ask for it with `GetSchema`, powers the default
+    /// [`get_sample_match_data`](Self::get_sample_match_data), and lets
+    /// tests check data with [`LiveDataSchema::validate`](crate::LiveDataSchema::validate).
+    ///
     /// Default implementation returns `None`.
-    fn get_sample_match_data(&self, _subpack: u8) -> Option<serde_json::Value> {
+    fn schema(&self, _subpack: u8) -> Option<crate::LiveDataSchema> {
         None
     }
+
+    /// The match a subpack is currently tracking, as its
+    /// `(external_match_id, game_time_secs)`.
+    ///
+    /// Used by the `EmitTestMoment` diagnostic to place its synthetic moment
+    /// in the running match at the current game time.
+    ///
+    /// Default implementation returns `None`: the test moment then goes to
+    /// a match named [`TEST_MOMENT_MATCH_ID`](crate::runner::TEST_MOMENT_MATCH_ID)
+    /// at time 0.
+    fn current_match(&self, _subpack: u8) -> Option<(String, f64)> {
+        None
+    }
+
+    /// Stats the daemon should compute from base stats instead of storing
+    /// them (see [`derived`](crate::derived) for the expression syntax).
+    ///
+    /// Sent in the `Initialized` response and on `GetDerivedStats`.
+    ///
+    /// Default implementation declares none.
+    fn derived_stats(&self) -> Vec<DerivedStatSpec> {
+        Vec::new()
+    }
+
+    /// Called when the daemon reports its clip storage status.
+    ///
+    /// Advisory: a pack can stop emitting low-priority moments while
+    /// `status.budget_exhausted` is set. The daemon enforces its own limits
+    /// regardless.
+    ///
+    /// Default implementation ignores the status.
+    fn on_storage_status(&mut self, _status: StorageStatus) {}
+
+    /// Called when the daemon selects the subpack (game mode) to report to.
+    ///
+    /// The runner also records it as the emitter's
+    /// [`active_subpack`](crate::Emitter::active_subpack), so packs that
+    /// only need the index can read it there instead of overriding this.
+    ///
+    /// Default implementation does nothing.
+    fn on_active_subpack(&mut self, _subpack: u8) {}
+
+    /// Called when the daemon stops recording a match (`MuteMatch`), e.g.
+    /// because the user turned recording off mid-game.
+    ///
+    /// The runner already drops the match's statistics, events and moments
+    /// emitted through the [global](crate::Emitter::global) emitter (and its
+    /// clones); `SetComplete` still goes out so the daemon can close the
+    /// match. Override this to also stop the work behind those emits, or to
+    /// mute emitters of your own, which the runner does not know about.
+    ///
+    /// Default implementation does nothing.
+    fn on_mute_match(&mut self, _subpack: u8, _external_match_id: &str) {}
+
+    /// Called when the daemon resumes recording a muted match
+    /// (`UnmuteMatch`). Emits for it are written again from now on.
+    ///
+    /// Default implementation does nothing.
+    fn on_unmute_match(&mut self, _subpack: u8, _external_match_id: &str) {}
+
+    /// Validate and apply a settings update.
+    ///
+    /// Called on `UpdateSettings`. Returning `Ok` answers with `Ack`;
+    /// returning `Err` rejects the update with an `Error` response carrying
+    /// your message and code, so the UI can show why. Validate everything
+    /// before applying anything, e.g. with a
+    /// [`SettingsSchema`](crate::SettingsSchema):
+    ///
+    /// ```rust,ignore
+    /// fn on_settings_update(&mut self, settings: Value) -> GamepackResult<()> {
+    ///     self.settings_schema.validate(&settings)?;
+    ///     self.settings.apply(settings);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// Default implementation accepts and ignores the settings.
+    fn on_settings_update(&mut self, _settings: serde_json::Value) -> GamepackResult<()> {
+        Ok(())
+    }
+
+    /// Check a trigger configuration the user is about to save.
+    ///
+    /// Called on `ValidateTriggerConfig`. The daemon shows each issue next to
+    /// the trigger it names in the trigger settings and won't save the
+    /// configuration until none are left. [`TriggerConfig::issues`] covers
+    /// the usual checks:
+    ///
+    /// ```rust,ignore
+    /// fn validate_trigger_config(&self, triggers: &[TriggerConfig]) -> Vec<TriggerIssue> {
+    ///     triggers.iter().flat_map(|t| t.issues(MOMENT_IDS)).collect()
+    /// }
+    /// ```
+    ///
+    /// Default implementation reports no issues.
+    fn validate_trigger_config(&self, _triggers: &[TriggerConfig]) -> Vec<TriggerIssue> {
+        vec![]
+    }
+
+    /// Decide whether to record a moment that fired a trigger.
+    ///
+    /// Called on `ShouldRecord`, for packs whose trigger logic is richer
+    /// than the daemon's config (e.g. skip kills while spectating, or widen
+    /// the window for a comeback). The daemon's config takes precedence: it
+    /// only asks about moments with an enabled trigger, so this can skip a
+    /// recording or change its [window](RecordDecision::with_window) but
+    /// never record a moment the user turned off. Declare
+    /// [`Capability::RecordDecisions`] when overriding this.
+    ///
+    /// Default implementation records with the configured window.
+    fn should_record(&self, _moment_id: &str, _data: &serde_json::Value) -> RecordDecision {
+        RecordDecision::record()
+    }
+
+    /// Describe how the pack turns events into moments for a subpack.
+    ///
+    /// Called on `GetTriggerRules`, for UI that explains which events can
+    /// lead to a clip. The rules are for display only; see [`TriggerRule`].
+    /// Declare [`Capability::TriggerRules`] when overriding this.
+    ///
+    /// Default implementation returns no rules.
+    fn get_trigger_rules(&self, _subpack: u8) -> Vec<TriggerRule> {
+        vec![]
+    }
+
+    /// Checkpoint in-memory state before the pack binary is replaced.
+    ///
+    /// Called on `PrepareUpdate`. Return state the new binary can reload
+    /// (e.g. the current match ID and accumulated stats); the daemon hands
+    /// it back through [`on_restore_state`](Self::on_restore_state) after
+    /// the restart. A `Shutdown` follows shortly after.
+    ///
+    /// Default implementation has nothing to save.
+    fn on_prepare_update(&mut self) -> GamepackResult<Option<serde_json::Value>> {
+        Ok(None)
+    }
+
+    /// Reload state checkpointed by the previous pack instance.
+    ///
+    /// Called on `RestoreState`, after `init`, with the value the old binary
+    /// returned from [`on_prepare_update`](Self::on_prepare_update).
+    ///
+    /// Default implementation ignores the state.
+    fn on_restore_state(&mut self, _state: serde_json::Value) -> GamepackResult<()> {
+        Ok(())
+    }
 }
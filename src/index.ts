@@ -55,6 +55,7 @@ export type {
   GameEvent,
   GameStatus,
   MatchData,
+  PlayerIdentity,
 } from "./types";
 
 // Export React context and hooks for pack sandboxed APIs
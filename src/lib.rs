@@ -23,6 +23,9 @@
 //!             game_id: 99,
 //!             slug: "my-game".to_string(),
 //!             protocol_version: 1,
+//!             pack_version: gamepack_runtime::pack_version!(),
+//!             game_version: None,
+//!             player: None,
 //!         })
 //!     }
 //!
@@ -49,22 +52,76 @@
 //! - Each command has a `request_id` for correlation
 //!
 //! See [`GamepackCommand`] and [`GamepackResponse`] for the full protocol.
+//!
+//! ## Testing
+//!
+//! Hold an [`Emitter`] in your handler and emit through it. In tests, build
+//! it with [`testing::capturing_emitter`] to assert on emitted messages
+//! without touching stdout.
+//!
+//! ## Features
+//!
+//! - `binary-timeline`: compact binary encoding for timeline batches
+//!   (`types::encode_timeline` / `types::decode_timeline`).
 
+pub mod channel;
+pub mod clock;
 pub mod commands;
+pub mod connection;
+pub mod consistency;
+pub mod debug;
+pub mod derived;
+pub mod emitter;
+pub mod framing;
 pub mod handler;
+pub mod metrics;
+pub mod patch;
+pub mod push;
 pub mod responses;
 pub mod runner;
+pub mod sample;
+pub mod schema;
+pub mod stats;
+pub mod template;
+pub mod testing;
+pub mod timeline;
+pub mod transport;
 pub mod types;
 pub mod version;
 
 // Re-export main types at crate root for convenience
+pub use channel::ChannelEmitter;
+pub use clock::{Clock, FixedClock, SystemClock};
 pub use commands::GamepackCommand;
-pub use handler::{GamepackError, GamepackHandler, GamepackResult};
-pub use responses::GamepackResponse;
-pub use runner::{emit_game_events, emit_match_data, emit_moments, emit_statistics, run_gamepack};
+pub use connection::{ConnectionEvent, ConnectionState, ConnectionStateMachine};
+pub use consistency::KeyConsistencyChecker;
+pub use derived::{eval_derived, DerivedStatSpec};
+pub use emitter::{EmitError, EmitSink, EmitStats, Emitter, MatchSwitch, StdoutSink};
+pub use handler::{GamepackError, GamepackHandler, GamepackResult, SessionEndTask, SessionStartTask};
+pub use metrics::{InMemoryMetrics, Metrics};
+pub use responses::{GamepackResponse, Responder};
+pub use runner::{
+    active_subpack, emit_abandoned, emit_game_events, emit_match_data, emit_moments,
+    emit_statistics, run_gamepack, run_gamepack_with_connections, run_gamepack_with_options,
+    run_gamepack_with_transport, session_end_progress, warn, PanicPolicy, RunOutcome, RunnerOptions,
+};
+pub use timeline::{MatchSummaryBuffer, TimelineBuffer};
+pub use transport::{StdioTransport, StreamTransport, Transport};
 pub use types::{
-    EntryType, GameEvent, GameStatus, GetMatchTimelineRequest, GetMatchTimelineResponse,
-    InitResponse, IsMatchInProgressRequest, IsMatchInProgressResponse, MatchData,
-    MatchDataMessage, Moment, SummarySource, TimelineEntry,
+    match_duration_secs, merge_capture_windows, normalize_numbers, sort_events_chronological,
+    BuildInfo, Capability, CaptureWindow, ClipPlan, EntryType, ErrorInfo, GameEvent, GameStatus,
+    GetMatchTimelineRequest, GetMatchTimelineResponse, InitResponse, IsMatchInProgressRequest,
+    IsMatchInProgressResponse, MatchData, MatchDataMessage, MatchId, MatchResult, MatchSummary,
+    Moment, ObjectiveEvent, PlayerIdentity, PlayerRow, RecordDecision, Scoreboard, StatValue,
+    StorageStatus, SummarySource, Team, TimelineEntry, Timestamp, TriggerConfig, TriggerIssue,
+    TriggerRule, MAX_SAFE_INTEGER, SCOREBOARD_KEY,
+};
+pub use patch::JsonPatchOp;
+pub use push::PushedEvents;
+pub use sample::{SampleGenerator, SampleRng};
+pub use schema::{
+    format_value, ColumnSpec, FieldKind, LiveDataSchema, SettingsSchema, ValueFormat,
 };
-pub use version::PROTOCOL_VERSION;
+pub use stats::StatsAccumulator;
+pub use template::render_template;
+pub use version::{ProtocolChange, PROTOCOL_VERSION};
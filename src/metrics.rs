@@ -0,0 +1,112 @@
+//! Optional instrumentation of what goes over the pipe.
+//!
+//! Attach a [`Metrics`] implementation to the [`Emitter`](crate::Emitter)
+//! with [`with_metrics`](crate::Emitter::with_metrics). Both the emit path
+//! and the runner's responses (which go through the global emitter's hooks)
+//! report to it, so installing the emitter globally covers everything the
+//! pack writes:
+//!
+//! ```rust
+//! use gamepack_runtime::metrics::InMemoryMetrics;
+//! use gamepack_runtime::Emitter;
+//!
+//! let metrics = InMemoryMetrics::new();
+//! let emitter = Emitter::stdout().with_metrics(metrics.clone());
+//! // emitter.install_global() ... later, e.g. from a debug command:
+//! println!("{:?}", metrics.bytes_by_type());
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::responses::GamepackResponse;
+
+/// Receives measurements from the runtime. Implementations must be cheap:
+/// they are called on the write path.
+pub trait Metrics: Send + Sync {
+    /// A message of `message_type` was serialized to `bytes` bytes.
+    ///
+    /// `bytes` is the serialized JSON length, before the trailing newline.
+    /// `message_type` is the response variant name (e.g. `"Events"`), with
+    /// `WriteMatchData` broken down by its inner message (e.g.
+    /// `"WriteStatistics"`).
+    fn record_bytes(&self, message_type: &str, bytes: usize);
+}
+
+/// Name a response is recorded under; see [`Metrics::record_bytes`].
+pub(crate) fn message_type(response: &GamepackResponse) -> &'static str {
+    match response {
+        GamepackResponse::WriteMatchData { message, .. } => message.into(),
+        response => response.into(),
+    }
+}
+
+/// [`Metrics`] that aggregates byte counts in memory.
+///
+/// Clones share the same counts, so keep one clone for reading and hand the
+/// other to the [`Emitter`](crate::Emitter).
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryMetrics {
+    bytes: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl InMemoryMetrics {
+    /// Create metrics with nothing recorded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bytes recorded across all message types.
+    pub fn total_bytes(&self) -> u64 {
+        self.bytes.lock().unwrap().values().sum()
+    }
+
+    /// Bytes recorded for one message type (0 if none).
+    pub fn bytes_for(&self, message_type: &str) -> u64 {
+        self.bytes
+            .lock()
+            .unwrap()
+            .get(message_type)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Bytes recorded per message type.
+    pub fn bytes_by_type(&self) -> HashMap<String, u64> {
+        self.bytes.lock().unwrap().clone()
+    }
+}
+
+impl Metrics for InMemoryMetrics {
+    fn record_bytes(&self, message_type: &str, bytes: usize) {
+        *self
+            .bytes
+            .lock()
+            .unwrap()
+            .entry(message_type.to_string())
+            .or_default() += bytes as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::capturing_emitter;
+
+    #[test]
+    fn emits_record_serialized_length_without_newline() {
+        let metrics = InMemoryMetrics::new();
+        let (emitter, _sink) = capturing_emitter();
+        let emitter = emitter.with_metrics(metrics.clone());
+
+        emitter.emit_moments(0, "m1", vec![]).unwrap();
+        emitter.emit_moments(0, "m1", vec![]).unwrap();
+
+        let line = concat!(
+            r#"{"type":"write_match_data","message":{"type":"write_moments","subpack":0,"#,
+            r#""external_match_id":"m1","moments":[]}}"#,
+        );
+        assert_eq!(metrics.bytes_for("WriteMoments"), 2 * line.len() as u64);
+        assert_eq!(metrics.total_bytes(), 2 * line.len() as u64);
+    }
+}
@@ -0,0 +1,259 @@
+//! JSON Patch (RFC 6902) support for incremental live-data updates.
+//!
+//! Instead of re-sending a large live-data blob to change one nested value,
+//! the runner can send a `LiveDataPatch` with the operations that turn the
+//! daemon's last-known live data into the new one. Only the `add`, `remove`
+//! and `replace` operations are produced and applied.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::handler::{GamepackError, GamepackResult};
+
+/// A single JSON Patch operation.
+///
+/// `path` is a JSON Pointer (RFC 6901), e.g. `"/players/0/kills"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JsonPatchOp {
+    /// Add a member to an object, or insert into an array (`-` appends).
+    Add { path: String, value: Value },
+    /// Remove the value at `path`.
+    Remove { path: String },
+    /// Replace the existing value at `path`.
+    Replace { path: String, value: Value },
+}
+
+/// Compute the operations that turn `from` into `to`.
+///
+/// Objects are compared member by member; arrays and scalars that differ
+/// are replaced as a whole. Applying the result to `from` with [`apply`]
+/// yields `to`.
+pub fn diff(from: &Value, to: &Value) -> Vec<JsonPatchOp> {
+    let mut ops = Vec::new();
+    diff_at(String::new(), from, to, &mut ops);
+    ops
+}
+
+fn diff_at(path: String, from: &Value, to: &Value, ops: &mut Vec<JsonPatchOp>) {
+    match (from, to) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, old) in a {
+                let child = format!("{}/{}", path, escape(key));
+                match b.get(key) {
+                    Some(new) => diff_at(child, old, new, ops),
+                    None => ops.push(JsonPatchOp::Remove { path: child }),
+                }
+            }
+            for (key, new) in b {
+                if !a.contains_key(key) {
+                    ops.push(JsonPatchOp::Add {
+                        path: format!("{}/{}", path, escape(key)),
+                        value: new.clone(),
+                    });
+                }
+            }
+        }
+        _ if from != to => ops.push(JsonPatchOp::Replace {
+            path,
+            value: to.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Apply operations to `target` in order.
+///
+/// Fails with code `"invalid_patch"` if a path does not resolve. Operations
+/// before the failing one have already been applied.
+pub fn apply(target: &mut Value, ops: &[JsonPatchOp]) -> GamepackResult<()> {
+    for op in ops {
+        match op {
+            JsonPatchOp::Add { path, value } => add(target, path, value.clone())?,
+            JsonPatchOp::Remove { path } => {
+                remove(target, path)?;
+            }
+            JsonPatchOp::Replace { path, value } => {
+                let slot = target
+                    .pointer_mut(path)
+                    .ok_or_else(|| invalid(path, "path does not exist"))?;
+                *slot = value.clone();
+            }
+        }
+    }
+    Ok(())
+}
+
+fn add(target: &mut Value, path: &str, value: Value) -> GamepackResult<()> {
+    let Some((parent, key)) = split(path) else {
+        *target = value;
+        return Ok(());
+    };
+    match target.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(key, value);
+        }
+        Some(Value::Array(items)) => {
+            let index = if key == "-" {
+                items.len()
+            } else {
+                array_index(&key, items.len() + 1).ok_or_else(|| invalid(path, "bad index"))?
+            };
+            items.insert(index, value);
+        }
+        _ => return Err(invalid(path, "parent is not a container")),
+    }
+    Ok(())
+}
+
+fn remove(target: &mut Value, path: &str) -> GamepackResult<Value> {
+    let (parent, key) = split(path).ok_or_else(|| invalid(path, "cannot remove the root"))?;
+    let removed = match target.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.remove(&key),
+        Some(Value::Array(items)) => {
+            array_index(&key, items.len()).map(|index| items.remove(index))
+        }
+        _ => None,
+    };
+    removed.ok_or_else(|| invalid(path, "path does not exist"))
+}
+
+/// Split a pointer into its parent pointer and unescaped last segment.
+/// Returns `None` for the root pointer `""`.
+fn split(path: &str) -> Option<(&str, String)> {
+    let (parent, last) = path.rsplit_once('/')?;
+    Some((parent, last.replace("~1", "/").replace("~0", "~")))
+}
+
+/// Parse an array index segment, requiring it to be below `bound`.
+fn array_index(segment: &str, bound: usize) -> Option<usize> {
+    if segment.len() > 1 && segment.starts_with('0') {
+        return None;
+    }
+    segment.parse().ok().filter(|index| *index < bound)
+}
+
+/// Escape an object key as a JSON Pointer segment.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn invalid(path: &str, reason: &str) -> GamepackError {
+    GamepackError::with_code(format!("Invalid patch at '{}': {}", path, reason), "invalid_patch")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_produces_add_replace_remove() {
+        let from = json!({"gold": 500, "items": ["Boots"], "dead": false});
+        let to = json!({"gold": 650, "items": ["Boots"], "level": 3});
+
+        let ops = diff(&from, &to);
+
+        assert_eq!(
+            ops,
+            vec![
+                JsonPatchOp::Remove {
+                    path: "/dead".into()
+                },
+                JsonPatchOp::Replace {
+                    path: "/gold".into(),
+                    value: json!(650)
+                },
+                JsonPatchOp::Add {
+                    path: "/level".into(),
+                    value: json!(3)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_then_apply_round_trips_nested_values() {
+        let from = json!({"player": {"stats": {"kills": 1, "a/b": 0}, "name": "Me"}, "t": 1});
+        let to = json!({"player": {"stats": {"kills": 2, "deaths": 1}, "name": "Me"}, "t": [1]});
+
+        let ops = diff(&from, &to);
+        let mut patched = from.clone();
+        apply(&mut patched, &ops).unwrap();
+
+        assert_eq!(patched, to);
+        assert!(ops.iter().any(|op| matches!(
+            op,
+            JsonPatchOp::Remove { path } if path == "/player/stats/a~1b"
+        )));
+    }
+
+    #[test]
+    fn diff_of_equal_values_is_empty() {
+        let value = json!({"a": [1, 2], "b": {"c": null}});
+        assert!(diff(&value, &value).is_empty());
+    }
+
+    #[test]
+    fn apply_array_ops() {
+        let mut value = json!({"items": ["Boots", "Sword"]});
+
+        apply(
+            &mut value,
+            &[
+                JsonPatchOp::Add {
+                    path: "/items/-".into(),
+                    value: json!("Shield"),
+                },
+                JsonPatchOp::Add {
+                    path: "/items/0".into(),
+                    value: json!("Potion"),
+                },
+                JsonPatchOp::Remove {
+                    path: "/items/2".into(),
+                },
+                JsonPatchOp::Replace {
+                    path: "/items/1".into(),
+                    value: json!("Greaves"),
+                },
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(value, json!({"items": ["Potion", "Greaves", "Shield"]}));
+    }
+
+    #[test]
+    fn apply_rejects_missing_paths() {
+        let mut value = json!({"a": 1});
+
+        for op in [
+            JsonPatchOp::Remove { path: "/b".into() },
+            JsonPatchOp::Replace {
+                path: "/b".into(),
+                value: json!(2),
+            },
+            JsonPatchOp::Add {
+                path: "/x/y".into(),
+                value: json!(2),
+            },
+        ] {
+            let err = apply(&mut value, &[op]).unwrap_err();
+            assert_eq!(err.code.as_deref(), Some("invalid_patch"));
+        }
+        assert_eq!(value, json!({"a": 1}));
+    }
+
+    #[test]
+    fn ops_serialize_as_rfc6902() {
+        let op = JsonPatchOp::Replace {
+            path: "/gold".into(),
+            value: json!(650),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&op).unwrap(),
+            json!({"op": "replace", "path": "/gold", "value": 650})
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Pushing events without reporting them twice.
+//!
+//! A pack can push events the moment it sees them with
+//! [`Emitter::emit_events`] and still answer `PollEvents`. If the same
+//! event source feeds both, the next poll would repeat what was just
+//! pushed. [`PushedEvents`] remembers what went out on the push path so
+//! the poll path can skip it:
+//!
+//! ```rust
+//! use gamepack_runtime::push::PushedEvents;
+//! use gamepack_runtime::testing::capturing_emitter;
+//! use gamepack_runtime::GameEvent;
+//! use serde_json::json;
+//!
+//! let (emitter, _sink) = capturing_emitter();
+//! let pushed = PushedEvents::new();
+//! let kill = GameEvent::new("Kill", 12.0, json!({"victim": "Zed"}));
+//!
+//! // Background thread:
+//! pushed.push(&emitter, 0, "m1", vec![kill.clone()]).unwrap();
+//!
+//! // poll_events, with the same event read again from the game:
+//! assert!(pushed.take_unpushed(vec![kill]).is_empty());
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::emitter::{EmitError, Emitter};
+use crate::types::GameEvent;
+
+/// What makes two events the same event: type, timestamp and data, the
+/// identity the daemon dedupes by.
+type EventKey = (String, u64, String);
+
+fn key(event: &GameEvent) -> EventKey {
+    (
+        event.event_type.clone(),
+        event.timestamp_secs.to_bits(),
+        event.data.to_string(),
+    )
+}
+
+/// Record of events pushed but not yet seen by a poll.
+///
+/// Clones share the same record, so hand one to the background thread that
+/// pushes and keep the other in the handler.
+#[derive(Debug, Clone, Default)]
+pub struct PushedEvents {
+    pushed: Arc<Mutex<HashMap<EventKey, usize>>>,
+}
+
+impl PushedEvents {
+    /// Create a record with nothing pushed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Push `events` with [`Emitter::emit_events`] and remember them.
+    ///
+    /// They are remembered before the emit, so a poll racing with it cannot
+    /// report them again, and forgotten if the emit fails.
+    pub fn push(
+        &self,
+        emitter: &Emitter,
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        events: Vec<GameEvent>,
+    ) -> Result<(), EmitError> {
+        let keys: Vec<EventKey> = events.iter().map(key).collect();
+        {
+            let mut pushed = self.pushed.lock().unwrap();
+            for key in &keys {
+                *pushed.entry(key.clone()).or_default() += 1;
+            }
+        }
+        let result = emitter.emit_events(subpack, external_match_id, events);
+        if result.is_err() {
+            let mut pushed = self.pushed.lock().unwrap();
+            for key in &keys {
+                if let Some(n) = pushed.get_mut(key) {
+                    *n -= 1;
+                    if *n == 0 {
+                        pushed.remove(key);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Drop the events in `polled` that were already pushed and return the
+    /// rest, in order.
+    ///
+    /// Each pushed event suppresses one polled copy and is then forgotten,
+    /// so a genuinely repeated event (two identical pushes, or a push and a
+    /// later identical occurrence) is still reported.
+    pub fn take_unpushed(&self, polled: Vec<GameEvent>) -> Vec<GameEvent> {
+        let mut pushed = self.pushed.lock().unwrap();
+        polled
+            .into_iter()
+            .filter(|event| {
+                let key = key(event);
+                match pushed.get_mut(&key) {
+                    Some(n) => {
+                        *n -= 1;
+                        if *n == 0 {
+                            pushed.remove(&key);
+                        }
+                        false
+                    }
+                    None => true,
+                }
+            })
+            .collect()
+    }
+
+    /// Pushed events no poll has matched yet.
+    pub fn pending(&self) -> usize {
+        self.pushed.lock().unwrap().values().sum()
+    }
+
+    /// Forget everything pushed, e.g. when a match ends.
+    pub fn clear(&self) {
+        self.pushed.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::responses::GamepackResponse;
+    use crate::testing::capturing_emitter;
+    use serde_json::json;
+
+    #[test]
+    fn poll_skips_events_already_pushed() {
+        let (emitter, sink) = capturing_emitter();
+        let pushed = PushedEvents::new();
+        let kill = GameEvent::new("Kill", 12.0, json!({"victim": "Zed"}));
+        let assist = GameEvent::new("Assist", 12.0, json!({"victim": "Zed"}));
+
+        pushed.push(&emitter, 0, "m1", vec![kill.clone(), kill.clone()]).unwrap();
+        assert_eq!(pushed.pending(), 2);
+
+        let polled = vec![kill.clone(), assist, kill.clone(), kill];
+        let rest = pushed.take_unpushed(polled);
+        let types: Vec<&str> = rest.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types, vec!["Assist", "Kill"]);
+        assert_eq!(pushed.pending(), 0);
+
+        let responses = sink.responses();
+        assert_eq!(responses.len(), 1);
+        assert!(matches!(
+            &responses[0],
+            GamepackResponse::Events { events, .. } if events.len() == 2
+        ));
+    }
+
+    #[test]
+    fn failed_push_is_not_remembered() {
+        let (emitter, _sink) = capturing_emitter();
+        let pushed = PushedEvents::new();
+        let bad = GameEvent::new("Kill", f64::NAN, json!({}));
+
+        assert!(pushed.push(&emitter, 0, "m1", vec![bad]).is_err());
+        assert_eq!(pushed.pending(), 0);
+    }
+
+    /// Sink that runs a poll while the push is being written.
+    #[derive(Clone)]
+    struct PollingSink {
+        pushed: PushedEvents,
+        polled: GameEvent,
+        reported: Arc<Mutex<Vec<GameEvent>>>,
+    }
+
+    impl crate::emitter::EmitSink for PollingSink {
+        fn emit(&self, _response: &GamepackResponse, _line: &str) -> std::io::Result<()> {
+            let rest = self.pushed.take_unpushed(vec![self.polled.clone()]);
+            self.reported.lock().unwrap().extend(rest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn poll_during_the_emit_already_skips_the_push() {
+        let pushed = PushedEvents::new();
+        let kill = GameEvent::new("Kill", 12.0, json!({"victim": "Zed"}));
+        let sink = PollingSink {
+            pushed: pushed.clone(),
+            polled: kill.clone(),
+            reported: Arc::default(),
+        };
+        let emitter = Emitter::new(sink.clone());
+
+        pushed.push(&emitter, 0, "m1", vec![kill]).unwrap();
+        assert!(sink.reported.lock().unwrap().is_empty());
+        assert_eq!(pushed.pending(), 0);
+    }
+}
@@ -2,12 +2,20 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::types::{GameEvent, MatchDataMessage, TimelineEntry};
+use crate::derived::DerivedStatSpec;
+use crate::handler::{GamepackError, GamepackResult};
+use crate::patch::JsonPatchOp;
+use crate::schema::LiveDataSchema;
+use crate::types::{
+    count, ensure_finite, BuildInfo, Capability, CaptureWindow, ErrorInfo, GameEvent, GameStatus,
+    GetMatchTimelineResponse, IsMatchInProgressResponse, MatchDataMessage, MatchSummary,
+    PlayerIdentity, RecordDecision, TimelineEntry, Timestamp, TriggerIssue, TriggerRule,
+};
 
 /// Responses from a gamepack to the main daemon.
 ///
 /// Each response includes the `request_id` from the corresponding command.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, strum::IntoStaticStr)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum GamepackResponse {
     /// Initialization complete.
@@ -17,8 +25,32 @@ pub enum GamepackResponse {
         game_id: i32,
         /// URL-friendly slug (e.g., "league", "valorant")
         slug: String,
-        /// Protocol version this pack implements
+        /// Protocol version for this session: the pack's version, lowered to
+        /// the daemon's if it sent an older one in `Init`
         protocol_version: u32,
+        /// Build version of the pack (informational; absent for older packs)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pack_version: Option<String>,
+        /// Optional protocol features enabled for this session (the subset of
+        /// the daemon's `daemon_features` this runtime supports)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        features: Vec<String>,
+        /// Stats the daemon computes from base stats (see [`DerivedStatSpec`])
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        derived_stats: Vec<DerivedStatSpec>,
+        /// Game version or patch, if known at init (game-defined format)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        game_version: Option<String>,
+        /// Player signed in to the game, if known at init
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        player: Option<PlayerIdentity>,
+        /// Features the pack supports, sorted (absent for older packs)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        capabilities: Vec<Capability>,
+        /// Identifies this run of the pack process; see
+        /// [`run_gamepack`](crate::run_gamepack#connection-id)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        connection_id: Option<String>,
     },
 
     /// Game running status.
@@ -39,20 +71,98 @@ pub enum GamepackResponse {
         game_phase: Option<String>,
         /// Whether the player is actively in a game
         is_in_game: bool,
+        /// Game version or patch, in a game-defined format
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        game_version: Option<String>,
+        /// Player signed in to the game client, once known
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        player: Option<PlayerIdentity>,
     },
 
-    /// Polled events.
+    /// Response to GetGameVersion command.
+    GameVersion {
+        request_id: String,
+        /// Game version or patch (e.g. "14.3"), in a game-defined format;
+        /// None if not known yet
+        version: Option<String>,
+    },
+
+    /// Response to GetBuildInfo command; see [`BuildInfo`].
+    BuildInfo {
+        request_id: String,
+        /// Build version of the pack
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pack_version: Option<String>,
+        /// Target triple, e.g. "x86_64-pc-windows-msvc"
+        target_triple: String,
+        /// Cargo profile, e.g. "release"
+        profile: String,
+        /// Enabled cargo features
+        features: Vec<String>,
+        /// Compiler that built the pack
+        rustc_version: String,
+    },
+
+    /// Polled events, or events pushed as they happen.
+    ///
+    /// Pushed events (see [`Emitter::emit_events`](crate::Emitter::emit_events))
+    /// are unsolicited: they have an empty `request_id` and name their match.
     Events {
         request_id: String,
         /// New game events since last poll
         events: Vec<GameEvent>,
+        /// Non-fatal problems noticed while polling; see [`Warning`](Self::Warning)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<String>,
+        /// Subpack of the match the events belong to; set on pushed events
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subpack: Option<u8>,
+        /// Match the events belong to; set on pushed events
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        external_match_id: Option<String>,
     },
 
     /// Live match data.
+    ///
+    /// No `data` means there is nothing to show right now (no game yet, or
+    /// not loaded); the daemon keeps showing what it last had. `cleared`
+    /// means the pack explicitly cleared its live data, e.g. because the
+    /// match ended, and the daemon should hide the live panel. A handler
+    /// signals it by returning `Some(Value::Null)` from `get_live_data`.
     LiveData {
         request_id: String,
         /// Game-specific live match data (stats, scores, etc.)
         data: Option<serde_json::Value>,
+        /// Whether live data was explicitly cleared; `data` is then absent
+        #[serde(default, skip_serializing_if = "crate::types::is_false")]
+        cleared: bool,
+        /// Non-fatal problems noticed while collecting it; see
+        /// [`Warning`](Self::Warning)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<String>,
+    },
+
+    /// Incremental live data update (RFC 6902 JSON Patch).
+    ///
+    /// Only sent when `live_data_patch` was negotiated at init. The daemon
+    /// applies `ops` to its last-known live data. When there is no base yet
+    /// (first update, or after live data was cleared) a full `LiveData` is
+    /// sent instead.
+    LiveDataPatch {
+        request_id: String,
+        /// Operations to apply, in order
+        ops: Vec<JsonPatchOp>,
+    },
+
+    /// Live data for every tracked match.
+    ///
+    /// Each entry pairs an `external_match_id` with that match's live data,
+    /// serialized as a two-element array `["<id>", {...}]`. Matches with no
+    /// data are left out, so an empty list means nothing is live.
+    LiveDataAll {
+        request_id: String,
+        /// `(external_match_id, data)` per match, in display order
+        matches: Vec<(String, serde_json::Value)>,
     },
 
     /// Session started acknowledgment.
@@ -69,6 +179,34 @@ pub enum GamepackResponse {
         match_data: Option<serde_json::Value>,
     },
 
+    /// How far a slow session end has got, written through the emitter
+    /// while the handler works; see
+    /// [`session_end_progress`](crate::session_end_progress).
+    ///
+    /// Carries the `SessionEnd` command's `request_id`. Any number of these
+    /// may precede the `SessionEnded` for that id, never follow it.
+    SessionEndProgress {
+        request_id: String,
+        /// Fraction done, from 0.0 to 1.0
+        progress: f32,
+        /// What the pack is doing, for the daemon's UI
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+
+    /// One piece of a `SessionEnded` whose match data was too large for one
+    /// line; see [`GamepackResponse::chunk_session_end`]. Only sent when
+    /// `session_end_chunks` was negotiated.
+    SessionEndedChunk {
+        request_id: String,
+        /// Position of this piece, from 0
+        seq: u32,
+        /// Whether this is the final piece
+        last: bool,
+        /// Next slice of the match data's JSON text
+        data: String,
+    },
+
     /// Error response.
     Error {
         request_id: String,
@@ -78,9 +216,31 @@ pub enum GamepackResponse {
         code: Option<String>,
     },
 
+    /// Something went wrong that the daemon should know about, but did not
+    /// stop the pack from doing its job.
+    ///
+    /// Unlike `Error`, a warning never answers a command: the command still
+    /// gets its normal response, and the daemon only logs or surfaces the
+    /// warning. A warning raised while handling a command (see
+    /// [`warn`](crate::warn)) carries that command's `request_id` and is
+    /// written just before its response; one sent from
+    /// [`Emitter::emit_warning`](crate::Emitter::emit_warning) has an empty
+    /// `request_id`. `Events` and `LiveData` carry their warnings inline in
+    /// `warnings` instead.
+    Warning {
+        request_id: String,
+        /// Human-readable description
+        message: String,
+        /// Optional code for programmatic handling, e.g. `"partial_data"`
+        code: Option<String>,
+    },
+
     /// Shutdown complete.
     ShutdownComplete { request_id: String },
 
+    /// Generic acknowledgment for commands with no result payload.
+    Ack { request_id: String },
+
     /// Event icon resolved.
     EventIconResolved {
         request_id: String,
@@ -104,6 +264,20 @@ pub enum GamepackResponse {
         set_complete: Option<MatchDataMessage>,
     },
 
+    /// Response to MatchExists command.
+    MatchExistsResult {
+        request_id: String,
+        /// Whether the pack knows the match (finished or not)
+        exists: bool,
+    },
+
+    /// Response to GetActiveMatches command.
+    ActiveMatches {
+        request_id: String,
+        /// `(subpack, external_match_id)` of each active match, sorted
+        matches: Vec<(u8, String)>,
+    },
+
     /// Response to GetMatchTimeline command.
     MatchTimeline {
         request_id: String,
@@ -113,6 +287,20 @@ pub enum GamepackResponse {
         entries: Vec<TimelineEntry>,
     },
 
+    /// Response to GetRecentMatches command.
+    RecentMatches {
+        request_id: String,
+        /// Finished matches, newest first (empty if the pack keeps none)
+        matches: Vec<MatchSummary>,
+    },
+
+    /// Response to RunRecovery command, after the recovered match data.
+    RecoveryComplete {
+        request_id: String,
+        /// Match data messages the pack wrote during recovery
+        recovered: usize,
+    },
+
     // ========================================================================
     // MATCH DATA (gamepack → daemon, unsolicited)
     // ========================================================================
@@ -122,6 +310,31 @@ pub enum GamepackResponse {
     WriteMatchData {
         /// The match data message to process
         message: MatchDataMessage,
+        /// Label of the thread/worker that emitted the message.
+        ///
+        /// Local diagnostics only: set by the emitter when source tagging is
+        /// enabled (see [`Emitter::set_source`](crate::Emitter::set_source))
+        /// and omitted in production.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        /// Connection id of the run that wrote the message, when the emitter
+        /// has [connection tags](crate::Emitter::with_connection_tags) enabled
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        connection_id: Option<String>,
+    },
+
+    /// The game clock of a running match, sent periodically even when
+    /// nothing else changed. See
+    /// [`Emitter::emit_heartbeat`](crate::Emitter::emit_heartbeat).
+    Heartbeat {
+        /// Subpack index
+        subpack: u8,
+        /// Game's native match ID
+        external_match_id: String,
+        /// Current in-game time in seconds
+        game_time_secs: f64,
+        /// When the heartbeat was sent
+        wall_clock: Timestamp,
     },
 
     // ========================================================================
@@ -136,29 +349,469 @@ pub enum GamepackResponse {
         /// Match data as JSON (pack-specific schema, same format as MatchCard expects)
         data: serde_json::Value,
     },
+
+    /// Response to GetSchema command.
+    Schema {
+        request_id: String,
+        /// Subpack index this schema is for
+        subpack: u8,
+        /// Schema declared in code; None if the pack only uses config.json
+        schema: Option<LiveDataSchema>,
+    },
+
+    // ========================================================================
+    // DERIVED STATS
+    // ========================================================================
+
+    /// Response to GetDerivedStats command.
+    DerivedStats {
+        request_id: String,
+        /// Current derived stat declarations
+        specs: Vec<DerivedStatSpec>,
+    },
+
+    // ========================================================================
+    // HEALTH
+    // ========================================================================
+
+    /// Response to GetLastError command.
+    LastError {
+        request_id: String,
+        /// Most recent error since the last successful init, if any
+        error: Option<ErrorInfo>,
+    },
+
+    /// Response to TimeSync command.
+    TimeSyncResult {
+        request_id: String,
+        /// Echoed from the command
+        daemon_send_ns: u64,
+        /// Pack clock when the command was handled
+        pack_recv_ns: u64,
+        /// Pack clock when this response was built
+        pack_send_ns: u64,
+    },
+
+    // ========================================================================
+    // TRIGGERS
+    // ========================================================================
+
+    /// Response to ValidateTriggerConfig command.
+    TriggerConfigValidation {
+        request_id: String,
+        /// Problems found; empty when the configuration is valid
+        issues: Vec<TriggerIssue>,
+    },
+
+    /// Response to GetTriggerRules command.
+    TriggerRules {
+        request_id: String,
+        /// Subpack the rules are for
+        subpack: u8,
+        /// The pack's event-to-moment rules; empty if it describes none
+        rules: Vec<TriggerRule>,
+    },
+
+    /// Response to ShouldRecord command; see [`RecordDecision`].
+    RecordDecision {
+        request_id: String,
+        /// Whether to record the moment
+        record: bool,
+        /// Why, for the daemon's logs
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+        /// Capture window to use instead of the trigger's configured one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        window: Option<CaptureWindow>,
+    },
+
+    // ========================================================================
+    // UPDATES
+    // ========================================================================
+
+    /// Response to PrepareUpdate command.
+    UpdatePrepared {
+        request_id: String,
+        /// Checkpoint to send back via `RestoreState` after the restart
+        state: Option<serde_json::Value>,
+    },
 }
 
 impl GamepackResponse {
     /// Get the request_id from any response variant.
-    /// Returns empty string for unsolicited messages (WriteMatchData).
+    /// Returns empty string for unsolicited messages (WriteMatchData, Heartbeat).
     pub fn request_id(&self) -> &str {
         match self {
             Self::Initialized { request_id, .. } => request_id,
             Self::RunningStatus { request_id, .. } => request_id,
             Self::GameStatus { request_id, .. } => request_id,
+            Self::GameVersion { request_id, .. } => request_id,
+            Self::BuildInfo { request_id, .. } => request_id,
             Self::Events { request_id, .. } => request_id,
             Self::LiveData { request_id, .. } => request_id,
+            Self::LiveDataPatch { request_id, .. } => request_id,
+            Self::LiveDataAll { request_id, .. } => request_id,
             Self::SessionStarted { request_id, .. } => request_id,
             Self::SessionEnded { request_id, .. } => request_id,
+            Self::SessionEndedChunk { request_id, .. } => request_id,
+            Self::SessionEndProgress { request_id, .. } => request_id,
             Self::Error { request_id, .. } => request_id,
+            Self::Warning { request_id, .. } => request_id,
             Self::ShutdownComplete { request_id, .. } => request_id,
+            Self::Ack { request_id } => request_id,
             Self::EventIconResolved { request_id, .. } => request_id,
             Self::MatchInProgressStatus { request_id, .. } => request_id,
+            Self::MatchExistsResult { request_id, .. } => request_id,
+            Self::ActiveMatches { request_id, .. } => request_id,
+            Self::RecoveryComplete { request_id, .. } => request_id,
             Self::MatchTimeline { request_id, .. } => request_id,
+            Self::RecentMatches { request_id, .. } => request_id,
             Self::SampleMatchData { request_id, .. } => request_id,
-            // WriteMatchData is unsolicited, no request_id
-            Self::WriteMatchData { .. } => "",
+            Self::Schema { request_id, .. } => request_id,
+            Self::DerivedStats { request_id, .. } => request_id,
+            Self::LastError { request_id, .. } => request_id,
+            Self::TimeSyncResult { request_id, .. } => request_id,
+            Self::TriggerConfigValidation { request_id, .. } => request_id,
+            Self::RecordDecision { request_id, .. } => request_id,
+            Self::TriggerRules { request_id, .. } => request_id,
+            Self::UpdatePrepared { request_id, .. } => request_id,
+            // Unsolicited, no request_id
+            Self::WriteMatchData { .. } | Self::Heartbeat { .. } => "",
+        }
+    }
+
+    /// One-line human-readable summary for logs.
+    ///
+    /// For example `"Events[id=req_1, 2 events]"`. Unsolicited
+    /// `WriteMatchData` is summarized by its inner message. This is for
+    /// diagnostics only and does not affect the wire format.
+    pub fn describe(&self) -> String {
+        let name: &'static str = self.into();
+        let id = self.request_id();
+        let details = match self {
+            Self::Initialized {
+                game_id,
+                slug,
+                protocol_version,
+                pack_version,
+                ..
+            } => match pack_version {
+                Some(pack) => format!(
+                    "id={}, game={}/{}, v{}, pack={}",
+                    id, game_id, slug, protocol_version, pack
+                ),
+                None => format!("id={}, game={}/{}, v{}", id, game_id, slug, protocol_version),
+            },
+            Self::RunningStatus { running, .. } => format!("id={}, running={}", id, running),
+            Self::GameStatus {
+                connected,
+                game_phase,
+                is_in_game,
+                ..
+            } => format!(
+                "id={}, connected={}, phase={}, in_game={}",
+                id,
+                connected,
+                game_phase.as_deref().unwrap_or("-"),
+                is_in_game
+            ),
+            Self::GameVersion { version, .. } => {
+                format!("id={}, version={}", id, version.as_deref().unwrap_or("-"))
+            }
+            Self::BuildInfo {
+                pack_version,
+                target_triple,
+                profile,
+                ..
+            } => format!(
+                "id={}, pack={}, {} {}",
+                id,
+                pack_version.as_deref().unwrap_or("-"),
+                target_triple,
+                profile
+            ),
+            Self::Events {
+                events,
+                subpack,
+                external_match_id,
+                ..
+            } => match (subpack, external_match_id) {
+                (Some(subpack), Some(match_id)) => format!(
+                    "subpack={}, match={}, {}",
+                    subpack,
+                    match_id,
+                    count(events.len(), "event", "events")
+                ),
+                _ => format!("id={}, {}", id, count(events.len(), "event", "events")),
+            },
+            Self::LiveData { data, cleared, .. } => {
+                let data = match (data, cleared) {
+                    (_, true) => "cleared",
+                    (Some(_), false) => "data",
+                    (None, false) => "no data",
+                };
+                format!("id={}, {}", id, data)
+            }
+            Self::LiveDataPatch { ops, .. } => {
+                format!("id={}, {}", id, count(ops.len(), "op", "ops"))
+            }
+            Self::LiveDataAll { matches, .. } => {
+                format!("id={}, {}", id, count(matches.len(), "match", "matches"))
+            }
+            Self::SessionEndedChunk { seq, last, data, .. } => format!(
+                "id={}, seq={}, {}{}",
+                id,
+                seq,
+                count(data.len(), "byte", "bytes"),
+                if *last { ", last" } else { "" }
+            ),
+            Self::SessionEndProgress {
+                progress, message, ..
+            } => match message {
+                Some(message) => format!("id={}, {:.0}% {}", id, progress * 100.0, message),
+                None => format!("id={}, {:.0}%", id, progress * 100.0),
+            },
+            Self::SessionEnded { match_data, .. } => format!(
+                "id={}, {}",
+                id,
+                if match_data.is_some() {
+                    "match data"
+                } else {
+                    "no match data"
+                }
+            ),
+            Self::Error { message, code, .. } | Self::Warning { message, code, .. } => {
+                match code {
+                    Some(code) => format!("id={}, {}: {}", id, code, message),
+                    None => format!("id={}, {}", id, message),
+                }
+            }
+            Self::EventIconResolved {
+                event_key,
+                icon_url,
+                ..
+            } => format!(
+                "id={}, event={}, {}",
+                id,
+                event_key,
+                if icon_url.is_some() { "found" } else { "not found" }
+            ),
+            Self::MatchInProgressStatus {
+                still_playing,
+                set_complete,
+                ..
+            } => {
+                let mut details = format!("id={}, still_playing={}", id, still_playing);
+                if let Some(message) = set_complete {
+                    details.push_str(&format!(", {}", message.describe()));
+                }
+                details
+            }
+            Self::MatchExistsResult { exists, .. } => format!("id={}, exists={}", id, exists),
+            Self::ActiveMatches { matches, .. } => {
+                format!("id={}, {}", id, count(matches.len(), "match", "matches"))
+            }
+            Self::RecoveryComplete { recovered, .. } => {
+                format!("id={}, {}", id, count(*recovered, "message", "messages"))
+            }
+            Self::MatchTimeline { found, entries, .. } => format!(
+                "id={}, {}, {}",
+                id,
+                if *found { "found" } else { "not found" },
+                count(entries.len(), "entry", "entries")
+            ),
+            Self::RecentMatches { matches, .. } => {
+                format!("id={}, {}", id, count(matches.len(), "match", "matches"))
+            }
+            Self::WriteMatchData {
+                message, source, ..
+            } => {
+                return match source {
+                    Some(source) => format!("{} from {}", message.describe(), source),
+                    None => message.describe(),
+                };
+            }
+            Self::Heartbeat {
+                subpack,
+                external_match_id,
+                game_time_secs,
+                ..
+            } => {
+                return format!(
+                    "{}[subpack={}, match={} @ {}s]",
+                    name, subpack, external_match_id, game_time_secs
+                );
+            }
+            Self::SampleMatchData { subpack, .. } => format!("id={}, subpack={}", id, subpack),
+            Self::Schema { subpack, schema, .. } => format!(
+                "id={}, subpack={}, {}",
+                id,
+                subpack,
+                match schema {
+                    Some(schema) => count(schema.fields.len(), "field", "fields"),
+                    None => "no schema".to_string(),
+                }
+            ),
+            Self::DerivedStats { specs, .. } => {
+                format!("id={}, {}", id, count(specs.len(), "spec", "specs"))
+            }
+            Self::LastError { error, .. } => match error {
+                Some(error) => format!("id={}, {}", id, error.message),
+                None => format!("id={}, none", id),
+            },
+            Self::TriggerConfigValidation { issues, .. } => {
+                format!("id={}, {}", id, count(issues.len(), "issue", "issues"))
+            }
+            Self::TriggerRules { subpack, rules, .. } => format!(
+                "id={}, subpack={}, {}",
+                id,
+                subpack,
+                count(rules.len(), "rule", "rules")
+            ),
+            Self::RecordDecision { record, reason, .. } => {
+                let decision = if *record { "record" } else { "skip" };
+                match reason {
+                    Some(reason) => format!("id={}, {} ({})", id, decision, reason),
+                    None => format!("id={}, {}", id, decision),
+                }
+            }
+            Self::UpdatePrepared { state, .. } => {
+                let state = if state.is_some() { "state" } else { "no state" };
+                format!("id={}, {}", id, state)
+            }
+            _ => format!("id={}", id),
+        };
+        format!("{}[{}]", name, details)
+    }
+
+    /// Serialize with sorted keys, for golden files and hashing; not the
+    /// wire format. See [`debug::canonical_json`](crate::debug::canonical_json).
+    pub fn to_canonical_json(&self) -> String {
+        crate::debug::canonical_json(self)
+    }
+
+    /// Serialize to one NDJSON line, without the trailing newline.
+    ///
+    /// serde_json escapes newlines inside strings, so a raw `\n` or `\r`
+    /// should never appear. The line is checked anyway before it is written:
+    /// a stray line break would split one message in two for the daemon's
+    /// line splitter, so pathological output fails here instead.
+    ///
+    /// Non-finite floats (NaN, infinity) in event, moment or statistics
+    /// times also fail, typically from dividing by a zero duration. serde_json
+    /// would write them as `null`, which the daemon rejects.
+    pub fn to_line(&self) -> serde_json::Result<String> {
+        match self {
+            Self::WriteMatchData { message, .. } => message.check_finite()?,
+            Self::Events { events, .. } => events.iter().try_for_each(GameEvent::check_finite)?,
+            Self::Heartbeat { game_time_secs, .. } => {
+                ensure_finite("game_time_secs", *game_time_secs)?
+            }
+            _ => {}
+        }
+        let line = serde_json::to_string(self)?;
+        if line.contains(['\n', '\r']) {
+            return Err(serde::ser::Error::custom("serialized response contains a raw line break"));
+        }
+        Ok(line)
+    }
+
+    /// [`to_line`](Self::to_line), with statistics and final stats maps
+    /// written in sorted key order.
+    ///
+    /// Equal maps then always produce byte-identical lines, which keeps
+    /// diffs of logged output quiet. Costs one sort of each map's keys.
+    pub fn to_stable_line(&self) -> serde_json::Result<String> {
+        crate::types::with_sorted_maps(|| self.to_line())
+    }
+
+    /// Split a `SessionEnded` whose match data serializes to more than
+    /// `max_bytes` into `SessionEndedChunk`s. Anything else, including a
+    /// `SessionEnded` that fits, is returned unchanged as the only element.
+    ///
+    /// Each chunk's `data` holds at most `max_bytes` bytes of the match
+    /// data's compact JSON text, cut on character boundaries (a single
+    /// character wider than `max_bytes` gets a chunk of its own). The line
+    /// itself is somewhat longer, since quotes in `data` are escaped. The
+    /// daemon concatenates `data` in `seq` order up to the chunk marked
+    /// `last` and parses the result, as
+    /// [`reassemble_session_end`](Self::reassemble_session_end) does.
+    pub fn chunk_session_end(self, max_bytes: usize) -> Vec<GamepackResponse> {
+        let Self::SessionEnded {
+            request_id,
+            match_data: Some(match_data),
+        } = &self
+        else {
+            return vec![self];
+        };
+        let text = match_data.to_string();
+        if text.len() <= max_bytes {
+            return vec![self];
+        }
+
+        let mut pieces = Vec::new();
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            let mut end = max_bytes.min(rest.len());
+            while end > 0 && !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            if end == 0 {
+                end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+            }
+            pieces.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        let total = pieces.len();
+        pieces
+            .into_iter()
+            .enumerate()
+            .map(|(seq, data)| Self::SessionEndedChunk {
+                request_id: request_id.clone(),
+                seq: seq as u32,
+                last: seq + 1 == total,
+                data: data.to_string(),
+            })
+            .collect()
+    }
+
+    /// Rebuild the match data from the `SessionEndedChunk`s of one session
+    /// end, in the order received.
+    ///
+    /// Fails with code `"invalid_chunks"` if a chunk is missing, out of
+    /// order or not a chunk, if nothing is marked `last` (or something
+    /// follows it), or if the joined text is not valid JSON.
+    pub fn reassemble_session_end(
+        chunks: &[GamepackResponse],
+    ) -> GamepackResult<serde_json::Value> {
+        let invalid = |reason: String| {
+            GamepackError::with_code(
+                format!("Invalid session end chunks: {}", reason),
+                "invalid_chunks",
+            )
+        };
+        let mut text = String::new();
+        for (expected, chunk) in chunks.iter().enumerate() {
+            match chunk {
+                Self::SessionEndedChunk {
+                    seq, last, data, ..
+                } if *seq as usize == expected => {
+                    text.push_str(data);
+                    if *last && expected + 1 < chunks.len() {
+                        let extra = expected + 1;
+                        return Err(invalid(format!("chunk {} follows the last one", extra)));
+                    }
+                    if *last {
+                        return serde_json::from_str(&text).map_err(|e| invalid(e.to_string()));
+                    }
+                }
+                other => {
+                    let got = other.describe();
+                    return Err(invalid(format!("expected chunk {}, got {}", expected, got)));
+                }
+            }
         }
+        Err(invalid("no chunk is marked last".to_string()))
     }
 
     /// Create an error response.
@@ -183,3 +836,816 @@ impl GamepackResponse {
         }
     }
 }
+
+/// Builds the response to one command with its `request_id` filled in.
+///
+/// Create it from the command (`Responder::new(cmd.request_id())`) and call
+/// the method for the matching response. Each method consumes the responder,
+/// so a command gets exactly one response and its id cannot be mismatched
+/// or forgotten when new variants are added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Responder {
+    request_id: String,
+}
+
+impl Responder {
+    /// Create a responder for the command with this `request_id`.
+    pub fn new(request_id: impl Into<String>) -> Self {
+        Self {
+            request_id: request_id.into(),
+        }
+    }
+
+    /// The id every response from this responder carries.
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// `Initialized`
+    pub fn initialized(
+        self,
+        game_id: i32,
+        slug: String,
+        protocol_version: u32,
+        pack_version: Option<String>,
+        features: Vec<String>,
+        derived_stats: Vec<DerivedStatSpec>,
+    ) -> GamepackResponse {
+        GamepackResponse::Initialized {
+            request_id: self.request_id,
+            game_id,
+            slug,
+            protocol_version,
+            pack_version,
+            features,
+            derived_stats,
+            game_version: None,
+            player: None,
+            capabilities: vec![],
+            connection_id: None,
+        }
+    }
+
+    /// `RunningStatus`
+    pub fn running_status(self, running: bool) -> GamepackResponse {
+        GamepackResponse::RunningStatus {
+            request_id: self.request_id,
+            running,
+        }
+    }
+
+    /// `GameStatus`
+    pub fn game_status(self, status: GameStatus) -> GamepackResponse {
+        GamepackResponse::GameStatus {
+            request_id: self.request_id,
+            connected: status.connected,
+            connection_status: status.connection_status,
+            game_phase: status.game_phase,
+            is_in_game: status.is_in_game,
+            game_version: status.game_version,
+            player: status.player,
+        }
+    }
+
+    /// `GameVersion`
+    pub fn game_version(self, version: Option<String>) -> GamepackResponse {
+        GamepackResponse::GameVersion {
+            request_id: self.request_id,
+            version,
+        }
+    }
+
+    /// `BuildInfo`
+    pub fn build_info(self, info: BuildInfo) -> GamepackResponse {
+        GamepackResponse::BuildInfo {
+            request_id: self.request_id,
+            pack_version: info.pack_version,
+            target_triple: info.target_triple,
+            profile: info.profile,
+            features: info.features,
+            rustc_version: info.rustc_version,
+        }
+    }
+
+    /// `Events`
+    pub fn events(self, events: Vec<GameEvent>) -> GamepackResponse {
+        GamepackResponse::Events {
+            request_id: self.request_id,
+            events,
+            warnings: vec![],
+            subpack: None,
+            external_match_id: None,
+        }
+    }
+
+    /// `LiveData`; `Some(Value::Null)` is sent as cleared live data.
+    pub fn live_data(self, data: Option<serde_json::Value>) -> GamepackResponse {
+        let cleared = matches!(data, Some(serde_json::Value::Null));
+        GamepackResponse::LiveData {
+            request_id: self.request_id,
+            data: data.filter(|_| !cleared),
+            cleared,
+            warnings: vec![],
+        }
+    }
+
+    /// `LiveDataPatch`
+    pub fn live_data_patch(self, ops: Vec<JsonPatchOp>) -> GamepackResponse {
+        GamepackResponse::LiveDataPatch {
+            request_id: self.request_id,
+            ops,
+        }
+    }
+
+    /// `LiveDataAll`
+    pub fn live_data_all(self, matches: Vec<(String, serde_json::Value)>) -> GamepackResponse {
+        GamepackResponse::LiveDataAll {
+            request_id: self.request_id,
+            matches,
+        }
+    }
+
+    /// `SessionStarted`
+    pub fn session_started(self, context: Option<serde_json::Value>) -> GamepackResponse {
+        GamepackResponse::SessionStarted {
+            request_id: self.request_id,
+            context,
+        }
+    }
+
+    /// `SessionEnded`
+    pub fn session_ended(self, match_data: Option<serde_json::Value>) -> GamepackResponse {
+        GamepackResponse::SessionEnded {
+            request_id: self.request_id,
+            match_data,
+        }
+    }
+
+    /// `SessionEndProgress`
+    pub fn session_end_progress(self, progress: f32, message: Option<String>) -> GamepackResponse {
+        GamepackResponse::SessionEndProgress {
+            request_id: self.request_id,
+            progress,
+            message,
+        }
+    }
+
+    /// `Error` from a handler error, keeping its code.
+    pub fn error(self, error: GamepackError) -> GamepackResponse {
+        GamepackResponse::Error {
+            request_id: self.request_id,
+            message: error.message,
+            code: error.code,
+        }
+    }
+
+    /// `Warning` about this command, with an optional code.
+    pub fn warning(self, message: impl Into<String>, code: Option<String>) -> GamepackResponse {
+        GamepackResponse::Warning {
+            request_id: self.request_id,
+            message: message.into(),
+            code,
+        }
+    }
+
+    /// `ShutdownComplete`
+    pub fn shutdown_complete(self) -> GamepackResponse {
+        GamepackResponse::ShutdownComplete {
+            request_id: self.request_id,
+        }
+    }
+
+    /// `Ack`
+    pub fn ack(self) -> GamepackResponse {
+        GamepackResponse::Ack {
+            request_id: self.request_id,
+        }
+    }
+
+    /// `EventIconResolved`
+    pub fn event_icon_resolved(
+        self,
+        event_key: String,
+        icon_url: Option<String>,
+    ) -> GamepackResponse {
+        GamepackResponse::EventIconResolved {
+            request_id: self.request_id,
+            event_key,
+            icon_url,
+        }
+    }
+
+    /// `MatchInProgressStatus`
+    pub fn match_in_progress_status(self, status: IsMatchInProgressResponse) -> GamepackResponse {
+        GamepackResponse::MatchInProgressStatus {
+            request_id: self.request_id,
+            still_playing: status.still_playing,
+            set_complete: status.set_complete,
+        }
+    }
+
+    /// `MatchExistsResult`
+    pub fn match_exists_result(self, exists: bool) -> GamepackResponse {
+        GamepackResponse::MatchExistsResult {
+            request_id: self.request_id,
+            exists,
+        }
+    }
+
+    /// `ActiveMatches`
+    pub fn active_matches(self, matches: Vec<(u8, String)>) -> GamepackResponse {
+        GamepackResponse::ActiveMatches {
+            request_id: self.request_id,
+            matches,
+        }
+    }
+
+    /// `RecoveryComplete`
+    pub fn recovery_complete(self, recovered: usize) -> GamepackResponse {
+        GamepackResponse::RecoveryComplete {
+            request_id: self.request_id,
+            recovered,
+        }
+    }
+
+    /// `MatchTimeline`
+    pub fn match_timeline(self, timeline: GetMatchTimelineResponse) -> GamepackResponse {
+        GamepackResponse::MatchTimeline {
+            request_id: self.request_id,
+            found: timeline.found,
+            entries: timeline.entries,
+        }
+    }
+
+    /// `RecentMatches`
+    pub fn recent_matches(self, matches: Vec<MatchSummary>) -> GamepackResponse {
+        GamepackResponse::RecentMatches {
+            request_id: self.request_id,
+            matches,
+        }
+    }
+
+    /// `SampleMatchData`
+    pub fn sample_match_data(self, subpack: u8, data: serde_json::Value) -> GamepackResponse {
+        GamepackResponse::SampleMatchData {
+            request_id: self.request_id,
+            subpack,
+            data,
+        }
+    }
+
+    /// `Schema`
+    pub fn schema(self, subpack: u8, schema: Option<LiveDataSchema>) -> GamepackResponse {
+        GamepackResponse::Schema {
+            request_id: self.request_id,
+            subpack,
+            schema,
+        }
+    }
+
+    /// `DerivedStats`
+    pub fn derived_stats(self, specs: Vec<DerivedStatSpec>) -> GamepackResponse {
+        GamepackResponse::DerivedStats {
+            request_id: self.request_id,
+            specs,
+        }
+    }
+
+    /// `LastError`
+    pub fn last_error(self, error: Option<ErrorInfo>) -> GamepackResponse {
+        GamepackResponse::LastError {
+            request_id: self.request_id,
+            error,
+        }
+    }
+
+    /// `TimeSyncResult`
+    pub fn time_sync_result(
+        self,
+        daemon_send_ns: u64,
+        pack_recv_ns: u64,
+        pack_send_ns: u64,
+    ) -> GamepackResponse {
+        GamepackResponse::TimeSyncResult {
+            request_id: self.request_id,
+            daemon_send_ns,
+            pack_recv_ns,
+            pack_send_ns,
+        }
+    }
+
+    /// `TriggerConfigValidation`
+    pub fn trigger_config_validation(self, issues: Vec<TriggerIssue>) -> GamepackResponse {
+        GamepackResponse::TriggerConfigValidation {
+            request_id: self.request_id,
+            issues,
+        }
+    }
+
+    /// `TriggerRules`
+    pub fn trigger_rules(self, subpack: u8, rules: Vec<TriggerRule>) -> GamepackResponse {
+        GamepackResponse::TriggerRules {
+            request_id: self.request_id,
+            subpack,
+            rules,
+        }
+    }
+
+    /// `RecordDecision`
+    pub fn record_decision(self, decision: RecordDecision) -> GamepackResponse {
+        GamepackResponse::RecordDecision {
+            request_id: self.request_id,
+            record: decision.record,
+            reason: decision.reason,
+            window: decision.window,
+        }
+    }
+
+    /// `UpdatePrepared`
+    pub fn update_prepared(self, state: Option<serde_json::Value>) -> GamepackResponse {
+        GamepackResponse::UpdatePrepared {
+            request_id: self.request_id,
+            state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{SummarySource, Timestamp};
+    use serde_json::json;
+
+    #[test]
+    fn describe_lifecycle_responses() {
+        let cases = [
+            (
+                GamepackResponse::Initialized {
+                    request_id: "r".into(),
+                    game_id: 99,
+                    slug: "test".into(),
+                    protocol_version: 1,
+                    pack_version: None,
+                    features: vec![],
+                    derived_stats: vec![],
+                    game_version: None,
+                    player: None,
+                    capabilities: vec![],
+                    connection_id: None,
+                },
+                "Initialized[id=r, game=99/test, v1]",
+            ),
+            (
+                GamepackResponse::Initialized {
+                    request_id: "r".into(),
+                    game_id: 99,
+                    slug: "test".into(),
+                    protocol_version: 1,
+                    pack_version: Some("1.4.0".into()),
+                    features: vec![],
+                    derived_stats: vec![],
+                    game_version: None,
+                    player: None,
+                    capabilities: vec![],
+                    connection_id: None,
+                },
+                "Initialized[id=r, game=99/test, v1, pack=1.4.0]",
+            ),
+            (
+                GamepackResponse::RunningStatus {
+                    request_id: "r".into(),
+                    running: true,
+                },
+                "RunningStatus[id=r, running=true]",
+            ),
+            (
+                GamepackResponse::GameStatus {
+                    request_id: "r".into(),
+                    connected: true,
+                    connection_status: "ok".into(),
+                    game_phase: Some("InProgress".into()),
+                    is_in_game: true,
+                    game_version: None,
+                    player: None,
+                },
+                "GameStatus[id=r, connected=true, phase=InProgress, in_game=true]",
+            ),
+            (
+                GamepackResponse::Events {
+                    request_id: "r".into(),
+                    events: vec![GameEvent::new("Kill", 1.0, json!({}))],
+                    warnings: vec![],
+                    subpack: None,
+                    external_match_id: None,
+                },
+                "Events[id=r, 1 event]",
+            ),
+            (
+                GamepackResponse::Events {
+                    request_id: String::new(),
+                    events: vec![],
+                    warnings: vec![],
+                    subpack: Some(1),
+                    external_match_id: Some("abc".into()),
+                },
+                "Events[subpack=1, match=abc, 0 events]",
+            ),
+            (
+                GamepackResponse::LiveData {
+                    request_id: "r".into(),
+                    data: None,
+                    cleared: false,
+                    warnings: vec![],
+                },
+                "LiveData[id=r, no data]",
+            ),
+            (
+                Responder::new("r").live_data(Some(serde_json::Value::Null)),
+                "LiveData[id=r, cleared]",
+            ),
+            (
+                GamepackResponse::LiveDataPatch {
+                    request_id: "r".into(),
+                    ops: vec![JsonPatchOp::Remove {
+                        path: "/gold".into(),
+                    }],
+                },
+                "LiveDataPatch[id=r, 1 op]",
+            ),
+            (
+                GamepackResponse::LiveDataAll {
+                    request_id: "r".into(),
+                    matches: vec![("a".into(), json!({})), ("b".into(), json!({}))],
+                },
+                "LiveDataAll[id=r, 2 matches]",
+            ),
+            (
+                GamepackResponse::SessionStarted {
+                    request_id: "r".into(),
+                    context: None,
+                },
+                "SessionStarted[id=r]",
+            ),
+            (
+                GamepackResponse::SessionEnded {
+                    request_id: "r".into(),
+                    match_data: Some(json!({})),
+                },
+                "SessionEnded[id=r, match data]",
+            ),
+            (
+                GamepackResponse::SessionEndedChunk {
+                    request_id: "r".into(),
+                    seq: 2,
+                    last: true,
+                    data: "}}".into(),
+                },
+                "SessionEndedChunk[id=r, seq=2, 2 bytes, last]",
+            ),
+            (
+                GamepackResponse::ShutdownComplete {
+                    request_id: "r".into(),
+                },
+                "ShutdownComplete[id=r]",
+            ),
+            (
+                GamepackResponse::Ack {
+                    request_id: "r".into(),
+                },
+                "Ack[id=r]",
+            ),
+        ];
+
+        for (response, expected) in cases {
+            assert_eq!(response.describe(), expected);
+        }
+    }
+
+    #[test]
+    fn describe_other_responses() {
+        let cases = [
+            (
+                GamepackResponse::error_with_code("r", "boom", "E1"),
+                "Error[id=r, E1: boom]",
+            ),
+            (GamepackResponse::error("r", "boom"), "Error[id=r, boom]"),
+            (
+                Responder::new("r").warning("stats API timed out", Some("partial_data".into())),
+                "Warning[id=r, partial_data: stats API timed out]",
+            ),
+            (
+                GamepackResponse::EventIconResolved {
+                    request_id: "r".into(),
+                    event_key: "DragonKill".into(),
+                    icon_url: None,
+                },
+                "EventIconResolved[id=r, event=DragonKill, not found]",
+            ),
+            (
+                GamepackResponse::MatchInProgressStatus {
+                    request_id: "r".into(),
+                    still_playing: false,
+                    set_complete: Some(MatchDataMessage::set_complete(
+                        0,
+                        "abc",
+                        SummarySource::Api,
+                    )),
+                },
+                "MatchInProgressStatus[id=r, still_playing=false, \
+                 SetComplete[subpack=0, match=abc, source=api]]",
+            ),
+            (
+                GamepackResponse::MatchExistsResult {
+                    request_id: "r".into(),
+                    exists: true,
+                },
+                "MatchExistsResult[id=r, exists=true]",
+            ),
+            (
+                GamepackResponse::RecoveryComplete {
+                    request_id: "r".into(),
+                    recovered: 2,
+                },
+                "RecoveryComplete[id=r, 2 messages]",
+            ),
+            (
+                GamepackResponse::MatchTimeline {
+                    request_id: "r".into(),
+                    found: true,
+                    entries: vec![],
+                },
+                "MatchTimeline[id=r, found, 0 entries]",
+            ),
+            (
+                GamepackResponse::WriteMatchData {
+                    message: MatchDataMessage::write_moments(0, "abc", vec![]),
+                    source: None,
+                    connection_id: None,
+                },
+                "WriteMoments[subpack=0, match=abc, 0 moments]",
+            ),
+            (
+                GamepackResponse::WriteMatchData {
+                    message: MatchDataMessage::write_moments(0, "abc", vec![]),
+                    source: Some("stats-poller".into()),
+                    connection_id: None,
+                },
+                "WriteMoments[subpack=0, match=abc, 0 moments] from stats-poller",
+            ),
+            (
+                GamepackResponse::Heartbeat {
+                    subpack: 0,
+                    external_match_id: "abc".into(),
+                    game_time_secs: 125.5,
+                    wall_clock: Timestamp::from_unix_millis(0),
+                },
+                "Heartbeat[subpack=0, match=abc @ 125.5s]",
+            ),
+            (
+                GamepackResponse::SampleMatchData {
+                    request_id: "r".into(),
+                    subpack: 1,
+                    data: json!({}),
+                },
+                "SampleMatchData[id=r, subpack=1]",
+            ),
+            (
+                GamepackResponse::DerivedStats {
+                    request_id: "r".into(),
+                    specs: vec![DerivedStatSpec::new("kda", "(kills + assists) / deaths")],
+                },
+                "DerivedStats[id=r, 1 spec]",
+            ),
+            (
+                GamepackResponse::LastError {
+                    request_id: "r".into(),
+                    error: Some(ErrorInfo {
+                        message: "boom".into(),
+                        code: None,
+                        at: Timestamp::from_unix_millis(0),
+                    }),
+                },
+                "LastError[id=r, boom]",
+            ),
+            (
+                GamepackResponse::UpdatePrepared {
+                    request_id: "r".into(),
+                    state: None,
+                },
+                "UpdatePrepared[id=r, no state]",
+            ),
+        ];
+
+        for (response, expected) in cases {
+            assert_eq!(response.describe(), expected);
+        }
+    }
+
+    #[test]
+    fn initialized_pack_version_round_trips() {
+        let response = GamepackResponse::Initialized {
+            request_id: "r".into(),
+            game_id: 99,
+            slug: "test".into(),
+            protocol_version: 1,
+            pack_version: Some("1.4.0".into()),
+            features: vec![],
+            derived_stats: vec![],
+            game_version: None,
+            player: None,
+            capabilities: vec![],
+            connection_id: None,
+        };
+
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(json.contains(r#""pack_version":"1.4.0""#));
+        match serde_json::from_str(&json).unwrap() {
+            GamepackResponse::Initialized { pack_version, .. } => {
+                assert_eq!(pack_version.as_deref(), Some("1.4.0"));
+            }
+            other => panic!("Expected Initialized, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn initialized_without_pack_version_matches_old_format() {
+        let response = GamepackResponse::Initialized {
+            request_id: "r".into(),
+            game_id: 99,
+            slug: "test".into(),
+            protocol_version: 1,
+            pack_version: None,
+            features: vec![],
+            derived_stats: vec![],
+            game_version: None,
+            player: None,
+            capabilities: vec![],
+            connection_id: None,
+        };
+
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(!json.contains("pack_version"));
+
+        // Lines from packs that predate the field still parse
+        let old = r#"{"type":"initialized","request_id":"r","game_id":99,"slug":"test","protocol_version":1}"#;
+        match serde_json::from_str(old).unwrap() {
+            GamepackResponse::Initialized { pack_version, .. } => assert!(pack_version.is_none()),
+            other => panic!("Expected Initialized, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn responder_fills_request_id_in_every_response() {
+        let responder = || Responder::new("req_42");
+        let responses = vec![
+            responder().initialized(99, "test".into(), 1, None, vec![], vec![]),
+            responder().running_status(true),
+            responder().game_status(GameStatus::disconnected()),
+            responder().game_version(None),
+            responder().events(vec![]),
+            responder().live_data(None),
+            responder().live_data_patch(vec![]),
+            responder().live_data_all(vec![]),
+            responder().session_started(None),
+            responder().session_ended(None),
+            responder().error(GamepackError::new("boom")),
+            responder().shutdown_complete(),
+            responder().ack(),
+            responder().event_icon_resolved("Kill".into(), None),
+            responder().match_in_progress_status(IsMatchInProgressResponse::ended()),
+            responder().match_exists_result(false),
+            responder().recovery_complete(0),
+            responder().match_timeline(GetMatchTimelineResponse {
+                found: false,
+                entries: vec![],
+            }),
+            responder().sample_match_data(0, json!({})),
+            responder().derived_stats(vec![]),
+            responder().last_error(None),
+            responder().trigger_config_validation(vec![]),
+            responder().update_prepared(None),
+        ];
+
+        for response in responses {
+            assert_eq!(response.request_id(), "req_42", "{}", response.describe());
+        }
+    }
+
+    #[test]
+    fn to_line_keeps_embedded_newlines_escaped() {
+        let event = GameEvent::new("Chat", 1.0, json!({"player": "Line\nBreak\r\nName"}));
+        let response = GamepackResponse::Events {
+            request_id: "r\n1".into(),
+            events: vec![event],
+            warnings: vec![],
+            subpack: None,
+            external_match_id: None,
+        };
+
+        let line = response.to_line().unwrap();
+
+        assert_eq!(line.lines().count(), 1);
+        assert!(!line.contains(['\n', '\r']));
+        match serde_json::from_str(&line).unwrap() {
+            GamepackResponse::Events { request_id, events, .. } => {
+                assert_eq!(request_id, "r\n1");
+                assert_eq!(events[0].data["player"], "Line\nBreak\r\nName");
+            }
+            other => panic!("Expected Events, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn game_version_serializes_and_is_optional() {
+        let responder = || Responder::new("r");
+        let known = responder().game_version(Some("14.3".into()));
+        assert_eq!(
+            serde_json::to_value(&known).unwrap(),
+            json!({"type": "game_version", "request_id": "r", "version": "14.3"})
+        );
+        let unknown = responder().game_version(None);
+        let json = serde_json::to_string(&unknown).unwrap();
+        assert!(json.contains(r#""version":null"#));
+        assert_eq!(unknown.describe(), "GameVersion[id=r, version=-]");
+
+        let status = responder().game_status(GameStatus::in_match("InProgress"));
+        assert!(!serde_json::to_string(&status).unwrap().contains("game_version"));
+        let status = GameStatus::in_match("InProgress").with_game_version("14.3");
+        let json = serde_json::to_string(&responder().game_status(status)).unwrap();
+        match serde_json::from_str(&json).unwrap() {
+            GamepackResponse::GameStatus { game_version, .. } => {
+                assert_eq!(game_version.as_deref(), Some("14.3"));
+            }
+            other => panic!("Expected GameStatus, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn oversized_session_end_is_chunked_and_reassembles() {
+        let data = json!({"result": "win", "note": "snö ✓", "k": [1, 2, 3]});
+        let ended = GamepackResponse::SessionEnded {
+            request_id: "s1".to_string(),
+            match_data: Some(data.clone()),
+        };
+
+        let chunks = ended.clone().chunk_session_end(7);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            let GamepackResponse::SessionEndedChunk { request_id, data, .. } = chunk else {
+                panic!("Expected SessionEndedChunk, got {:?}", chunk);
+            };
+            assert_eq!(request_id, "s1");
+            assert!(data.len() <= 7);
+        }
+        assert_eq!(GamepackResponse::reassemble_session_end(&chunks).unwrap(), data);
+
+        // Missing the last chunk
+        let err = GamepackResponse::reassemble_session_end(&chunks[..chunks.len() - 1]);
+        assert_eq!(err.unwrap_err().code.as_deref(), Some("invalid_chunks"));
+
+        // Small enough: unchanged
+        let whole = ended.chunk_session_end(64 * 1024);
+        assert!(matches!(
+            whole.as_slice(),
+            [GamepackResponse::SessionEnded { match_data: Some(_), .. }]
+        ));
+    }
+
+    #[test]
+    fn game_status_carries_player_identity() {
+        let status = GameStatus::client_connected()
+            .with_player(PlayerIdentity::new().with_display_name("Faker#KR1"));
+
+        let line = Responder::new("r").game_status(status).to_line().unwrap();
+        assert!(line.contains(r#""player":{"display_name":"Faker#KR1"}"#));
+
+        let line = Responder::new("r")
+            .game_status(GameStatus::client_connected())
+            .to_line()
+            .unwrap();
+        assert!(!line.contains("player"));
+    }
+
+    #[test]
+    fn cleared_live_data_round_trips_distinct_from_no_data() {
+        let cleared = Responder::new("r").live_data(Some(serde_json::Value::Null));
+        let absent = Responder::new("r").live_data(None);
+
+        let cleared_line = cleared.to_line().unwrap();
+        let absent_line = absent.to_line().unwrap();
+        assert_eq!(
+            cleared_line,
+            r#"{"type":"live_data","request_id":"r","data":null,"cleared":true}"#
+        );
+        assert_eq!(absent_line, r#"{"type":"live_data","request_id":"r","data":null}"#);
+
+        for (line, expected) in [(cleared_line, true), (absent_line, false)] {
+            match serde_json::from_str(&line).unwrap() {
+                GamepackResponse::LiveData { data, cleared, .. } => {
+                    assert_eq!((data, cleared), (None, expected));
+                }
+                other => panic!("Expected LiveData, got {:?}", other),
+            }
+        }
+    }
+}
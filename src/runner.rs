@@ -1,25 +1,39 @@
 //! Main loop runner for gamepacks.
 
-use std::io::{BufRead, Write};
-use std::sync::Mutex;
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::thread::{self, JoinHandle};
 
 use crate::commands::GamepackCommand;
-use crate::handler::GamepackHandler;
-use crate::responses::GamepackResponse;
-use crate::types::{GameEvent, InitResponse, MatchDataMessage, Moment};
-use crate::version::PROTOCOL_VERSION;
+use crate::emitter::{EmitError, Emitter};
+use crate::framing::FrameError;
+use crate::handler::{GamepackError, GamepackHandler, GamepackResult};
+use crate::responses::{GamepackResponse, Responder};
+use crate::transport::{
+    current_connection, multiplex, set_current_connection, BroadcastSink, MultiplexTransport,
+    StdioTransport, StreamTransport, Transport,
+};
+use crate::types::{
+    sort_events_chronological, Capability, ErrorInfo, GameEvent, GetMatchTimelineRequest,
+    InitResponse, MatchDataMessage, Moment, StorageStatus,
+};
+use crate::version::{
+    FEATURE_LIVE_DATA_PATCH, FEATURE_SESSION_END_CHUNKS, PROTOCOL_VERSION, SUPPORTED_FEATURES,
+};
 use std::collections::HashMap;
 
-/// Global stdout lock for thread-safe message emission.
-/// This is used by `emit_match_data` to send unsolicited messages.
-static STDOUT_LOCK: Mutex<()> = Mutex::new(());
-
 /// Emit a match data message to the daemon (unsolicited).
 ///
 /// This function can be called from any thread to send match data updates
 /// to the daemon. Messages are thread-safe and will be properly interleaved
 /// with command responses.
 ///
+/// Messages go through [`Emitter::global`]. To unit-test emission logic,
+/// hold an [`Emitter`] in your handler instead and back it with a
+/// [`CapturingSink`](crate::testing::CapturingSink) in tests.
+///
 /// For convenience, use the typed helpers:
 /// - [`emit_statistics`] for WriteStatistics
 /// - [`emit_game_events`] for WriteGameEvents
@@ -39,14 +53,7 @@ static STDOUT_LOCK: Mutex<()> = Mutex::new(());
 /// ));
 /// ```
 pub fn emit_match_data(message: MatchDataMessage) {
-    let response = GamepackResponse::WriteMatchData { message };
-
-    if let Ok(json) = serde_json::to_string(&response) {
-        let _lock = STDOUT_LOCK.lock();
-        let mut stdout = std::io::stdout();
-        let _ = writeln!(stdout, "{}", json);
-        let _ = stdout.flush();
-    }
+    let _ = Emitter::global().emit_match_data(message);
 }
 
 /// Emit statistics to the daemon.
@@ -115,6 +122,31 @@ pub fn emit_game_events(
     ));
 }
 
+/// Mark a match as abandoned: ended, but without a result.
+///
+/// Use this instead of a `SetComplete` when the player quit or lost the
+/// connection mid-match; see [`MatchDataMessage::SetAbandoned`] for how it
+/// differs from completing with live fallback stats.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use gamepack_runtime::emit_abandoned;
+///
+/// emit_abandoned(0, "match123", 312.5);
+/// ```
+pub fn emit_abandoned(
+    subpack: u8,
+    external_match_id: impl Into<String>,
+    at_game_time_secs: f64,
+) {
+    emit_match_data(MatchDataMessage::set_abandoned(
+        subpack,
+        external_match_id,
+        at_game_time_secs,
+    ));
+}
+
 /// Emit moments to the daemon.
 ///
 /// Moments are recordable things that might trigger a clip. The daemon will:
@@ -146,278 +178,3784 @@ pub fn emit_moments(
     ));
 }
 
-/// Run the gamepack main loop with the provided handler.
-///
-/// This function handles all stdin/stdout communication with the main daemon.
-/// It reads NDJSON commands from stdin, dispatches them to the handler, and
-/// writes NDJSON responses to stdout.
+/// Subpack the daemon last selected with `SetActiveSubpack` (0 until then).
 ///
-/// # Example
+/// For packs that don't track the game mode themselves:
 ///
 /// ```rust,ignore
-/// use gamepack_runtime::{run_gamepack, GamepackHandler};
-///
-/// struct MyGameIntegration { /* ... */ }
-/// impl GamepackHandler for MyGameIntegration { /* ... */ }
+/// use gamepack_runtime::{active_subpack, emit_moments};
 ///
-/// fn main() {
-///     let handler = MyGameIntegration::new();
-///     run_gamepack(handler);
-/// }
+/// emit_moments(active_subpack(), "match123", moments);
 /// ```
 ///
-/// # Protocol
-///
-/// The function will continue reading commands until:
-/// - A `shutdown` command is received
-/// - stdin is closed
-/// - An unrecoverable error occurs
-pub fn run_gamepack<H: GamepackHandler>(mut handler: H) {
-    let stdin = std::io::stdin();
-    let mut stdout = std::io::stdout();
+/// Passing a subpack explicitly always wins; see
+/// [`Emitter::active_subpack`].
+pub fn active_subpack() -> u8 {
+    Emitter::global().active_subpack()
+}
 
-    for line in stdin.lock().lines() {
-        let line = match line {
-            Ok(l) if !l.trim().is_empty() => l,
-            Ok(_) => continue, // Skip empty lines
-            Err(_) => break,   // stdin closed
-        };
+thread_local! {
+    /// Warnings raised with [`warn`] by the command being handled on this
+    /// thread; `None` outside of a command.
+    static PENDING_WARNINGS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
 
-        let response = match serde_json::from_str::<GamepackCommand>(&line) {
-            Ok(cmd) => dispatch_command(&mut handler, cmd),
-            Err(e) => GamepackResponse::error("", format!("Parse error: {}", e)),
-        };
+    /// Emitter and `request_id` of the `SessionEnd` being handled on this
+    /// thread, for [`session_end_progress`]; `None` otherwise.
+    static SESSION_END: RefCell<Option<(Emitter, String)>> = const { RefCell::new(None) };
+}
 
-        if let Ok(json) = serde_json::to_string(&response) {
-            let _ = writeln!(stdout, "{}", json);
-            let _ = stdout.flush();
+/// Report a non-fatal problem with the command being handled.
+///
+/// Call it from a handler method when the result is usable but not quite
+/// right, e.g. events were returned but the stats API timed out. The
+/// command still succeeds. For `PollEvents` and `GetLiveData` the warning
+/// goes into the response's `warnings`; for any other command it is
+/// written as a separate `Warning` line with the command's `request_id`,
+/// just before the response. To fail the command, return an error instead.
+///
+/// Outside of a handler method (e.g. on a background thread) there is no
+/// command to attach to, and this falls back to
+/// [`Emitter::emit_warning`] on the global emitter.
+///
+/// ```rust,ignore
+/// use gamepack_runtime::warn;
+///
+/// fn poll_events(&mut self) -> Vec<GameEvent> {
+///     if self.stats_timed_out {
+///         warn("stats API timed out, events may be missing kill details");
+///     }
+///     self.pending_events.drain(..).collect()
+/// }
+/// ```
+pub fn warn(message: impl Into<String>) {
+    let message = message.into();
+    let unhandled = PENDING_WARNINGS.with(|pending| match pending.borrow_mut().as_mut() {
+        Some(warnings) => {
+            warnings.push(message);
+            None
         }
+        None => Some(message),
+    });
+    if let Some(message) = unhandled {
+        let _ = Emitter::global().emit_warning(message, None);
+    }
+}
 
-        // Exit after shutdown
-        if matches!(response, GamepackResponse::ShutdownComplete { .. }) {
-            break;
+/// Report how far the session end being handled has got.
+///
+/// Call it from [`on_session_end`](GamepackHandler::on_session_end) or a
+/// [background session end](GamepackHandler::background_session_end) task
+/// that takes a while, e.g. while fetching post-game stats, so the daemon
+/// can show progress instead of a frozen spinner. Each call writes a
+/// `SessionEndProgress` with the `SessionEnd` command's `request_id`
+/// through the emitter at once; the `SessionEnded` follows when the handler
+/// returns.
+///
+/// `progress` is the fraction done, from 0.0 to 1.0: values outside are
+/// clamped and NaN is sent as 0.0. Report it non-decreasing; the daemon
+/// shows the latest. Outside of a session end there is nothing to report
+/// on, and the call is ignored.
+///
+/// ```rust,ignore
+/// use gamepack_runtime::session_end_progress;
+///
+/// Some(Box::new(move || {
+///     session_end_progress(0.1, Some("waiting for post-game stats"));
+///     let stats = api.fetch_post_game(&match_id)?;
+///     session_end_progress(0.8, Some("building match data"));
+///     Some(build_match_data(stats))
+/// }))
+/// ```
+pub fn session_end_progress(progress: f32, message: Option<&str>) {
+    let progress = if progress.is_nan() {
+        0.0
+    } else {
+        progress.clamp(0.0, 1.0)
+    };
+    SESSION_END.with(|session_end| {
+        if let Some((emitter, request_id)) = session_end.borrow().as_ref() {
+            let response = Responder::new(request_id.as_str())
+                .session_end_progress(progress, message.map(str::to_string));
+            let _ = emitter.emit_deferred_response(response);
         }
+    });
+}
+
+/// Makes [`session_end_progress`] report on a `SessionEnd` for as long as
+/// it is alive, on the current thread.
+struct SessionEndScope;
+
+impl SessionEndScope {
+    fn enter(emitter: Emitter, request_id: &str) -> Self {
+        SESSION_END.with(|session_end| {
+            *session_end.borrow_mut() = Some((emitter, request_id.to_string()));
+        });
+        Self
     }
 }
 
-/// Dispatch a command to the appropriate handler method.
-fn dispatch_command<H: GamepackHandler>(handler: &mut H, cmd: GamepackCommand) -> GamepackResponse {
-    let request_id = cmd.request_id().to_string();
+impl Drop for SessionEndScope {
+    fn drop(&mut self) {
+        SESSION_END.with(|session_end| *session_end.borrow_mut() = None);
+    }
+}
 
-    match cmd {
-        GamepackCommand::Init { .. } => match handler.init() {
-            Ok(InitResponse {
-                game_id,
-                slug,
-                protocol_version,
-            }) => GamepackResponse::Initialized {
-                request_id,
-                game_id,
-                slug,
-                // Use the handler's version or fall back to crate version
-                protocol_version: if protocol_version > 0 {
-                    protocol_version
-                } else {
-                    PROTOCOL_VERSION
-                },
-            },
-            Err(e) => GamepackResponse::Error {
-                request_id,
-                message: e.message,
-                code: e.code,
-            },
-        },
+/// Number of recent `request_id`s remembered when duplicate rejection is enabled.
+///
+/// The window is bounded so memory stays constant over a long-running session:
+/// once it is full, the oldest id is forgotten and may be reused again.
+pub const DUPLICATE_REQUEST_ID_WINDOW: usize = 1024;
 
-        GamepackCommand::DetectRunning { .. } => GamepackResponse::RunningStatus {
-            request_id,
-            running: handler.detect_running(),
-        },
+/// Match that `EmitTestMoment` writes to when the handler reports no
+/// [current match](GamepackHandler::current_match).
+pub const TEST_MOMENT_MATCH_ID: &str = "diagnostic_test";
 
-        GamepackCommand::GetStatus { .. } => {
-            let status = handler.get_status();
-            GamepackResponse::GameStatus {
-                request_id,
-                connected: status.connected,
-                connection_status: status.connection_status,
-                game_phase: status.game_phase,
-                is_in_game: status.is_in_game,
-            }
+/// Default for [`RunnerOptions::session_end_chunk_bytes`].
+pub const SESSION_END_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Options controlling the behavior of the gamepack main loop.
+///
+/// Pass to [`run_gamepack_with_options`]. [`run_gamepack`] uses the defaults.
+#[derive(Debug, Clone)]
+pub struct RunnerOptions {
+    /// Reject commands that reuse a recently-seen `request_id`.
+    ///
+    /// When enabled, the runner remembers the last
+    /// [`DUPLICATE_REQUEST_ID_WINDOW`] ids and answers a repeat with an
+    /// `Error` (code `"duplicate_request_id"`) instead of dispatching it
+    /// to the handler. Commands with an empty `request_id` are never
+    /// considered duplicates. With
+    /// [several connections](run_gamepack_with_connections), ids are
+    /// remembered per connection.
+    pub reject_duplicate_request_ids: bool,
+
+    /// Keep dispatching polling commands to the handler while paused.
+    ///
+    /// By default, while the daemon has paused the pack (see
+    /// `GamepackCommand::Pause`), the runner answers `PollEvents` with no
+    /// events and `GetLiveData`, `RefreshLiveData` and `GetLiveDataAll` with
+    /// no data without calling the handler. Set this to forward those
+    /// commands anyway.
+    pub poll_while_paused: bool,
+
+    /// What to do when a handler method panics. See [`PanicPolicy`].
+    pub on_panic: PanicPolicy,
+
+    /// Longest command line accepted, in bytes (excluding the newline).
+    ///
+    /// A longer line is discarded as it arrives, without buffering it, and
+    /// answered with an `Error` (code `"line_too_long"`) with an empty
+    /// `request_id`. `None` (the default) accepts lines of any length.
+    /// Applies to the stdio transport; custom transports set their own limit.
+    pub max_line_bytes: Option<usize>,
+
+    /// Largest `SessionEnded` match data sent in one line, in bytes of
+    /// compact JSON. `None` (the default) uses [`SESSION_END_CHUNK_BYTES`].
+    ///
+    /// Only applies when the daemon negotiated `session_end_chunks` at init.
+    /// Larger match data is then sent as `SessionEndedChunk` responses; see
+    /// [`GamepackResponse::chunk_session_end`]. Without the feature,
+    /// `SessionEnded` is always sent whole.
+    pub session_end_chunk_bytes: Option<usize>,
+
+    /// Sort `poll_events` output by timestamp before answering `PollEvents`
+    /// (on by default).
+    ///
+    /// The daemon times clips assuming events arrive in chronological
+    /// order. The sort is stable, so events with equal timestamps keep the
+    /// order the handler returned them in; see
+    /// [`sort_events_chronological`].
+    /// Turn it off if the handler already guarantees the order.
+    pub sort_events: bool,
+
+    /// Most unsolicited messages the emitter writes per second. `None`
+    /// (the default) leaves emission unlimited, or keeps a limit already
+    /// set on the emitter.
+    ///
+    /// Guards the daemon against a runaway pack. Statistics are dropped
+    /// over the limit while `SetComplete` and errors always get through;
+    /// see [`Emitter::with_max_emit_rate`] for the priority tiers. Responses
+    /// to commands are not limited.
+    pub max_emit_rate: Option<f64>,
+
+    /// Buffer responses up to this many bytes instead of flushing each one.
+    /// `None` (the default) flushes every response as it is written.
+    ///
+    /// Buffered responses go out once the limit is reached or when no
+    /// further command is waiting, so a burst of commands costs one write
+    /// instead of one per command; each answer in the burst waits until the
+    /// burst is handled. `Error` and `ShutdownComplete` are always flushed
+    /// at once. Applies to the stdio transport; see
+    /// [`StreamTransport::with_coalesced_writes`](crate::StreamTransport::with_coalesced_writes).
+    pub coalesce_response_bytes: Option<usize>,
+
+    /// Write the keys of statistics and final stats maps in sorted order,
+    /// in responses and everything the emitter writes (off by default).
+    ///
+    /// Those maps otherwise come out in hash order, which differs between
+    /// runs and makes diffs of logged output noisy. Sorting costs one pass
+    /// over each map's keys per message; see
+    /// [`GamepackResponse::to_stable_line`].
+    pub stable_map_order: bool,
+
+    /// Most events a `poll_events` call and most entries a
+    /// `get_match_timeline` call should return. `None` (the default)
+    /// answers with whatever the handler returned.
+    ///
+    /// A handler that returns more, usually because it forgot to drain a
+    /// buffer, gets a warning naming the method and the count, and only
+    /// the first `limit` items are sent (the earliest, for events). The
+    /// limit is a safety net against buggy handlers, not a rate control:
+    /// the dropped items are not re-sent later, so pick it well above any
+    /// legitimate batch.
+    pub soft_result_limit: Option<usize>,
+}
+
+impl Default for RunnerOptions {
+    fn default() -> Self {
+        Self {
+            reject_duplicate_request_ids: false,
+            poll_while_paused: false,
+            on_panic: PanicPolicy::default(),
+            max_line_bytes: None,
+            session_end_chunk_bytes: None,
+            sort_events: true,
+            max_emit_rate: None,
+            coalesce_response_bytes: None,
+            stable_map_order: false,
+            soft_result_limit: None,
         }
+    }
+}
 
-        GamepackCommand::PollEvents { .. } => {
-            let events = handler.poll_events();
-            GamepackResponse::Events { request_id, events }
+/// How the runner reacts to handler panics.
+///
+/// Every panic is answered with an `Error` response (code
+/// `"handler_panic"`) and recorded for `GetLastError` first. When the policy
+/// then calls for an abort, the runner calls the handler's `shutdown`,
+/// flushes and closes the emitter, flushes output, and returns
+/// [`RunOutcome::Aborted`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Tolerate every panic and keep serving commands.
+    #[default]
+    Continue,
+    /// Abort on the first panic.
+    Abort,
+    /// Tolerate up to this many panics in total; abort on the next one.
+    ContinueUntil(usize),
+}
+
+impl PanicPolicy {
+    /// Whether to abort after the `panics`-th panic (1-based).
+    fn should_abort(self, panics: usize) -> bool {
+        match self {
+            Self::Continue => false,
+            Self::Abort => true,
+            Self::ContinueUntil(max) => panics > max,
         }
+    }
+}
+
+/// How the main loop ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// `Shutdown` was handled.
+    Completed,
+    /// Stdin closed (or became unreadable) before `Shutdown`; the daemon is
+    /// gone and the runner shut down on its own.
+    StdinClosed,
+    /// A handler panic triggered the [`PanicPolicy`].
+    Aborted,
+    /// Writing a response failed because the daemon closed our stdout
+    /// (a broken pipe), possibly while still holding stdin open. The runner
+    /// stopped reading commands it could not answer and shut down.
+    PipeBroken,
+}
 
-        GamepackCommand::GetLiveData { .. } => {
-            let data = handler.get_live_data();
-            GamepackResponse::LiveData { request_id, data }
+impl RunOutcome {
+    /// Process exit code for this outcome: `0` when completed or the daemon
+    /// closed either pipe, `1` when aborted.
+    ///
+    /// ```rust,ignore
+    /// fn main() {
+    ///     let outcome = gamepack_runtime::run_gamepack(MyGame::new());
+    ///     std::process::exit(outcome.exit_code());
+    /// }
+    /// ```
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Completed | Self::StdinClosed | Self::PipeBroken => 0,
+            Self::Aborted => 1,
         }
+    }
+}
+
+/// Bounded set of recently-seen request ids (FIFO eviction).
+struct RecentRequestIds {
+    order: VecDeque<String>,
+    seen: HashSet<String>,
+    capacity: usize,
+}
 
-        GamepackCommand::SessionStart { .. } => {
-            let context = handler.on_session_start();
-            GamepackResponse::SessionStarted { request_id, context }
+impl RecentRequestIds {
+    fn new(capacity: usize) -> Self {
+        Self {
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+            capacity,
         }
+    }
 
-        GamepackCommand::SessionEnd { context, .. } => {
-            let match_data = handler.on_session_end(context);
-            GamepackResponse::SessionEnded {
-                request_id,
-                match_data: match_data.map(|m| serde_json::to_value(m).unwrap_or_default()),
+    /// Record an id. Returns `false` if it was already in the window.
+    fn insert(&mut self, request_id: &str) -> bool {
+        if self.seen.contains(request_id) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
             }
         }
+        self.order.push_back(request_id.to_string());
+        self.seen.insert(request_id.to_string());
+        true
+    }
+}
 
-        GamepackCommand::Shutdown { .. } => {
-            handler.shutdown();
-            GamepackResponse::ShutdownComplete { request_id }
+/// How a live data answer relates to the patch base, settled when the
+/// answer is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LiveDataAnswer {
+    /// `GetLiveData`: a patch against the base when there is one
+    Diff,
+    /// `RefreshLiveData`: always the full payload
+    Full,
+}
+
+/// Runner state wrapping a handler for the lifetime of the main loop.
+struct Runner<H> {
+    handler: H,
+    options: RunnerOptions,
+    recent_ids: RecentRequestIds,
+    /// Most recent handler error or panic, cleared on successful init.
+    /// Reported via `GetLastError`.
+    last_error: Option<ErrorInfo>,
+    /// Whether the daemon has paused the pack.
+    paused: bool,
+    /// Emitter shared with the free `emit_*` functions; closed on shutdown.
+    emitter: Emitter,
+    /// Whether `live_data_patch` was negotiated at init.
+    live_data_patches: bool,
+    /// Whether `session_end_chunks` was negotiated at init.
+    session_end_chunks: bool,
+    /// Handler panics so far, for [`PanicPolicy::ContinueUntil`].
+    panics: usize,
+    /// Set when the panic policy calls for ending the loop.
+    aborted: bool,
+    /// Random id of this run, reported in `Initialized`.
+    connection_id: String,
+    /// Response to the first successful `Init`, repeated for later ones.
+    initialized: Option<GamepackResponse>,
+    /// Warnings from the command just handled, written before its response.
+    warnings: Vec<String>,
+    /// Set when the command just handled is answered by a background task.
+    deferred: bool,
+    /// Set when the command just handled is answered with live data.
+    live_data_answer: Option<LiveDataAnswer>,
+    /// Background session tasks, waited for before exiting.
+    session_tasks: Vec<JoinHandle<()>>,
+    /// Whether several daemons share this runner; see
+    /// [`run_gamepack_with_connections`].
+    multiplexed: bool,
+}
+
+impl<H: GamepackHandler> Runner<H> {
+    fn new(handler: H, options: RunnerOptions) -> Self {
+        Self {
+            handler,
+            options,
+            recent_ids: RecentRequestIds::new(DUPLICATE_REQUEST_ID_WINDOW),
+            last_error: None,
+            paused: false,
+            emitter: Emitter::global().clone(),
+            live_data_patches: false,
+            session_end_chunks: false,
+            panics: 0,
+            aborted: false,
+            connection_id: new_connection_id(),
+            initialized: None,
+            warnings: Vec::new(),
+            deferred: false,
+            live_data_answer: None,
+            session_tasks: Vec::new(),
+            multiplexed: false,
         }
+    }
 
-        GamepackCommand::ResolveEventIcon { event_key, .. } => {
-            let icon_url = handler.resolve_event_icon(&event_key);
-            GamepackResponse::EventIconResolved {
-                request_id,
-                event_key,
-                icon_url,
+    /// Answer the current command from a background thread: run `task`
+    /// there and write its response through the emitter. Returns a
+    /// placeholder the main loop does not write.
+    fn defer(
+        &mut self,
+        request_id: String,
+        task: impl FnOnce() -> GamepackResponse + Send + 'static,
+    ) -> GamepackResponse {
+        let emitter = self.emitter.clone();
+        let chunk_limit = self.session_end_chunks.then(|| {
+            self.options
+                .session_end_chunk_bytes
+                .unwrap_or(SESSION_END_CHUNK_BYTES)
+        });
+        let placeholder = Responder::new(request_id.clone()).ack();
+        // Answer on the connection the command came in on
+        let connection = current_connection();
+        self.session_tasks.push(thread::spawn(move || {
+            set_current_connection(connection);
+            let response = panic::catch_unwind(AssertUnwindSafe(task)).unwrap_or_else(|payload| {
+                GamepackResponse::error_with_code(
+                    request_id,
+                    format!("Handler panicked: {}", panic_message(payload.as_ref())),
+                    "handler_panic",
+                )
+            });
+            let responses = match chunk_limit {
+                Some(limit) => response.chunk_session_end(limit),
+                None => vec![response],
+            };
+            for response in responses {
+                let _ = emitter.emit_deferred_response(response);
             }
+        }));
+        self.deferred = true;
+        placeholder
+    }
+
+    /// Wait for background session tasks, so their responses go out.
+    fn join_session_tasks(&mut self) {
+        for task in self.session_tasks.drain(..) {
+            let _ = task.join();
         }
+    }
 
-        GamepackCommand::IsMatchInProgress {
-            subpack,
-            external_match_id,
-            ..
-        } => {
-            let response = handler.is_match_in_progress(subpack, &external_match_id);
-            GamepackResponse::MatchInProgressStatus {
-                request_id,
-                still_playing: response.still_playing,
-                set_complete: response.set_complete,
+    /// Handle a single parsed command, applying runner-level policies.
+    ///
+    /// Handler errors and panics are converted into `Error` responses and
+    /// recorded as the last error.
+    fn handle_command(&mut self, cmd: GamepackCommand) -> GamepackResponse {
+        let respond = Responder::new(cmd.request_id());
+        let request_id = respond.request_id();
+
+        if self.options.reject_duplicate_request_ids && !request_id.is_empty() {
+            // Daemons sharing the runner pick their ids independently
+            let seen = match current_connection() {
+                Some(connection) => format!("{}/{}", connection, request_id),
+                None => request_id.to_string(),
+            };
+            if !self.recent_ids.insert(&seen) {
+                let message = format!("Duplicate request_id: {}", request_id);
+                return respond.error(GamepackError::with_code(message, "duplicate_request_id"));
             }
         }
 
-        GamepackCommand::GetMatchTimeline { .. } => {
-            // This command is typically sent FROM the daemon TO provide timeline data,
-            // but it can also be used for the gamepack to request its own data back.
-            // Default implementation returns empty - daemon handles this.
-            GamepackResponse::MatchTimeline {
-                request_id,
-                found: false,
-                entries: vec![],
+        PENDING_WARNINGS.with(|pending| *pending.borrow_mut() = Some(Vec::new()));
+        let result = match panic::catch_unwind(AssertUnwindSafe(|| {
+            let result = self.dispatch_command(cmd);
+            let _ = self.emitter.emit_due_statistics();
+            for switch in self.emitter.take_match_switches() {
+                self.handler
+                    .on_match_switch(&switch.old_external_match_id, &switch.new_external_match_id);
             }
-        }
+            result
+        })) {
+            Ok(result) => result,
+            Err(payload) => {
+                self.panics += 1;
+                self.aborted = self.options.on_panic.should_abort(self.panics);
+                Err(GamepackError::with_code(
+                    format!("Handler panicked: {}", panic_message(payload.as_ref())),
+                    "handler_panic",
+                ))
+            }
+        };
 
-        GamepackCommand::GetSampleMatchData { subpack, .. } => {
-            let data = handler.get_sample_match_data(subpack);
-            match data {
-                Some(data) => GamepackResponse::SampleMatchData {
-                    request_id,
-                    subpack,
-                    data,
-                },
-                None => GamepackResponse::Error {
-                    request_id,
-                    message: format!("Sample data not implemented for subpack {}", subpack),
-                    code: Some("NOT_IMPLEMENTED".to_string()),
-                },
+        let mut warnings = PENDING_WARNINGS
+            .with(|pending| pending.borrow_mut().take())
+            .unwrap_or_default();
+
+        let mut response = match result {
+            Ok(mut response) => {
+                if let GamepackResponse::Initialized { connection_id, .. } = &mut response {
+                    self.last_error = None;
+                    *connection_id = Some(self.connection_id.clone());
+                }
+                response
+            }
+            Err(e) => {
+                self.last_error = Some(ErrorInfo::now(e.message.clone(), e.code.clone()));
+                respond.error(e)
             }
+        };
+        if let GamepackResponse::Events { warnings: inline, .. }
+        | GamepackResponse::LiveData { warnings: inline, .. } = &mut response
+        {
+            inline.append(&mut warnings);
         }
+        self.warnings = warnings;
+        response
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::handler::GamepackResult;
-    use crate::types::{GameEvent, GameStatus, MatchData};
-
-    struct TestHandler {
-        initialized: bool,
+impl<H: GamepackHandler> Runner<H> {
+    /// Tear down without a `Shutdown` command (panic policy or stdin
+    /// closed): give the handler a chance to clean up, push out anything
+    /// still buffered, then stop all further emission.
+    fn teardown(&mut self) {
+        self.join_session_tasks();
+        // A handler that just panicked may panic again; we are exiting anyway
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| self.handler.shutdown()));
+        let _ = self.emitter.flush();
+        self.emitter.close();
     }
 
-    impl GamepackHandler for TestHandler {
-        fn init(&mut self) -> GamepackResult<InitResponse> {
-            self.initialized = true;
-            Ok(InitResponse {
-                game_id: 99,
-                slug: "test".to_string(),
-                protocol_version: 1,
-            })
+    /// Serve commands from `transport` until shutdown, end of input, or abort.
+    fn run(&mut self, transport: &mut impl Transport) -> RunOutcome {
+        self.emitter.start_session(self.connection_id.clone());
+        if let Some(per_sec) = self.options.max_emit_rate {
+            self.emitter.set_max_emit_rate(per_sec);
         }
-
-        fn detect_running(&self) -> bool {
-            true
+        if self.options.stable_map_order {
+            self.emitter.set_stable_map_order();
         }
+        loop {
+            let mut response = match transport.read_message() {
+                Ok(Some(line)) if line.trim().is_empty() => continue,
+                Ok(Some(line)) => match serde_json::from_str(&line) {
+                    Ok(cmd) => self.handle_command(cmd),
+                    Err(e) => GamepackResponse::error("", format!("Parse error: {}", e)),
+                },
+                // Skip the message rather than treating it as end of input
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    match e.get_ref().and_then(|e| e.downcast_ref::<FrameError>()) {
+                        Some(frame) => {
+                            GamepackResponse::error_with_code("", &frame.message, frame.code)
+                        }
+                        None => GamepackResponse::error("", format!("Invalid message: {}", e)),
+                    }
+                }
+                // Flushing buffered responses before the read found stdout closed
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                    eprintln!("gamepack-runtime: output closed ({}), shutting down", e);
+                    self.teardown();
+                    return RunOutcome::PipeBroken;
+                }
+                // stdin closed: the daemon is gone, shut down implicitly
+                Ok(None) | Err(_) => {
+                    self.teardown();
+                    return RunOutcome::StdinClosed;
+                }
+            };
 
-        fn get_status(&self) -> GameStatus {
-            GameStatus::connected("Test connected")
-        }
+            // Held until the answer is written and flushed, so no push
+            // lands between the base moving and the daemon reading it
+            let emitter = self.emitter.clone();
+            let mut base = self
+                .live_data_answer
+                .is_some()
+                .then(|| emitter.lock_live_data_base());
+            if let Some(base) = &mut base {
+                self.settle_live_data(&mut response, base);
+            }
 
-        fn poll_events(&mut self) -> Vec<GameEvent> {
-            vec![]
-        }
+            let line = match self.serialize(&response) {
+                Ok(line) => Ok(line),
+                // Answer with an error instead of leaving the request unanswered
+                Err(e) => {
+                    let message = format!("Failed to serialize {}: {}", response.describe(), e);
+                    self.emitter.report_serialize_error(&EmitError::Serialize(e));
+                    response = GamepackResponse::error_with_code(
+                        response.request_id(),
+                        message,
+                        "serialize_error",
+                    );
+                    response.to_line()
+                }
+            };
+            let mut written = Ok(());
+            for message in std::mem::take(&mut self.warnings) {
+                let warning = Responder::new(response.request_id()).warning(message, None);
+                if let (Ok(json), Ok(())) = (warning.to_line(), &written) {
+                    written = self.write_response(transport, &warning, json);
+                }
+            }
+            // A background task writes this command's response when done
+            let deferred = std::mem::take(&mut self.deferred);
+            if let (Ok(json), Ok(()), false) = (line, &written, deferred) {
+                written = self.write_response(transport, &response, json);
+            }
+            // Never left buffered, even when the transport coalesces writes
+            let urgent = matches!(
+                response,
+                GamepackResponse::Error { .. } | GamepackResponse::ShutdownComplete { .. }
+            );
+            if urgent || base.is_some() || self.aborted {
+                written = written.and_then(|()| transport.flush());
+            }
+            drop(base);
 
-        fn get_live_data(&self) -> Option<serde_json::Value> {
-            Some(serde_json::json!({"test": true}))
-        }
+            if self.aborted {
+                self.teardown();
+                return RunOutcome::Aborted;
+            }
 
-        fn on_session_start(&mut self) -> Option<serde_json::Value> {
-            Some(serde_json::json!({"started": true}))
-        }
+            // Exit after shutdown without reading further; the emitter is
+            // already closed, so this response is the last line out
+            if matches!(response, GamepackResponse::ShutdownComplete { .. }) {
+                return RunOutcome::Completed;
+            }
 
-        fn on_session_end(&mut self, _context: serde_json::Value) -> Option<MatchData> {
-            Some(MatchData::new("test", 99, "win", serde_json::json!({})))
+            // Nobody reads our output anymore: stop instead of answering
+            // commands into the void
+            if let Err(e) = written {
+                if matches!(e.kind(), io::ErrorKind::BrokenPipe | io::ErrorKind::WouldBlock) {
+                    eprintln!("gamepack-runtime: output closed ({}), shutting down", e);
+                    self.teardown();
+                    return RunOutcome::PipeBroken;
+                }
+            }
         }
+    }
 
-        fn shutdown(&mut self) {}
+    /// Settle a live data answer against the patch `base`: for
+    /// `GetLiveData`, a patch when the daemon holds a base, and the data
+    /// becomes the base the next answer or push is diffed against.
+    ///
+    /// The caller holds `base` locked until the answer is written. The base
+    /// is shared with patches the pack pushes through the emitter, so a push
+    /// made while the command was handled is written first and diffed
+    /// against what the daemon held before; this answer then diffs against
+    /// the push.
+    fn settle_live_data(
+        &mut self,
+        response: &mut GamepackResponse,
+        base: &mut Option<serde_json::Value>,
+    ) {
+        let Some(answer) = self.live_data_answer.take() else {
+            return;
+        };
+        let GamepackResponse::LiveData {
+            request_id,
+            data,
+            warnings,
+            ..
+        } = response
+        else {
+            return;
+        };
+        // Cleared data is never a base
+        let next = data.clone().filter(|_| self.live_data_patches);
+        let ops = match (std::mem::replace(base, next), &*base) {
+            (Some(old), Some(new)) if answer == LiveDataAnswer::Diff => {
+                crate::patch::diff(&old, new)
+            }
+            // No base yet (or patches not negotiated): send it whole
+            _ => return,
+        };
+        // A patch has no room for warnings; they go out as lines of their own
+        self.warnings.append(warnings);
+        *response = Responder::new(std::mem::take(request_id)).live_data_patch(ops);
     }
 
-    #[test]
-    fn test_dispatch_init() {
-        let mut handler = TestHandler { initialized: false };
-        let response = dispatch_command(
-            &mut handler,
-            GamepackCommand::Init {
-                request_id: "test_1".to_string(),
-            },
-        );
+    /// One response line, with sorted maps if
+    /// [`stable_map_order`](RunnerOptions::stable_map_order) is set.
+    fn serialize(&self, response: &GamepackResponse) -> serde_json::Result<String> {
+        if self.options.stable_map_order {
+            response.to_stable_line()
+        } else {
+            response.to_line()
+        }
+    }
 
-        assert!(handler.initialized);
-        match response {
-            GamepackResponse::Initialized {
-                request_id,
-                game_id,
-                slug,
-                ..
-            } => {
-                assert_eq!(request_id, "test_1");
-                assert_eq!(game_id, 99);
-                assert_eq!(slug, "test");
+    /// Write a serialized response, as chunks if it is a `SessionEnded`
+    /// whose match data exceeds the negotiated chunk size.
+    fn write_response(
+        &self,
+        transport: &mut impl Transport,
+        response: &GamepackResponse,
+        json: String,
+    ) -> io::Result<()> {
+        if self.session_end_chunks && matches!(response, GamepackResponse::SessionEnded { .. }) {
+            let limit = self
+                .options
+                .session_end_chunk_bytes
+                .unwrap_or(SESSION_END_CHUNK_BYTES);
+            let chunks = response.clone().chunk_session_end(limit);
+            if !matches!(chunks.as_slice(), [GamepackResponse::SessionEnded { .. }]) {
+                for chunk in chunks {
+                    if let Ok(line) = chunk.to_line() {
+                        self.emitter.record_bytes(&chunk, line.len());
+                        transport.write_message(&line)?;
+                    }
+                }
+                return Ok(());
             }
-            _ => panic!("Expected Initialized response"),
         }
+        self.emitter.record_bytes(response, json.len());
+        transport.write_message(&json)
     }
+}
 
-    #[test]
-    fn test_dispatch_get_status() {
-        let mut handler = TestHandler { initialized: false };
-        let response = dispatch_command(
-            &mut handler,
-            GamepackCommand::GetStatus {
-                request_id: "test_2".to_string(),
-            },
-        );
+/// Random version 4 UUID, e.g. `"3f2b8c1e-9a4d-4e7f-b6a5-0c1d2e3f4a5b"`.
+///
+/// Seeded from std's randomly keyed hasher plus time and process id; unique
+/// enough to tell runs apart, not for anything security-related.
+fn new_connection_id() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
 
-        match response {
-            GamepackResponse::GameStatus {
-                request_id,
-                connected,
-                connection_status,
-                ..
-            } => {
-                assert_eq!(request_id, "test_2");
-                assert!(connected);
-                assert_eq!(connection_status, "Test connected");
-            }
-            _ => panic!("Expected GameStatus response"),
-        }
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let mut bytes = [0u8; 16];
+    for (i, half) in bytes.chunks_mut(8).enumerate() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        hasher.write_u32(std::process::id());
+        hasher.write_usize(i);
+        half.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Keep the first `limit` items a handler `method` returned, warning when
+/// there were more. See [`RunnerOptions::soft_result_limit`].
+fn cap_result<T>(items: &mut Vec<T>, limit: Option<usize>, method: &str, what: &str) {
+    let Some(limit) = limit.filter(|limit| items.len() > *limit) else {
+        return;
+    };
+    warn(format!(
+        "{} returned {} {}, over the soft limit of {}; sending the first {}",
+        method,
+        items.len(),
+        what,
+        limit,
+        limit
+    ));
+    items.truncate(limit);
+}
+
+/// Pack clock for `TimeSync`: nanoseconds since the Unix epoch.
+///
+/// Wall-clock time is read once per process; later readings add the
+/// monotonic time since then, so they never go backwards even if the
+/// system clock is adjusted.
+fn pack_clock_ns() -> u64 {
+    use std::sync::OnceLock;
+    use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+    static ANCHOR: OnceLock<(Instant, u64)> = OnceLock::new();
+    let (anchor, anchor_ns) = ANCHOR.get_or_init(|| {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        (Instant::now(), since_epoch.as_nanos() as u64)
+    });
+    anchor_ns.saturating_add(anchor.elapsed().as_nanos() as u64)
+}
+
+/// Extract a readable message from a panic payload.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/// Run the gamepack main loop with the provided handler.
+///
+/// This function handles all stdin/stdout communication with the main daemon.
+/// It reads NDJSON commands from stdin, dispatches them to the handler, and
+/// writes NDJSON responses to stdout.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use gamepack_runtime::{run_gamepack, GamepackHandler};
+///
+/// struct MyGameIntegration { /* ... */ }
+/// impl GamepackHandler for MyGameIntegration { /* ... */ }
+///
+/// fn main() {
+///     let handler = MyGameIntegration::new();
+///     run_gamepack(handler);
+/// }
+/// ```
+///
+/// # Protocol
+///
+/// The function will continue reading commands until:
+/// - A `shutdown` command is received
+/// - stdin is closed
+/// - An unrecoverable error occurs
+///
+/// After `Shutdown`, `ShutdownComplete` is the last line written: the
+/// emitter is closed first (waiting out any emit already being written), and
+/// the loop returns without reading or answering anything the daemon sent
+/// after `Shutdown`.
+///
+/// Stdin closing is treated as an implicit shutdown: the handler's
+/// `shutdown` still runs and the emitter is flushed and closed before
+/// returning [`RunOutcome::StdinClosed`]. So is stdout closing: the first
+/// response that fails to write with a broken pipe (or `WouldBlock`) stops
+/// the loop with [`RunOutcome::PipeBroken`], even if stdin is still open.
+///
+/// Handler errors and panics are answered with an `Error` response and do
+/// not stop the loop (unless [`RunnerOptions::on_panic`] says otherwise).
+/// The most recent one is kept for the daemon's health panel
+/// (`GetLastError`) until the next successful `Init`.
+///
+/// # Global emitter
+///
+/// The runner answers through the process-wide [`Emitter::global`], the
+/// one the free `emit_*` functions write to, and records the session on it:
+/// the connection id, negotiated features and mutes, and
+/// [`RunnerOptions::max_emit_rate`] and
+/// [`stable_map_order`](RunnerOptions::stable_map_order), which apply to
+/// every clone of it. On exit the runner closes it. Running again in the
+/// same process reopens it and starts a fresh session, keeping the rate
+/// limit and map order.
+///
+/// # Connection id
+///
+/// Each run gets a random UUID, the connection id. It stays the same for
+/// the whole run and is reported in every `Initialized` response. Emitters
+/// with [connection tags](Emitter::with_connection_tags) also attach it to
+/// each `WriteMatchData`. When the daemon restarts a pack, it keeps the id
+/// from the newest `Initialized` and discards tagged lines that carry
+/// another id, e.g. output the old process buffered before it exited.
+///
+/// # Repeated `Init`
+///
+/// Some daemons send `Init` again after reconnecting. The handler's `init`
+/// only runs for the first `Init` that succeeds; later ones are answered
+/// with the same `Initialized` (under their own `request_id`), keeping the
+/// protocol version and features negotiated the first time. The next live
+/// data is sent in full, since the daemon may have lost its patch base.
+///
+/// An `Init` with `force: true` runs the handler's `init` again and
+/// renegotiates, for daemons that know the pack must reinitialize. A failed
+/// `Init` is not cached, so the daemon can simply retry.
+///
+/// Returns how the loop ended; see [`RunOutcome::exit_code`].
+pub fn run_gamepack<H: GamepackHandler>(handler: H) -> RunOutcome {
+    run_gamepack_with_options(handler, RunnerOptions::default())
+}
+
+/// Run the gamepack main loop with custom [`RunnerOptions`].
+///
+/// Behaves like [`run_gamepack`] but allows opting into runner-level
+/// policies such as duplicate `request_id` rejection.
+pub fn run_gamepack_with_options<H: GamepackHandler>(
+    handler: H,
+    options: RunnerOptions,
+) -> RunOutcome {
+    let mut transport = StdioTransport::new();
+    if let Some(max) = options.max_line_bytes {
+        transport = transport.with_max_line_bytes(max);
+    }
+    if let Some(max) = options.coalesce_response_bytes {
+        transport = transport.with_coalesced_writes(max);
+    }
+    run_gamepack_with_transport(handler, options, &mut transport)
+}
+
+/// Run the gamepack main loop over a custom [`Transport`].
+///
+/// Behaves like [`run_gamepack_with_options`], reading commands from and
+/// writing responses to `transport` instead of stdin and stdout.
+/// [`RunnerOptions::max_line_bytes`] and
+/// [`RunnerOptions::coalesce_response_bytes`] are not applied: line limits
+/// and write buffering belong to the transport (see
+/// [`StreamTransport::with_max_line_bytes`]).
+/// Unsolicited messages still go through the global [`Emitter`]; see the
+/// [`transport`](crate::transport) module.
+///
+/// [`StreamTransport::with_max_line_bytes`]: crate::transport::StreamTransport::with_max_line_bytes
+pub fn run_gamepack_with_transport<H: GamepackHandler>(
+    handler: H,
+    options: RunnerOptions,
+    transport: &mut impl Transport,
+) -> RunOutcome {
+    Runner::new(handler, options).run(transport)
+}
+
+/// Serve several daemon connections from one pack process.
+///
+/// Each connection is read on its own thread. Commands from all of them go
+/// through one runner and are handled one at a time, in the order they
+/// arrive, so the handler is shared without any locking on its part. Each
+/// answer, including its warnings and a background `SessionEnded`, goes
+/// back to the connection the command came in on.
+///
+/// This is by design, instead of one runner per connection sharing the
+/// handler behind a mutex: the handler needs no `Send` or `Sync` bound and
+/// sees every command in one order. The cost is the same as with a mutex,
+/// a slow command holds up the other connections too, so long work belongs
+/// in the background hooks such as
+/// [`background_session_end`](GamepackHandler::background_session_end).
+///
+/// Everything else the runner holds is shared too: one `Init` initializes
+/// the pack for every connection (later ones get the cached `Initialized`),
+/// a `Pause` pauses it for all of them, and a `Shutdown` from any
+/// connection ends the process. `live_data_patch` is never negotiated,
+/// since the daemons cannot share one patch base. Request ids are tracked
+/// per connection: a background answer goes to the connection that sent
+/// the command, and
+/// [`reject_duplicate_request_ids`](RunnerOptions::reject_duplicate_request_ids)
+/// only rejects an id its own connection used recently.
+///
+/// Unsolicited messages (match data, heartbeats, emitted warnings) go to
+/// every connection: this installs a global [`Emitter`] writing to all of
+/// them. Fails with [`io::ErrorKind::AlreadyExists`], without answering
+/// any command, if a global emitter was already installed or used (the
+/// free `emit_*` functions and [`Emitter::global`] create one), since the
+/// free `emit_*` functions would then never reach the connections.
+///
+/// A connection whose input ends is no longer read, but still receives
+/// messages until writing to it fails. The loop ends once every
+/// connection's input has ended. [`RunnerOptions::max_line_bytes`] applies
+/// to each connection. [`RunnerOptions::coalesce_response_bytes`] is not
+/// applied, and write coalescing configured on the connections is ignored.
+///
+/// ```rust,ignore
+/// let connections = listener
+///     .incoming()
+///     .take(2)
+///     .map(|stream| {
+///         let stream = stream?;
+///         Ok(StreamTransport::new(stream.try_clone()?, stream))
+///     })
+///     .collect::<std::io::Result<Vec<_>>>()?;
+/// let outcome =
+///     run_gamepack_with_connections(MyGame::new(), RunnerOptions::default(), connections)?;
+/// ```
+pub fn run_gamepack_with_connections<H, R, W>(
+    handler: H,
+    options: RunnerOptions,
+    connections: Vec<StreamTransport<R, W>>,
+) -> io::Result<RunOutcome>
+where
+    H: GamepackHandler,
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let (mut transport, sink) = multiplex_with_options(connections, &options);
+    let emitter = Emitter::new(sink);
+    if emitter.clone().install_global().is_err() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "a global emitter is already installed; emit_* functions would not reach the \
+             connections",
+        ));
+    }
+    let mut runner = Runner::new(handler, options);
+    runner.emitter = emitter;
+    runner.multiplexed = true;
+    Ok(runner.run(&mut transport))
+}
+
+/// [`multiplex`] `connections`, each with the line limit from `options`.
+fn multiplex_with_options<R, W>(
+    connections: Vec<StreamTransport<R, W>>,
+    options: &RunnerOptions,
+) -> (MultiplexTransport<W>, BroadcastSink<W>)
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let connections = connections
+        .into_iter()
+        .map(|connection| match options.max_line_bytes {
+            Some(max) => connection.with_max_line_bytes(max),
+            None => connection,
+        })
+        .collect();
+    multiplex(connections)
+}
+
+impl<H: GamepackHandler> Runner<H> {
+    /// Dispatch a command to the appropriate handler method.
+    fn dispatch_command(&mut self, cmd: GamepackCommand) -> GamepackResult<GamepackResponse> {
+        let respond = Responder::new(cmd.request_id());
+        let handler = &mut self.handler;
+        let short_circuit = self.paused && !self.options.poll_while_paused;
+
+        Ok(match cmd {
+            GamepackCommand::Init {
+                request_id,
+                protocol_version: daemon_version,
+                daemon_features,
+                force,
+            } => {
+                if let Some(cached) = self.initialized.as_ref().filter(|_| force != Some(true)) {
+                    let mut response = cached.clone();
+                    if let GamepackResponse::Initialized { request_id: id, .. } = &mut response {
+                        *id = request_id;
+                    }
+                    // A reconnected daemon may not have the last live data
+                    self.emitter.swap_live_data_base(None);
+                    return Ok(response);
+                }
+                let InitResponse {
+                    game_id,
+                    slug,
+                    protocol_version,
+                    pack_version,
+                    game_version,
+                    player,
+                } = handler.init()?;
+                let features: Vec<String> = daemon_features
+                    .into_iter()
+                    .filter(|f| SUPPORTED_FEATURES.contains(&f.as_str()))
+                    // One patch base cannot serve several daemons
+                    .filter(|f| !(self.multiplexed && f == FEATURE_LIVE_DATA_PATCH))
+                    .collect();
+                self.live_data_patches = features.iter().any(|f| f == FEATURE_LIVE_DATA_PATCH);
+                self.session_end_chunks =
+                    features.iter().any(|f| f == FEATURE_SESSION_END_CHUNKS);
+                self.emitter.swap_live_data_base(None);
+                self.emitter.set_live_data_patches(self.live_data_patches);
+                // Use the handler's version or fall back to crate version,
+                // then step down to the daemon's if it is older
+                let pack_protocol = if protocol_version > 0 {
+                    protocol_version
+                } else {
+                    PROTOCOL_VERSION
+                };
+                let protocol_version =
+                    daemon_version.map_or(pack_protocol, |daemon| daemon.min(pack_protocol));
+                self.emitter.set_negotiated_version(protocol_version);
+                handler.on_negotiated(protocol_version);
+                let mut response = respond.initialized(
+                    game_id,
+                    slug,
+                    protocol_version,
+                    pack_version,
+                    features,
+                    handler.derived_stats(),
+                );
+                if let GamepackResponse::Initialized {
+                    game_version: v,
+                    player: p,
+                    capabilities,
+                    ..
+                } = &mut response
+                {
+                    *v = game_version;
+                    *p = player;
+                    let mut declared = handler.capabilities();
+                    declared.extend(Capability::ALWAYS);
+                    *capabilities = declared.into_iter().collect();
+                    capabilities.sort();
+                }
+                self.initialized = Some(response.clone());
+                response
+            }
+
+            GamepackCommand::DetectRunning { .. } => {
+                respond.running_status(handler.detect_running())
+            }
+
+            GamepackCommand::GetStatus { .. } => respond.game_status(handler.get_status()),
+
+            GamepackCommand::GetGameVersion { .. } => {
+                respond.game_version(handler.get_game_version())
+            }
+
+            GamepackCommand::GetBuildInfo { .. } => {
+                let mut info = handler.get_build_info();
+                if info.pack_version.is_none() {
+                    if let Some(GamepackResponse::Initialized { pack_version, .. }) =
+                        &self.initialized
+                    {
+                        info.pack_version = pack_version.clone();
+                    }
+                }
+                respond.build_info(info)
+            }
+
+            GamepackCommand::PollEvents { .. } if short_circuit => respond.events(vec![]),
+
+            GamepackCommand::PollEvents { .. } => {
+                let mut events = handler.poll_events();
+                if self.options.sort_events {
+                    sort_events_chronological(&mut events);
+                }
+                cap_result(&mut events, self.options.soft_result_limit, "poll_events", "events");
+                respond.events(events)
+            }
+
+            GamepackCommand::GetLiveData { .. } | GamepackCommand::RefreshLiveData { .. }
+                if short_circuit =>
+            {
+                self.emitter.swap_live_data_base(None);
+                respond.live_data(None)
+            }
+
+            GamepackCommand::GetLiveDataAll { .. } if short_circuit => {
+                respond.live_data_all(vec![])
+            }
+
+            GamepackCommand::GetLiveDataAll { .. } => {
+                respond.live_data_all(handler.get_live_data_all())
+            }
+
+            // Turned into a patch, or not, when written; see `settle_live_data`
+            GamepackCommand::RefreshLiveData { .. } => {
+                self.live_data_answer = Some(LiveDataAnswer::Full);
+                respond.live_data(handler.get_live_data())
+            }
+
+            GamepackCommand::GetLiveData { .. } => {
+                self.live_data_answer = Some(LiveDataAnswer::Diff);
+                respond.live_data(handler.get_live_data())
+            }
+
+            GamepackCommand::SessionStart { .. } => match handler.background_session_start() {
+                Some(task) => {
+                    let id = respond.request_id().to_string();
+                    self.defer(id, move || respond.session_started(task()))
+                }
+                None => respond.session_started(handler.on_session_start()),
+            },
+
+            GamepackCommand::SessionEnd { context, .. } => {
+                if let Some(task) = handler.background_session_end(&context) {
+                    let id = respond.request_id().to_string();
+                    let emitter = self.emitter.clone();
+                    return Ok(self.defer(id, move || {
+                        let _progress = SessionEndScope::enter(emitter, respond.request_id());
+                        let match_data = task();
+                        respond.session_ended(
+                            match_data.map(|m| serde_json::to_value(m).unwrap_or_default()),
+                        )
+                    }));
+                }
+                let _progress = SessionEndScope::enter(self.emitter.clone(), respond.request_id());
+                let match_data = handler.on_session_end(context);
+                respond.session_ended(
+                    match_data.map(|m| serde_json::to_value(m).unwrap_or_default()),
+                )
+            }
+
+            GamepackCommand::Shutdown { .. } => {
+                // Let background session tasks answer before the emitter closes
+                for task in self.session_tasks.drain(..) {
+                    let _ = task.join();
+                }
+                handler.shutdown();
+                // The handler may still flush final messages from `shutdown`;
+                // anything emitted after this point (e.g. by background
+                // threads that outlive the handler) is dropped.
+                self.emitter.close();
+                respond.shutdown_complete()
+            }
+
+            GamepackCommand::Pause { .. } => {
+                if !self.paused {
+                    self.paused = true;
+                    handler.on_pause();
+                }
+                respond.ack()
+            }
+
+            GamepackCommand::Resume { .. } => {
+                if self.paused {
+                    self.paused = false;
+                    handler.on_resume();
+                }
+                respond.ack()
+            }
+
+            GamepackCommand::ResolveEventIcon { event_key, .. } => {
+                let icon_url = handler.resolve_event_icon(&event_key);
+                respond.event_icon_resolved(event_key, icon_url)
+            }
+
+            GamepackCommand::IsMatchInProgress {
+                subpack,
+                external_match_id,
+                ..
+            } => {
+                let status = handler.is_match_in_progress(subpack, &external_match_id);
+                respond.match_in_progress_status(status)
+            }
+
+            GamepackCommand::MatchExists {
+                subpack,
+                external_match_id,
+                ..
+            } => respond.match_exists_result(handler.match_exists(subpack, &external_match_id)),
+
+            GamepackCommand::GetActiveMatches { .. } => {
+                let mut matches = handler.get_active_matches();
+                matches.extend(self.emitter.active_matches());
+                matches.sort();
+                matches.dedup();
+                respond.active_matches(matches)
+            }
+
+            GamepackCommand::RunRecovery { .. } => {
+                let messages = handler.on_run_recovery();
+                let recovered = messages
+                    .into_iter()
+                    .filter_map(|message| self.emitter.emit_match_data(message).ok())
+                    .count();
+                respond.recovery_complete(recovered)
+            }
+
+            GamepackCommand::GetMatchTimeline {
+                subpack,
+                external_match_id,
+                entry_types,
+                limit,
+                start_secs,
+                end_secs,
+                ..
+            } => {
+                // Packs without their own timeline keep the default (not found) -
+                // the daemon holds the authoritative copy.
+                let mut timeline = handler.get_match_timeline(&GetMatchTimelineRequest {
+                    subpack,
+                    external_match_id,
+                    entry_types,
+                    limit,
+                    start_secs,
+                    end_secs,
+                });
+                cap_result(
+                    &mut timeline.entries,
+                    self.options.soft_result_limit,
+                    "get_match_timeline",
+                    "entries",
+                );
+                respond.match_timeline(timeline)
+            }
+
+            GamepackCommand::GetRecentMatches { subpack, limit, .. } => {
+                let mut matches = handler.get_recent_matches(subpack, limit);
+                if let Some(limit) = limit {
+                    matches.truncate(limit as usize);
+                }
+                respond.recent_matches(matches)
+            }
+
+            GamepackCommand::EmitTestMoment {
+                subpack, moment_id, ..
+            } => {
+                let (external_match_id, game_time_secs) = handler
+                    .current_match(subpack)
+                    .unwrap_or_else(|| (TEST_MOMENT_MATCH_ID.to_string(), 0.0));
+                let data = serde_json::json!({"test": true});
+                let moment = Moment::new(moment_id, game_time_secs, data);
+                match self.emitter.emit_moments(subpack, external_match_id, vec![moment]) {
+                    Ok(()) => respond.ack(),
+                    Err(e) => respond.error(GamepackError::with_code(
+                        format!("Failed to emit test moment: {}", e),
+                        "emit_failed",
+                    )),
+                }
+            }
+
+            GamepackCommand::GetSchema { subpack, .. } => {
+                respond.schema(subpack, handler.schema(subpack))
+            }
+
+            GamepackCommand::GetSampleMatchData { subpack, .. } => {
+                match handler.get_sample_match_data(subpack) {
+                    Some(data) => respond.sample_match_data(subpack, data),
+                    None => respond.error(GamepackError::with_code(
+                        format!("Sample data not implemented for subpack {}", subpack),
+                        "NOT_IMPLEMENTED",
+                    )),
+                }
+            }
+
+            GamepackCommand::GetDerivedStats { .. } => {
+                respond.derived_stats(handler.derived_stats())
+            }
+
+            GamepackCommand::GetLastError { .. } => respond.last_error(self.last_error.clone()),
+
+            GamepackCommand::TimeSync { daemon_send_ns, .. } => {
+                let pack_recv_ns = pack_clock_ns();
+                respond.time_sync_result(daemon_send_ns, pack_recv_ns, pack_clock_ns())
+            }
+
+            GamepackCommand::StorageStatus {
+                free_bytes,
+                clip_count,
+                budget_exhausted,
+                ..
+            } => {
+                handler.on_storage_status(StorageStatus {
+                    free_bytes,
+                    clip_count,
+                    budget_exhausted,
+                });
+                respond.ack()
+            }
+
+            GamepackCommand::SetActiveSubpack { subpack, .. } => {
+                self.emitter.set_active_subpack(subpack);
+                handler.on_active_subpack(subpack);
+                respond.ack()
+            }
+
+            GamepackCommand::MuteMatch {
+                subpack,
+                external_match_id,
+                ..
+            } => {
+                self.emitter.set_muted(subpack, &external_match_id, true);
+                handler.on_mute_match(subpack, &external_match_id);
+                respond.ack()
+            }
+
+            GamepackCommand::UnmuteMatch {
+                subpack,
+                external_match_id,
+                ..
+            } => {
+                self.emitter.set_muted(subpack, &external_match_id, false);
+                handler.on_unmute_match(subpack, &external_match_id);
+                respond.ack()
+            }
+
+            GamepackCommand::PrepareUpdate { .. } => {
+                respond.update_prepared(handler.on_prepare_update()?)
+            }
+
+            GamepackCommand::UpdateSettings { settings, .. } => {
+                handler.on_settings_update(settings)?;
+                respond.ack()
+            }
+
+            GamepackCommand::ValidateTriggerConfig { triggers, .. } => {
+                respond.trigger_config_validation(handler.validate_trigger_config(&triggers))
+            }
+
+            GamepackCommand::ShouldRecord {
+                moment_id, data, ..
+            } => respond.record_decision(handler.should_record(&moment_id, &data)),
+
+            GamepackCommand::GetTriggerRules { subpack, .. } => {
+                respond.trigger_rules(subpack, handler.get_trigger_rules(subpack))
+            }
+
+            GamepackCommand::RestoreState { state, .. } => {
+                handler.on_restore_state(state)?;
+                respond.ack()
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derived::DerivedStatSpec;
+    use crate::handler::GamepackResult;
+    use crate::transport::StreamTransport;
+    use crate::types::{
+        GameEvent, GameStatus, MatchData, MatchResult, MatchSummary, RecordDecision, SummarySource,
+    };
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct TestHandler {
+        initialized: bool,
+        /// Calls to `init`, successful or not
+        inits: u32,
+        fail_init: bool,
+        panic_on_poll: bool,
+        polls: usize,
+        pauses: usize,
+        resumes: usize,
+        /// When set, each poll also emits statistics for `current_match`
+        emitter: Option<Emitter>,
+        current_match: String,
+        switches: Vec<(String, String)>,
+        restored: Option<serde_json::Value>,
+        storage: Vec<StorageStatus>,
+        negotiated: Option<u32>,
+        shutdowns: u32,
+        settings: Option<serde_json::Value>,
+        /// When set, there is no live data
+        idle: bool,
+        /// When set, live data is explicitly cleared
+        live_cleared: bool,
+        /// When set, session end runs in the background and takes this long
+        slow_session_end: Option<std::time::Duration>,
+        /// When set, answers `GetRecentMatches` (ignoring the limit)
+        recent: Option<crate::MatchSummaryBuffer>,
+        /// When set, polled events have a NaN timestamp
+        nan_events: bool,
+        active_subpack: Option<u8>,
+        /// `(subpack, match, muted)` per mute hook call
+        mutes: Vec<(u8, String, bool)>,
+        /// When set, polls and session starts raise it with `warn`
+        warning: Option<String>,
+        /// When set, polls return these instead of one `Tick`
+        events: Option<Vec<GameEvent>>,
+        /// Matches `on_run_recovery` completes, drained on each call
+        stale: Vec<String>,
+        /// Progress each session end reports
+        progress: Vec<f32>,
+        /// When set, a match switch pushes this as live data
+        push_on_switch: Option<serde_json::Value>,
+    }
+
+    impl GamepackHandler for TestHandler {
+        fn init(&mut self) -> GamepackResult<InitResponse> {
+            self.inits += 1;
+            if self.fail_init {
+                return Err(GamepackError::with_code("client not found", "no_client"));
+            }
+            self.initialized = true;
+            Ok(InitResponse {
+                game_id: 99,
+                slug: "test".to_string(),
+                protocol_version: 1,
+                pack_version: crate::pack_version!(),
+                game_version: Some("14.3".to_string()),
+                player: None,
+            })
+        }
+
+        fn detect_running(&self) -> bool {
+            true
+        }
+
+        fn get_status(&self) -> GameStatus {
+            GameStatus::connected("Test connected")
+        }
+
+        fn poll_events(&mut self) -> Vec<GameEvent> {
+            if self.panic_on_poll {
+                panic!("poll exploded");
+            }
+            self.polls += 1;
+            if let Some(emitter) = &self.emitter {
+                emitter
+                    .emit_statistics(0, self.current_match.clone(), 1.0, HashMap::new())
+                    .unwrap();
+            }
+            if let Some(warning) = &self.warning {
+                warn(warning.clone());
+            }
+            if let Some(events) = &self.events {
+                return events.clone();
+            }
+            let at = if self.nan_events { f64::NAN } else { self.polls as f64 };
+            vec![GameEvent::new("Tick", at, serde_json::json!({}))]
+        }
+
+        fn get_trigger_rules(&self, subpack: u8) -> Vec<crate::TriggerRule> {
+            match subpack {
+                0 => vec![
+                    crate::TriggerRule::new("ChampionKill", "pentakill")
+                        .with_condition("5 kills within 10 seconds"),
+                    crate::TriggerRule::new("DragonKill", "dragon"),
+                ],
+                _ => vec![],
+            }
+        }
+
+        fn should_record(&self, moment_id: &str, data: &serde_json::Value) -> RecordDecision {
+            match (moment_id, data["spectating"].as_bool()) {
+                (_, Some(true)) => RecordDecision::skip("spectating"),
+                ("comeback", _) => RecordDecision::record()
+                    .with_window(crate::CaptureWindow::new(30.0, 10.0).unwrap()),
+                _ => RecordDecision::record(),
+            }
+        }
+
+        fn get_active_matches(&self) -> Vec<(u8, String)> {
+            let current = (!self.current_match.is_empty()).then(|| self.current_match.clone());
+            current.into_iter().map(|id| (0, id)).collect()
+        }
+
+        fn current_match(&self, _subpack: u8) -> Option<(String, f64)> {
+            (!self.current_match.is_empty()).then(|| (self.current_match.clone(), 42.0))
+        }
+
+        fn schema(&self, subpack: u8) -> Option<crate::LiveDataSchema> {
+            (subpack == 1).then(|| {
+                crate::LiveDataSchema::new()
+                    .field("kills", crate::FieldKind::Integer)
+                    .nullable_field("rank", crate::FieldKind::Text)
+            })
+        }
+
+        fn get_live_data(&self) -> Option<serde_json::Value> {
+            if self.idle {
+                return None;
+            }
+            if self.live_cleared {
+                return Some(serde_json::Value::Null);
+            }
+            Some(serde_json::json!({"test": true, "polls": self.polls}))
+        }
+
+        fn on_session_start(&mut self) -> Option<serde_json::Value> {
+            if let Some(warning) = &self.warning {
+                warn(warning.clone());
+            }
+            Some(serde_json::json!({"started": true}))
+        }
+
+        fn on_session_end(&mut self, _context: serde_json::Value) -> Option<MatchData> {
+            for progress in &self.progress {
+                session_end_progress(*progress, None);
+            }
+            Some(MatchData::new("test", 99, "win", serde_json::json!({})))
+        }
+
+        fn background_session_end(
+            &mut self,
+            _context: &serde_json::Value,
+        ) -> Option<crate::handler::SessionEndTask> {
+            let delay = self.slow_session_end?;
+            let progress = self.progress.clone();
+            Some(Box::new(move || {
+                for progress in progress {
+                    session_end_progress(progress, Some("fetching"));
+                }
+                std::thread::sleep(delay);
+                Some(MatchData::new("test", 99, "win", serde_json::json!({})))
+            }))
+        }
+
+        fn shutdown(&mut self) {
+            self.shutdowns += 1;
+        }
+
+        fn on_pause(&mut self) {
+            self.pauses += 1;
+        }
+
+        fn on_resume(&mut self) {
+            self.resumes += 1;
+        }
+
+        fn on_match_switch(&mut self, old_external_id: &str, new_external_id: &str) {
+            self.switches.push((old_external_id.to_string(), new_external_id.to_string()));
+            if let (Some(emitter), Some(data)) = (&self.emitter, &self.push_on_switch) {
+                emitter.emit_live_data_patch(data).unwrap();
+            }
+        }
+
+        fn derived_stats(&self) -> Vec<DerivedStatSpec> {
+            vec![DerivedStatSpec::new("kda", "(kills + assists) / deaths")]
+        }
+
+        fn on_settings_update(&mut self, settings: serde_json::Value) -> GamepackResult<()> {
+            crate::SettingsSchema::new()
+                .setting("clip_kills", crate::FieldKind::Bool)
+                .validate(&settings)?;
+            self.settings = Some(settings);
+            Ok(())
+        }
+
+        fn on_negotiated(&mut self, version: u32) {
+            self.negotiated = Some(version);
+        }
+
+        fn on_storage_status(&mut self, status: StorageStatus) {
+            self.storage.push(status);
+        }
+
+        fn on_active_subpack(&mut self, subpack: u8) {
+            self.active_subpack = Some(subpack);
+        }
+
+        fn on_mute_match(&mut self, subpack: u8, external_match_id: &str) {
+            self.mutes.push((subpack, external_match_id.to_string(), true));
+        }
+
+        fn on_unmute_match(&mut self, subpack: u8, external_match_id: &str) {
+            self.mutes.push((subpack, external_match_id.to_string(), false));
+        }
+
+        fn on_prepare_update(&mut self) -> GamepackResult<Option<serde_json::Value>> {
+            Ok(Some(serde_json::json!({"polls": self.polls})))
+        }
+
+        fn on_restore_state(&mut self, state: serde_json::Value) -> GamepackResult<()> {
+            self.restored = Some(state);
+            Ok(())
+        }
+
+        fn on_run_recovery(&mut self) -> Vec<MatchDataMessage> {
+            self.stale
+                .drain(..)
+                .flat_map(|id| {
+                    let stats = MatchDataMessage::write_statistics(0, &id, 1800.0, HashMap::new());
+                    [stats, MatchDataMessage::set_complete(0, id, SummarySource::LiveFallback)]
+                })
+                .collect()
+        }
+
+        fn get_recent_matches(&self, subpack: u8, _limit: Option<u32>) -> Vec<MatchSummary> {
+            self.recent
+                .as_ref()
+                .map(|recent| recent.recent(subpack, None))
+                .unwrap_or_default()
+        }
+
+        fn capabilities(&self) -> HashSet<Capability> {
+            // Deliberately leaves out the required ones
+            match self.recent {
+                Some(_) => HashSet::from([Capability::RecentMatches]),
+                None => Capability::always(),
+            }
+        }
+    }
+
+    fn test_runner() -> Runner<TestHandler> {
+        Runner::new(TestHandler::default(), RunnerOptions::default())
+    }
+
+    #[test]
+    fn test_dispatch_init() {
+        let mut runner = test_runner();
+        let response = runner
+            .dispatch_command(GamepackCommand::Init {
+                request_id: "test_1".to_string(),
+                protocol_version: None,
+                daemon_features: vec![],
+                force: None,
+            })
+            .unwrap();
+
+        assert!(runner.handler.initialized);
+        match response {
+            GamepackResponse::Initialized {
+                request_id,
+                game_id,
+                slug,
+                pack_version,
+                ..
+            } => {
+                assert_eq!(request_id, "test_1");
+                assert_eq!(game_id, 99);
+                assert_eq!(slug, "test");
+                assert_eq!(pack_version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+            }
+            _ => panic!("Expected Initialized response"),
+        }
+    }
+
+    #[test]
+    fn test_dispatch_get_status() {
+        let mut runner = test_runner();
+        let response = runner
+            .dispatch_command(GamepackCommand::GetStatus {
+                request_id: "test_2".to_string(),
+            })
+            .unwrap();
+
+        match response {
+            GamepackResponse::GameStatus {
+                request_id,
+                connected,
+                connection_status,
+                ..
+            } => {
+                assert_eq!(request_id, "test_2");
+                assert!(connected);
+                assert_eq!(connection_status, "Test connected");
+            }
+            _ => panic!("Expected GameStatus response"),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_request_id_rejected_when_enabled() {
+        let mut runner = Runner::new(
+            TestHandler::default(),
+            RunnerOptions {
+                reject_duplicate_request_ids: true,
+                ..Default::default()
+            },
+        );
+        let cmd = || GamepackCommand::DetectRunning {
+            request_id: "dup_1".to_string(),
+        };
+
+        let first = runner.handle_command(cmd());
+        assert!(matches!(first, GamepackResponse::RunningStatus { .. }));
+
+        match runner.handle_command(cmd()) {
+            GamepackResponse::Error {
+                request_id, code, ..
+            } => {
+                assert_eq!(request_id, "dup_1");
+                assert_eq!(code.as_deref(), Some("duplicate_request_id"));
+            }
+            other => panic!("Expected Error response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_request_id_allowed_by_default() {
+        let mut runner = test_runner();
+        for _ in 0..2 {
+            let response = runner.handle_command(GamepackCommand::DetectRunning {
+                request_id: "dup_1".to_string(),
+            });
+            assert!(matches!(response, GamepackResponse::RunningStatus { .. }));
+        }
+    }
+
+    #[test]
+    fn test_recent_request_ids_window_is_bounded() {
+        let mut ids = RecentRequestIds::new(2);
+        assert!(ids.insert("a"));
+        assert!(ids.insert("b"));
+        assert!(!ids.insert("a"));
+        assert!(ids.insert("c")); // evicts "a"
+        assert!(ids.insert("a"));
+    }
+
+    fn get_last_error(runner: &mut Runner<TestHandler>) -> Option<ErrorInfo> {
+        match runner.handle_command(GamepackCommand::GetLastError {
+            request_id: "health".to_string(),
+        }) {
+            GamepackResponse::LastError { request_id, error } => {
+                assert_eq!(request_id, "health");
+                error
+            }
+            other => panic!("Expected LastError response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_last_error_empty_initially() {
+        let mut runner = test_runner();
+        assert!(get_last_error(&mut runner).is_none());
+    }
+
+    #[test]
+    fn test_last_error_records_handler_error_and_clears_on_init() {
+        let mut runner = test_runner();
+        runner.handler.fail_init = true;
+
+        let response = runner.handle_command(GamepackCommand::Init {
+            request_id: "init_1".to_string(),
+            protocol_version: None,
+            daemon_features: vec![],
+            force: None,
+        });
+        assert!(matches!(response, GamepackResponse::Error { .. }));
+
+        let error = get_last_error(&mut runner).expect("error recorded");
+        assert_eq!(error.message, "client not found");
+        assert_eq!(error.code.as_deref(), Some("no_client"));
+
+        runner.handler.fail_init = false;
+        let response = runner.handle_command(GamepackCommand::Init {
+            request_id: "init_2".to_string(),
+            protocol_version: None,
+            daemon_features: vec![],
+            force: None,
+        });
+        assert!(matches!(response, GamepackResponse::Initialized { .. }));
+        assert!(get_last_error(&mut runner).is_none());
+    }
+
+    #[test]
+    fn test_handler_panic_is_caught_and_recorded() {
+        let mut runner = test_runner();
+        runner.handler.panic_on_poll = true;
+
+        match runner.handle_command(GamepackCommand::PollEvents {
+            request_id: "poll_1".to_string(),
+        }) {
+            GamepackResponse::Error {
+                request_id, code, ..
+            } => {
+                assert_eq!(request_id, "poll_1");
+                assert_eq!(code.as_deref(), Some("handler_panic"));
+            }
+            other => panic!("Expected Error response, got {:?}", other),
+        }
+
+        let error = get_last_error(&mut runner).expect("panic recorded");
+        assert!(error.message.contains("poll exploded"));
+        assert_eq!(error.code.as_deref(), Some("handler_panic"));
+    }
+
+    #[test]
+    fn test_paused_runner_short_circuits_polling() {
+        let mut runner = test_runner();
+
+        let response = runner.handle_command(GamepackCommand::Pause { request_id: "p".into() });
+        assert!(matches!(response, GamepackResponse::Ack { .. }));
+        assert_eq!(runner.handler.pauses, 1);
+
+        match runner.handle_command(GamepackCommand::PollEvents { request_id: "e".into() }) {
+            GamepackResponse::Events { events, .. } => assert!(events.is_empty()),
+            other => panic!("Expected Events response, got {:?}", other),
+        }
+        match runner.handle_command(GamepackCommand::GetLiveData { request_id: "l".into() }) {
+            GamepackResponse::LiveData { data, .. } => assert!(data.is_none()),
+            other => panic!("Expected LiveData response, got {:?}", other),
+        }
+        assert_eq!(runner.handler.polls, 0);
+
+        // Status still reaches the handler while paused
+        let response = runner.handle_command(GamepackCommand::GetStatus { request_id: "s".into() });
+        assert!(matches!(response, GamepackResponse::GameStatus { connected: true, .. }));
+
+        runner.handle_command(GamepackCommand::Resume { request_id: "r".into() });
+        assert_eq!(runner.handler.resumes, 1);
+        match runner.handle_command(GamepackCommand::PollEvents { request_id: "e2".into() }) {
+            GamepackResponse::Events { events, .. } => assert_eq!(events.len(), 1),
+            other => panic!("Expected Events response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pause_and_resume_hooks_fire_only_on_transitions() {
+        let mut runner = test_runner();
+
+        runner.handle_command(GamepackCommand::Resume { request_id: "r0".into() });
+        runner.handle_command(GamepackCommand::Pause { request_id: "p1".into() });
+        runner.handle_command(GamepackCommand::Pause { request_id: "p2".into() });
+        runner.handle_command(GamepackCommand::Resume { request_id: "r1".into() });
+
+        assert_eq!(runner.handler.pauses, 1);
+        assert_eq!(runner.handler.resumes, 1);
+    }
+
+    #[test]
+    fn test_poll_while_paused_option_forwards_polls() {
+        let mut runner = Runner::new(
+            TestHandler::default(),
+            RunnerOptions {
+                poll_while_paused: true,
+                ..Default::default()
+            },
+        );
+
+        runner.handle_command(GamepackCommand::Pause { request_id: "p".into() });
+        runner.handle_command(GamepackCommand::PollEvents { request_id: "e".into() });
+
+        assert_eq!(runner.handler.polls, 1);
+    }
+
+    #[test]
+    fn shutdown_closes_emitter_for_background_threads() {
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        let mut runner = test_runner();
+        runner.emitter = emitter.clone();
+        // Stands in for a pack thread that keeps pushing after shutdown
+        let background = emitter;
+
+        background.emit_statistics(0, "m1", 1.0, HashMap::new()).unwrap();
+        let response = runner.handle_command(GamepackCommand::Shutdown {
+            request_id: "s1".to_string(),
+        });
+        assert!(matches!(response, GamepackResponse::ShutdownComplete { .. }));
+
+        let late = background.emit_statistics(0, "m1", 2.0, HashMap::new());
+        assert!(matches!(late, Err(crate::EmitError::Closed)));
+        assert_eq!(sink.messages().len(), 1);
+    }
+
+    #[test]
+    fn a_later_run_reopens_the_emitter_with_a_fresh_session() {
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        let mut runner = test_runner();
+        runner.emitter = emitter.clone();
+        run_lines(&mut runner, "{\"type\":\"shutdown\",\"request_id\":\"s1\"}\n");
+        assert!(emitter.emit_statistics(0, "m1", 1.0, HashMap::new()).is_err());
+        emitter.set_muted(0, "m1", true);
+
+        let mut runner = test_runner();
+        runner.emitter = emitter.clone();
+        runner.handler.emitter = Some(emitter.clone());
+        runner.handler.current_match = "m1".to_string();
+        run_lines(&mut runner, POLLS);
+
+        assert!(!emitter.is_muted(0, "m1"));
+        assert!(!sink.messages().is_empty());
+        assert_eq!(emitter.connection_id(), Some(runner.connection_id.clone()));
+    }
+
+    #[test]
+    fn abrupt_match_switch_completes_old_match_and_notifies_handler() {
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        let emitter = emitter.with_match_switch_detection(true);
+        let mut runner = test_runner();
+        runner.emitter = emitter.clone();
+        runner.handler.emitter = Some(emitter);
+
+        let poll = |runner: &mut Runner<TestHandler>| {
+            runner.handle_command(GamepackCommand::PollEvents {
+                request_id: String::new(),
+            })
+        };
+        runner.handler.current_match = "custom-1".to_string();
+        poll(&mut runner);
+        // Player leaves and joins a new custom game without a SessionEnd
+        runner.handler.current_match = "custom-2".to_string();
+        poll(&mut runner);
+
+        assert_eq!(
+            runner.handler.switches,
+            vec![("custom-1".to_string(), "custom-2".to_string())]
+        );
+        let completed: Vec<_> = sink
+            .messages()
+            .into_iter()
+            .filter(|m| matches!(m, MatchDataMessage::SetComplete { .. }))
+            .collect();
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].external_match_id(), "custom-1");
+    }
+
+    #[test]
+    fn prepare_update_state_restores_into_new_instance() {
+        let mut old = test_runner();
+        old.handle_command(GamepackCommand::PollEvents {
+            request_id: "p1".to_string(),
+        });
+
+        let state = match old.handle_command(GamepackCommand::PrepareUpdate {
+            request_id: "u1".to_string(),
+        }) {
+            GamepackResponse::UpdatePrepared { request_id, state } => {
+                assert_eq!(request_id, "u1");
+                state.expect("Expected checkpoint state")
+            }
+            other => panic!("Expected UpdatePrepared response, got {:?}", other),
+        };
+
+        let mut new = test_runner();
+        let response = new.handle_command(GamepackCommand::RestoreState {
+            request_id: "r1".to_string(),
+            state,
+        });
+
+        assert!(matches!(response, GamepackResponse::Ack { .. }));
+        assert_eq!(new.handler.restored, Some(serde_json::json!({"polls": 1})));
+    }
+
+    fn init_with_features(runner: &mut Runner<TestHandler>, features: &[&str]) -> Vec<String> {
+        match runner.handle_command(GamepackCommand::Init {
+            request_id: "init".to_string(),
+            protocol_version: None,
+            daemon_features: features.iter().map(|f| f.to_string()).collect(),
+            force: None,
+        }) {
+            GamepackResponse::Initialized { features, .. } => features,
+            other => panic!("Expected Initialized response, got {:?}", other),
+        }
+    }
+
+    fn live_data(runner: &mut Runner<TestHandler>) -> GamepackResponse {
+        answer(
+            runner,
+            GamepackCommand::GetLiveData {
+                request_id: String::new(),
+            },
+        )
+    }
+
+    /// Handle a command and settle live data as the main loop does when
+    /// writing the answer.
+    fn answer(runner: &mut Runner<TestHandler>, cmd: GamepackCommand) -> GamepackResponse {
+        let mut response = runner.handle_command(cmd);
+        let emitter = runner.emitter.clone();
+        runner.settle_live_data(&mut response, &mut emitter.lock_live_data_base());
+        response
+    }
+
+    #[test]
+    fn init_enables_only_supported_daemon_features() {
+        let mut runner = test_runner();
+
+        let features = init_with_features(&mut runner, &["live_data_patch", "teleport"]);
+
+        assert_eq!(features, vec!["live_data_patch".to_string()]);
+        assert!(runner.live_data_patches);
+    }
+
+    #[test]
+    fn negotiated_live_data_sends_full_base_then_patches() {
+        let mut runner = test_runner();
+        // The patch base lives on the emitter: keep it from other tests
+        runner.emitter = crate::testing::capturing_emitter().0;
+        init_with_features(&mut runner, &["live_data_patch"]);
+
+        let base = match live_data(&mut runner) {
+            GamepackResponse::LiveData { data: Some(data), .. } => data,
+            other => panic!("Expected full LiveData first, got {:?}", other),
+        };
+
+        runner.handler.polls = 3;
+        match live_data(&mut runner) {
+            GamepackResponse::LiveDataPatch { ops, .. } => {
+                let mut patched = base;
+                crate::patch::apply(&mut patched, &ops).unwrap();
+                assert_eq!(patched, serde_json::json!({"test": true, "polls": 3}));
+            }
+            other => panic!("Expected LiveDataPatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pushed_patch_moves_the_base_for_the_next_live_data() {
+        let mut runner = test_runner();
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        runner.emitter = emitter.clone();
+        init_with_features(&mut runner, &["live_data_patch"]);
+        live_data(&mut runner);
+
+        // The pack pushes a field the handler later drops again
+        let pushed = serde_json::json!({"test": true, "polls": 0, "b": 1});
+        emitter.emit_live_data_patch(&pushed).unwrap();
+        let mut daemon = pushed;
+        match live_data(&mut runner) {
+            GamepackResponse::LiveDataPatch { ops, .. } => {
+                crate::patch::apply(&mut daemon, &ops).unwrap();
+            }
+            other => panic!("Expected LiveDataPatch, got {:?}", other),
+        }
+        assert_eq!(daemon, serde_json::json!({"test": true, "polls": 0}));
+        assert_eq!(sink.responses().len(), 1);
+    }
+
+    /// Transport serving fixed lines, starting match `m2` on `emitter`
+    /// before the last one is read.
+    struct SwitchBeforeLast {
+        lines: VecDeque<String>,
+        emitter: Emitter,
+        written: Vec<String>,
+    }
+
+    impl Transport for SwitchBeforeLast {
+        fn read_message(&mut self) -> io::Result<Option<String>> {
+            if self.lines.len() == 1 {
+                self.emitter.emit_statistics(0, "m2", 1.0, HashMap::new()).unwrap();
+            }
+            Ok(self.lines.pop_front())
+        }
+
+        fn write_message(&mut self, message: &str) -> io::Result<()> {
+            self.written.push(message.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn push_while_answering_live_data_is_diffed_against_what_the_daemon_holds() {
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        let emitter = emitter.with_match_switch_detection(true);
+        let mut runner = test_runner();
+        runner.emitter = emitter.clone();
+        runner.handler.emitter = Some(emitter.clone());
+        runner.handler.push_on_switch = Some(serde_json::json!({"test": true, "pushed": true}));
+        emitter.emit_statistics(0, "m1", 1.0, HashMap::new()).unwrap();
+        // The switch is handled after the second `get_live_data` is
+        // dispatched, before its answer is written
+        let mut transport = SwitchBeforeLast {
+            lines: [
+                r#"{"type":"init","request_id":"i","daemon_features":["live_data_patch"]}"#,
+                r#"{"type":"get_live_data","request_id":"l1"}"#,
+                r#"{"type":"get_live_data","request_id":"l2"}"#,
+            ]
+            .map(String::from)
+            .into(),
+            emitter: emitter.clone(),
+            written: Vec::new(),
+        };
+        runner.run(&mut transport);
+        assert_eq!(runner.handler.switches.len(), 1);
+
+        let answer = |i: usize| serde_json::from_str(&transport.written[i]).unwrap();
+        let mut daemon = match answer(1) {
+            GamepackResponse::LiveData { data, .. } => data.unwrap(),
+            other => panic!("Expected LiveData, got {:?}", other),
+        };
+        // The push went out first, so the daemon applies it first
+        let mut patches = sink.responses();
+        patches.retain(|r| matches!(r, GamepackResponse::LiveDataPatch { .. }));
+        assert_eq!(patches.len(), 1);
+        patches.push(answer(2));
+        for response in patches {
+            match response {
+                GamepackResponse::LiveDataPatch { ops, .. } => {
+                    crate::patch::apply(&mut daemon, &ops).unwrap();
+                }
+                other => panic!("Expected LiveDataPatch, got {:?}", other),
+            }
+        }
+        assert_eq!(daemon, serde_json::json!({"test": true, "polls": 0}));
+    }
+
+    #[test]
+    fn live_data_without_negotiation_is_always_full() {
+        let mut runner = test_runner();
+        init_with_features(&mut runner, &[]);
+
+        for _ in 0..2 {
+            assert!(matches!(
+                live_data(&mut runner),
+                GamepackResponse::LiveData { data: Some(_), .. }
+            ));
+        }
+    }
+
+    /// Run the loop over NDJSON `input`, returning the outcome and output lines.
+    fn run_lines(runner: &mut Runner<TestHandler>, input: &str) -> (RunOutcome, Vec<String>) {
+        run_bytes(runner, input.as_bytes())
+    }
+
+    /// Like [`run_lines`], for input that need not be UTF-8.
+    fn run_bytes(runner: &mut Runner<TestHandler>, input: &[u8]) -> (RunOutcome, Vec<String>) {
+        let mut output = Vec::new();
+        let mut transport = StreamTransport::new(input, &mut output);
+        if let Some(max) = runner.options.max_line_bytes {
+            transport = transport.with_max_line_bytes(max);
+        }
+        let outcome = runner.run(&mut transport);
+        let lines = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        (outcome, lines)
+    }
+
+    fn panicking_runner(on_panic: PanicPolicy) -> Runner<TestHandler> {
+        let (emitter, _sink) = crate::testing::capturing_emitter();
+        let handler = TestHandler {
+            panic_on_poll: true,
+            ..Default::default()
+        };
+        let options = RunnerOptions {
+            on_panic,
+            ..Default::default()
+        };
+        let mut runner = Runner::new(handler, options);
+        runner.emitter = emitter;
+        runner
+    }
+
+    const POLLS: &str = concat!(
+        r#"{"type":"poll_events","request_id":"p1"}"#,
+        "\n",
+        r#"{"type":"poll_events","request_id":"p2"}"#,
+        "\n",
+        r#"{"type":"poll_events","request_id":"p3"}"#,
+        "\n",
+    );
+
+    #[test]
+    fn panic_policy_continue_tolerates_every_panic() {
+        let mut runner = panicking_runner(PanicPolicy::Continue);
+
+        let (outcome, lines) = run_lines(&mut runner, POLLS);
+
+        assert_eq!(outcome, RunOutcome::StdinClosed);
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|l| l.contains("handler_panic")));
+    }
+
+    #[test]
+    fn panic_policy_abort_stops_after_first_panic() {
+        let mut runner = panicking_runner(PanicPolicy::Abort);
+
+        let (outcome, lines) = run_lines(&mut runner, POLLS);
+
+        assert_eq!(outcome, RunOutcome::Aborted);
+        assert_eq!(outcome.exit_code(), 1);
+        // The error for the panicking command is still written
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("handler_panic"));
+        assert!(runner.emitter.is_closed());
+    }
+
+    #[test]
+    fn panic_policy_continue_until_aborts_after_limit() {
+        let mut runner = panicking_runner(PanicPolicy::ContinueUntil(2));
+
+        let (outcome, lines) = run_lines(&mut runner, POLLS);
+
+        assert_eq!(outcome, RunOutcome::Aborted);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(runner.panics, 3);
+    }
+
+    #[test]
+    fn run_completes_on_shutdown() {
+        let mut runner = test_runner();
+        let (emitter, _sink) = crate::testing::capturing_emitter();
+        runner.emitter = emitter;
+        let input = concat!(
+            r#"{"type":"shutdown","request_id":"s1"}"#,
+            "\n",
+            r#"{"type":"poll_events","request_id":"p1"}"#,
+            "\n",
+        );
+
+        let (outcome, lines) = run_lines(&mut runner, input);
+
+        assert_eq!(outcome, RunOutcome::Completed);
+        assert_eq!(outcome.exit_code(), 0);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(runner.handler.polls, 0);
+    }
+
+    #[test]
+    fn storage_status_invokes_hook_and_acks() {
+        let mut runner = test_runner();
+
+        let response = runner.handle_command(GamepackCommand::StorageStatus {
+            request_id: "st1".to_string(),
+            free_bytes: 0,
+            clip_count: 500,
+            budget_exhausted: true,
+        });
+
+        assert!(matches!(response, GamepackResponse::Ack { request_id } if request_id == "st1"));
+        assert_eq!(
+            runner.handler.storage,
+            vec![StorageStatus {
+                free_bytes: 0,
+                clip_count: 500,
+                budget_exhausted: true,
+            }]
+        );
+    }
+
+    fn negotiate(daemon_version: Option<u32>) -> (Runner<TestHandler>, u32) {
+        let mut runner = test_runner();
+        let (emitter, _sink) = crate::testing::capturing_emitter();
+        runner.emitter = emitter;
+        let response = runner.handle_command(GamepackCommand::Init {
+            request_id: "init".to_string(),
+            protocol_version: daemon_version,
+            daemon_features: vec![],
+            force: None,
+        });
+        match response {
+            GamepackResponse::Initialized {
+                protocol_version, ..
+            } => (runner, protocol_version),
+            other => panic!("Expected Initialized response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn on_negotiated_fires_with_resolved_version() {
+        // TestHandler implements version 1
+        let (runner, version) = negotiate(Some(3));
+
+        assert_eq!(version, 1);
+        assert_eq!(runner.handler.negotiated, Some(1));
+        assert_eq!(runner.emitter.negotiated_version(), 1);
+    }
+
+    #[test]
+    fn negotiation_steps_down_to_older_daemon() {
+        let (runner, version) = negotiate(Some(0));
+
+        assert_eq!(version, 0);
+        assert_eq!(runner.handler.negotiated, Some(0));
+        assert_eq!(runner.emitter.negotiated_version(), 0);
+    }
+
+    #[test]
+    fn negotiation_without_daemon_version_uses_pack_version() {
+        let (runner, version) = negotiate(None);
+
+        assert_eq!(version, 1);
+        assert_eq!(runner.handler.negotiated, Some(1));
+    }
+
+    #[test]
+    fn stdin_close_shuts_down_implicitly() {
+        let mut runner = test_runner();
+        let (emitter, _sink) = crate::testing::capturing_emitter();
+        runner.emitter = emitter;
+        let input = concat!(r#"{"type":"poll_events","request_id":"p1"}"#, "\n");
+
+        let (outcome, lines) = run_lines(&mut runner, input);
+
+        assert_eq!(outcome, RunOutcome::StdinClosed);
+        assert_eq!(outcome.exit_code(), 0);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(runner.handler.shutdowns, 1);
+        assert!(runner.emitter.is_closed());
+    }
+
+    #[test]
+    fn derived_stats_are_declared_at_init_and_on_request() {
+        let mut runner = test_runner();
+        let (emitter, _sink) = crate::testing::capturing_emitter();
+        runner.emitter = emitter;
+        let expected = vec![DerivedStatSpec::new("kda", "(kills + assists) / deaths")];
+
+        match runner.handle_command(GamepackCommand::Init {
+            request_id: "init".to_string(),
+            protocol_version: None,
+            daemon_features: vec![],
+            force: None,
+        }) {
+            GamepackResponse::Initialized { derived_stats, .. } => {
+                assert_eq!(derived_stats, expected)
+            }
+            other => panic!("Expected Initialized response, got {:?}", other),
+        }
+
+        match runner.handle_command(GamepackCommand::GetDerivedStats {
+            request_id: "d1".to_string(),
+        }) {
+            GamepackResponse::DerivedStats { request_id, specs } => {
+                assert_eq!(request_id, "d1");
+                assert_eq!(specs, expected);
+            }
+            other => panic!("Expected DerivedStats response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn match_exists_defaults_to_unknown() {
+        let mut runner = test_runner();
+
+        let response = runner.handle_command(GamepackCommand::MatchExists {
+            request_id: "e1".to_string(),
+            subpack: 0,
+            external_match_id: "m1".to_string(),
+        });
+
+        assert!(matches!(
+            response,
+            GamepackResponse::MatchExistsResult { request_id, exists: false } if request_id == "e1"
+        ));
+    }
+
+    fn get_recent_matches(runner: &mut Runner<TestHandler>, limit: Option<u32>) -> Vec<String> {
+        let response = runner.handle_command(GamepackCommand::GetRecentMatches {
+            request_id: "r1".to_string(),
+            subpack: 0,
+            limit,
+        });
+        match response {
+            GamepackResponse::RecentMatches { matches, .. } => {
+                matches.into_iter().map(|m| m.external_match_id).collect()
+            }
+            other => panic!("Expected RecentMatches response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn recent_matches_default_to_empty() {
+        let mut runner = test_runner();
+        assert!(get_recent_matches(&mut runner, None).is_empty());
+    }
+
+    #[test]
+    fn recent_matches_come_from_buffer_and_respect_limit() {
+        let mut recent = crate::MatchSummaryBuffer::new(10);
+        for id in ["m1", "m2", "m3"] {
+            let played_at = crate::Timestamp::from_unix_millis(0);
+            let summary = serde_json::json!({});
+            recent.record(0, MatchSummary::new(id, MatchResult::Win, played_at, summary));
+        }
+        let mut runner = test_runner();
+        runner.handler.recent = Some(recent);
+
+        assert_eq!(get_recent_matches(&mut runner, None), vec!["m3", "m2", "m1"]);
+        // The handler ignores the limit; the runner still enforces it
+        assert_eq!(get_recent_matches(&mut runner, Some(2)), vec!["m3", "m2"]);
+    }
+
+    #[test]
+    fn refresh_live_data_bypasses_patch_base() {
+        let mut runner = test_runner();
+        // The patch base lives on the emitter: keep it from other tests
+        runner.emitter = crate::testing::capturing_emitter().0;
+        init_with_features(&mut runner, &["live_data_patch"]);
+        live_data(&mut runner);
+
+        runner.handler.polls = 5;
+        match answer(
+            &mut runner,
+            GamepackCommand::RefreshLiveData {
+                request_id: "refresh".into(),
+            },
+        ) {
+            GamepackResponse::LiveData { request_id, data, .. } => {
+                assert_eq!(request_id, "refresh");
+                assert_eq!(data, Some(serde_json::json!({"test": true, "polls": 5})));
+            }
+            other => panic!("Expected full LiveData, got {:?}", other),
+        }
+
+        // Later patches are relative to the refreshed data
+        runner.handler.polls = 6;
+        match live_data(&mut runner) {
+            GamepackResponse::LiveDataPatch { ops, .. } => assert_eq!(ops.len(), 1),
+            other => panic!("Expected LiveDataPatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn nothing_follows_shutdown_complete() {
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        let mut runner = test_runner();
+        runner.emitter = emitter.clone();
+        let input = concat!(
+            r#"{"type":"poll_events","request_id":"p1"}"#,
+            "\n",
+            r#"{"type":"shutdown","request_id":"s1"}"#,
+            "\n",
+            r#"{"type":"poll_events","request_id":"p2"}"#,
+            "\n",
+            r#"{"type":"get_status","request_id":"g1"}"#,
+            "\n",
+        );
+
+        let (outcome, lines) = run_lines(&mut runner, input);
+        // A background thread still emitting after the loop ended
+        let late = emitter.emit_statistics(0, "m1", 1.0, HashMap::new());
+
+        assert_eq!(outcome, RunOutcome::Completed);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains("shutdown_complete"));
+        assert_eq!(runner.handler.polls, 1);
+        assert!(matches!(late, Err(crate::EmitError::Closed)));
+        assert!(sink.responses().is_empty());
+    }
+
+    fn update_settings(
+        runner: &mut Runner<TestHandler>,
+        settings: serde_json::Value,
+    ) -> GamepackResponse {
+        runner.handle_command(GamepackCommand::UpdateSettings {
+            request_id: "set1".to_string(),
+            settings,
+        })
+    }
+
+    #[test]
+    fn accepted_settings_are_applied_and_acked() {
+        let mut runner = test_runner();
+
+        let response = update_settings(&mut runner, serde_json::json!({"clip_kills": false}));
+
+        assert!(matches!(response, GamepackResponse::Ack { request_id } if request_id == "set1"));
+        assert_eq!(runner.handler.settings, Some(serde_json::json!({"clip_kills": false})));
+    }
+
+    #[test]
+    fn rejected_settings_answer_with_pack_error() {
+        let mut runner = test_runner();
+
+        let response = update_settings(&mut runner, serde_json::json!({"clip_kills": "yes"}));
+
+        match response {
+            GamepackResponse::Error {
+                request_id,
+                message,
+                code,
+            } => {
+                assert_eq!(request_id, "set1");
+                assert!(message.contains("clip_kills"), "{}", message);
+                assert_eq!(code.as_deref(), Some("invalid_settings"));
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+        assert!(runner.handler.settings.is_none());
+    }
+
+    #[test]
+    fn live_data_all_defaults_to_single_active_match() {
+        let mut runner = test_runner();
+
+        match runner.handle_command(GamepackCommand::GetLiveDataAll {
+            request_id: "all".into(),
+        }) {
+            GamepackResponse::LiveDataAll { request_id, matches } => {
+                assert_eq!(request_id, "all");
+                assert_eq!(
+                    matches,
+                    vec![("active".to_string(), serde_json::json!({"test": true, "polls": 0}))]
+                );
+            }
+            other => panic!("Expected LiveDataAll, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn live_data_all_is_empty_without_live_data() {
+        let idle = TestHandler {
+            idle: true,
+            ..TestHandler::default()
+        };
+        assert!(idle.get_live_data_all().is_empty());
+
+        let mut runner = test_runner();
+        runner.handle_command(GamepackCommand::Pause { request_id: "p".into() });
+        match runner.handle_command(GamepackCommand::GetLiveDataAll {
+            request_id: "all".into(),
+        }) {
+            GamepackResponse::LiveDataAll { matches, .. } => assert!(matches.is_empty()),
+            other => panic!("Expected LiveDataAll, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn overlong_command_lines_are_rejected_and_skipped() {
+        let options = RunnerOptions {
+            max_line_bytes: Some(64),
+            ..Default::default()
+        };
+        let mut runner = Runner::new(TestHandler::default(), options);
+        runner.emitter = crate::testing::capturing_emitter().0;
+        let input = format!(
+            "{{\"type\":\"session_end\",\"request_id\":\"big\",\"context\":\"{}\"}}\n{}",
+            "x".repeat(100),
+            r#"{"type":"poll_events","request_id":"p1"}"#,
+        );
+
+        let (_, lines) = run_lines(&mut runner, &input);
+
+        assert_eq!(lines.len(), 2);
+        match serde_json::from_str(&lines[0]).unwrap() {
+            GamepackResponse::Error { request_id, code, .. } => {
+                assert_eq!(request_id, "");
+                assert_eq!(code.as_deref(), Some("line_too_long"));
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+        assert!(lines[1].contains(r#""request_id":"p1""#));
+    }
+
+    #[test]
+    fn invalid_utf8_line_is_rejected_and_skipped() {
+        let mut runner = test_runner();
+        runner.emitter = crate::testing::capturing_emitter().0;
+        let mut input = b"{\"type\":\"poll_events\",\"request_id\":\"\xff\"}\n".to_vec();
+        input.extend_from_slice(br#"{"type":"poll_events","request_id":"p1"}"#);
+        input.push(b'\n');
+
+        let (outcome, lines) = run_bytes(&mut runner, &input);
+
+        assert_eq!(outcome, RunOutcome::StdinClosed);
+        assert_eq!(lines.len(), 2);
+        match serde_json::from_str(&lines[0]).unwrap() {
+            GamepackResponse::Error { code, message, .. } => {
+                assert_eq!(code.as_deref(), Some("invalid_utf8"));
+                assert!(message.contains("byte 36"), "{}", message);
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+        match serde_json::from_str(&lines[1]).unwrap() {
+            GamepackResponse::Events { request_id, .. } => assert_eq!(request_id, "p1"),
+            other => panic!("Expected Events, got {:?}", other),
+        }
+        assert_eq!(runner.handler.polls, 1);
+    }
+
+    #[test]
+    fn responses_are_recorded_in_metrics() {
+        let metrics = crate::InMemoryMetrics::new();
+        let mut runner = test_runner();
+        runner.emitter = crate::testing::capturing_emitter().0.with_metrics(metrics.clone());
+
+        let (_, lines) = run_lines(&mut runner, POLLS);
+
+        let total: usize = lines.iter().map(String::len).sum();
+        assert_eq!(metrics.bytes_for("Events"), total as u64);
+        assert_eq!(metrics.bytes_by_type().len(), 1);
+    }
+
+    #[test]
+    fn trigger_config_is_valid_by_default() {
+        let mut runner = test_runner();
+
+        match runner.handle_command(GamepackCommand::ValidateTriggerConfig {
+            request_id: "t".into(),
+            triggers: vec![crate::TriggerConfig::new("anything").with_window(-5.0, 0.0)],
+        }) {
+            GamepackResponse::TriggerConfigValidation { request_id, issues } => {
+                assert_eq!(request_id, "t");
+                assert!(issues.is_empty());
+            }
+            other => panic!("Expected TriggerConfigValidation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn connection_id_is_shared_by_handshake_and_emits() {
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        let emitter = emitter.with_connection_tags(true);
+        let mut runner = test_runner();
+        runner.emitter = emitter.clone();
+        runner.handler.emitter = Some(emitter);
+        let input = concat!(
+            r#"{"type":"init","request_id":"i"}"#,
+            "\n",
+            r#"{"type":"poll_events","request_id":"p1"}"#,
+            "\n",
+            r#"{"type":"poll_events","request_id":"p2"}"#,
+            "\n",
+        );
+
+        let (_, lines) = run_lines(&mut runner, input);
+
+        let handshake = match serde_json::from_str(&lines[0]).unwrap() {
+            GamepackResponse::Initialized { connection_id, .. } => connection_id.unwrap(),
+            other => panic!("Expected Initialized, got {:?}", other),
+        };
+        assert_eq!(handshake.len(), 36);
+        assert_eq!(&handshake[14..15], "4");
+        let emitted: Vec<Option<String>> = sink
+            .responses()
+            .into_iter()
+            .map(|response| match response {
+                GamepackResponse::WriteMatchData { connection_id, .. } => connection_id,
+                other => panic!("Expected WriteMatchData, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(emitted, vec![Some(handshake.clone()); 2]);
+
+        // Another run gets another id
+        assert_ne!(test_runner().connection_id, handshake);
+    }
+
+    #[test]
+    fn unserializable_response_is_reported_and_answered_with_error() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let record = Arc::clone(&reported);
+        let mut runner = Runner::new(
+            TestHandler {
+                nan_events: true,
+                ..TestHandler::default()
+            },
+            RunnerOptions::default(),
+        );
+        runner.emitter = crate::testing::capturing_emitter()
+            .0
+            .on_serialize_error(move |e| record.lock().unwrap().push(e.to_string()));
+
+        let (_, lines) = run_lines(&mut runner, r#"{"type":"poll_events","request_id":"p1"}"#);
+
+        assert_eq!(lines.len(), 1);
+        match serde_json::from_str(&lines[0]).unwrap() {
+            GamepackResponse::Error { request_id, code, .. } => {
+                assert_eq!(request_id, "p1");
+                assert_eq!(code.as_deref(), Some("serialize_error"));
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 1);
+        assert!(reported[0].contains("timestamp_secs is NaN"), "{}", reported[0]);
+    }
+
+    #[test]
+    fn set_active_subpack_calls_hook_and_sets_emit_default() {
+        let mut runner = test_runner();
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        runner.emitter = emitter.clone();
+        assert_eq!(emitter.active_subpack(), 0);
+
+        let response = runner.handle_command(GamepackCommand::SetActiveSubpack {
+            request_id: "s1".to_string(),
+            subpack: 2,
+        });
+
+        assert!(matches!(response, GamepackResponse::Ack { request_id } if request_id == "s1"));
+        assert_eq!(runner.handler.active_subpack, Some(2));
+        assert_eq!(emitter.active_subpack(), 2);
+
+        // The active subpack is a default; an explicit one is sent as given
+        emitter.emit_moments(emitter.active_subpack(), "m1", vec![]).unwrap();
+        emitter.emit_moments(0, "m1", vec![]).unwrap();
+        let subpacks: Vec<u8> = sink.messages().iter().map(MatchDataMessage::subpack).collect();
+        assert_eq!(subpacks, vec![2, 0]);
+    }
+
+    #[test]
+    fn muted_match_emits_are_dropped_until_unmuted() {
+        let mut runner = test_runner();
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        runner.emitter = emitter.clone();
+
+        let response = runner.handle_command(GamepackCommand::MuteMatch {
+            request_id: "m".to_string(),
+            subpack: 0,
+            external_match_id: "m1".to_string(),
+        });
+        assert!(matches!(response, GamepackResponse::Ack { request_id } if request_id == "m"));
+        assert!(emitter.is_muted(0, "m1"));
+
+        emitter.emit_moments(0, "m1", vec![]).unwrap();
+        emitter.emit_moments(1, "m1", vec![]).unwrap();
+        emitter.emit_moments(0, "m2", vec![]).unwrap();
+        assert_eq!(sink.messages().len(), 2);
+
+        runner.handle_command(GamepackCommand::UnmuteMatch {
+            request_id: "u".to_string(),
+            subpack: 0,
+            external_match_id: "m1".to_string(),
+        });
+        emitter.emit_moments(0, "m1", vec![]).unwrap();
+
+        let written: Vec<String> = sink
+            .messages()
+            .iter()
+            .map(|m| format!("{}/{}", m.subpack(), m.external_match_id()))
+            .collect();
+        assert_eq!(written, vec!["1/m1", "0/m2", "0/m1"]);
+        assert_eq!(emitter.stats().muted, 1);
+        assert_eq!(
+            runner.handler.mutes,
+            vec![(0, "m1".to_string(), true), (0, "m1".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn loop_runs_over_any_transport() {
+        let mut runner = test_runner();
+        runner.emitter = crate::testing::capturing_emitter().0;
+        let mut transport = crate::testing::VecTransport::new([
+            r#"{"type":"poll_events","request_id":"p1"}"#,
+            "",
+            r#"{"type":"shutdown","request_id":"s1"}"#,
+            r#"{"type":"poll_events","request_id":"never"}"#,
+        ]);
+
+        let outcome = runner.run(&mut transport);
+
+        assert_eq!(outcome, RunOutcome::Completed);
+        let ids: Vec<String> = transport
+            .responses()
+            .iter()
+            .map(|response| response.request_id().to_string())
+            .collect();
+        assert_eq!(ids, vec!["p1", "s1"]);
+    }
+
+    /// Transport whose only message is malformed, with no `FrameError`.
+    struct Garbled(bool);
+
+    impl Transport for Garbled {
+        fn read_message(&mut self) -> io::Result<Option<String>> {
+            if std::mem::replace(&mut self.0, true) {
+                return Ok(None);
+            }
+            Err(io::Error::new(io::ErrorKind::InvalidData, "bad frame"))
+        }
+
+        fn write_message(&mut self, message: &str) -> io::Result<()> {
+            assert!(message.contains("Invalid message: bad frame"), "{}", message);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn invalid_data_from_transport_is_answered_and_skipped() {
+        let mut runner = test_runner();
+        runner.emitter = crate::testing::capturing_emitter().0;
+
+        assert_eq!(runner.run(&mut Garbled(false)), RunOutcome::StdinClosed);
+    }
+
+    #[test]
+    fn game_version_defaults_to_unknown_and_is_reported_at_init() {
+        let mut runner = test_runner();
+
+        let response = runner.handle_command(GamepackCommand::GetGameVersion {
+            request_id: "v1".to_string(),
+        });
+        assert!(matches!(
+            response,
+            GamepackResponse::GameVersion { request_id, version: None } if request_id == "v1"
+        ));
+
+        match runner.handle_command(GamepackCommand::Init {
+            request_id: "i1".to_string(),
+            protocol_version: None,
+            daemon_features: vec![],
+            force: None,
+        }) {
+            GamepackResponse::Initialized { game_version, .. } => {
+                assert_eq!(game_version.as_deref(), Some("14.3"));
+            }
+            other => panic!("Expected Initialized, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn negotiated_session_end_chunks_split_large_match_data() {
+        let mut runner = Runner::new(
+            TestHandler::default(),
+            RunnerOptions {
+                session_end_chunk_bytes: Some(16),
+                ..RunnerOptions::default()
+            },
+        );
+        init_with_features(&mut runner, &["session_end_chunks"]);
+
+        let (_, lines) = run_lines(
+            &mut runner,
+            "{\"type\":\"session_end\",\"request_id\":\"s1\",\"context\":{}}\n",
+        );
+
+        let chunks: Vec<GamepackResponse> =
+            lines.iter().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert!(chunks.len() > 1);
+        let data = GamepackResponse::reassemble_session_end(&chunks).unwrap();
+        let expected = MatchData::new("test", 99, "win", serde_json::json!({}));
+        assert_eq!(data, serde_json::to_value(expected).unwrap());
+    }
+
+    #[test]
+    fn session_end_is_sent_whole_without_chunk_negotiation() {
+        let mut runner = Runner::new(
+            TestHandler::default(),
+            RunnerOptions {
+                session_end_chunk_bytes: Some(16),
+                ..RunnerOptions::default()
+            },
+        );
+        init_with_features(&mut runner, &[]);
+
+        let (_, lines) = run_lines(
+            &mut runner,
+            "{\"type\":\"session_end\",\"request_id\":\"s1\",\"context\":{}}\n",
+        );
+
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("\"type\":\"session_ended\""));
+    }
+
+    fn init_capabilities(runner: &mut Runner<TestHandler>) -> Vec<Capability> {
+        match runner.handle_command(GamepackCommand::Init {
+            request_id: "init".to_string(),
+            protocol_version: None,
+            daemon_features: vec![],
+            force: None,
+        }) {
+            GamepackResponse::Initialized { capabilities, .. } => capabilities,
+            other => panic!("Expected Initialized response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn init_reports_default_capabilities() {
+        let mut runner = test_runner();
+
+        assert_eq!(init_capabilities(&mut runner), Capability::ALWAYS.to_vec());
+    }
+
+    #[test]
+    fn declared_capabilities_propagate_to_initialized() {
+        let mut runner = Runner::new(
+            TestHandler {
+                recent: Some(crate::MatchSummaryBuffer::new(4)),
+                ..TestHandler::default()
+            },
+            RunnerOptions::default(),
+        );
+
+        let capabilities = init_capabilities(&mut runner);
+
+        let mut expected = Capability::ALWAYS.to_vec();
+        expected.push(Capability::RecentMatches);
+        assert_eq!(capabilities, expected);
+        let response = runner.handle_command(GamepackCommand::Init {
+            request_id: "init".to_string(),
+            protocol_version: None,
+            daemon_features: vec![],
+            force: None,
+        });
+        assert!(response.to_line().unwrap().contains(
+            r#""capabilities":["status","events","live_data","sessions","recent_matches"]"#
+        ));
+    }
+
+    fn init_command(request_id: &str, force: Option<bool>) -> GamepackCommand {
+        GamepackCommand::Init {
+            request_id: request_id.to_string(),
+            protocol_version: None,
+            daemon_features: vec![],
+            force,
+        }
+    }
+
+    #[test]
+    fn repeated_init_is_answered_from_cache() {
+        let mut runner = test_runner();
+
+        let first = runner.handle_command(init_command("i1", None));
+        let second = runner.handle_command(init_command("i2", None));
+
+        assert_eq!(runner.handler.inits, 1);
+        match (first, second) {
+            (
+                GamepackResponse::Initialized { request_id: a, game_id: x, .. },
+                GamepackResponse::Initialized { request_id: b, game_id: y, .. },
+            ) => {
+                assert_eq!((a.as_str(), b.as_str()), ("i1", "i2"));
+                assert_eq!(x, y);
+            }
+            other => panic!("Expected two Initialized responses, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn forced_or_retried_init_calls_handler_again() {
+        let mut runner = test_runner();
+        runner.handler.fail_init = true;
+        assert!(matches!(
+            runner.handle_command(init_command("i1", None)),
+            GamepackResponse::Error { .. }
+        ));
+
+        // A failed init is not cached
+        runner.handler.fail_init = false;
+        runner.handle_command(init_command("i2", None));
+        runner.handle_command(init_command("i3", Some(false)));
+        assert_eq!(runner.handler.inits, 2);
+
+        runner.handle_command(init_command("i4", Some(true)));
+        assert_eq!(runner.handler.inits, 3);
+    }
+
+    #[test]
+    fn warnings_attach_to_poll_responses() {
+        let mut runner = Runner::new(
+            TestHandler {
+                warning: Some("stats API timed out".into()),
+                ..Default::default()
+            },
+            RunnerOptions::default(),
+        );
+
+        let response = runner.handle_command(GamepackCommand::PollEvents {
+            request_id: "p1".into(),
+        });
+
+        assert!(matches!(
+            &response,
+            GamepackResponse::Events { events, warnings, .. }
+                if events.len() == 1 && warnings == &["stats API timed out"]
+        ));
+        assert!(runner.warnings.is_empty());
+
+        // Warnings from one command do not leak into the next
+        runner.handler.warning = None;
+        let response = runner.handle_command(GamepackCommand::PollEvents {
+            request_id: "p2".into(),
+        });
+        assert!(matches!(
+            response,
+            GamepackResponse::Events { warnings, .. } if warnings.is_empty()
+        ));
+    }
+
+    #[test]
+    fn warnings_for_other_commands_precede_the_response() {
+        let mut runner = Runner::new(
+            TestHandler {
+                warning: Some("clock skew detected".into()),
+                ..Default::default()
+            },
+            RunnerOptions::default(),
+        );
+
+        let (_, lines) = run_lines(
+            &mut runner,
+            "{\"type\":\"session_start\",\"request_id\":\"s1\"}\n",
+        );
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            r#"{"type":"warning","request_id":"s1","message":"clock skew detected","code":null}"#
+        );
+        assert!(lines[1].contains("\"type\":\"session_started\""));
+    }
+
+    #[test]
+    fn polled_events_are_sorted_unless_disabled() {
+        let event = |name: &str, at: f64| GameEvent::new(name, at, serde_json::json!({}));
+        let handler = || TestHandler {
+            events: Some(vec![
+                event("Baron", 30.0),
+                event("Kill", 10.0),
+                event("Assist", 30.0),
+                event("FirstBlood", 5.0),
+            ]),
+            ..Default::default()
+        };
+        let poll = |runner: &mut Runner<TestHandler>| {
+            let cmd = GamepackCommand::PollEvents {
+                request_id: "p".into(),
+            };
+            match runner.handle_command(cmd) {
+                GamepackResponse::Events { events, .. } => {
+                    events.into_iter().map(|e| e.event_type).collect::<Vec<_>>()
+                }
+                other => panic!("Expected Events, got {:?}", other),
+            }
+        };
+
+        let mut runner = Runner::new(handler(), RunnerOptions::default());
+        // Ties keep handler order: Baron was returned before Assist
+        assert_eq!(poll(&mut runner), ["FirstBlood", "Kill", "Baron", "Assist"]);
+
+        let options = RunnerOptions {
+            sort_events: false,
+            ..Default::default()
+        };
+        let mut runner = Runner::new(handler(), options);
+        assert_eq!(poll(&mut runner), ["Baron", "Kill", "Assist", "FirstBlood"]);
+    }
+
+    #[test]
+    fn run_recovery_writes_recovered_messages_then_completes() {
+        let mut runner = test_runner();
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        runner.emitter = emitter;
+        runner.handler.stale = vec!["m1".to_string(), "m2".to_string()];
+        let recover = |runner: &mut Runner<TestHandler>| {
+            runner.handle_command(GamepackCommand::RunRecovery {
+                request_id: "rr".to_string(),
+            })
+        };
+
+        let response = recover(&mut runner);
+
+        assert!(matches!(
+            response,
+            GamepackResponse::RecoveryComplete { request_id, recovered: 4 } if request_id == "rr"
+        ));
+        let completed: Vec<String> = sink
+            .messages()
+            .iter()
+            .filter(|m| matches!(m, MatchDataMessage::SetComplete { .. }))
+            .map(|m| m.external_match_id().to_string())
+            .collect();
+        assert_eq!(completed, vec!["m1", "m2"]);
+
+        // Nothing left to recover
+        assert!(matches!(
+            recover(&mut runner),
+            GamepackResponse::RecoveryComplete { recovered: 0, .. }
+        ));
+        assert_eq!(sink.messages().len(), 4);
+    }
+
+    #[test]
+    fn cleared_live_data_is_flagged_and_resets_patch_base() {
+        let mut runner = test_runner();
+        // The patch base lives on the emitter: keep it from other tests
+        runner.emitter = crate::testing::capturing_emitter().0;
+        init_with_features(&mut runner, &["live_data_patch"]);
+        live_data(&mut runner);
+
+        runner.handler.live_cleared = true;
+        match live_data(&mut runner) {
+            GamepackResponse::LiveData { data, cleared, .. } => {
+                assert_eq!((data, cleared), (None, true));
+            }
+            other => panic!("Expected cleared LiveData, got {:?}", other),
+        }
+
+        // The next data after a clear is sent whole
+        runner.handler.live_cleared = false;
+        assert!(matches!(
+            live_data(&mut runner),
+            GamepackResponse::LiveData { data: Some(_), cleared: false, .. }
+        ));
+    }
+
+    #[test]
+    fn build_info_defaults_to_runtime_build_with_init_pack_version() {
+        let mut runner = test_runner();
+        init_with_features(&mut runner, &[]);
+
+        let response = runner.handle_command(GamepackCommand::GetBuildInfo {
+            request_id: "b1".to_string(),
+        });
+        let GamepackResponse::BuildInfo {
+            request_id,
+            pack_version,
+            target_triple,
+            profile,
+            rustc_version,
+            ..
+        } = &response
+        else {
+            panic!("Expected BuildInfo, got {:?}", response);
+        };
+        assert_eq!(request_id, "b1");
+        assert_eq!(pack_version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+        assert_eq!(target_triple, env!("GAMEPACK_BUILD_TARGET"));
+        assert!(!profile.is_empty());
+        assert!(rustc_version.starts_with("rustc "), "{}", rustc_version);
+
+        let json: serde_json::Value = serde_json::from_str(&response.to_line().unwrap()).unwrap();
+        assert_eq!(json["type"], "build_info");
+        for field in ["pack_version", "target_triple", "profile", "features", "rustc_version"] {
+            assert!(json.get(field).is_some(), "missing {}", field);
+        }
+    }
+
+    /// Transport whose reader never runs dry but whose writer is closed.
+    struct HalfOpen {
+        reads: usize,
+    }
+
+    impl Transport for HalfOpen {
+        fn read_message(&mut self) -> io::Result<Option<String>> {
+            self.reads += 1;
+            assert!(self.reads < 100, "kept reading after the pipe broke");
+            Ok(Some(r#"{"type":"poll_events","request_id":"p"}"#.to_string()))
+        }
+
+        fn write_message(&mut self, _message: &str) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "reader gone"))
+        }
+    }
+
+    #[test]
+    fn broken_stdout_stops_the_loop_and_shuts_down() {
+        let mut runner = test_runner();
+        runner.emitter = crate::testing::capturing_emitter().0;
+        let mut transport = HalfOpen { reads: 0 };
+
+        assert_eq!(runner.run(&mut transport), RunOutcome::PipeBroken);
+        assert_eq!(transport.reads, 1);
+        assert_eq!(runner.handler.shutdowns, 1);
+        assert!(runner.emitter.is_closed());
+    }
+
+    #[test]
+    fn code_schema_is_reported_and_drives_default_sample_data() {
+        let mut runner = test_runner();
+
+        let schema = match runner.handle_command(GamepackCommand::GetSchema {
+            request_id: "s1".into(),
+            subpack: 1,
+        }) {
+            GamepackResponse::Schema {
+                subpack: 1,
+                schema: Some(schema),
+                ..
+            } => schema,
+            other => panic!("Expected Schema, got {:?}", other),
+        };
+        assert_eq!(schema.fields.len(), 2);
+
+        match runner.handle_command(GamepackCommand::GetSampleMatchData {
+            request_id: "d1".into(),
+            subpack: 1,
+        }) {
+            GamepackResponse::SampleMatchData { data, .. } => schema.validate(&data).unwrap(),
+            other => panic!("Expected SampleMatchData, got {:?}", other),
+        }
+
+        // Without a code schema: no schema, and no default sample data
+        assert!(matches!(
+            runner.handle_command(GamepackCommand::GetSchema {
+                request_id: "s0".into(),
+                subpack: 0,
+            }),
+            GamepackResponse::Schema { schema: None, .. }
+        ));
+        assert!(matches!(
+            runner.handle_command(GamepackCommand::GetSampleMatchData {
+                request_id: "d0".into(),
+                subpack: 0,
+            }),
+            GamepackResponse::Error { .. }
+        ));
+    }
+
+    /// Transport serving fixed lines and recording when each reply is written.
+    struct Timed {
+        lines: VecDeque<String>,
+        written: Vec<(std::time::Instant, String)>,
+    }
+
+    impl Transport for Timed {
+        fn read_message(&mut self) -> io::Result<Option<String>> {
+            Ok(self.lines.pop_front())
+        }
+
+        fn write_message(&mut self, message: &str) -> io::Result<()> {
+            self.written.push((std::time::Instant::now(), message.to_string()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn background_session_end_does_not_block_other_commands() {
+        let delay = std::time::Duration::from_millis(300);
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        let mut runner = test_runner();
+        runner.emitter = emitter;
+        runner.handler.slow_session_end = Some(delay);
+        let mut transport = Timed {
+            lines: [
+                r#"{"type":"session_end","request_id":"e1","context":{}}"#,
+                r#"{"type":"get_status","request_id":"s1"}"#,
+            ]
+            .map(String::from)
+            .into(),
+            written: Vec::new(),
+        };
+
+        let started = std::time::Instant::now();
+        assert_eq!(runner.run(&mut transport), RunOutcome::StdinClosed);
+
+        // Status was answered on the main loop while the session end ran...
+        let [(answered, status)] = &transport.written[..] else {
+            panic!("Expected only the status reply, got {:?}", transport.written);
+        };
+        assert!(status.contains(r#""request_id":"s1""#), "{}", status);
+        assert!(answered.duration_since(started) < delay);
+        // ...and the session end was answered through the emitter, waited
+        // for before the loop returned
+        assert!(started.elapsed() >= delay);
+        let responses = sink.responses();
+        let [GamepackResponse::SessionEnded {
+            request_id,
+            match_data: Some(_),
+        }] = &responses[..]
+        else {
+            panic!("Expected one SessionEnded, got {:?}", responses);
+        };
+        assert_eq!(request_id, "e1");
+    }
+
+    #[test]
+    fn background_session_end_is_not_rate_limited() {
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        let mut runner = test_runner();
+        runner.emitter = emitter;
+        runner.options.max_emit_rate = Some(1.0);
+        runner.handler.slow_session_end = Some(std::time::Duration::from_millis(10));
+        runner.handler.progress = vec![0.25, 0.5, 0.75];
+
+        let started = std::time::Instant::now();
+        run_lines(
+            &mut runner,
+            "{\"type\":\"session_end\",\"request_id\":\"e1\",\"context\":{}}\n",
+        );
+
+        // Four answers at one per second would take three seconds
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+        let responses = sink.responses();
+        assert_eq!(responses.len(), 4);
+        assert!(responses.iter().all(|r| r.request_id() == "e1"));
+        assert_eq!(runner.emitter.stats().rate_limited, 0);
+    }
+
+    #[test]
+    fn session_end_progress_precedes_session_ended() {
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        let mut runner = test_runner();
+        runner.emitter = emitter;
+        runner.handler.progress = vec![0.25, f32::NAN, 1.5];
+        let end = |id: &str| GamepackCommand::SessionEnd {
+            request_id: id.into(),
+            context: serde_json::json!({}),
+        };
+
+        let response = runner.handle_command(end("e1"));
+        assert!(matches!(response, GamepackResponse::SessionEnded { .. }));
+        let progress: Vec<_> = sink
+            .responses()
+            .into_iter()
+            .map(|response| match response {
+                GamepackResponse::SessionEndProgress {
+                    request_id,
+                    progress,
+                    message: None,
+                } => (request_id, progress),
+                other => panic!("Expected SessionEndProgress, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(progress, [("e1".into(), 0.25), ("e1".into(), 0.0), ("e1".into(), 1.0)]);
+
+        // Background session ends report through the emitter too, in order
+        runner.handler.slow_session_end = Some(std::time::Duration::ZERO);
+        runner.handler.progress = vec![0.5];
+        runner.handle_command(end("e2"));
+        runner.join_session_tasks();
+        let kinds: Vec<String> = sink.responses()[3..].iter().map(|r| r.describe()).collect();
+        assert_eq!(
+            kinds,
+            ["SessionEndProgress[id=e2, 50% fetching]", "SessionEnded[id=e2, match data]"]
+        );
+
+        // Outside a session end there is nothing to report on
+        session_end_progress(0.5, None);
+        assert_eq!(sink.responses().len(), 5);
+    }
+
+    #[test]
+    fn emit_test_moment_writes_a_synthetic_moment() {
+        let (emitter, sink) = crate::testing::capturing_emitter();
+        let mut runner = test_runner();
+        runner.emitter = emitter;
+        let test_moment = |runner: &mut Runner<TestHandler>| {
+            runner.handle_command(GamepackCommand::EmitTestMoment {
+                request_id: "t".into(),
+                subpack: 1,
+                moment_id: "pentakill".into(),
+            })
+        };
+
+        assert!(matches!(test_moment(&mut runner), GamepackResponse::Ack { .. }));
+        runner.handler.current_match = "live-7".to_string();
+        test_moment(&mut runner);
+
+        let written: Vec<_> = sink
+            .messages()
+            .into_iter()
+            .map(|message| match message {
+                MatchDataMessage::WriteMoments {
+                    subpack,
+                    external_match_id,
+                    moments,
+                    ..
+                } => (subpack, external_match_id, moments),
+                other => panic!("Expected WriteMoments, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(written.len(), 2);
+        assert_eq!((written[0].0, written[0].1.as_str()), (1, TEST_MOMENT_MATCH_ID));
+        assert_eq!(written[1].1, "live-7");
+        let [first] = &written[0].2[..] else { panic!("Expected one moment") };
+        assert_eq!((first.moment_id.as_str(), first.game_time_secs), ("pentakill", 0.0));
+        assert_eq!(first.data, serde_json::json!({"test": true}));
+        assert_eq!(written[1].2[0].game_time_secs, 42.0);
+    }
+
+    #[test]
+    fn should_record_returns_the_handlers_decision() {
+        let mut runner = test_runner();
+        let mut ask = |moment_id: &str, data: serde_json::Value| {
+            let response = runner.handle_command(GamepackCommand::ShouldRecord {
+                request_id: "r".into(),
+                moment_id: moment_id.into(),
+                data,
+            });
+            serde_json::to_value(response).unwrap()
+        };
+
+        assert_eq!(
+            ask("pentakill", serde_json::json!({})),
+            serde_json::json!({"type": "record_decision", "request_id": "r", "record": true})
+        );
+        let skipped = ask("pentakill", serde_json::json!({"spectating": true}));
+        assert_eq!((&skipped["record"], &skipped["reason"]), (&false.into(), &"spectating".into()));
+        assert_eq!(
+            ask("comeback", serde_json::json!({}))["window"],
+            serde_json::json!({"pre_secs": 30.0, "post_secs": 10.0})
+        );
+    }
+
+    #[test]
+    fn get_trigger_rules_describes_the_handlers_rules() {
+        let mut runner = test_runner();
+        let mut rules = |subpack: u8| {
+            let response = runner.handle_command(GamepackCommand::GetTriggerRules {
+                request_id: "r".into(),
+                subpack,
+            });
+            serde_json::to_value(response).unwrap()
+        };
+
+        assert_eq!(
+            rules(0),
+            serde_json::json!({
+                "type": "trigger_rules",
+                "request_id": "r",
+                "subpack": 0,
+                "rules": [
+                    {
+                        "from_event": "ChampionKill",
+                        "produces_moment": "pentakill",
+                        "condition": "5 kills within 10 seconds",
+                    },
+                    {"from_event": "DragonKill", "produces_moment": "dragon"},
+                ],
+            })
+        );
+        assert_eq!(rules(3)["rules"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn get_active_matches_combines_handler_and_emitter_views() {
+        let mut runner = test_runner();
+        let (emitter, _sink) = crate::testing::capturing_emitter();
+        runner.emitter = emitter.with_match_switch_detection(true);
+        let active = |runner: &mut Runner<TestHandler>| {
+            match runner.handle_command(GamepackCommand::GetActiveMatches {
+                request_id: "a".into(),
+            }) {
+                GamepackResponse::ActiveMatches { request_id, matches } => {
+                    assert_eq!(request_id, "a");
+                    matches
+                }
+                other => panic!("Expected ActiveMatches, got {:?}", other),
+            }
+        };
+        let pair = |subpack: u8, id: &str| (subpack, id.to_string());
+
+        assert!(active(&mut runner).is_empty());
+
+        runner.emitter.emit_statistics(1, "m2", 1.0, HashMap::new()).unwrap();
+        runner.emitter.emit_statistics(0, "m1", 1.0, HashMap::new()).unwrap();
+        runner.handler.current_match = "m1".to_string();
+        assert_eq!(active(&mut runner), vec![pair(0, "m1"), pair(1, "m2")]);
+
+        let complete = MatchDataMessage::set_complete(1, "m2", SummarySource::LiveFallback);
+        runner.emitter.emit_match_data(complete).unwrap();
+        runner.handler.current_match.clear();
+        assert_eq!(active(&mut runner), vec![pair(0, "m1")]);
+    }
+
+    /// Output that counts how often it is flushed.
+    #[derive(Default)]
+    struct CountingOutput {
+        bytes: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl io::Write for CountingOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn coalesced_writes_answer_a_burst_with_fewer_flushes() {
+        let input = concat!(
+            r#"{"type":"poll_events","request_id":"p1"}"#,
+            "\n",
+            r#"{"type":"get_status","request_id":"p2"}"#,
+            "\nnot json\n",
+            r#"{"type":"poll_events","request_id":"p3"}"#,
+            "\n",
+            r#"{"type":"get_status","request_id":"p4"}"#,
+            "\n",
+        );
+        let run = |coalesce: bool| {
+            let mut runner = test_runner();
+            runner.emitter = crate::testing::capturing_emitter().0;
+            let mut output = CountingOutput::default();
+            let mut transport = StreamTransport::new(input.as_bytes(), &mut output);
+            if coalesce {
+                transport = transport.with_coalesced_writes(64 * 1024);
+            }
+            assert_eq!(runner.run(&mut transport), RunOutcome::StdinClosed);
+            let ids: Vec<String> = String::from_utf8(output.bytes)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<GamepackResponse>(line).unwrap())
+                .map(|response| response.request_id().to_string())
+                .collect();
+            (ids, output.flushes)
+        };
+
+        let (plain_ids, plain_flushes) = run(false);
+        let (ids, flushes) = run(true);
+
+        assert_eq!(plain_ids, vec!["p1", "p2", "", "p3", "p4"]);
+        assert_eq!(ids, plain_ids);
+        assert_eq!(plain_flushes, 5);
+        // Once for the parse error, once when the input ran dry
+        assert_eq!(flushes, 2);
+    }
+
+    #[test]
+    fn results_over_the_soft_limit_are_capped_with_a_warning() {
+        let event = |at: f64| GameEvent::new("Kill", at, serde_json::json!({}));
+        let handler = || TestHandler {
+            events: Some(vec![event(3.0), event(1.0), event(2.0)]),
+            ..Default::default()
+        };
+        let poll = |runner: &mut Runner<TestHandler>| {
+            runner.handle_command(GamepackCommand::PollEvents {
+                request_id: "p".into(),
+            })
+        };
+
+        let at_limit = RunnerOptions {
+            soft_result_limit: Some(3),
+            ..Default::default()
+        };
+        let response = poll(&mut Runner::new(handler(), at_limit));
+        assert!(matches!(
+            response,
+            GamepackResponse::Events { events, warnings, .. }
+                if events.len() == 3 && warnings.is_empty()
+        ));
+
+        let over_limit = RunnerOptions {
+            soft_result_limit: Some(2),
+            ..Default::default()
+        };
+        match poll(&mut Runner::new(handler(), over_limit)) {
+            GamepackResponse::Events {
+                events, warnings, ..
+            } => {
+                let times: Vec<_> = events.iter().map(|e| e.timestamp_secs).collect();
+                assert_eq!(times, [1.0, 2.0]);
+                assert_eq!(
+                    warnings,
+                    ["poll_events returned 3 events, over the soft limit of 2; \
+                      sending the first 2"]
+                );
+            }
+            other => panic!("Expected Events, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn time_sync_reports_monotonic_pack_timestamps() {
+        let mut runner = test_runner();
+        let sync = |runner: &mut Runner<TestHandler>, id: &str| {
+            match runner.handle_command(GamepackCommand::TimeSync {
+                request_id: id.into(),
+                daemon_send_ns: 1_000,
+            }) {
+                GamepackResponse::TimeSyncResult {
+                    request_id,
+                    daemon_send_ns,
+                    pack_recv_ns,
+                    pack_send_ns,
+                } => {
+                    assert_eq!(request_id, id);
+                    assert_eq!(daemon_send_ns, 1_000);
+                    assert!(pack_recv_ns > 0 && pack_recv_ns <= pack_send_ns);
+                    (pack_recv_ns, pack_send_ns)
+                }
+                other => panic!("Expected TimeSyncResult, got {:?}", other),
+            }
+        };
+
+        let (_, first_send) = sync(&mut runner, "t1");
+        let (second_recv, _) = sync(&mut runner, "t2");
+        assert!(second_recv >= first_send);
+
+        let line = serde_json::to_string(&GamepackCommand::TimeSync {
+            request_id: "t3".into(),
+            daemon_send_ns: 5,
+        })
+        .unwrap();
+        assert_eq!(line, r#"{"type":"time_sync","request_id":"t3","daemon_send_ns":5}"#);
+    }
+
+    /// Writer a test can read while a connection owns it.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedOutput {
+        fn responses(&self) -> Vec<GamepackResponse> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn each_connection_gets_its_own_answers_and_every_emit() {
+        let input = |lines: &[&str]| io::Cursor::new(lines.join("\n").into_bytes());
+        let (first, second) = (SharedOutput::default(), SharedOutput::default());
+        let connections = vec![
+            StreamTransport::new(
+                input(&[
+                    r#"{"type":"init","request_id":"a1"}"#,
+                    r#"{"type":"get_status","request_id":"a2"}"#,
+                ]),
+                first.clone(),
+            ),
+            StreamTransport::new(
+                input(&[
+                    r#"{"type":"init","request_id":"b1","daemon_features":["live_data_patch"]}"#,
+                    r#"{"type":"session_end","request_id":"b2","context":{}}"#,
+                ]),
+                second.clone(),
+            ),
+        ];
+        let (mut transport, sink) = multiplex(connections);
+        let mut runner = test_runner();
+        runner.emitter = Emitter::new(sink);
+        runner.multiplexed = true;
+        runner.handler.slow_session_end = Some(std::time::Duration::from_millis(20));
+
+        runner.emitter.emit_statistics(0, "m1", 1.0, HashMap::new()).unwrap();
+        assert_eq!(runner.run(&mut transport), RunOutcome::StdinClosed);
+
+        let answered = |output: &SharedOutput| {
+            let mut ids: Vec<String> = output
+                .responses()
+                .iter()
+                .map(|response| response.request_id().to_string())
+                .filter(|id| !id.is_empty())
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(answered(&first), vec!["a1", "a2"]);
+        // Including the session end answered in the background
+        assert_eq!(answered(&second), vec!["b1", "b2"]);
+        for output in [&first, &second] {
+            let responses = output.responses();
+            assert!(matches!(responses[0], GamepackResponse::WriteMatchData { .. }));
+            for response in &responses {
+                if let GamepackResponse::Initialized { features, .. } = response {
+                    assert!(features.is_empty(), "{:?}", features);
+                }
+            }
+        }
+        assert_eq!(runner.handler.inits, 1);
+    }
+
+    #[test]
+    fn connections_with_the_same_ids_are_kept_apart() {
+        let commands = [
+            r#"{"type":"init","request_id":"1"}"#,
+            r#"{"type":"session_end","request_id":"2","context":{}}"#,
+        ];
+        let outputs = [SharedOutput::default(), SharedOutput::default()];
+        let connections = outputs
+            .iter()
+            .map(|output| {
+                let input = io::Cursor::new(commands.join("\n").into_bytes());
+                StreamTransport::new(input, output.clone())
+            })
+            .collect();
+        let (mut transport, sink) = multiplex(connections);
+        let mut runner = Runner::new(
+            TestHandler::default(),
+            RunnerOptions {
+                reject_duplicate_request_ids: true,
+                ..RunnerOptions::default()
+            },
+        );
+        runner.emitter = Emitter::new(sink);
+        runner.multiplexed = true;
+        runner.handler.slow_session_end = Some(std::time::Duration::from_millis(20));
+
+        assert_eq!(runner.run(&mut transport), RunOutcome::StdinClosed);
+
+        // Each daemon gets exactly its own two answers, none rejected
+        for output in &outputs {
+            let mut answers: Vec<(&'static str, String)> = output
+                .responses()
+                .iter()
+                .map(|response| (response.into(), response.request_id().to_string()))
+                .collect();
+            answers.sort();
+            assert_eq!(
+                answers,
+                [("Initialized", "1".to_string()), ("SessionEnded", "2".to_string())]
+            );
+        }
+    }
+
+    #[test]
+    fn line_limit_applies_to_each_connection() {
+        let command = format!(r#"{{"type":"get_status","request_id":"{}"}}"#, "x".repeat(64));
+        let outputs = [SharedOutput::default(), SharedOutput::default()];
+        let connections = outputs
+            .iter()
+            .map(|output| {
+                let input = io::Cursor::new(format!("{}\n", command).into_bytes());
+                StreamTransport::new(input, output.clone())
+            })
+            .collect();
+        let options = RunnerOptions {
+            max_line_bytes: Some(64),
+            ..RunnerOptions::default()
+        };
+        let (mut transport, sink) = multiplex_with_options(connections, &options);
+        let mut runner = Runner::new(TestHandler::default(), options);
+        runner.emitter = Emitter::new(sink);
+        runner.multiplexed = true;
+
+        assert_eq!(runner.run(&mut transport), RunOutcome::StdinClosed);
+        for output in &outputs {
+            let responses = output.responses();
+            let [GamepackResponse::Error { code: Some(code), .. }] = &responses[..] else {
+                panic!("Expected one Error, got {:?}", responses);
+            };
+            assert_eq!(code, "line_too_long");
+        }
+    }
+
+    #[test]
+    fn connections_are_refused_once_a_global_emitter_exists() {
+        Emitter::global();
+        let output = SharedOutput::default();
+        let input = io::Cursor::new(br#"{"type":"init","request_id":"a1"}"#.to_vec());
+        let connections = vec![StreamTransport::new(input, output.clone())];
+
+        let handler = TestHandler::default();
+        let result = run_gamepack_with_connections(handler, RunnerOptions::default(), connections);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert!(output.responses().is_empty());
     }
 }
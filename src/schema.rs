@@ -0,0 +1,494 @@
+//! Declarative description of a pack's data shape.
+//!
+//! A [`LiveDataSchema`] lists the top-level fields of a JSON object (live
+//! data, or a subpack's match card data) and what kind of value each one
+//! holds. It mirrors the column declarations in the pack's `config.json` and
+//! is what [`SampleGenerator`](crate::SampleGenerator) builds previews from.
+//!
+//! A [`SettingsSchema`] does the same for the user-editable settings a pack
+//! accepts via `UpdateSettings`, so updates can be checked before they are
+//! applied.
+//!
+//! A field can also carry a [`ValueFormat`] telling the daemon how to display
+//! it. [`format_value`] implements the formats, so pack-side previews and the
+//! daemon render the same text.
+//!
+//! A pack can also declare a subpack's schema in code through
+//! [`GamepackHandler::schema`](crate::GamepackHandler::schema), which the
+//! daemon reads with `GetSchema` and the default sample data is generated
+//! from. The code schema is optional and never replaces `config.json`: where
+//! both describe a field, the daemon uses the `config.json` column.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::handler::{GamepackError, GamepackResult};
+
+/// Kind of value a field holds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    /// Whole number
+    Integer,
+    /// Any number
+    Float,
+    /// Ratio in `0.0..=1.0`
+    Percentage,
+    /// `true` / `false`
+    Bool,
+    /// Free-form string
+    Text,
+    /// Whole number of seconds
+    DurationSecs,
+    /// One of a fixed set of strings (e.g. champion or map names)
+    OneOf(Vec<String>),
+}
+
+impl FieldKind {
+    /// Whether `value` is of this kind.
+    pub fn matches(&self, value: &Value) -> bool {
+        match self {
+            Self::Integer => value.is_i64() || value.is_u64(),
+            Self::Float => value.is_number(),
+            Self::Percentage => value.as_f64().is_some_and(|f| (0.0..=1.0).contains(&f)),
+            Self::Bool => value.is_boolean(),
+            Self::Text => value.is_string(),
+            Self::DurationSecs => value.is_u64(),
+            Self::OneOf(options) => value
+                .as_str()
+                .is_some_and(|s| options.iter().any(|option| option == s)),
+        }
+    }
+}
+
+/// How to display a numeric field. See [`format_value`].
+///
+/// | Format       | Value     | Displayed |
+/// |--------------|-----------|-----------|
+/// | `Integer`    | `1234.6`  | `1235`    |
+/// | `Thousands`  | `12500`   | `12.5k`   |
+/// | `Percent`    | `0.532`   | `53.2%`   |
+/// | `Duration`   | `754`     | `12:34`   |
+/// | `Decimal(1)` | `7.333`   | `7.3`     |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueFormat {
+    /// Rounded to a whole number
+    Integer,
+    /// Abbreviated with `k`, `M` or `B` and at most one decimal; values
+    /// below 1000 are shown as whole numbers
+    Thousands,
+    /// A ratio (`0.5` = half) shown as a percentage with at most one decimal
+    Percent,
+    /// Seconds shown as `m:ss`, or `h:mm:ss` from one hour
+    Duration,
+    /// Exactly this many decimals
+    Decimal(u8),
+}
+
+/// Render `value` for display in the given format.
+///
+/// Numbers are rounded half away from zero, and a result that rounds to
+/// zero never keeps a minus sign. Values that are not numbers are shown
+/// as-is: strings without quotes, anything else as JSON.
+///
+/// ```rust
+/// use gamepack_runtime::{format_value, ValueFormat};
+/// use serde_json::json;
+///
+/// assert_eq!(format_value(&json!(12500), ValueFormat::Thousands), "12.5k");
+/// assert_eq!(format_value(&json!(7.333), ValueFormat::Decimal(1)), "7.3");
+/// ```
+pub fn format_value(value: &Value, format: ValueFormat) -> String {
+    let Some(number) = value.as_f64() else {
+        return match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+    };
+    match format {
+        ValueFormat::Integer => match (value.as_i64(), value.as_u64()) {
+            // Integers print exactly, even beyond f64 precision
+            (Some(n), _) => n.to_string(),
+            (_, Some(n)) => n.to_string(),
+            _ => fixed(number, 0),
+        },
+        ValueFormat::Thousands => thousands(number),
+        ValueFormat::Percent => format!("{}%", trimmed(number * 100.0)),
+        ValueFormat::Duration => duration(number),
+        ValueFormat::Decimal(places) => fixed(number, places as usize),
+    }
+}
+
+/// `number` with exactly `places` decimals and no negative zero.
+fn fixed(number: f64, places: usize) -> String {
+    // `format!` rounds ties to even; round half away from zero first
+    let factor = 10f64.powi(places as i32);
+    let rounded = (number * factor).round() / factor;
+    let number = if rounded.is_finite() { rounded } else { number };
+    let text = format!("{:.*}", places, number);
+    match text.strip_prefix('-') {
+        Some(unsigned) if unsigned.chars().all(|c| c == '0' || c == '.') => unsigned.to_string(),
+        _ => text,
+    }
+}
+
+/// `number` with at most one decimal, dropping a trailing `.0`.
+fn trimmed(number: f64) -> String {
+    let text = fixed(number, 1);
+    match text.strip_suffix(".0") {
+        Some(whole) => whole.to_string(),
+        None => text,
+    }
+}
+
+fn thousands(number: f64) -> String {
+    if fixed(number, 0).trim_start_matches('-').len() <= 3 {
+        return fixed(number, 0);
+    }
+    for (scale, suffix) in [(1e3, "k"), (1e6, "M")] {
+        // Move up a unit when rounding would show e.g. "1000k"
+        if (number / scale).abs() < 999.95 {
+            return format!("{}{}", trimmed(number / scale), suffix);
+        }
+    }
+    format!("{}B", trimmed(number / 1e9))
+}
+
+fn duration(number: f64) -> String {
+    let total = number.abs().round() as u64;
+    let sign = if number.round() < 0.0 { "-" } else { "" };
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}{}:{:02}:{:02}", sign, hours, minutes, seconds)
+    } else {
+        format!("{}{}:{:02}", sign, minutes, seconds)
+    }
+}
+
+/// A named field and its kind.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSpec {
+    /// Field key in the JSON object
+    pub name: String,
+    /// What the field holds
+    pub kind: FieldKind,
+    /// How to display the field (None = the daemon's default rendering)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<ValueFormat>,
+    /// Whether the field may be `null` or missing
+    #[serde(default, skip_serializing_if = "crate::types::is_false")]
+    pub nullable: bool,
+}
+
+impl ColumnSpec {
+    /// Declare a field.
+    pub fn new(name: impl Into<String>, kind: FieldKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            format: None,
+            nullable: false,
+        }
+    }
+
+    /// Set how the field is displayed.
+    pub fn with_format(mut self, format: ValueFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Allow the field to be `null` or missing.
+    pub fn nullable(mut self) -> Self {
+        self.nullable = true;
+        self
+    }
+}
+
+/// The fields of a JSON object, in declaration order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LiveDataSchema {
+    pub fields: Vec<ColumnSpec>,
+}
+
+impl LiveDataSchema {
+    /// Create an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field.
+    pub fn field(mut self, name: impl Into<String>, kind: FieldKind) -> Self {
+        self.fields.push(ColumnSpec::new(name, kind));
+        self
+    }
+
+    /// Add a field with a display format.
+    pub fn formatted_field(
+        mut self,
+        name: impl Into<String>,
+        kind: FieldKind,
+        format: ValueFormat,
+    ) -> Self {
+        self.fields.push(ColumnSpec::new(name, kind).with_format(format));
+        self
+    }
+
+    /// Add a field that may be `null` or missing.
+    pub fn nullable_field(mut self, name: impl Into<String>, kind: FieldKind) -> Self {
+        self.fields.push(ColumnSpec::new(name, kind).nullable());
+        self
+    }
+
+    /// Check that `value` is an object with every declared field of the
+    /// declared kind, except that [nullable](ColumnSpec::nullable) fields
+    /// may be `null` or missing. Extra fields are allowed.
+    ///
+    /// Fails with code `"schema_mismatch"` naming the first offending field.
+    pub fn validate(&self, value: &Value) -> GamepackResult<()> {
+        let object = value.as_object().ok_or_else(|| mismatch("expected an object"))?;
+        for column in &self.fields {
+            match object.get(&column.name) {
+                Some(field) if column.kind.matches(field) => {}
+                None | Some(Value::Null) if column.nullable => {}
+                Some(field) => {
+                    return Err(mismatch(&format!(
+                        "field '{}' is {}, expected {:?}",
+                        column.name, field, column.kind
+                    )))
+                }
+                None => return Err(mismatch(&format!("missing field '{}'", column.name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The settings a pack accepts, for validating `UpdateSettings` payloads.
+///
+/// Updates may be partial: every key present must be declared and hold a
+/// value of the declared kind, but declared keys may be omitted.
+///
+/// ```rust
+/// use gamepack_runtime::{FieldKind, SettingsSchema};
+/// use serde_json::json;
+///
+/// let schema = SettingsSchema::new()
+///     .setting("clip_kills", FieldKind::Bool)
+///     .setting("min_kill_streak", FieldKind::Integer);
+///
+/// assert!(schema.validate(&json!({"clip_kills": false})).is_ok());
+/// assert!(schema.validate(&json!({"clip_kills": "no"})).is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SettingsSchema {
+    pub settings: Vec<ColumnSpec>,
+}
+
+impl SettingsSchema {
+    /// Create an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a setting.
+    pub fn setting(mut self, name: impl Into<String>, kind: FieldKind) -> Self {
+        self.settings.push(ColumnSpec::new(name, kind));
+        self
+    }
+
+    /// Check a settings update.
+    ///
+    /// Fails with code `"invalid_settings"` naming the first unknown key or
+    /// mistyped value.
+    pub fn validate(&self, settings: &Value) -> GamepackResult<()> {
+        let object = settings
+            .as_object()
+            .ok_or_else(|| invalid_settings("expected an object"))?;
+        for (key, value) in object {
+            match self.settings.iter().find(|column| &column.name == key) {
+                Some(column) if column.kind.matches(value) => {}
+                Some(column) => {
+                    return Err(invalid_settings(&format!(
+                        "'{}' is {}, expected {:?}",
+                        key, value, column.kind
+                    )))
+                }
+                None => return Err(invalid_settings(&format!("unknown setting '{}'", key))),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn invalid_settings(reason: &str) -> GamepackError {
+    GamepackError::with_code(format!("Invalid settings: {}", reason), "invalid_settings")
+}
+
+fn mismatch(reason: &str) -> GamepackError {
+    GamepackError::with_code(format!("Schema mismatch: {}", reason), "schema_mismatch")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> LiveDataSchema {
+        LiveDataSchema::new()
+            .field("kills", FieldKind::Integer)
+            .field("kill_share", FieldKind::Percentage)
+            .field("champion", FieldKind::OneOf(vec!["Ahri".into(), "Lux".into()]))
+    }
+
+    #[test]
+    fn validate_accepts_conforming_objects() {
+        let value = json!({"kills": 3, "kill_share": 0.5, "champion": "Lux", "extra": null});
+        assert!(schema().validate(&value).is_ok());
+    }
+
+    #[test]
+    fn validate_reports_first_mismatch() {
+        for value in [
+            json!([]),
+            json!({"kills": 3.5, "kill_share": 0.5, "champion": "Lux"}),
+            json!({"kills": 3, "kill_share": 1.5, "champion": "Lux"}),
+            json!({"kills": 3, "kill_share": 0.5, "champion": "Zed"}),
+            json!({"kills": 3, "kill_share": 0.5}),
+        ] {
+            let err = schema().validate(&value).unwrap_err();
+            assert_eq!(err.code.as_deref(), Some("schema_mismatch"), "{}", value);
+        }
+    }
+
+    #[test]
+    fn schema_serializes_like_config_columns() {
+        let json = serde_json::to_value(schema()).unwrap();
+        assert_eq!(
+            json["fields"][2],
+            json!({"name": "champion", "kind": {"one_of": ["Ahri", "Lux"]}})
+        );
+        assert_eq!(json["fields"][0]["kind"], json!("integer"));
+    }
+
+    #[test]
+    fn nullable_fields_may_be_null_or_missing() {
+        let schema = LiveDataSchema::new()
+            .field("kills", FieldKind::Integer)
+            .nullable_field("rank", FieldKind::Text);
+
+        assert!(schema.validate(&json!({"kills": 1})).is_ok());
+        assert!(schema.validate(&json!({"kills": 1, "rank": null})).is_ok());
+        assert!(schema.validate(&json!({"kills": 1, "rank": 3})).is_err());
+        assert!(schema.validate(&json!({"kills": null, "rank": "Gold"})).is_err());
+
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["fields"][1]["nullable"], json!(true));
+        assert!(json["fields"][0].get("nullable").is_none());
+        assert_eq!(serde_json::from_value::<LiveDataSchema>(json).unwrap(), schema);
+    }
+
+    #[test]
+    fn settings_schema_accepts_partial_updates() {
+        let schema = SettingsSchema::new()
+            .setting("clip_kills", FieldKind::Bool)
+            .setting("volume", FieldKind::Percentage);
+
+        assert!(schema.validate(&json!({"volume": 0.3})).is_ok());
+        assert!(schema.validate(&json!({})).is_ok());
+    }
+
+    #[test]
+    fn settings_schema_rejects_unknown_and_mistyped_keys() {
+        let schema = SettingsSchema::new().setting("clip_kills", FieldKind::Bool);
+
+        for (settings, reason) in [
+            (json!({"clip_kills": 1}), "'clip_kills' is 1"),
+            (json!({"clip_deaths": true}), "unknown setting 'clip_deaths'"),
+            (json!(true), "expected an object"),
+        ] {
+            let err = schema.validate(&settings).unwrap_err();
+            assert_eq!(err.code.as_deref(), Some("invalid_settings"));
+            assert!(err.message.contains(reason), "{}", err.message);
+        }
+    }
+
+    fn formatted(format: ValueFormat, values: &[Value]) -> Vec<String> {
+        values.iter().map(|value| format_value(value, format)).collect()
+    }
+
+    #[test]
+    fn format_integer() {
+        let values = [json!(1234.6), json!(-3.5), json!(-0.4), json!(0), json!(u64::MAX)];
+        assert_eq!(
+            formatted(ValueFormat::Integer, &values),
+            vec!["1235", "-4", "0", "0", "18446744073709551615"]
+        );
+    }
+
+    #[test]
+    fn format_thousands() {
+        let values = [
+            json!(999),
+            json!(1000),
+            json!(12500),
+            json!(-12500),
+            json!(999_960),
+            json!(1_250_000),
+            json!(3_000_000_000_i64),
+            json!(0),
+        ];
+        assert_eq!(
+            formatted(ValueFormat::Thousands, &values),
+            vec!["999", "1k", "12.5k", "-12.5k", "1M", "1.3M", "3B", "0"]
+        );
+    }
+
+    #[test]
+    fn format_percent() {
+        let values = [json!(0.532), json!(1), json!(0), json!(-0.25), json!(1.5)];
+        assert_eq!(
+            formatted(ValueFormat::Percent, &values),
+            vec!["53.2%", "100%", "0%", "-25%", "150%"]
+        );
+    }
+
+    #[test]
+    fn format_duration() {
+        let values = [json!(754), json!(59.6), json!(0), json!(3725), json!(-65), json!(-0.2)];
+        assert_eq!(
+            formatted(ValueFormat::Duration, &values),
+            vec!["12:34", "1:00", "0:00", "1:02:05", "-1:05", "0:00"]
+        );
+    }
+
+    #[test]
+    fn format_decimal() {
+        let values = [json!(7.333), json!(-7.35), json!(-0.04), json!(0), json!(1e12)];
+        assert_eq!(
+            formatted(ValueFormat::Decimal(1), &values),
+            vec!["7.3", "-7.4", "0.0", "0.0", "1000000000000.0"]
+        );
+        assert_eq!(format_value(&json!(2), ValueFormat::Decimal(0)), "2");
+    }
+
+    #[test]
+    fn format_passes_non_numbers_through() {
+        assert_eq!(format_value(&json!("Ahri"), ValueFormat::Thousands), "Ahri");
+        assert_eq!(format_value(&json!(null), ValueFormat::Percent), "null");
+    }
+
+    #[test]
+    fn format_is_declared_on_fields() {
+        let formatted = LiveDataSchema::new()
+            .formatted_field("gold", FieldKind::Integer, ValueFormat::Thousands)
+            .formatted_field("cs_per_min", FieldKind::Float, ValueFormat::Decimal(1));
+
+        let json = serde_json::to_value(&formatted).unwrap();
+        assert_eq!(json["fields"][0]["format"], json!("thousands"));
+        assert_eq!(json["fields"][1]["format"], json!({"decimal": 1}));
+        assert!(schema().fields.iter().all(|field| field.format.is_none()));
+        let kills = serde_json::to_value(&schema().fields[0]).unwrap();
+        assert!(kills.get("format").is_none());
+    }
+}
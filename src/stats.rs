@@ -0,0 +1,163 @@
+//! Keeping a running stat map between emits.
+//!
+//! Most packs track a handful of counters (kills, deaths, assists, gold)
+//! and re-emit the whole map whenever one changes. [`StatsAccumulator`]
+//! holds that map, and
+//! [`Emitter::emit_accumulated`](crate::Emitter::emit_accumulated) writes it
+//! only when something changed since the last emit:
+//!
+//! ```rust
+//! use gamepack_runtime::testing::capturing_emitter;
+//! use gamepack_runtime::StatsAccumulator;
+//!
+//! let (emitter, _sink) = capturing_emitter();
+//! let mut stats = StatsAccumulator::new();
+//! stats.set("champion", "Ahri");
+//! stats.inc("kills", 1);
+//!
+//! assert!(emitter.emit_accumulated(&mut stats, 0, "m1", 95.0).unwrap());
+//! // Nothing changed: nothing written
+//! assert!(!emitter.emit_accumulated(&mut stats, 0, "m1", 96.0).unwrap());
+//! ```
+//!
+//! Each emit carries the full map, not just the changed keys: the daemon
+//! delta-compresses the timeline itself and upserts the summary from the
+//! full map. [`delta`](StatsAccumulator::delta) shows what changed, e.g.
+//! for logging.
+
+use std::collections::HashMap;
+
+use crate::types::StatValue;
+
+/// The current stat map of one match, and what was last emitted.
+///
+/// Not synchronized: keep it in the handler, or behind a `Mutex` if a
+/// background thread updates it too. Use one per match and drop or
+/// [`clear`](Self::clear) it when the match ends.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatsAccumulator {
+    current: HashMap<String, StatValue>,
+    /// The map as of the last emit
+    emitted: HashMap<String, StatValue>,
+}
+
+impl StatsAccumulator {
+    /// Create an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `by` to a counter, starting from 0 if unset.
+    ///
+    /// A [`Float`](StatValue::Float) stays a float. Text and flags are not
+    /// counters and are replaced by `by`.
+    pub fn inc(&mut self, key: &str, by: i64) {
+        let value = match self.current.get(key) {
+            Some(StatValue::Int(n)) => StatValue::Int(n.saturating_add(by)),
+            Some(StatValue::Float(f)) => StatValue::Float(f + by as f64),
+            _ => StatValue::Int(by),
+        };
+        self.current.insert(key.to_string(), value);
+    }
+
+    /// Set a stat, replacing any previous value.
+    pub fn set(&mut self, key: &str, value: impl Into<StatValue>) {
+        self.current.insert(key.to_string(), value.into());
+    }
+
+    /// The current stat map.
+    pub fn current(&self) -> &HashMap<String, StatValue> {
+        &self.current
+    }
+
+    /// Stats that differ from the last emit, with their current values.
+    pub fn delta(&self) -> HashMap<String, StatValue> {
+        self.current
+            .iter()
+            .filter(|(key, value)| self.emitted.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Whether anything changed since the last emit.
+    pub fn has_changes(&self) -> bool {
+        self.current != self.emitted
+    }
+
+    /// Forget all stats, e.g. when the match ends.
+    pub fn clear(&mut self) {
+        self.current.clear();
+        self.emitted.clear();
+    }
+
+    /// Remember the current map as emitted.
+    pub(crate) fn mark_emitted(&mut self) {
+        self.emitted = self.current.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::capturing_emitter;
+    use crate::types::MatchDataMessage;
+    use serde_json::json;
+
+    #[test]
+    fn inc_and_set_keep_a_running_map() {
+        let mut stats = StatsAccumulator::new();
+        stats.inc("kills", 1);
+        stats.inc("kills", 2);
+        stats.set("kda", 2.5);
+        stats.inc("kda", 1);
+        stats.set("role", "Mid");
+        stats.inc("role", 4);
+        stats.set("kills", 10);
+
+        assert_eq!(
+            stats.current(),
+            &HashMap::from([
+                ("kills".to_string(), StatValue::Int(10)),
+                ("kda".to_string(), StatValue::Float(3.5)),
+                ("role".to_string(), StatValue::Int(4)),
+            ])
+        );
+    }
+
+    #[test]
+    fn emits_full_map_only_after_changes() {
+        let (emitter, sink) = capturing_emitter();
+        let mut stats = StatsAccumulator::new();
+        stats.inc("kills", 1);
+        stats.set("gold", 500);
+
+        assert_eq!(stats.delta().len(), 2);
+        assert!(emitter.emit_accumulated(&mut stats, 0, "m1", 10.0).unwrap());
+        assert!(stats.delta().is_empty());
+
+        stats.inc("kills", 1);
+        stats.set("gold", 500);
+        assert_eq!(stats.delta(), HashMap::from([("kills".to_string(), StatValue::Int(2))]));
+        assert!(emitter.emit_accumulated(&mut stats, 0, "m1", 20.0).unwrap());
+        assert!(!emitter.emit_accumulated(&mut stats, 0, "m1", 30.0).unwrap());
+
+        let written: Vec<_> = sink
+            .messages()
+            .into_iter()
+            .map(|message| match message {
+                MatchDataMessage::WriteStatistics {
+                    game_time_secs,
+                    stats,
+                    ..
+                } => (game_time_secs, stats),
+                other => panic!("Expected WriteStatistics, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(written.len(), 2);
+        assert_eq!(written[1].0, 20.0);
+        assert_eq!(
+            written[1].1,
+            HashMap::from([("kills".to_string(), json!(2)), ("gold".to_string(), json!(500))])
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Clip title and description templates.
+//!
+//! A [`Moment`](crate::Moment) can carry a title and description template
+//! the daemon fills in from the moment's `data` when it names the clip.
+//! [`render_template`] is the one implementation both sides use, so a pack
+//! can preview exactly the text the daemon will show.
+//!
+//! # Syntax
+//!
+//! - `{key}` is replaced by `data[key]`. Dots descend into nested objects
+//!   (`{killer.name}`), and a number indexes into an array (`{victims.0}`).
+//!   Spaces around the key are ignored.
+//! - Strings are inserted without quotes, numbers and booleans as written in
+//!   JSON, and arrays and objects as compact JSON.
+//! - `{{` and `}}` produce literal braces.
+//!
+//! A placeholder whose key is missing or `null` is left in the output as
+//! written, e.g. `"Pentakill by {player}"`, so a typo shows up in the title
+//! instead of silently producing an empty one. A `{` without a closing `}`
+//! is kept as literal text.
+//!
+//! ```rust
+//! use gamepack_runtime::template::render_template;
+//! use serde_json::json;
+//!
+//! let data = json!({"champion": "Ahri", "kills": 5});
+//! assert_eq!(render_template("{kills} kills on {champion}", &data), "5 kills on Ahri");
+//! assert_eq!(render_template("{champion} ({role})", &data), "Ahri ({role})");
+//! ```
+
+use serde_json::Value;
+
+/// Fill `{placeholder}`s in `template` from `data`; see the
+/// [module documentation](self) for the syntax.
+pub fn render_template(template: &str, data: &Value) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(at) = rest.find(['{', '}']) {
+        out.push_str(&rest[..at]);
+        let tail = &rest[at..];
+        if let Some(after) = tail.strip_prefix("{{").or_else(|| tail.strip_prefix("}}")) {
+            out.push_str(&tail[..1]);
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix('}') {
+            out.push('}');
+            rest = after;
+        } else if let Some(end) = tail.find('}') {
+            match lookup(data, &tail[1..end]) {
+                Some(value) => out.push_str(&display(value)),
+                None => out.push_str(&tail[..=end]),
+            }
+            rest = &tail[end + 1..];
+        } else {
+            out.push_str(tail);
+            rest = "";
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Value at a dotted `path`, or `None` if missing or `null`.
+fn lookup<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
+    let path = path.trim();
+    if path.is_empty() {
+        return None;
+    }
+    let value = path.split('.').try_fold(data, |value, part| match value {
+        Value::Object(map) => map.get(part),
+        Value::Array(items) => part.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })?;
+    (!value.is_null()).then_some(value)
+}
+
+/// Text a value is inserted as.
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn interpolates_scalars_and_nested_paths() {
+        let data = json!({
+            "player": "Faker",
+            "kills": 5,
+            "gold": 1250.5,
+            "first_blood": true,
+            "killer": {"name": "Ahri", "level": 11},
+            "victims": ["Zed", "Lux"],
+        });
+
+        assert_eq!(
+            render_template("{player} got {kills} kills ({gold}g, fb={first_blood})", &data),
+            "Faker got 5 kills (1250.5g, fb=true)"
+        );
+        assert_eq!(
+            render_template("{killer.name} lvl {killer.level} killed {victims.1}", &data),
+            "Ahri lvl 11 killed Lux"
+        );
+        assert_eq!(render_template("{victims}", &data), r#"["Zed","Lux"]"#);
+        assert_eq!(render_template("{ player }", &data), "Faker");
+    }
+
+    #[test]
+    fn missing_placeholders_are_left_as_written() {
+        let data = json!({"player": "Faker", "team": null, "victims": ["Zed"]});
+
+        assert_eq!(render_template("{player} on {team}", &data), "Faker on {team}");
+        assert_eq!(render_template("{plyer} wins", &data), "{plyer} wins");
+        let nested = "{victims.3} / {player.name}";
+        assert_eq!(render_template(nested, &data), nested);
+        assert_eq!(render_template("{}", &data), "{}");
+        assert_eq!(render_template("no data", &Value::Null), "no data");
+    }
+
+    #[test]
+    fn braces_escape_and_unclosed_placeholders() {
+        let data = json!({"n": 3});
+
+        assert_eq!(render_template("{{n}} is {n}", &data), "{n} is 3");
+        assert_eq!(render_template("set}} {n}", &data), "set} 3");
+        assert_eq!(render_template("{n} {unclosed", &data), "3 {unclosed");
+        assert_eq!(render_template("x } y", &data), "x } y");
+    }
+}
@@ -0,0 +1,174 @@
+//! Utilities for unit-testing gamepack logic without real IO.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use crate::emitter::{EmitSink, Emitter};
+use crate::responses::GamepackResponse;
+use crate::transport::Transport;
+use crate::types::MatchDataMessage;
+
+/// Sink that records every emitted message in memory.
+///
+/// Clones share the same buffer, so keep one clone for assertions and hand
+/// the other to an [`Emitter`].
+#[derive(Debug, Clone, Default)]
+pub struct CapturingSink {
+    captured: Arc<Mutex<Vec<GamepackResponse>>>,
+}
+
+impl CapturingSink {
+    /// Create an empty capturing sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All captured responses, in emission order.
+    pub fn responses(&self) -> Vec<GamepackResponse> {
+        self.captured.lock().unwrap().clone()
+    }
+
+    /// Captured match data messages (the payloads of `WriteMatchData`).
+    pub fn messages(&self) -> Vec<MatchDataMessage> {
+        self.captured
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|response| match response {
+                GamepackResponse::WriteMatchData { message, .. } => Some(message.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Discard everything captured so far.
+    pub fn clear(&self) {
+        self.captured.lock().unwrap().clear();
+    }
+}
+
+impl EmitSink for CapturingSink {
+    fn emit(&self, response: &GamepackResponse, _line: &str) -> io::Result<()> {
+        self.captured.lock().unwrap().push(response.clone());
+        Ok(())
+    }
+}
+
+/// Create an [`Emitter`] backed by a fresh [`CapturingSink`].
+pub fn capturing_emitter() -> (Emitter, CapturingSink) {
+    let sink = CapturingSink::new();
+    (Emitter::new(sink.clone()), sink)
+}
+
+/// [`Transport`] that serves a fixed list of messages, then reports the
+/// daemon gone, and records everything written.
+///
+/// ```rust
+/// use gamepack_runtime::testing::VecTransport;
+///
+/// let transport = VecTransport::new([r#"{"type":"poll_events","request_id":"p1"}"#]);
+/// // run_gamepack_with_transport(handler, options, &mut transport) ...
+/// assert!(transport.written().is_empty());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VecTransport {
+    input: VecDeque<String>,
+    written: Vec<String>,
+}
+
+impl VecTransport {
+    /// Serve `messages` in order.
+    pub fn new(messages: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            input: messages.into_iter().map(Into::into).collect(),
+            written: Vec::new(),
+        }
+    }
+
+    /// Messages written so far, in order.
+    pub fn written(&self) -> &[String] {
+        &self.written
+    }
+
+    /// Written messages parsed as responses.
+    ///
+    /// Panics if one is not a valid response.
+    pub fn responses(&self) -> Vec<GamepackResponse> {
+        self.written
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+impl Transport for VecTransport {
+    fn read_message(&mut self) -> io::Result<Option<String>> {
+        Ok(self.input.pop_front())
+    }
+
+    fn write_message(&mut self, message: &str) -> io::Result<()> {
+        self.written.push(message.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Moment;
+    use serde_json::json;
+
+    /// Example pack logic: emit a moment on the fifth kill.
+    struct KillTracker {
+        emitter: Emitter,
+        kills: u32,
+    }
+
+    impl KillTracker {
+        fn on_kill(&mut self, game_time_secs: f64) {
+            self.kills += 1;
+            if self.kills == 5 {
+                let moment = Moment::new("pentakill", game_time_secs, json!({"kills": 5}));
+                self.emitter.emit_moments(0, "match123", vec![moment]).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn pentakill_emits_moment() {
+        let (emitter, sink) = capturing_emitter();
+        let mut tracker = KillTracker { emitter, kills: 0 };
+
+        for i in 0..4 {
+            tracker.on_kill(100.0 + i as f64);
+        }
+        assert!(sink.messages().is_empty());
+
+        tracker.on_kill(104.0);
+        match sink.messages().as_slice() {
+            [MatchDataMessage::WriteMoments {
+                external_match_id,
+                moments,
+                ..
+            }] => {
+                assert_eq!(external_match_id, "match123");
+                assert_eq!(moments[0].moment_id, "pentakill");
+                assert_eq!(moments[0].game_time_secs, 104.0);
+            }
+            other => panic!("Expected one WriteMoments, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn capturing_sink_clear_discards_messages() {
+        let (emitter, sink) = capturing_emitter();
+        emitter
+            .emit_statistics(0, "m1", 10.0, Default::default())
+            .unwrap();
+        assert_eq!(sink.responses().len(), 1);
+
+        sink.clear();
+        assert!(sink.responses().is_empty());
+    }
+}
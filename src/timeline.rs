@@ -0,0 +1,311 @@
+//! In-memory match timeline storage for gamepacks.
+//!
+//! Packs that keep their own copy of the timeline (for recovery or to answer
+//! `GetMatchTimeline`) can use [`TimelineBuffer`] instead of rolling their own.
+//! [`MatchSummaryBuffer`] does the same for finished matches and
+//! `GetRecentMatches`.
+
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+
+use crate::types::{
+    EntryType, GetMatchTimelineRequest, GetMatchTimelineResponse, MatchSummary, TimelineEntry,
+};
+
+/// Timeline entries per match, keyed by `(subpack, external_match_id)`.
+///
+/// Entries are kept in insertion order, which is expected to be chronological.
+#[derive(Debug, Clone, Default)]
+pub struct TimelineBuffer {
+    matches: HashMap<(u8, String), Vec<TimelineEntry>>,
+}
+
+impl TimelineBuffer {
+    /// Create an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an entry to a match's timeline.
+    pub fn push(
+        &mut self,
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        entry: TimelineEntry,
+    ) {
+        self.matches
+            .entry((subpack, external_match_id.into()))
+            .or_default()
+            .push(entry);
+    }
+
+    /// All entries for a match, in insertion order.
+    pub fn entries(&self, subpack: u8, external_match_id: &str) -> Option<&[TimelineEntry]> {
+        self.matches
+            .get(&(subpack, external_match_id.to_string()))
+            .map(Vec::as_slice)
+    }
+
+    /// Whether the buffer holds a timeline for a match.
+    pub fn contains(&self, subpack: u8, external_match_id: &str) -> bool {
+        self.entries(subpack, external_match_id).is_some()
+    }
+
+    /// Drop all entries for a match.
+    pub fn remove(&mut self, subpack: u8, external_match_id: &str) -> Option<Vec<TimelineEntry>> {
+        self.matches.remove(&(subpack, external_match_id.to_string()))
+    }
+
+    /// Answer a timeline request.
+    ///
+    /// Filters are applied in this order:
+    /// 1. `entry_types` — keep only the listed types (unknown names match nothing)
+    /// 2. `start_secs`/`end_secs` — keep entries whose `game_time_secs` lies
+    ///    in the inclusive range (either bound may be omitted)
+    /// 3. `limit` — keep the latest N of what remains
+    ///
+    /// So when both a range and a limit are set, the range filters first and
+    /// the limit then selects the latest entries within that range.
+    pub fn query(&self, request: &GetMatchTimelineRequest) -> GetMatchTimelineResponse {
+        let Some(entries) = self.entries(request.subpack, &request.external_match_id) else {
+            return GetMatchTimelineResponse {
+                found: false,
+                entries: vec![],
+            };
+        };
+
+        let types: Option<Vec<EntryType>> = request.entry_types.as_ref().map(|names| {
+            names
+                .iter()
+                .filter_map(|name| EntryType::from_str(name).ok())
+                .collect()
+        });
+
+        let mut selected: Vec<TimelineEntry> = entries
+            .iter()
+            .filter(|e| types.as_ref().is_none_or(|t| t.contains(&e.entry_type)))
+            .filter(|e| request.start_secs.is_none_or(|start| e.game_time_secs >= start))
+            .filter(|e| request.end_secs.is_none_or(|end| e.game_time_secs <= end))
+            .cloned()
+            .collect();
+
+        if let Some(limit) = request.limit {
+            let limit = limit as usize;
+            if selected.len() > limit {
+                selected.drain(..selected.len() - limit);
+            }
+        }
+
+        GetMatchTimelineResponse {
+            found: true,
+            entries: selected,
+        }
+    }
+}
+
+/// Summaries of recently finished matches, per subpack, newest first.
+///
+/// Retention is bounded: each subpack keeps at most `retention` summaries
+/// and the oldest is dropped when a new one is recorded. Nothing is
+/// persisted, so the history starts empty each time the pack starts.
+#[derive(Debug, Clone)]
+pub struct MatchSummaryBuffer {
+    retention: usize,
+    matches: HashMap<u8, VecDeque<MatchSummary>>,
+}
+
+impl MatchSummaryBuffer {
+    /// Create an empty buffer keeping up to `retention` matches per subpack.
+    pub fn new(retention: usize) -> Self {
+        Self {
+            retention,
+            matches: HashMap::new(),
+        }
+    }
+
+    /// Record a finished match as the newest of its subpack.
+    ///
+    /// Recording a match that is already held replaces it (and moves it to
+    /// the front), so a repeated `SetComplete` does not list it twice.
+    pub fn record(&mut self, subpack: u8, summary: MatchSummary) {
+        let matches = self.matches.entry(subpack).or_default();
+        matches.retain(|m| m.external_match_id != summary.external_match_id);
+        matches.push_front(summary);
+        matches.truncate(self.retention);
+    }
+
+    /// Up to `limit` of a subpack's matches, newest first (all if `None`).
+    pub fn recent(&self, subpack: u8, limit: Option<u32>) -> Vec<MatchSummary> {
+        let limit = limit.map_or(usize::MAX, |limit| limit as usize);
+        self.matches
+            .get(&subpack)
+            .map(|matches| matches.iter().take(limit).cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Timestamp;
+    use serde_json::json;
+
+    fn buffer() -> TimelineBuffer {
+        let mut buffer = TimelineBuffer::new();
+        let at = "2024-01-15T10:30:00Z";
+        for t in [10.0, 20.0, 30.0, 40.0, 50.0] {
+            buffer.push(0, "m1", TimelineEntry::event("Kill", t, at, json!({})));
+            buffer.push(0, "m1", TimelineEntry::statistic(t, at, json!({"kills": t})));
+        }
+        buffer
+    }
+
+    fn request() -> GetMatchTimelineRequest {
+        GetMatchTimelineRequest {
+            subpack: 0,
+            external_match_id: "m1".to_string(),
+            entry_types: None,
+            limit: None,
+            start_secs: None,
+            end_secs: None,
+        }
+    }
+
+    fn times(response: &GetMatchTimelineResponse) -> Vec<f64> {
+        response.entries.iter().map(|e| e.game_time_secs).collect()
+    }
+
+    #[test]
+    fn contains_reports_known_matches() {
+        let mut buffer = buffer();
+
+        assert!(buffer.contains(0, "m1"));
+        assert!(!buffer.contains(1, "m1"));
+        assert!(!buffer.contains(0, "m2"));
+
+        buffer.remove(0, "m1");
+        assert!(!buffer.contains(0, "m1"));
+    }
+
+    #[test]
+    fn query_unknown_match_is_not_found() {
+        let response = buffer().query(&GetMatchTimelineRequest {
+            external_match_id: "nope".to_string(),
+            ..request()
+        });
+
+        assert!(!response.found);
+        assert!(response.entries.is_empty());
+    }
+
+    #[test]
+    fn query_range_is_inclusive() {
+        let response = buffer().query(&GetMatchTimelineRequest {
+            entry_types: Some(vec!["event".to_string()]),
+            start_secs: Some(20.0),
+            end_secs: Some(40.0),
+            ..request()
+        });
+
+        assert!(response.found);
+        assert_eq!(times(&response), vec![20.0, 30.0, 40.0]);
+    }
+
+    #[test]
+    fn query_open_ended_ranges() {
+        let events = Some(vec!["event".to_string()]);
+
+        let from = buffer().query(&GetMatchTimelineRequest {
+            entry_types: events.clone(),
+            start_secs: Some(35.0),
+            ..request()
+        });
+        assert_eq!(times(&from), vec![40.0, 50.0]);
+
+        let until = buffer().query(&GetMatchTimelineRequest {
+            entry_types: events,
+            end_secs: Some(15.0),
+            ..request()
+        });
+        assert_eq!(times(&until), vec![10.0]);
+    }
+
+    #[test]
+    fn query_range_covers_pregame_entries() {
+        let mut buffer = TimelineBuffer::new();
+        let at = "2024-01-15T10:30:00Z";
+        for t in [-30.0, -5.0, 0.0, 10.0] {
+            buffer.push(0, "m1", TimelineEntry::event("Kill", t, at, json!({})));
+        }
+
+        let countdown = buffer.query(&GetMatchTimelineRequest {
+            end_secs: Some(-1.0),
+            ..request()
+        });
+        assert_eq!(times(&countdown), vec![-30.0, -5.0]);
+
+        let around_start = buffer.query(&GetMatchTimelineRequest {
+            start_secs: Some(-10.0),
+            end_secs: Some(0.0),
+            ..request()
+        });
+        assert_eq!(times(&around_start), vec![-5.0, 0.0]);
+    }
+
+    #[test]
+    fn query_range_applies_before_limit() {
+        let response = buffer().query(&GetMatchTimelineRequest {
+            entry_types: Some(vec!["event".to_string()]),
+            start_secs: Some(10.0),
+            end_secs: Some(40.0),
+            limit: Some(2),
+            ..request()
+        });
+
+        // Latest two within the range, not the latest two overall
+        assert_eq!(times(&response), vec![30.0, 40.0]);
+    }
+
+    #[test]
+    fn query_empty_range_is_found_but_empty() {
+        let response = buffer().query(&GetMatchTimelineRequest {
+            start_secs: Some(41.0),
+            end_secs: Some(49.0),
+            ..request()
+        });
+
+        assert!(response.found);
+        assert!(response.entries.is_empty());
+    }
+
+    fn summary(id: &str) -> MatchSummary {
+        MatchSummary::new(id, "win", Timestamp::from_unix_millis(0), json!({}))
+    }
+
+    fn ids(matches: &[MatchSummary]) -> Vec<&str> {
+        matches.iter().map(|m| m.external_match_id.as_str()).collect()
+    }
+
+    #[test]
+    fn summaries_are_newest_first_and_bounded() {
+        let mut summaries = MatchSummaryBuffer::new(3);
+        for id in ["m1", "m2", "m3", "m4"] {
+            summaries.record(0, summary(id));
+        }
+        summaries.record(1, summary("other"));
+
+        assert_eq!(ids(&summaries.recent(0, None)), vec!["m4", "m3", "m2"]);
+        assert_eq!(ids(&summaries.recent(0, Some(2))), vec!["m4", "m3"]);
+        assert!(summaries.recent(2, None).is_empty());
+    }
+
+    #[test]
+    fn recording_a_match_again_replaces_it() {
+        let mut summaries = MatchSummaryBuffer::new(3);
+        summaries.record(0, summary("m1"));
+        summaries.record(0, summary("m2"));
+        summaries.record(0, summary("m1"));
+
+        assert_eq!(ids(&summaries.recent(0, None)), vec!["m1", "m2"]);
+    }
+}
@@ -0,0 +1,351 @@
+//! Where commands come from and where responses go.
+//!
+//! The main loop reads commands and writes responses through a
+//! [`Transport`]. [`run_gamepack`](crate::run_gamepack) uses
+//! [`StdioTransport`]; [`run_gamepack_with_transport`](crate::run_gamepack_with_transport)
+//! accepts any other, e.g. [`StreamTransport`] over a socket, or
+//! [`VecTransport`](crate::testing::VecTransport) in tests.
+//!
+//! Only the runner's responses go through the transport. Unsolicited
+//! messages go through the [`Emitter`](crate::Emitter), so a pack using a
+//! transport other than stdio should install a global emitter whose sink
+//! writes to the same place.
+//!
+//! [`run_gamepack_with_connections`](crate::run_gamepack_with_connections)
+//! serves several [`StreamTransport`]s at once. It sets up both sides
+//! itself: answers go back to the connection the command came in on, and
+//! unsolicited messages go to every connection.
+
+use std::cell::Cell;
+use std::io::{self, Read, StdinLock, Stdout, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::emitter::EmitSink;
+use crate::framing::LineReader;
+use crate::responses::GamepackResponse;
+
+/// A bidirectional channel of messages, one JSON document each.
+pub trait Transport {
+    /// Next message, or `None` once the daemon has gone away.
+    ///
+    /// Input that should be answered with an error and skipped, rather than
+    /// end the loop, is reported as an [`io::ErrorKind::InvalidData`] error.
+    /// If it wraps a [`FrameError`](crate::framing::FrameError), the
+    /// runner answers with that error's code. Any other error ends the loop
+    /// like `None`.
+    fn read_message(&mut self) -> io::Result<Option<String>>;
+
+    /// Send one message (without a delimiter) and flush it to the daemon,
+    /// unless the transport coalesces writes; see [`flush`](Self::flush).
+    fn write_message(&mut self, message: &str) -> io::Result<()>;
+
+    /// Push out messages `write_message` buffered.
+    ///
+    /// The runner calls it right after writing an `Error` or
+    /// `ShutdownComplete`, which must never wait. Transports that write
+    /// every message through need not implement it.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// NDJSON over any reader and writer.
+#[derive(Debug)]
+pub struct StreamTransport<R, W> {
+    lines: LineReader<R>,
+    output: W,
+    /// Flush threshold in bytes; present when coalescing writes
+    coalesce_bytes: Option<usize>,
+    /// Lines written but not yet flushed, when coalescing
+    pending: Vec<u8>,
+}
+
+impl<R: Read, W: Write> StreamTransport<R, W> {
+    /// Read commands from `input` and write responses to `output`.
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            lines: LineReader::new(input),
+            output,
+            coalesce_bytes: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Reject command lines longer than `max` bytes with `"line_too_long"`.
+    /// See [`RunnerOptions::max_line_bytes`](crate::RunnerOptions::max_line_bytes).
+    pub fn with_max_line_bytes(mut self, max: usize) -> Self {
+        self.lines = self.lines.with_max_line_bytes(max);
+        self
+    }
+
+    /// Buffer responses and write them out together instead of flushing
+    /// each one.
+    ///
+    /// Buffered lines are flushed once `max_bytes` have piled up, or when
+    /// the input has no complete command left, i.e. before waiting for the
+    /// daemon. A burst of commands is then answered with one write instead
+    /// of one per command, at the cost of holding each answer until the
+    /// burst is handled. `Error` and `ShutdownComplete` are flushed at once
+    /// (see [`Transport::flush`]), and unsolicited messages from the
+    /// [`Emitter`](crate::Emitter) are never held back, so they may overtake
+    /// a buffered response.
+    /// See also
+    /// [`RunnerOptions::coalesce_response_bytes`](crate::RunnerOptions::coalesce_response_bytes).
+    pub fn with_coalesced_writes(mut self, max_bytes: usize) -> Self {
+        self.coalesce_bytes = Some(max_bytes);
+        self
+    }
+
+    /// The line reader and the writer, for serving them separately.
+    /// Nothing is buffered yet when this is called.
+    fn into_parts(self) -> (LineReader<R>, W) {
+        (self.lines, self.output)
+    }
+}
+
+/// Next frame from `lines` as a line; see [`Transport::read_message`].
+fn next_line<R: Read>(lines: &mut LineReader<R>) -> io::Result<Option<String>> {
+    match lines.next_frame()? {
+        Some(frame) => frame
+            .into_line()
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        None => Ok(None),
+    }
+}
+
+impl<R: Read, W: Write> Transport for StreamTransport<R, W> {
+    fn read_message(&mut self) -> io::Result<Option<String>> {
+        // About to wait for the daemon: nothing may stay buffered meanwhile
+        if !self.pending.is_empty() && !self.lines.has_complete_line() {
+            self.flush()?;
+        }
+        next_line(&mut self.lines)
+    }
+
+    fn write_message(&mut self, message: &str) -> io::Result<()> {
+        let Some(max) = self.coalesce_bytes else {
+            writeln!(self.output, "{}", message)?;
+            return self.output.flush();
+        };
+        self.pending.extend_from_slice(message.as_bytes());
+        self.pending.push(b'\n');
+        if self.pending.len() >= max {
+            return self.flush();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Without coalescing every write was flushed already
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        self.output.write_all(&std::mem::take(&mut self.pending))?;
+        self.output.flush()
+    }
+}
+
+/// NDJSON over the process's stdin and stdout; the default transport.
+#[derive(Debug)]
+pub struct StdioTransport(StreamTransport<StdinLock<'static>, Stdout>);
+
+impl StdioTransport {
+    /// Lock stdin for reading and write to stdout.
+    pub fn new() -> Self {
+        Self(StreamTransport::new(io::stdin().lock(), io::stdout()))
+    }
+
+    /// See [`StreamTransport::with_max_line_bytes`].
+    pub fn with_max_line_bytes(self, max: usize) -> Self {
+        Self(self.0.with_max_line_bytes(max))
+    }
+
+    /// See [`StreamTransport::with_coalesced_writes`].
+    pub fn with_coalesced_writes(self, max_bytes: usize) -> Self {
+        Self(self.0.with_coalesced_writes(max_bytes))
+    }
+}
+
+impl Default for StdioTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for StdioTransport {
+    fn read_message(&mut self) -> io::Result<Option<String>> {
+        self.0.read_message()
+    }
+
+    fn write_message(&mut self, message: &str) -> io::Result<()> {
+        self.0.write_message(message)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+thread_local! {
+    /// Connection whose command this thread is answering, when the runner
+    /// serves several; `None` otherwise.
+    static CONNECTION: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Connection whose command this thread is answering; see
+/// [`MultiplexTransport`].
+pub(crate) fn current_connection() -> Option<usize> {
+    CONNECTION.with(Cell::get)
+}
+
+/// Answer `connection`'s command on this thread, e.g. in a background task.
+pub(crate) fn set_current_connection(connection: Option<usize>) {
+    CONNECTION.with(|current| current.set(connection));
+}
+
+/// A line read from one of several connections, by connection index.
+type Incoming = (usize, io::Result<Option<String>>);
+
+/// Writing side shared by a [`MultiplexTransport`] and its [`BroadcastSink`].
+struct Outputs<W> {
+    /// One writer per connection; `None` once writing to it failed
+    writers: Vec<Option<W>>,
+}
+
+impl<W: Write> Outputs<W> {
+    /// Write and flush one line to `connection`, dropping the connection
+    /// if that fails.
+    fn write_to(&mut self, connection: usize, line: &str) {
+        let Some(slot) = self.writers.get_mut(connection) else {
+            return;
+        };
+        let failed = slot.as_mut().is_some_and(|output| {
+            writeln!(output, "{}", line)
+                .and_then(|()| output.flush())
+                .is_err()
+        });
+        if failed {
+            eprintln!("gamepack-runtime: connection {} closed its output", connection);
+            *slot = None;
+        }
+    }
+
+    /// Fails with a broken pipe once no connection can be written to.
+    fn check_open(&self) -> io::Result<()> {
+        if self.writers.iter().any(Option::is_some) {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "every connection closed its output"))
+        }
+    }
+}
+
+/// Several connections read as one [`Transport`], answering each command on
+/// the connection it came in on.
+///
+/// A thread per connection reads lines and queues them, so commands are
+/// handled in the order they arrive, whichever connection sent them.
+/// Reading a command makes its connection the
+/// [current one](current_connection) of the reading thread. Ends once
+/// every connection's input has ended.
+pub(crate) struct MultiplexTransport<W> {
+    incoming: Receiver<Incoming>,
+    outputs: Arc<Mutex<Outputs<W>>>,
+}
+
+/// [`EmitSink`] over the connections of a [`MultiplexTransport`].
+///
+/// Answers written through the emitter (a deferred `SessionEnded`, session
+/// end progress) go to the emitting thread's
+/// [current connection](current_connection), which the runner carries into
+/// its background tasks. Everything else goes to every connection.
+pub(crate) struct BroadcastSink<W> {
+    outputs: Arc<Mutex<Outputs<W>>>,
+}
+
+/// Split `connections` into a transport for the runner and a sink for the
+/// emitter, and start reading each connection.
+///
+/// Write coalescing configured on the connections is not applied.
+pub(crate) fn multiplex<R, W>(
+    connections: Vec<StreamTransport<R, W>>,
+) -> (MultiplexTransport<W>, BroadcastSink<W>)
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let (sender, incoming) = mpsc::channel::<Incoming>();
+    let mut writers = Vec::with_capacity(connections.len());
+    for (connection, transport) in connections.into_iter().enumerate() {
+        let (mut lines, output) = transport.into_parts();
+        writers.push(Some(output));
+        let sender = sender.clone();
+        thread::spawn(move || loop {
+            let read = next_line(&mut lines);
+            let ended = match &read {
+                Ok(line) => line.is_none(),
+                Err(e) => e.kind() != io::ErrorKind::InvalidData,
+            };
+            if sender.send((connection, read)).is_err() || ended {
+                break;
+            }
+        });
+    }
+    let outputs = Arc::new(Mutex::new(Outputs { writers }));
+    let transport = MultiplexTransport {
+        incoming,
+        outputs: Arc::clone(&outputs),
+    };
+    (transport, BroadcastSink { outputs })
+}
+
+impl<W: Write> Transport for MultiplexTransport<W> {
+    fn read_message(&mut self) -> io::Result<Option<String>> {
+        loop {
+            // Every reader thread has ended once the channel disconnects
+            let Ok((connection, read)) = self.incoming.recv() else {
+                return Ok(None);
+            };
+            match read {
+                // One daemon went away; keep serving the others
+                Ok(None) => continue,
+                Err(e) if e.kind() != io::ErrorKind::InvalidData => {
+                    eprintln!("gamepack-runtime: connection {} unreadable ({})", connection, e);
+                    continue;
+                }
+                read => {
+                    set_current_connection(Some(connection));
+                    return read;
+                }
+            }
+        }
+    }
+
+    fn write_message(&mut self, message: &str) -> io::Result<()> {
+        let mut outputs = self.outputs.lock().unwrap();
+        if let Some(connection) = current_connection() {
+            outputs.write_to(connection, message);
+        }
+        outputs.check_open()
+    }
+}
+
+impl<W: Write + Send> EmitSink for BroadcastSink<W> {
+    fn emit(&self, response: &GamepackResponse, line: &str) -> io::Result<()> {
+        let mut outputs = self.outputs.lock().unwrap();
+        // Unsolicited messages have no request id, wherever they come from
+        let answering = current_connection().filter(|_| !response.request_id().is_empty());
+        match answering {
+            Some(connection) => outputs.write_to(connection, line),
+            None => {
+                for connection in 0..outputs.writers.len() {
+                    outputs.write_to(connection, line);
+                }
+            }
+        }
+        outputs.check_open()
+    }
+}
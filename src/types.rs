@@ -2,11 +2,21 @@
 //!
 //! NOTE: All types here are GAME-AGNOSTIC. No League/TFT/etc specifics.
 //! Each gamepack defines its own subpacks and column schemas in config.json.
+//!
+//! In-game times (`timestamp_secs`, `game_time_secs`) count seconds from
+//! match start and may be negative: `-30.0` is 30 seconds before the match
+//! starts, e.g. during loading or a countdown. They only have to be finite.
 
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
 use strum::{Display, EnumString};
 
+use crate::clock::Clock;
+use crate::handler::{GamepackError, GamepackResult};
+
 // ============================================================================
 // TYPE-SAFE ENUMS
 // ============================================================================
@@ -14,6 +24,9 @@ use strum::{Display, EnumString};
 /// Type of entry in the match timeline.
 ///
 /// Used for filtering and ensuring type safety when storing/retrieving timeline data.
+///
+/// Deserializing a type this version doesn't know yields
+/// [`Unknown`](Self::Unknown) instead of failing the whole message.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[derive(Display, EnumString)]
 #[serde(rename_all = "snake_case")]
@@ -25,12 +38,28 @@ pub enum EntryType {
     Statistic,
     /// Recordable moments that may trigger clips
     Moment,
+    /// A type added in a newer protocol version. Log and skip such entries
+    /// rather than processing them, and never send it: it serializes as
+    /// `"unknown"`, which no version understands.
+    #[serde(other)]
+    Unknown,
+}
+
+impl EntryType {
+    /// Whether this is a type from a newer protocol version; see
+    /// [`Unknown`](Self::Unknown).
+    pub fn is_unknown(self) -> bool {
+        self == Self::Unknown
+    }
 }
 
 /// Source of match summary data.
 ///
 /// Indicates whether the final stats came from an official API or were
 /// reconstructed from live data.
+///
+/// Deserializing a source this version doesn't know yields
+/// [`Unknown`](Self::Unknown) instead of failing the whole message.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[derive(Display, EnumString)]
 #[serde(rename_all = "snake_case")]
@@ -40,6 +69,242 @@ pub enum SummarySource {
     Api,
     /// Stats reconstructed from live data (fallback when API unavailable)
     LiveFallback,
+    /// A source added in a newer protocol version. Log it and treat the
+    /// stats as of unknown provenance rather than acting on the value, and
+    /// never send it: it serializes as `"unknown"`, which no version
+    /// understands.
+    #[serde(other)]
+    Unknown,
+}
+
+impl SummarySource {
+    /// Whether this is a source from a newer protocol version; see
+    /// [`Unknown`](Self::Unknown).
+    pub fn is_unknown(self) -> bool {
+        self == Self::Unknown
+    }
+}
+
+/// Normalized match result, used by the daemon for filtering.
+///
+/// Game-specific outcomes that don't fit (e.g. "surrender_at_15") go in
+/// [`MatchData::outcome_detail`] alongside the closest normalized result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum MatchResult {
+    /// The player's side won
+    Win,
+    /// The player's side lost
+    Loss,
+    /// Tie or draw
+    Draw,
+    /// Match voided early (no stats counted)
+    Remake,
+}
+
+impl From<MatchResult> for String {
+    fn from(result: MatchResult) -> Self {
+        result.to_string()
+    }
+}
+
+/// A feature of the pack the daemon can rely on, reported in `Initialized`.
+///
+/// The first four are backed by required handler methods and always present.
+/// The rest correspond to optional methods: the daemon only sends the
+/// matching commands (or offers the matching UI) to packs that declare them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum Capability {
+    /// `detect_running` and `get_status`
+    Status,
+    /// `poll_events`
+    Events,
+    /// `get_live_data`
+    LiveData,
+    /// `on_session_start` and `on_session_end`
+    Sessions,
+    /// `get_live_data_all`
+    LiveDataAll,
+    /// `get_game_version`
+    GameVersion,
+    /// `resolve_event_icon`
+    EventIcons,
+    /// `is_match_in_progress`
+    MatchInProgress,
+    /// `match_exists`
+    MatchExists,
+    /// `get_match_timeline`
+    MatchTimeline,
+    /// `get_recent_matches`
+    RecentMatches,
+    /// `get_sample_match_data`
+    SampleMatchData,
+    /// `on_settings_update`
+    Settings,
+    /// `validate_trigger_config`
+    TriggerValidation,
+    /// `on_prepare_update` and `on_restore_state`
+    StateHandoff,
+    /// `on_run_recovery`
+    Recovery,
+    /// `schema`
+    Schema,
+    /// `should_record`
+    RecordDecisions,
+    /// `get_trigger_rules`
+    TriggerRules,
+}
+
+impl Capability {
+    /// Capabilities every pack has, whatever it overrides.
+    pub const ALWAYS: [Capability; 4] =
+        [Self::Status, Self::Events, Self::LiveData, Self::Sessions];
+
+    /// [`ALWAYS`](Self::ALWAYS) as a set, to extend with optional ones.
+    pub fn always() -> HashSet<Capability> {
+        Self::ALWAYS.into_iter().collect()
+    }
+}
+
+// ============================================================================
+// TIMESTAMPS
+// ============================================================================
+
+/// Wall-clock timestamp in ISO 8601 format (UTC, millisecond precision).
+///
+/// Serializes as a plain string, e.g. `"2024-01-15T10:30:00.000Z"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(derive_more::Display, derive_more::From, derive_more::Deref, derive_more::AsRef)]
+#[serde(transparent)]
+pub struct Timestamp(String);
+
+impl Timestamp {
+    /// The current wall-clock time.
+    pub fn now() -> Self {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self::from_unix_millis(since_epoch.as_millis() as i64)
+    }
+
+    /// Create a timestamp from milliseconds since the Unix epoch.
+    pub fn from_unix_millis(millis: i64) -> Self {
+        let secs = millis.div_euclid(1000);
+        let days = secs.div_euclid(86_400);
+        let secs_of_day = secs.rem_euclid(86_400);
+        let (year, month, day) = civil_from_days(days);
+        Self(format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year,
+            month,
+            day,
+            secs_of_day / 3600,
+            secs_of_day % 3600 / 60,
+            secs_of_day % 60,
+            millis.rem_euclid(1000),
+        ))
+    }
+
+    /// The timestamp as an ISO 8601 string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// ============================================================================
+// CAPTURE WINDOWS
+// ============================================================================
+
+/// Longest pre- or post-capture duration accepted by [`CaptureWindow::new`].
+pub const MAX_CAPTURE_SECS: f64 = 600.0;
+
+/// Validated clip capture window around an event.
+///
+/// Both sides are guaranteed to be finite, non-negative, and at most
+/// [`MAX_CAPTURE_SECS`]. Use with [`GameEvent::with_window`] instead of the
+/// raw `with_pre_capture`/`with_post_capture` setters to catch bad values
+/// in the pack rather than having the daemon silently clamp them.
+///
+/// Serializes as `{"pre_secs": .., "post_secs": ..}`; deserializing applies
+/// the same checks as [`CaptureWindow::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawCaptureWindow")]
+pub struct CaptureWindow {
+    pre_secs: f64,
+    post_secs: f64,
+}
+
+impl CaptureWindow {
+    /// Create a capture window.
+    ///
+    /// Returns an error with code `"invalid_capture_window"` if either side is
+    /// negative, non-finite, or longer than [`MAX_CAPTURE_SECS`].
+    pub fn new(pre_secs: f64, post_secs: f64) -> GamepackResult<Self> {
+        for (name, secs) in [("pre", pre_secs), ("post", post_secs)] {
+            if !(0.0..=MAX_CAPTURE_SECS).contains(&secs) {
+                return Err(GamepackError::with_code(
+                    format!(
+                        "{}-capture must be between 0 and {} seconds, got {}",
+                        name, MAX_CAPTURE_SECS, secs
+                    ),
+                    "invalid_capture_window",
+                ));
+            }
+        }
+        Ok(Self {
+            pre_secs,
+            post_secs,
+        })
+    }
+
+    /// Seconds to capture before the event.
+    pub fn pre(&self) -> f64 {
+        self.pre_secs
+    }
+
+    /// Seconds to capture after the event.
+    pub fn post(&self) -> f64 {
+        self.post_secs
+    }
+
+    /// Total clip length in seconds.
+    pub fn total(&self) -> f64 {
+        self.pre_secs + self.post_secs
+    }
+}
+
+/// Unchecked wire form of a [`CaptureWindow`].
+#[derive(Deserialize)]
+struct RawCaptureWindow {
+    pre_secs: f64,
+    post_secs: f64,
+}
+
+impl TryFrom<RawCaptureWindow> for CaptureWindow {
+    type Error = GamepackError;
+
+    fn try_from(raw: RawCaptureWindow) -> GamepackResult<Self> {
+        Self::new(raw.pre_secs, raw.post_secs)
+    }
 }
 
 // ============================================================================
@@ -52,9 +317,15 @@ pub struct GameEvent {
     /// Event type identifier (e.g., "ChampionKill", "DragonKill")
     pub event_type: String,
 
-    /// Timestamp in seconds from game start
+    /// Timestamp in seconds from game start. Drives clip timing. Negative
+    /// before the match starts (loading, countdown).
     pub timestamp_secs: f64,
 
+    /// Authoritative server time of the event, in seconds; see
+    /// [`GameEvent::with_server_time`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_time_secs: Option<f64>,
+
     /// Game-specific event data
     pub data: serde_json::Value,
 
@@ -65,6 +336,15 @@ pub struct GameEvent {
     /// Seconds to capture after the event (overrides default)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub post_capture_secs: Option<f64>,
+
+    /// Clip group hint; see [`GameEvent::with_clip_group`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clip_group: Option<String>,
+
+    /// How sure the pack is the event happened; see
+    /// [`GameEvent::with_confidence`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
 }
 
 impl GameEvent {
@@ -73,12 +353,47 @@ impl GameEvent {
         Self {
             event_type: event_type.into(),
             timestamp_secs,
+            server_time_secs: None,
             data,
             pre_capture_secs: None,
             post_capture_secs: None,
+            clip_group: None,
+            confidence: None,
         }
     }
 
+    /// Fail if the timestamp or a capture time is NaN or infinite.
+    pub(crate) fn check_finite(&self) -> serde_json::Result<()> {
+        ensure_finite("timestamp_secs", self.timestamp_secs)?;
+        if let Some(server) = self.server_time_secs {
+            ensure_finite("server_time_secs", server)?;
+        }
+        if let Some(pre) = self.pre_capture_secs {
+            ensure_finite("pre_capture_secs", pre)?;
+        }
+        if let Some(post) = self.post_capture_secs {
+            ensure_finite("post_capture_secs", post)?;
+        }
+        if let Some(confidence) = self.confidence {
+            ensure_finite("confidence", confidence.into())?;
+        }
+        Ok(())
+    }
+
+    /// Ask the daemon to record this event in one clip with others of the
+    /// same group.
+    ///
+    /// Items (events or moments) sharing a `clip_group` within a match whose
+    /// capture windows overlap or touch are merged into a single clip that
+    /// spans from the earliest pre-capture to the latest post-capture, e.g.
+    /// one clip for three kills in a row. This affects recording only; for
+    /// linking timeline entries after the fact use a moment's
+    /// `correlation_id`.
+    pub fn with_clip_group(mut self, group: impl Into<String>) -> Self {
+        self.clip_group = Some(group.into());
+        self
+    }
+
     /// Set custom pre-capture duration.
     pub fn with_pre_capture(mut self, secs: f64) -> Self {
         self.pre_capture_secs = Some(secs);
@@ -90,6 +405,190 @@ impl GameEvent {
         self.post_capture_secs = Some(secs);
         self
     }
+
+    /// Set both capture durations from a validated window.
+    pub fn with_window(mut self, window: CaptureWindow) -> Self {
+        self.pre_capture_secs = Some(window.pre());
+        self.post_capture_secs = Some(window.post());
+        self
+    }
+
+    /// Record the authoritative server time of the event alongside its
+    /// game time.
+    ///
+    /// For packs that ingest server or replay data, where the two clocks
+    /// drift apart (pauses, lag compensation, replays). The daemon keeps
+    /// using `timestamp_secs` for clip timing and the timeline; it only uses
+    /// the server time to reconcile with authoritative data ingested later.
+    pub fn with_server_time(mut self, secs: f64) -> Self {
+        self.server_time_secs = Some(secs);
+        self
+    }
+
+    /// Mark the event as detected with the given confidence, from `0.0`
+    /// (a guess) to `1.0` (certain).
+    ///
+    /// For packs that detect events heuristically, e.g. by reading the
+    /// screen, rather than from an official API. The daemon may weight
+    /// uncertain events, and may suppress triggers below a user-set
+    /// threshold. Events without a confidence are treated as certain.
+    ///
+    /// Returns an error with code `"invalid_confidence"` if `confidence` is
+    /// outside `0.0..=1.0` or NaN; see
+    /// [`with_clamped_confidence`](Self::with_clamped_confidence) for
+    /// detectors whose scores may overshoot.
+    pub fn with_confidence(mut self, confidence: f32) -> GamepackResult<Self> {
+        self.confidence = Some(check_confidence(confidence)?);
+        Ok(self)
+    }
+
+    /// [`with_confidence`](Self::with_confidence), clamping `confidence`
+    /// into `0.0..=1.0` instead of failing; NaN counts as `0.0`.
+    pub fn with_clamped_confidence(mut self, confidence: f32) -> Self {
+        self.confidence = Some(clamp_confidence(confidence));
+        self
+    }
+
+    /// Create a game event whose data is serialized from a typed payload.
+    ///
+    /// Fails with code `"data_encode_error"` if `data` cannot be represented
+    /// as JSON (e.g. a map with non-string keys).
+    pub fn with_typed_data<T: Serialize>(
+        event_type: impl Into<String>,
+        timestamp_secs: f64,
+        data: &T,
+    ) -> GamepackResult<Self> {
+        let data = serde_json::to_value(data).map_err(|e| {
+            GamepackError::with_code(
+                format!("Failed to encode event data: {}", e),
+                "data_encode_error",
+            )
+        })?;
+        Ok(Self::new(event_type, timestamp_secs, data))
+    }
+
+    /// Create an "objective taken" event with the standardized
+    /// [`ObjectiveEvent`] payload as its data.
+    pub fn objective(
+        event_type: impl Into<String>,
+        timestamp_secs: f64,
+        objective: ObjectiveEvent,
+    ) -> Self {
+        let data = serde_json::to_value(objective).expect("ObjectiveEvent is always valid JSON");
+        Self::new(event_type, timestamp_secs, data)
+    }
+
+    /// Parse the event's data into a typed payload.
+    ///
+    /// Fails with code `"data_decode_error"` if the data doesn't match `T`.
+    pub fn data_as<T: DeserializeOwned>(&self) -> GamepackResult<T> {
+        decode_data(&self.data)
+    }
+}
+
+/// Standardized `data` for objective/neutral events (dragons, towers, bomb
+/// plants, flag captures...).
+///
+/// Optional convention: packs may keep using raw data, but when an event's
+/// data has this shape the daemon can treat objectives from different games
+/// alike in cross-game stats. Serialized as
+/// `{"objective": "dragon", "team": "blue", "value": 1.0}`, with `team` and
+/// `value` omitted when unset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectiveEvent {
+    /// What was taken, lowercase snake_case (e.g. `"dragon"`, `"tower"`)
+    pub objective: String,
+    /// Team that took it, in the game's own terms (e.g. `"blue"`, `"attackers"`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    /// Game-specific magnitude (gold awarded, points, stacks...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+}
+
+impl ObjectiveEvent {
+    /// An objective with no team or value.
+    pub fn new(objective: impl Into<String>) -> Self {
+        Self {
+            objective: objective.into(),
+            team: None,
+            value: None,
+        }
+    }
+
+    /// Set the team that took the objective.
+    pub fn with_team(mut self, team: impl Into<String>) -> Self {
+        self.team = Some(team.into());
+        self
+    }
+
+    /// Set the objective's value.
+    pub fn with_value(mut self, value: f64) -> Self {
+        self.value = Some(value);
+        self
+    }
+}
+
+/// Deserialize a `data` payload, mapping failures to `"data_decode_error"`.
+fn decode_data<T: DeserializeOwned>(data: &serde_json::Value) -> GamepackResult<T> {
+    T::deserialize(data).map_err(|e| {
+        GamepackError::with_code(format!("Failed to decode data: {}", e), "data_decode_error")
+    })
+}
+
+/// The in-game identity of the player a pack is tracking.
+///
+/// The daemon attributes matches to the signed-in user; this lets it check
+/// that a match was played on the account the user expects (e.g. not on a
+/// sibling's account on the same PC). Every field is optional, so report
+/// what the game exposes and leave the rest `None`.
+///
+/// Privacy: the daemon treats `game_player_id` as an opaque token to
+/// compare for equality; it is not shown or sent anywhere. Prefer a stable
+/// id the game already issues (e.g. a PUUID) over anything you derive, and
+/// never put credentials or session tokens in it. `display_name` is shown
+/// to the user.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerIdentity {
+    /// Name shown in game (e.g. summoner name or Riot ID)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// Stable, opaque account id issued by the game (e.g. PUUID)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_player_id: Option<String>,
+    /// Game server region or shard (e.g. `"euw1"`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+impl PlayerIdentity {
+    /// Create an identity with nothing known yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the in-game display name.
+    pub fn with_display_name(mut self, name: impl Into<String>) -> Self {
+        self.display_name = Some(name.into());
+        self
+    }
+
+    /// Set the game's account id.
+    pub fn with_game_player_id(mut self, id: impl Into<String>) -> Self {
+        self.game_player_id = Some(id.into());
+        self
+    }
+
+    /// Set the region or shard.
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Whether no field is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 /// Response from the `init` command.
@@ -101,9 +600,116 @@ pub struct InitResponse {
     pub slug: String,
     /// Protocol version this pack implements
     pub protocol_version: u32,
+    /// Build version of the pack itself (semver), for logs and bug reports.
+    ///
+    /// Purely informational; the daemon never gates behavior on it. Use
+    /// [`pack_version!`](crate::pack_version) to fill it from your crate's
+    /// `Cargo.toml`.
+    pub pack_version: Option<String>,
+    /// Version or patch of the game itself (e.g. `"14.3"` for League), if
+    /// already known at init. The format is game-defined; the daemon treats
+    /// it as an opaque string.
+    pub game_version: Option<String>,
+    /// Player signed in to the game, if already known at init
+    pub player: Option<PlayerIdentity>,
+}
+
+impl InitResponse {
+    /// Create an init response for the current [`PROTOCOL_VERSION`](crate::PROTOCOL_VERSION),
+    /// without a pack version.
+    pub fn new(game_id: i32, slug: impl Into<String>) -> Self {
+        Self {
+            game_id,
+            slug: slug.into(),
+            protocol_version: crate::PROTOCOL_VERSION,
+            pack_version: None,
+            game_version: None,
+            player: None,
+        }
+    }
+
+    /// Set the pack's build version.
+    pub fn with_pack_version(mut self, version: impl Into<String>) -> Self {
+        self.pack_version = Some(version.into());
+        self
+    }
+
+    /// Set the game's version or patch.
+    pub fn with_game_version(mut self, version: impl Into<String>) -> Self {
+        self.game_version = Some(version.into());
+        self
+    }
+
+    /// Set the player signed in to the game.
+    pub fn with_player(mut self, player: PlayerIdentity) -> Self {
+        self.player = Some(player);
+        self
+    }
+}
+
+/// How the pack binary was built, returned by `get_build_info`.
+///
+/// Informational only, for support triage: the daemon logs it and never
+/// changes behavior based on it. [`BuildInfo::runtime`] describes the build
+/// this runtime was compiled into, which is the pack's own build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// Build version of the pack; the runner fills it from
+    /// [`InitResponse::pack_version`] when left unset
+    pub pack_version: Option<String>,
+    /// Target triple, e.g. `"x86_64-pc-windows-msvc"`
+    pub target_triple: String,
+    /// Cargo profile, e.g. `"release"`
+    pub profile: String,
+    /// Enabled cargo features: this runtime's, plus any the pack adds
+    pub features: Vec<String>,
+    /// Compiler that built it, e.g. `"rustc 1.80.0 (051478957 2024-07-21)"`
+    pub rustc_version: String,
+}
+
+impl BuildInfo {
+    /// Build info recorded when this runtime was compiled, without a pack
+    /// version.
+    pub fn runtime() -> Self {
+        let mut features = Vec::new();
+        if cfg!(feature = "binary-timeline") {
+            features.push("binary-timeline".to_string());
+        }
+        Self {
+            pack_version: None,
+            target_triple: env!("GAMEPACK_BUILD_TARGET").to_string(),
+            profile: env!("GAMEPACK_BUILD_PROFILE").to_string(),
+            features,
+            rustc_version: env!("GAMEPACK_BUILD_RUSTC").to_string(),
+        }
+    }
+
+    /// Set the pack's build version.
+    pub fn with_pack_version(mut self, version: impl Into<String>) -> Self {
+        self.pack_version = Some(version.into());
+        self
+    }
+
+    /// Add features enabled in the pack's own crate.
+    pub fn with_features(mut self, features: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.features.extend(features.into_iter().map(Into::into));
+        self
+    }
 }
 
 /// Current game status returned by `get_status`.
+///
+/// Named constructors for the common states:
+///
+/// | Constructor          | `connected` | `game_phase`    | `is_in_game` |
+/// |----------------------|-------------|-----------------|--------------|
+/// | `disconnected()`     | `false`     | `None`          | `false`      |
+/// | `client_connected()` | `true`      | `None`          | `false`      |
+/// | `in_lobby()`         | `true`      | `"Lobby"`       | `false`      |
+/// | `in_champ_select()`  | `true`      | `"ChampSelect"` | `false`      |
+/// | `in_match(phase)`    | `true`      | `phase`         | `true`       |
+///
+/// Champion select counts as out of game: no clips are captured yet.
 #[derive(Debug, Clone, Default)]
 pub struct GameStatus {
     /// Whether connected to the game's API/client
@@ -114,6 +720,11 @@ pub struct GameStatus {
     pub game_phase: Option<String>,
     /// Whether the player is actively in a game
     pub is_in_game: bool,
+    /// Version or patch of the running game (game-defined format), once
+    /// the client reports it
+    pub game_version: Option<String>,
+    /// Player signed in to the game client, once known
+    pub player: Option<PlayerIdentity>,
 }
 
 impl GameStatus {
@@ -124,6 +735,8 @@ impl GameStatus {
             connection_status: "Not connected".to_string(),
             game_phase: None,
             is_in_game: false,
+            game_version: None,
+            player: None,
         }
     }
 
@@ -134,9 +747,31 @@ impl GameStatus {
             connection_status: status.into(),
             game_phase: None,
             is_in_game: false,
+            game_version: None,
+            player: None,
         }
     }
 
+    /// Client is up, phase unknown or not applicable.
+    pub fn client_connected() -> Self {
+        Self::connected("Connected")
+    }
+
+    /// Client is up, player in a lobby or menus.
+    pub fn in_lobby() -> Self {
+        Self::client_connected().with_phase("Lobby")
+    }
+
+    /// Client is up, player picking characters before a match.
+    pub fn in_champ_select() -> Self {
+        Self::client_connected().with_phase("ChampSelect")
+    }
+
+    /// Player is in an active match, in the given phase (e.g. `"InProgress"`).
+    pub fn in_match(phase: impl Into<String>) -> Self {
+        Self::client_connected().with_phase(phase).in_game(true)
+    }
+
     /// Set the game phase.
     pub fn with_phase(mut self, phase: impl Into<String>) -> Self {
         self.game_phase = Some(phase.into());
@@ -148,6 +783,18 @@ impl GameStatus {
         self.is_in_game = in_game;
         self
     }
+
+    /// Set the game's version or patch.
+    pub fn with_game_version(mut self, version: impl Into<String>) -> Self {
+        self.game_version = Some(version.into());
+        self
+    }
+
+    /// Set the player signed in to the game client.
+    pub fn with_player(mut self, player: PlayerIdentity) -> Self {
+        self.player = Some(player);
+        self
+    }
 }
 
 /// Match data returned when a game session ends.
@@ -157,14 +804,25 @@ pub struct MatchData {
     pub game_slug: String,
     /// Game ID
     pub game_id: i32,
-    /// Match result ("win", "loss", "remake")
+    /// Normalized match result ("win", "loss", "draw", "remake"), used for
+    /// filtering. See [`MatchResult`].
     pub result: String,
+    /// Raw game-specific outcome for display (e.g. "surrender_at_15",
+    /// "sudden_death_win")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome_detail: Option<String>,
+    /// Player the match was played as, so the daemon can check it belongs
+    /// to the signed-in user's account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player: Option<PlayerIdentity>,
     /// Game-specific match details
     pub details: serde_json::Value,
 }
 
 impl MatchData {
     /// Create new match data.
+    ///
+    /// `result` accepts a [`MatchResult`] or a plain string.
     pub fn new(
         game_slug: impl Into<String>,
         game_id: i32,
@@ -175,9 +833,121 @@ impl MatchData {
             game_slug: game_slug.into(),
             game_id,
             result: result.into(),
+            outcome_detail: None,
+            player: None,
             details,
         }
     }
+
+    /// Set the raw game-specific outcome shown to the user.
+    pub fn with_outcome_detail(mut self, detail: impl Into<String>) -> Self {
+        self.outcome_detail = Some(detail.into());
+        self
+    }
+
+    /// Set the player the match was played as.
+    pub fn with_player(mut self, player: PlayerIdentity) -> Self {
+        self.player = Some(player);
+        self
+    }
+
+    /// The result as a [`MatchResult`], if it is one of the normalized values.
+    pub fn match_result(&self) -> Option<MatchResult> {
+        self.result.parse().ok()
+    }
+}
+
+// ============================================================================
+// CLIP PLANNING
+// ============================================================================
+
+/// One clip covering one or more events, from [`merge_capture_windows`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipPlan {
+    /// Clip start, in seconds of game time (negative only for clips of
+    /// pre-game events)
+    pub start_secs: f64,
+    /// Clip end, in seconds of game time
+    pub end_secs: f64,
+    /// Indices into the input slice of the events in this clip, ascending
+    pub event_indices: Vec<usize>,
+}
+
+/// Plan clips for `events` by merging overlapping capture windows.
+///
+/// For packs that cut their own clips instead of leaving it to the daemon.
+/// Each event covers `[timestamp - pre, timestamp + post]`, using its
+/// `pre_capture_secs`/`post_capture_secs` when set and the `(pre, post)`
+/// `defaults` otherwise. Windows of events at or after match start are
+/// clamped to start at 0, so they don't reach back into the countdown;
+/// events with a negative timestamp keep their full window.
+///
+/// Merge rule: two events end up in the same clip when they have the same
+/// `clip_group` (ungrouped events form one group of their own) and their
+/// windows overlap or touch, directly or through a chain of such events.
+/// Events in different groups are never merged, even when they overlap.
+///
+/// Plans are ordered by start, then end, then first event index, so equal
+/// input always yields the same output regardless of event order.
+pub fn merge_capture_windows(events: &[GameEvent], defaults: (f64, f64)) -> Vec<ClipPlan> {
+    let mut groups: HashMap<Option<&str>, Vec<(f64, f64, usize)>> = HashMap::new();
+    for (index, event) in events.iter().enumerate() {
+        let pre = event.pre_capture_secs.unwrap_or(defaults.0);
+        let post = event.post_capture_secs.unwrap_or(defaults.1);
+        let mut start = event.timestamp_secs - pre;
+        if event.timestamp_secs >= 0.0 {
+            start = start.max(0.0);
+        }
+        let end = event.timestamp_secs + post;
+        groups
+            .entry(event.clip_group.as_deref())
+            .or_default()
+            .push((start, end, index));
+    }
+
+    let mut plans: Vec<ClipPlan> = Vec::new();
+    for mut windows in groups.into_values() {
+        windows.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.2.cmp(&b.2)));
+        let mut current: Option<ClipPlan> = None;
+        for (start, end, index) in windows {
+            match &mut current {
+                Some(plan) if start <= plan.end_secs => {
+                    plan.end_secs = plan.end_secs.max(end);
+                    plan.event_indices.push(index);
+                }
+                _ => {
+                    plans.extend(current.take());
+                    current = Some(ClipPlan {
+                        start_secs: start,
+                        end_secs: end,
+                        event_indices: vec![index],
+                    });
+                }
+            }
+        }
+        plans.extend(current);
+    }
+
+    for plan in &mut plans {
+        plan.event_indices.sort_unstable();
+    }
+    plans.sort_by(|a, b| {
+        a.start_secs
+            .total_cmp(&b.start_secs)
+            .then(a.end_secs.total_cmp(&b.end_secs))
+            .then(a.event_indices[0].cmp(&b.event_indices[0]))
+    });
+    plans
+}
+
+/// Sort `events` by `timestamp_secs`, earliest first.
+///
+/// The sort is stable: events with the same timestamp keep their relative
+/// order. The runner applies this to `poll_events` output unless
+/// [`RunnerOptions::sort_events`](crate::RunnerOptions::sort_events) is
+/// turned off, since the daemon times clips assuming chronological order.
+pub fn sort_events_chronological(events: &mut [GameEvent]) {
+    events.sort_by(|a, b| a.timestamp_secs.total_cmp(&b.timestamp_secs));
 }
 
 // ============================================================================
@@ -189,14 +959,45 @@ impl MatchData {
 /// Moments are distinct from events - they represent things worth recording,
 /// not just things that happened. The daemon checks trigger configuration
 /// to decide whether to actually record.
+///
+/// A moment that evolves over time (e.g. a killstreak growing from double to
+/// penta) can be upgraded in place: give the first emission a
+/// `correlation_id`, then emit the upgraded moment with `supersedes` set to
+/// that id. The daemon replaces the superseded timeline entry instead of
+/// appending a new one.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Moment {
     /// Moment ID (must match a moment defined in config.json or will be auto-registered)
     pub moment_id: String,
-    /// In-game timestamp in seconds
+    /// In-game timestamp in seconds (negative before match start)
     pub game_time_secs: f64,
     /// Moment-specific data (context for the clip)
     pub data: serde_json::Value,
+    /// Pack-assigned identity of this moment instance, referenced by `supersedes`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// `correlation_id` of a previously emitted moment this one replaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supersedes: Option<String>,
+    /// Clip group hint; see [`Moment::with_clip_group`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clip_group: Option<String>,
+    /// Template for the clip's title; see [`Moment::with_title_template`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_template: Option<String>,
+    /// Template for the clip's description, same syntax as the title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description_template: Option<String>,
+    /// Shortest clip this moment may produce; see [`Moment::with_clip_bounds`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_clip_secs: Option<f64>,
+    /// Longest clip this moment may produce; see [`Moment::with_clip_bounds`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_clip_secs: Option<f64>,
+    /// How sure the pack is the moment happened; see
+    /// [`Moment::with_confidence`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
 }
 
 impl Moment {
@@ -206,46 +1007,715 @@ impl Moment {
             moment_id: moment_id.into(),
             game_time_secs,
             data,
+            correlation_id: None,
+            supersedes: None,
+            clip_group: None,
+            title_template: None,
+            description_template: None,
+            min_clip_secs: None,
+            max_clip_secs: None,
+            confidence: None,
         }
     }
-}
 
-// ============================================================================
-// MATCH DATA MESSAGES (Subpack Model)
-// ============================================================================
+    /// Fail if the game time, a clip bound or the confidence is NaN or
+    /// infinite.
+    pub(crate) fn check_finite(&self) -> serde_json::Result<()> {
+        ensure_finite("game_time_secs", self.game_time_secs)?;
+        let bounds = [("min_clip_secs", self.min_clip_secs), ("max_clip_secs", self.max_clip_secs)];
+        for (name, secs) in bounds {
+            secs.map_or(Ok(()), |secs| ensure_finite(name, secs))?;
+        }
+        if let Some(confidence) = self.confidence {
+            ensure_finite("confidence", confidence.into())?;
+        }
+        Ok(())
+    }
 
-/// Gamepack → Daemon: Write match data.
-///
-/// These are the three unsolicited messages a gamepack can send to the daemon
-/// during gameplay, plus SetComplete to mark matches finished.
-///
-/// **Data Flow:**
-/// - `WriteStatistics` → Timeline (delta) + Summary (UPSERT)
-/// - `WriteGameEvents` → Timeline (events)
-/// - `WriteMoments` → Timeline (moments) + Trigger check
-/// - `SetComplete` → Mark `is_in_progress=0`
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
-pub enum MatchDataMessage {
-    /// Write statistics to timeline (delta compressed) AND summary table (UPSERT).
+    /// Bound the length of the clip this moment triggers, e.g. at most 30
+    /// seconds for a slow siege however wide its capture window is.
     ///
-    /// This is the primary way to emit polled game state during gameplay.
-    /// The daemon will:
-    /// 1. Create match row if it doesn't exist (lazy creation)
-    /// 2. Store to timeline with delta compression (only changed fields)
-    /// 3. UPSERT to summary table (`p{guid}_{subpack}_match_details`)
-    WriteStatistics {
-        /// Subpack index (0 = default, 1+ = additional subpacks)
-        subpack: u8,
-        /// Game's native match ID (used for deduplication and API lookups)
-        external_match_id: String,
-        /// When the match started (ISO 8601) - only needed on first write
-        #[serde(skip_serializing_if = "Option::is_none")]
-        played_at: Option<String>,
-        /// In-game timestamp in seconds
-        game_time_secs: f64,
-        /// Stats to write (keys must match columns declared in subpack's schema)
-        stats: HashMap<String, serde_json::Value>,
+    /// The daemon first cuts the clip from the trigger's pre- and
+    /// post-capture times (and merges it with its clip group, if any), then
+    /// clamps the result to these bounds: a longer clip is trimmed and a
+    /// shorter one extended, always keeping the moment itself in the clip.
+    /// `None` leaves that side unbounded.
+    ///
+    /// Returns an error with code `"invalid_clip_bounds"` if a bound is not
+    /// a positive, finite number of seconds, or if `min_secs` exceeds
+    /// `max_secs`. [`with_min_clip_secs`](Self::with_min_clip_secs) and
+    /// [`with_max_clip_secs`](Self::with_max_clip_secs) set one side without
+    /// checking.
+    pub fn with_clip_bounds(
+        mut self,
+        min_secs: Option<f64>,
+        max_secs: Option<f64>,
+    ) -> GamepackResult<Self> {
+        for (name, secs) in [("min", min_secs), ("max", max_secs)] {
+            if secs.is_some_and(|secs| !(secs.is_finite() && secs > 0.0)) {
+                return Err(GamepackError::with_code(
+                    format!("{}_clip_secs must be a positive number, got {:?}", name, secs),
+                    "invalid_clip_bounds",
+                ));
+            }
+        }
+        if let (Some(min), Some(max)) = (min_secs, max_secs) {
+            if min > max {
+                return Err(GamepackError::with_code(
+                    format!("min_clip_secs {} exceeds max_clip_secs {}", min, max),
+                    "invalid_clip_bounds",
+                ));
+            }
+        }
+        self.min_clip_secs = min_secs;
+        self.max_clip_secs = max_secs;
+        Ok(self)
+    }
+
+    /// Set the shortest clip length, unchecked; see
+    /// [`with_clip_bounds`](Self::with_clip_bounds).
+    pub fn with_min_clip_secs(mut self, secs: f64) -> Self {
+        self.min_clip_secs = Some(secs);
+        self
+    }
+
+    /// Set the longest clip length, unchecked; see
+    /// [`with_clip_bounds`](Self::with_clip_bounds).
+    pub fn with_max_clip_secs(mut self, secs: f64) -> Self {
+        self.max_clip_secs = Some(secs);
+        self
+    }
+
+    /// Mark the moment as detected with the given confidence; see
+    /// [`GameEvent::with_confidence`]. The daemon may skip recording a
+    /// moment below the user's confidence threshold.
+    ///
+    /// Returns an error with code `"invalid_confidence"` if `confidence` is
+    /// outside `0.0..=1.0` or NaN.
+    pub fn with_confidence(mut self, confidence: f32) -> GamepackResult<Self> {
+        self.confidence = Some(check_confidence(confidence)?);
+        Ok(self)
+    }
+
+    /// [`with_confidence`](Self::with_confidence), clamping `confidence`
+    /// into `0.0..=1.0` instead of failing; NaN counts as `0.0`.
+    pub fn with_clamped_confidence(mut self, confidence: f32) -> Self {
+        self.confidence = Some(clamp_confidence(confidence));
+        self
+    }
+
+    /// Set the correlation ID identifying this moment instance.
+    pub fn with_correlation_id(mut self, id: impl Into<String>) -> Self {
+        self.correlation_id = Some(id.into());
+        self
+    }
+
+    /// Mark this moment as replacing a previously emitted one.
+    pub fn with_supersedes(mut self, correlation_id: impl Into<String>) -> Self {
+        self.supersedes = Some(correlation_id.into());
+        self
+    }
+
+    /// Ask the daemon to record this moment in one clip with others of the
+    /// same group. Same merge rules as [`GameEvent::with_clip_group`].
+    ///
+    /// Unlike `correlation_id`, which links timeline entries after the fact
+    /// (for `supersedes`), `clip_group` only influences how clips are cut.
+    pub fn with_clip_group(mut self, group: impl Into<String>) -> Self {
+        self.clip_group = Some(group.into());
+        self
+    }
+
+    /// Name the clip this moment triggers, e.g. `"{player} pentakill"`.
+    ///
+    /// The daemon fills the `{placeholder}`s from `data` with
+    /// [`render_template`](crate::template::render_template) (see the
+    /// [`template`](crate::template) module for the syntax); without a
+    /// template it names the clip after the moment id.
+    pub fn with_title_template(mut self, template: impl Into<String>) -> Self {
+        self.title_template = Some(template.into());
+        self
+    }
+
+    /// Describe the clip this moment triggers; rendered like the
+    /// [title](Self::with_title_template).
+    pub fn with_description_template(mut self, template: impl Into<String>) -> Self {
+        self.description_template = Some(template.into());
+        self
+    }
+
+    /// The title the daemon will give the clip, if the moment has a
+    /// title template.
+    pub fn render_title(&self) -> Option<String> {
+        let template = self.title_template.as_deref()?;
+        Some(crate::template::render_template(template, &self.data))
+    }
+
+    /// The description the daemon will give the clip, if the moment has a
+    /// description template.
+    pub fn render_description(&self) -> Option<String> {
+        let template = self.description_template.as_deref()?;
+        Some(crate::template::render_template(template, &self.data))
+    }
+
+    /// Parse the moment's data into a typed payload.
+    ///
+    /// Fails with code `"data_decode_error"` if the data doesn't match `T`.
+    pub fn data_as<T: DeserializeOwned>(&self) -> GamepackResult<T> {
+        decode_data(&self.data)
+    }
+}
+
+// ============================================================================
+// STAT VALUES
+// ============================================================================
+
+/// A single statistic value.
+///
+/// Serializes to the plain JSON scalar (`3`, `2.5`, `"Jungle"`, `true`), so it
+/// is wire-compatible with untyped `serde_json::Value` stats.
+///
+/// The daemon aggregates stats per key across the timeline, so **emit each key
+/// with the same variant every time**. A key that is `Int(3)` in one message
+/// and `Float(3.5)` in the next may aggregate incorrectly. If a stat can be
+/// fractional, always build it from an `f64`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StatValue {
+    /// Whole number (kills, gold, CS)
+    Int(i64),
+    /// Fractional number (KDA ratio, damage per minute)
+    Float(f64),
+    /// Free-form text (champion, role)
+    Text(String),
+    /// Flag (first blood, MVP)
+    Bool(bool),
+}
+
+impl StatValue {
+    /// Numeric value as `f64`, for summing/averaging.
+    ///
+    /// Returns `None` for `Text` and `Bool`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Int(n) => Some(*n as f64),
+            Self::Float(f) => Some(*f),
+            Self::Text(_) | Self::Bool(_) => None,
+        }
+    }
+
+    /// Numeric value as `i64`, only if no information is lost.
+    ///
+    /// Floats convert only when finite, whole, and within `i64` range, so
+    /// `3.0` yields `Some(3)` but `3.5` yields `None`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Int(n) => Some(*n),
+            Self::Float(f) => {
+                // i64::MAX as f64 rounds up to 2^63, so the upper bound is exclusive
+                let in_range = *f >= i64::MIN as f64 && *f < i64::MAX as f64;
+                (f.fract() == 0.0 && in_range).then_some(*f as i64)
+            }
+            Self::Text(_) | Self::Bool(_) => None,
+        }
+    }
+}
+
+macro_rules! stat_value_from {
+    ($variant:ident: $($ty:ty),+) => {
+        $(
+            impl From<$ty> for StatValue {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value.into())
+                }
+            }
+        )+
+    };
+}
+
+stat_value_from!(Int: i64, i32, i16, i8, u32, u16, u8);
+stat_value_from!(Float: f64, f32);
+stat_value_from!(Text: String, &str);
+stat_value_from!(Bool: bool);
+
+impl From<StatValue> for serde_json::Value {
+    fn from(value: StatValue) -> Self {
+        match value {
+            StatValue::Int(n) => n.into(),
+            StatValue::Float(f) => f.into(),
+            StatValue::Text(s) => s.into(),
+            StatValue::Bool(b) => b.into(),
+        }
+    }
+}
+
+// ============================================================================
+// SCOREBOARD
+// ============================================================================
+
+/// Live data key a [`Scoreboard`] is stored under.
+pub const SCOREBOARD_KEY: &str = "scoreboard";
+
+/// Standardized team and player scoreboard for live data.
+///
+/// Optional convention, like [`ObjectiveEvent`]: packs for team games may
+/// put one in their live data under [`SCOREBOARD_KEY`] (see
+/// [`insert_into`](Self::insert_into)), and the daemon can then show the
+/// scoreboard without knowing the game. The rest of the live data stays
+/// pack-specific. Serialized as:
+///
+/// ```json
+/// {
+///   "teams": [{"team_id": "blue", "name": "Blue Side", "score": 12, "is_local": true}],
+///   "players": [{"name": "Faker", "team_id": "blue", "kills": 5, "deaths": 1,
+///                "assists": 7, "is_local": true, "champion": "Ahri"}]
+/// }
+/// ```
+///
+/// Only `team_id` (teams) and `name` (players) are required; the other
+/// well-known fields are omitted when unset and `is_local` when false. Any
+/// further per-team or per-player fields go in `extra` and are serialized
+/// inline next to the well-known ones, like `champion` above.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scoreboard {
+    /// Teams, in display order
+    #[serde(default)]
+    pub teams: Vec<Team>,
+    /// Players, in display order
+    #[serde(default)]
+    pub players: Vec<PlayerRow>,
+}
+
+/// One team on a [`Scoreboard`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Team {
+    /// Team identifier in the game's own terms (e.g. `"blue"`, `"attackers"`),
+    /// referenced by [`PlayerRow::team_id`]
+    pub team_id: String,
+    /// Display name, if different from the id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Team score (kills, rounds, points...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<i64>,
+    /// Whether the tracked player is on this team
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub is_local: bool,
+    /// Game-specific fields, serialized inline
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// One player on a [`Scoreboard`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerRow {
+    /// Name shown in game
+    pub name: String,
+    /// [`Team::team_id`] of the player's team
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team_id: Option<String>,
+    /// Kills
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kills: Option<u32>,
+    /// Deaths
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deaths: Option<u32>,
+    /// Assists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assists: Option<u32>,
+    /// Individual score, for games that have one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<i64>,
+    /// Whether this is the tracked player
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub is_local: bool,
+    /// Game-specific fields, serialized inline
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+pub(crate) fn is_false(value: &bool) -> bool {
+    !value
+}
+
+thread_local! {
+    /// Whether map fields serialize in sorted key order on this thread; see
+    /// [`with_sorted_maps`].
+    static SORT_MAPS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `serialize` with `HashMap` fields (statistics, final stats) writing
+/// their keys in sorted order, so equal maps produce identical text.
+pub(crate) fn with_sorted_maps<T>(serialize: impl FnOnce() -> T) -> T {
+    let previous = SORT_MAPS.with(|sort| sort.replace(true));
+    let result = serialize();
+    SORT_MAPS.with(|sort| sort.set(previous));
+    result
+}
+
+/// `serialize_with` for `HashMap` fields: iteration order, or sorted inside
+/// [`with_sorted_maps`].
+fn serialize_map<S: Serializer, V: Serialize>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if SORT_MAPS.with(Cell::get) {
+        serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+    } else {
+        map.serialize(serializer)
+    }
+}
+
+/// [`serialize_map`] for an optional map; pair with `skip_serializing_if`.
+fn serialize_optional_map<S: Serializer, V: Serialize>(
+    map: &Option<HashMap<String, V>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match map {
+        Some(map) => serialize_map(map, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl Scoreboard {
+    /// An empty scoreboard.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a team.
+    pub fn with_team(mut self, team: Team) -> Self {
+        self.teams.push(team);
+        self
+    }
+
+    /// Add a player.
+    pub fn with_player(mut self, player: PlayerRow) -> Self {
+        self.players.push(player);
+        self
+    }
+
+    /// Players on the team with `team_id`, in display order.
+    pub fn players_on<'a>(&'a self, team_id: &'a str) -> impl Iterator<Item = &'a PlayerRow> {
+        self.players
+            .iter()
+            .filter(move |p| p.team_id.as_deref() == Some(team_id))
+    }
+
+    /// Parse a scoreboard from its JSON value.
+    ///
+    /// Fails with code `"data_decode_error"` if the value doesn't match the
+    /// schema.
+    pub fn from_value(value: &serde_json::Value) -> GamepackResult<Self> {
+        decode_data(value)
+    }
+
+    /// The scoreboard in `live_data`, if it has one.
+    ///
+    /// `Ok(None)` when there is no [`SCOREBOARD_KEY`]; fails with code
+    /// `"data_decode_error"` if the key holds something else.
+    pub fn from_live_data(live_data: &serde_json::Value) -> GamepackResult<Option<Self>> {
+        live_data
+            .get(SCOREBOARD_KEY)
+            .map(Self::from_value)
+            .transpose()
+    }
+
+    /// Store the scoreboard in `live_data` under [`SCOREBOARD_KEY`],
+    /// replacing any previous one.
+    ///
+    /// Live data that is not a JSON object (e.g. `null` before the pack has
+    /// anything else to report) is replaced by an object holding just the
+    /// scoreboard.
+    pub fn insert_into(&self, live_data: &mut serde_json::Value) {
+        if !live_data.is_object() {
+            *live_data = serde_json::Value::Object(serde_json::Map::new());
+        }
+        if let serde_json::Value::Object(map) = live_data {
+            map.insert(SCOREBOARD_KEY.to_string(), self.clone().into());
+        }
+    }
+}
+
+impl From<Scoreboard> for serde_json::Value {
+    fn from(scoreboard: Scoreboard) -> Self {
+        serde_json::to_value(scoreboard).expect("Scoreboard is always valid JSON")
+    }
+}
+
+impl Team {
+    /// A team with only its id set.
+    pub fn new(team_id: impl Into<String>) -> Self {
+        Self {
+            team_id: team_id.into(),
+            name: None,
+            score: None,
+            is_local: false,
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    /// Set the display name.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the team score.
+    pub fn with_score(mut self, score: i64) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    /// Mark this as the tracked player's team.
+    pub fn local(mut self) -> Self {
+        self.is_local = true;
+        self
+    }
+
+    /// Add a game-specific field.
+    pub fn with_extra(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+}
+
+impl PlayerRow {
+    /// A player with only a name set.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            team_id: None,
+            kills: None,
+            deaths: None,
+            assists: None,
+            score: None,
+            is_local: false,
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    /// Set the player's team.
+    pub fn with_team(mut self, team_id: impl Into<String>) -> Self {
+        self.team_id = Some(team_id.into());
+        self
+    }
+
+    /// Set kills, deaths and assists.
+    pub fn with_kda(mut self, kills: u32, deaths: u32, assists: u32) -> Self {
+        self.kills = Some(kills);
+        self.deaths = Some(deaths);
+        self.assists = Some(assists);
+        self
+    }
+
+    /// Set the individual score.
+    pub fn with_score(mut self, score: i64) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    /// Mark this as the tracked player.
+    pub fn local(mut self) -> Self {
+        self.is_local = true;
+        self
+    }
+
+    /// Add a game-specific field.
+    pub fn with_extra(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+}
+
+// ============================================================================
+// NUMBER NORMALIZATION
+// ============================================================================
+
+/// Largest integer every JSON parser reads exactly (2^53 - 1).
+///
+/// Parsers that store numbers as IEEE-754 doubles, including serde_json
+/// without `arbitrary_precision`, round integers beyond this.
+pub const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// Rewrite numbers in `value` (recursively) so that any JSON parser reads
+/// them the same way, and return how many were changed.
+///
+/// A pack built with serde_json's `arbitrary_precision` feature writes
+/// numbers exactly as stored, which a daemon built without it may round or
+/// fail to parse. After normalization every number is either an integer
+/// within ±[`MAX_SAFE_INTEGER`] or a finite `f64`:
+///
+/// | Input                                         | Output                  |
+/// |-----------------------------------------------|-------------------------|
+/// | Integer within ±`MAX_SAFE_INTEGER`            | unchanged               |
+/// | Finite float                                  | unchanged               |
+/// | Larger integer (e.g. `u64::MAX`)              | nearest `f64`           |
+/// | Beyond `f64` range (`arbitrary_precision`)    | `f64::MAX` (or `MIN`)   |
+///
+/// Large integers are rounded, not clamped, so magnitudes survive; send ids
+/// and hashes as strings if every digit matters.
+pub fn normalize_numbers(value: &mut serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Number(number) => {
+            let safe = |n: i64| (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&n);
+            let exact_integer = number.as_i64().is_some_and(safe);
+            let finite_float = number.is_f64() && number.as_f64().is_some_and(f64::is_finite);
+            match number.as_f64() {
+                Some(float) if !exact_integer && !finite_float => {
+                    *value = serde_json::Value::from(float.clamp(f64::MIN, f64::MAX));
+                    1
+                }
+                _ => 0,
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().map(normalize_numbers).sum(),
+        serde_json::Value::Object(fields) => fields.values_mut().map(normalize_numbers).sum(),
+        _ => 0,
+    }
+}
+
+// ============================================================================
+// MATCH IDS
+// ============================================================================
+
+/// A game's native match id, checked to be non-empty.
+///
+/// The daemon keys match rows by `external_match_id`, so an empty or
+/// whitespace-only id creates a nameless row that collects every write
+/// with such an id. Some game APIs return exactly that before a match has
+/// been assigned its id. Building a `MatchId` catches it in the pack; the
+/// [`MatchDataMessage`] constructors accept one wherever they take an id.
+///
+/// Serializes as the plain string; deserializing applies the same check as
+/// [`MatchId::new`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MatchId(String);
+
+impl MatchId {
+    /// Create a match id.
+    ///
+    /// Returns an error with code `"invalid_match_id"` if `id` is empty or
+    /// only whitespace. Other ids are kept exactly as given.
+    pub fn new(id: impl Into<String>) -> GamepackResult<Self> {
+        let id = id.into();
+        check_match_id(&id)?;
+        Ok(Self(id))
+    }
+
+    /// The id as sent to the daemon.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for MatchId {
+    type Error = GamepackError;
+
+    fn try_from(id: String) -> GamepackResult<Self> {
+        Self::new(id)
+    }
+}
+
+impl From<MatchId> for String {
+    fn from(id: MatchId) -> Self {
+        id.0
+    }
+}
+
+impl AsRef<str> for MatchId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for MatchId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Fail with `"invalid_match_id"` if `id` is empty or only whitespace.
+fn check_match_id(id: &str) -> GamepackResult<()> {
+    if id.trim().is_empty() {
+        return Err(GamepackError::with_code(
+            format!("external_match_id must not be empty or blank, got {:?}", id),
+            "invalid_match_id",
+        ));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// MATCH DATA MESSAGES (Subpack Model)
+// ============================================================================
+
+/// Gamepack → Daemon: Write match data.
+///
+/// These are the three unsolicited messages a gamepack can send to the daemon
+/// during gameplay, plus SetComplete to mark matches finished.
+///
+/// **Data Flow:**
+/// - `WriteStatistics` → Timeline (delta) + Summary (UPSERT)
+/// - `WriteGameEvents` → Timeline (events)
+/// - `WriteMoments` → Timeline (moments) + Trigger check
+/// - `SetComplete` → Mark `is_in_progress=0`
+///
+/// **Player Slots:** In team games a pack may track several players.
+/// `WriteStatistics` and `WriteGameEvents` take an optional `player_slot`;
+/// the daemon stores per-player rows keyed by `(match, player_slot)` within
+/// the subpack's tables. Slot 0 is the tracked user, and a message without a
+/// slot is treated as slot 0.
+///
+/// **Match IDs:** `external_match_id` must not be empty or only whitespace.
+/// The constructors take any string; pass a [`MatchId`] to have it checked
+/// up front, or call [`check_match_id`](Self::check_match_id) on the
+/// message.
+#[derive(Debug, Clone, Serialize, Deserialize, strum::IntoStaticStr)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MatchDataMessage {
+    /// Write statistics to timeline (delta compressed) AND summary table (UPSERT).
+    ///
+    /// This is the primary way to emit polled game state during gameplay.
+    /// The daemon will:
+    /// 1. Create match row if it doesn't exist (lazy creation)
+    /// 2. Store to timeline with delta compression (only changed fields)
+    /// 3. UPSERT to summary table (`p{guid}_{subpack}_match_details`)
+    ///
+    /// The map is partial: a key that is absent leaves the field as it
+    /// was, and a key set to `null` clears it. Send only what changed, and
+    /// `null` for a value that no longer applies (say a respawn timer once
+    /// the player is alive). See [`cleared_stats`](Self::cleared_stats).
+    WriteStatistics {
+        /// Subpack index (0 = default, 1+ = additional subpacks)
+        subpack: u8,
+        /// Game's native match ID (used for deduplication and API lookups)
+        external_match_id: String,
+        /// When the match started (ISO 8601) - only needed on first write
+        #[serde(skip_serializing_if = "Option::is_none")]
+        played_at: Option<String>,
+        /// Player the stats belong to (`None` = slot 0, the tracked user)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        player_slot: Option<u8>,
+        /// In-game timestamp in seconds, negative before match start. Drives
+        /// the timeline and clip timing.
+        game_time_secs: f64,
+        /// Authoritative server time in seconds, if the pack has it; see
+        /// [`MatchDataMessage::with_server_time`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        server_time_secs: Option<f64>,
+        /// Stats to write (keys must match columns declared in subpack's schema)
+        #[serde(serialize_with = "serialize_map")]
+        stats: HashMap<String, serde_json::Value>,
     },
 
     /// Write game events to timeline.
@@ -257,6 +1727,9 @@ pub enum MatchDataMessage {
         subpack: u8,
         /// Game's native match ID
         external_match_id: String,
+        /// Player the events belong to (`None` = slot 0, the tracked user)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        player_slot: Option<u8>,
         /// Events to append
         events: Vec<GameEvent>,
     },
@@ -268,6 +1741,10 @@ pub enum MatchDataMessage {
     /// 1. Store to timeline with entry_type='moment'
     /// 2. Check trigger configuration for each moment
     /// 3. Fire recording if trigger is enabled
+    ///
+    /// A moment with `supersedes` set replaces the timeline entry whose
+    /// `correlation_id` matches (within the same match) rather than being
+    /// appended. If no such entry exists, it is appended as usual.
     WriteMoments {
         /// Subpack index (0 = default, 1+ = additional subpacks)
         subpack: u8,
@@ -290,12 +1767,179 @@ pub enum MatchDataMessage {
         /// Where the final stats came from
         summary_source: SummarySource,
         /// Optional final stats to overwrite summary table
-        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_optional_map")]
         final_stats: Option<HashMap<String, serde_json::Value>>,
     },
+
+    /// Mark match as ended but incomplete (sets is_in_progress=0).
+    ///
+    /// Send this instead of `SetComplete` when the match stopped without a
+    /// result, e.g. the player quit the game or was disconnected and never
+    /// rejoined. The daemon tags the match abandoned rather than finished.
+    ///
+    /// `SetComplete` with [`SummarySource::LiveFallback`] is different: the
+    /// match did finish, only the official results were unavailable, so the
+    /// live stats stand in for them.
+    SetAbandoned {
+        /// Subpack index (0 = default, 1+ = additional subpacks)
+        subpack: u8,
+        /// Game's native match ID
+        external_match_id: String,
+        /// In-game time the match was abandoned at, in seconds
+        at_game_time_secs: f64,
+    },
 }
 
 impl MatchDataMessage {
+    /// One-line human-readable summary for logs.
+    ///
+    /// For example `"WriteStatistics[subpack=0, match=abc, 3 fields @ 120.5s]"`.
+    /// This is for diagnostics only and does not affect the wire format.
+    pub fn describe(&self) -> String {
+        let name: &'static str = self.into();
+        let details = match self {
+            Self::WriteStatistics {
+                subpack,
+                external_match_id,
+                player_slot,
+                game_time_secs,
+                stats,
+                ..
+            } => format!(
+                "subpack={}, match={}{}, {} @ {}s",
+                subpack,
+                external_match_id,
+                describe_slot(*player_slot),
+                count(stats.len(), "field", "fields"),
+                game_time_secs
+            ),
+            Self::WriteGameEvents {
+                subpack,
+                external_match_id,
+                player_slot,
+                events,
+            } => format!(
+                "subpack={}, match={}{}, {}",
+                subpack,
+                external_match_id,
+                describe_slot(*player_slot),
+                count(events.len(), "event", "events")
+            ),
+            Self::WriteMoments {
+                subpack,
+                external_match_id,
+                moments,
+            } => format!(
+                "subpack={}, match={}, {}",
+                subpack,
+                external_match_id,
+                count(moments.len(), "moment", "moments")
+            ),
+            Self::SetComplete {
+                subpack,
+                external_match_id,
+                summary_source,
+                final_stats,
+            } => match final_stats {
+                Some(stats) => format!(
+                    "subpack={}, match={}, source={}, {}",
+                    subpack,
+                    external_match_id,
+                    summary_source,
+                    count(stats.len(), "final stat", "final stats")
+                ),
+                None => format!(
+                    "subpack={}, match={}, source={}",
+                    subpack, external_match_id, summary_source
+                ),
+            },
+            Self::SetAbandoned {
+                subpack,
+                external_match_id,
+                at_game_time_secs,
+            } => format!(
+                "subpack={}, match={} @ {}s",
+                subpack, external_match_id, at_game_time_secs
+            ),
+        };
+        format!("{}[{}]", name, details)
+    }
+
+    /// Serialize with sorted keys, for golden files and hashing; not the
+    /// wire format. See [`debug::canonical_json`](crate::debug::canonical_json).
+    pub fn to_canonical_json(&self) -> String {
+        crate::debug::canonical_json(self)
+    }
+
+    /// Player slot the message targets (0, the tracked user, when unset).
+    ///
+    /// Always 0 for `WriteMoments`, `SetComplete` and `SetAbandoned`.
+    pub fn player_slot(&self) -> u8 {
+        match self {
+            Self::WriteStatistics { player_slot, .. }
+            | Self::WriteGameEvents { player_slot, .. } => player_slot.unwrap_or(0),
+            Self::WriteMoments { .. } | Self::SetComplete { .. } | Self::SetAbandoned { .. } => 0,
+        }
+    }
+
+    /// Stats a `WriteStatistics` clears, i.e. keys set to `null`, sorted.
+    /// Empty for other messages.
+    pub fn cleared_stats(&self) -> Vec<&str> {
+        let Self::WriteStatistics { stats, .. } = self else {
+            return vec![];
+        };
+        let mut cleared: Vec<&str> = stats
+            .iter()
+            .filter(|(_, value)| value.is_null())
+            .map(|(key, _)| key.as_str())
+            .collect();
+        cleared.sort_unstable();
+        cleared
+    }
+
+    /// Whether the message ends its match (`SetComplete` or `SetAbandoned`).
+    pub fn ends_match(&self) -> bool {
+        matches!(self, Self::SetComplete { .. } | Self::SetAbandoned { .. })
+    }
+
+    /// Subpack index the message targets.
+    pub fn subpack(&self) -> u8 {
+        match self {
+            Self::WriteStatistics { subpack, .. }
+            | Self::WriteGameEvents { subpack, .. }
+            | Self::WriteMoments { subpack, .. }
+            | Self::SetComplete { subpack, .. }
+            | Self::SetAbandoned { subpack, .. } => *subpack,
+        }
+    }
+
+    /// Game's native match ID the message targets.
+    pub fn external_match_id(&self) -> &str {
+        match self {
+            Self::WriteStatistics {
+                external_match_id, ..
+            }
+            | Self::WriteGameEvents {
+                external_match_id, ..
+            }
+            | Self::WriteMoments {
+                external_match_id, ..
+            }
+            | Self::SetComplete {
+                external_match_id, ..
+            }
+            | Self::SetAbandoned {
+                external_match_id, ..
+            } => external_match_id,
+        }
+    }
+
+    /// Fail with code `"invalid_match_id"` if the message's
+    /// `external_match_id` is empty or only whitespace; see [`MatchId`].
+    pub fn check_match_id(&self) -> GamepackResult<()> {
+        check_match_id(self.external_match_id())
+    }
+
     /// Create a WriteStatistics message.
     pub fn write_statistics(
         subpack: u8,
@@ -307,7 +1951,28 @@ impl MatchDataMessage {
             subpack,
             external_match_id: external_match_id.into(),
             played_at: None,
+            player_slot: None,
+            game_time_secs,
+            server_time_secs: None,
+            stats,
+        }
+    }
+
+    /// Create a WriteStatistics message for a specific player slot.
+    pub fn write_player_statistics(
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        player_slot: u8,
+        game_time_secs: f64,
+        stats: HashMap<String, serde_json::Value>,
+    ) -> Self {
+        Self::WriteStatistics {
+            subpack,
+            external_match_id: external_match_id.into(),
+            played_at: None,
+            player_slot: Some(player_slot),
             game_time_secs,
+            server_time_secs: None,
             stats,
         }
     }
@@ -324,11 +1989,43 @@ impl MatchDataMessage {
             subpack,
             external_match_id: external_match_id.into(),
             played_at: Some(played_at.into()),
+            player_slot: None,
             game_time_secs,
+            server_time_secs: None,
             stats,
         }
     }
 
+    /// Record the authoritative server time of a `WriteStatistics` snapshot
+    /// alongside its game time. No effect on other messages.
+    ///
+    /// As with [`GameEvent::with_server_time`], `game_time_secs` still
+    /// drives the timeline and clip timing; the daemon uses the server time
+    /// only to reconcile with authoritative data ingested later.
+    pub fn with_server_time(mut self, secs: f64) -> Self {
+        if let Self::WriteStatistics {
+            server_time_secs, ..
+        } = &mut self
+        {
+            *server_time_secs = Some(secs);
+        }
+        self
+    }
+
+    /// Create a WriteStatistics message from typed [`StatValue`]s.
+    ///
+    /// Produces the same wire format as [`write_statistics`](Self::write_statistics);
+    /// the typed map just makes it harder to mix integer and float values for a key.
+    pub fn write_typed_statistics(
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        game_time_secs: f64,
+        stats: HashMap<String, StatValue>,
+    ) -> Self {
+        let stats = stats.into_iter().map(|(k, v)| (k, v.into())).collect();
+        Self::write_statistics(subpack, external_match_id, game_time_secs, stats)
+    }
+
     /// Create a WriteGameEvents message.
     pub fn write_game_events(
         subpack: u8,
@@ -338,6 +2035,22 @@ impl MatchDataMessage {
         Self::WriteGameEvents {
             subpack,
             external_match_id: external_match_id.into(),
+            player_slot: None,
+            events,
+        }
+    }
+
+    /// Create a WriteGameEvents message for a specific player slot.
+    pub fn write_player_game_events(
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        player_slot: u8,
+        events: Vec<GameEvent>,
+    ) -> Self {
+        Self::WriteGameEvents {
+            subpack,
+            external_match_id: external_match_id.into(),
+            player_slot: Some(player_slot),
             events,
         }
     }
@@ -383,6 +2096,103 @@ impl MatchDataMessage {
             final_stats: Some(final_stats),
         }
     }
+
+    /// Create a SetAbandoned message.
+    pub fn set_abandoned(
+        subpack: u8,
+        external_match_id: impl Into<String>,
+        at_game_time_secs: f64,
+    ) -> Self {
+        Self::SetAbandoned {
+            subpack,
+            external_match_id: external_match_id.into(),
+            at_game_time_secs,
+        }
+    }
+
+    /// Fail if a time in the message is NaN or infinite.
+    ///
+    /// serde_json writes non-finite floats as `null`, which the daemon
+    /// rejects, so the message would be lost without an error. Stat values
+    /// cannot be non-finite: `serde_json::Value` has no NaN.
+    pub(crate) fn check_finite(&self) -> serde_json::Result<()> {
+        match self {
+            Self::WriteStatistics {
+                game_time_secs,
+                server_time_secs,
+                ..
+            } => {
+                ensure_finite("game_time_secs", *game_time_secs)?;
+                server_time_secs.map_or(Ok(()), |secs| ensure_finite("server_time_secs", secs))
+            }
+            Self::WriteGameEvents { events, .. } => {
+                events.iter().try_for_each(GameEvent::check_finite)
+            }
+            Self::WriteMoments { moments, .. } => moments.iter().try_for_each(Moment::check_finite),
+            Self::SetAbandoned {
+                at_game_time_secs, ..
+            } => ensure_finite("at_game_time_secs", *at_game_time_secs),
+            Self::SetComplete { .. } => Ok(()),
+        }
+    }
+
+    /// Apply [`normalize_numbers`] to every payload in the message (stats,
+    /// event and moment data, final stats) and return how many numbers
+    /// changed.
+    pub fn normalize_numbers(&mut self) -> usize {
+        match self {
+            Self::WriteStatistics { stats, .. }
+            | Self::SetComplete {
+                final_stats: Some(stats),
+                ..
+            } => stats.values_mut().map(normalize_numbers).sum(),
+            Self::WriteGameEvents { events, .. } => {
+                events.iter_mut().map(|event| normalize_numbers(&mut event.data)).sum()
+            }
+            Self::WriteMoments { moments, .. } => {
+                moments.iter_mut().map(|moment| normalize_numbers(&mut moment.data)).sum()
+            }
+            Self::SetComplete { .. } | Self::SetAbandoned { .. } => 0,
+        }
+    }
+}
+
+/// Serialization error for a float JSON cannot represent.
+pub(crate) fn ensure_finite(field: &str, value: f64) -> serde_json::Result<()> {
+    if value.is_finite() {
+        return Ok(());
+    }
+    Err(serde::ser::Error::custom(format!("{} is {}, which JSON cannot represent", field, value)))
+}
+
+/// `confidence` if it is in `0.0..=1.0`.
+fn check_confidence(confidence: f32) -> GamepackResult<f32> {
+    if (0.0..=1.0).contains(&confidence) {
+        return Ok(confidence);
+    }
+    Err(GamepackError::with_code(
+        format!("confidence must be between 0 and 1, got {}", confidence),
+        "invalid_confidence",
+    ))
+}
+
+/// `confidence` forced into `0.0..=1.0`, with NaN as `0.0`.
+fn clamp_confidence(confidence: f32) -> f32 {
+    if confidence.is_nan() {
+        0.0
+    } else {
+        confidence.clamp(0.0, 1.0)
+    }
+}
+
+/// Format a count with the matching noun form (e.g. "1 field", "3 fields").
+pub(crate) fn count(n: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", n, if n == 1 { singular } else { plural })
+}
+
+/// `", player=N"` for an explicit player slot, empty otherwise.
+fn describe_slot(player_slot: Option<u8>) -> String {
+    player_slot.map_or_else(String::new, |slot| format!(", player={}", slot))
 }
 
 // ============================================================================
@@ -437,6 +2247,42 @@ impl IsMatchInProgressResponse {
     }
 }
 
+/// Gamepack → Daemon: One finished match in a `RecentMatches` response.
+///
+/// Lets the daemon backfill history it missed (e.g. matches finished while
+/// it was down) without a full timeline per match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchSummary {
+    /// Game's native match ID
+    pub external_match_id: String,
+    /// Normalized match result ("win", "loss", "draw", "remake"). See
+    /// [`MatchResult`].
+    pub result: String,
+    /// When the match was played
+    pub played_at: Timestamp,
+    /// Game-specific summary (same shape as `MatchData::details`)
+    pub summary: serde_json::Value,
+}
+
+impl MatchSummary {
+    /// Create a match summary.
+    ///
+    /// `result` accepts a [`MatchResult`] or a plain string.
+    pub fn new(
+        external_match_id: impl Into<String>,
+        result: impl Into<String>,
+        played_at: Timestamp,
+        summary: serde_json::Value,
+    ) -> Self {
+        Self {
+            external_match_id: external_match_id.into(),
+            result: result.into(),
+            played_at,
+            summary,
+        }
+    }
+}
+
 // ============================================================================
 // TIMELINE DATA
 // ============================================================================
@@ -451,7 +2297,7 @@ pub struct TimelineEntry {
     pub entry_type: EntryType,
     /// Entry key: event type, "stats", or moment ID
     pub entry_key: String,
-    /// In-game timestamp in seconds
+    /// In-game timestamp in seconds (negative before match start)
     pub game_time_secs: f64,
     /// Wall clock time (ISO 8601)
     pub captured_at: String,
@@ -463,6 +2309,72 @@ pub struct TimelineEntry {
 }
 
 impl TimelineEntry {
+    /// Create an event entry from an emitted [`GameEvent`], stamping
+    /// `captured_at` from `clock`.
+    pub fn from_event(event: &GameEvent, clock: &dyn Clock) -> Self {
+        Self::event(
+            event.event_type.clone(),
+            event.timestamp_secs,
+            clock.now().to_string(),
+            event.data.clone(),
+        )
+    }
+
+    /// Create a moment entry from an emitted [`Moment`], stamping
+    /// `captured_at` from `clock`.
+    ///
+    /// `trigger_fired` is left unset: whether a recording fired is decided
+    /// by the daemon, not the pack.
+    pub fn from_moment(moment: &Moment, clock: &dyn Clock) -> Self {
+        Self {
+            entry_type: EntryType::Moment,
+            entry_key: moment.moment_id.clone(),
+            game_time_secs: moment.game_time_secs,
+            captured_at: clock.now().to_string(),
+            data: moment.data.clone(),
+            trigger_fired: None,
+        }
+    }
+
+    /// Create moment entries for a batch of [`Moment`]s once the daemon has
+    /// reported which of them triggered a recording, all stamped with the
+    /// same `captured_at` from `clock`.
+    ///
+    /// `fired` is keyed by the moment's `correlation_id` when it has one,
+    /// since that names the instance, and by its `moment_id` otherwise. A
+    /// moment with no result in `fired` is recorded as not having fired.
+    pub fn moments_from_results(
+        moments: &[Moment],
+        fired: &HashMap<String, bool>,
+        clock: &dyn Clock,
+    ) -> Vec<Self> {
+        let captured_at = clock.now().to_string();
+        moments
+            .iter()
+            .map(|moment| {
+                let key = moment.correlation_id.as_ref().unwrap_or(&moment.moment_id);
+                Self::moment(
+                    moment.moment_id.clone(),
+                    moment.game_time_secs,
+                    captured_at.clone(),
+                    moment.data.clone(),
+                    fired.get(key).copied().unwrap_or(false),
+                )
+            })
+            .collect()
+    }
+
+    /// Create a statistic entry from a stats map (as passed to
+    /// `WriteStatistics`), stamping `captured_at` from `clock`.
+    pub fn from_stats(
+        game_time_secs: f64,
+        stats: &HashMap<String, serde_json::Value>,
+        clock: &dyn Clock,
+    ) -> Self {
+        let fields = stats.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        Self::statistic(game_time_secs, clock.now().to_string(), serde_json::Value::Object(fields))
+    }
+
     /// Create an event entry.
     pub fn event(
         event_type: impl Into<String>,
@@ -480,72 +2392,492 @@ impl TimelineEntry {
         }
     }
 
-    /// Create a statistic entry (delta).
-    pub fn statistic(
-        game_time_secs: f64,
-        captured_at: impl Into<String>,
-        changed_fields: serde_json::Value,
-    ) -> Self {
+    /// Create a statistic entry (delta).
+    pub fn statistic(
+        game_time_secs: f64,
+        captured_at: impl Into<String>,
+        changed_fields: serde_json::Value,
+    ) -> Self {
+        Self {
+            entry_type: EntryType::Statistic,
+            entry_key: "stats".to_string(),
+            game_time_secs,
+            captured_at: captured_at.into(),
+            data: changed_fields,
+            trigger_fired: None,
+        }
+    }
+
+    /// Create a moment entry.
+    pub fn moment(
+        moment_id: impl Into<String>,
+        game_time_secs: f64,
+        captured_at: impl Into<String>,
+        data: serde_json::Value,
+        trigger_fired: bool,
+    ) -> Self {
+        Self {
+            entry_type: EntryType::Moment,
+            entry_key: moment_id.into(),
+            game_time_secs,
+            captured_at: captured_at.into(),
+            data,
+            trigger_fired: Some(trigger_fired),
+        }
+    }
+}
+
+/// Daemon → Gamepack: Request match timeline data.
+///
+/// Used for recovery when a gamepack needs to reconstruct match state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetMatchTimelineRequest {
+    /// Subpack index
+    pub subpack: u8,
+    /// Game's native match ID
+    pub external_match_id: String,
+    /// Filter by entry types (None = all types)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry_types: Option<Vec<String>>,
+    /// Max entries to return (latest N, applied after the time range)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Only entries at or after this game time (seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_secs: Option<f64>,
+    /// Only entries at or before this game time (seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_secs: Option<f64>,
+}
+
+/// Daemon → Gamepack: Response with match timeline data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetMatchTimelineResponse {
+    /// Whether the match was found
+    pub found: bool,
+    /// Timeline entries (empty if not found)
+    pub entries: Vec<TimelineEntry>,
+}
+
+/// Length of a match in seconds of game time, from its timeline.
+///
+/// Defined as the latest `game_time_secs` minus the earliest, over all
+/// entries of every type, in any order. A single entry gives `0.0`; no
+/// entries give `None`. Wall-clock `captured_at` is not used, so pauses
+/// and lag don't count.
+///
+/// With `include_pregame` false, entries before match start (negative game
+/// time, see the [module docs](self)) are left out, so the duration is
+/// measured from the first in-match entry; if every entry is pre-game the
+/// result is `None`. With it true, the countdown counts towards the length.
+pub fn match_duration_secs(entries: &[TimelineEntry], include_pregame: bool) -> Option<f64> {
+    let mut times = entries
+        .iter()
+        .map(|e| e.game_time_secs)
+        .filter(|t| include_pregame || *t >= 0.0);
+    let first = times.next()?;
+    let (min, max) = times.fold((first, first), |(min, max), t| (min.min(t), max.max(t)));
+    Some(max - min)
+}
+
+// ============================================================================
+// BINARY TIMELINE ENCODING
+// ============================================================================
+
+/// Version byte written at the start of every [`encode_timeline`] buffer.
+#[cfg(feature = "binary-timeline")]
+pub const TIMELINE_FORMAT_VERSION: u8 = 1;
+
+/// Entry type byte for [`EntryType::Unknown`].
+#[cfg(feature = "binary-timeline")]
+const UNKNOWN_ENTRY_TYPE: u8 = 255;
+
+/// Encode timeline entries into a compact binary buffer.
+///
+/// An alternative to JSON for large recovery transfers; JSON stays the
+/// default on the wire. All integers are little-endian and strings are a
+/// `u32` byte length followed by UTF-8:
+///
+/// ```text
+/// u8   format version (TIMELINE_FORMAT_VERSION)
+/// u32  key count, then each distinct entry_key as a string
+/// u32  entry count, then per entry:
+///      u8   entry type (0 = event, 1 = statistic, 2 = moment, 255 = unknown)
+///      u8   trigger_fired (0 = absent, 1 = false, 2 = true)
+///      u32  index into the key table
+///      f64  game_time_secs
+///      str  captured_at
+///      str  data as compact JSON
+/// ```
+#[cfg(feature = "binary-timeline")]
+pub fn encode_timeline(entries: &[TimelineEntry]) -> Vec<u8> {
+    fn put_len(buf: &mut Vec<u8>, len: usize) {
+        let len = u32::try_from(len).expect("timeline batch exceeds u32::MAX items");
+        buf.extend_from_slice(&len.to_le_bytes());
+    }
+    fn put_str(buf: &mut Vec<u8>, s: &str) {
+        put_len(buf, s.len());
+        buf.extend_from_slice(s.as_bytes());
+    }
+
+    let mut keys: Vec<&str> = Vec::new();
+    let mut key_index: HashMap<&str, usize> = HashMap::new();
+    for entry in entries {
+        key_index.entry(&entry.entry_key).or_insert_with(|| {
+            keys.push(&entry.entry_key);
+            keys.len() - 1
+        });
+    }
+
+    let mut buf = vec![TIMELINE_FORMAT_VERSION];
+    put_len(&mut buf, keys.len());
+    for key in &keys {
+        put_str(&mut buf, key);
+    }
+
+    put_len(&mut buf, entries.len());
+    for entry in entries {
+        buf.push(match entry.entry_type {
+            EntryType::Event => 0,
+            EntryType::Statistic => 1,
+            EntryType::Moment => 2,
+            EntryType::Unknown => UNKNOWN_ENTRY_TYPE,
+        });
+        buf.push(match entry.trigger_fired {
+            None => 0,
+            Some(false) => 1,
+            Some(true) => 2,
+        });
+        put_len(&mut buf, key_index[entry.entry_key.as_str()]);
+        buf.extend_from_slice(&entry.game_time_secs.to_le_bytes());
+        put_str(&mut buf, &entry.captured_at);
+        put_str(&mut buf, &entry.data.to_string());
+    }
+    buf
+}
+
+/// Decode a buffer produced by [`encode_timeline`].
+///
+/// Fails with code `"invalid_timeline_encoding"` on an unknown format
+/// version, truncated input, or malformed contents.
+#[cfg(feature = "binary-timeline")]
+pub fn decode_timeline(bytes: &[u8]) -> GamepackResult<Vec<TimelineEntry>> {
+    struct Reader<'a> {
+        bytes: &'a [u8],
+    }
+
+    impl<'a> Reader<'a> {
+        fn take(&mut self, n: usize) -> GamepackResult<&'a [u8]> {
+            if self.bytes.len() < n {
+                return Err(invalid("unexpected end of input"));
+            }
+            let (head, rest) = self.bytes.split_at(n);
+            self.bytes = rest;
+            Ok(head)
+        }
+        fn u8(&mut self) -> GamepackResult<u8> {
+            Ok(self.take(1)?[0])
+        }
+        fn len(&mut self) -> GamepackResult<usize> {
+            let bytes = self.take(4)?.try_into().expect("took 4 bytes");
+            Ok(u32::from_le_bytes(bytes) as usize)
+        }
+        fn f64(&mut self) -> GamepackResult<f64> {
+            let bytes = self.take(8)?.try_into().expect("took 8 bytes");
+            Ok(f64::from_le_bytes(bytes))
+        }
+        fn str(&mut self) -> GamepackResult<&'a str> {
+            let len = self.len()?;
+            std::str::from_utf8(self.take(len)?).map_err(|e| invalid(e.to_string()))
+        }
+    }
+
+    fn invalid(detail: impl std::fmt::Display) -> GamepackError {
+        GamepackError::with_code(
+            format!("Invalid timeline encoding: {}", detail),
+            "invalid_timeline_encoding",
+        )
+    }
+
+    let mut reader = Reader { bytes };
+    let version = reader.u8()?;
+    if version != TIMELINE_FORMAT_VERSION {
+        return Err(invalid(format!("unsupported format version {}", version)));
+    }
+
+    let key_count = reader.len()?;
+    let keys = (0..key_count)
+        .map(|_| reader.str())
+        .collect::<GamepackResult<Vec<_>>>()?;
+
+    let entry_count = reader.len()?;
+    // Every entry takes at least 22 bytes, so a bogus count fails here
+    // instead of attempting a huge allocation.
+    if entry_count > reader.bytes.len() / 22 {
+        return Err(invalid("unexpected end of input"));
+    }
+    let mut entries = Vec::with_capacity(entry_count);
+    for _ in 0..entry_count {
+        let entry_type = match reader.u8()? {
+            0 => EntryType::Event,
+            1 => EntryType::Statistic,
+            2 => EntryType::Moment,
+            UNKNOWN_ENTRY_TYPE => EntryType::Unknown,
+            other => return Err(invalid(format!("unknown entry type {}", other))),
+        };
+        let trigger_fired = match reader.u8()? {
+            0 => None,
+            1 => Some(false),
+            2 => Some(true),
+            other => return Err(invalid(format!("unknown trigger flag {}", other))),
+        };
+        let key = reader.len()?;
+        let entry_key = keys
+            .get(key)
+            .ok_or_else(|| invalid(format!("key index {} out of range", key)))?;
+        let game_time_secs = reader.f64()?;
+        let captured_at = reader.str()?.to_string();
+        let data = serde_json::from_str(reader.str()?).map_err(invalid)?;
+
+        entries.push(TimelineEntry {
+            entry_type,
+            entry_key: entry_key.to_string(),
+            game_time_secs,
+            captured_at,
+            data,
+            trigger_fired,
+        });
+    }
+
+    if !reader.bytes.is_empty() {
+        return Err(invalid("trailing bytes"));
+    }
+    Ok(entries)
+}
+
+// ============================================================================
+// HEALTH
+// ============================================================================
+
+/// Details of an error a gamepack encountered, kept for health reporting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorInfo {
+    /// Human-readable error message
+    pub message: String,
+    /// Optional error code for programmatic handling
+    pub code: Option<String>,
+    /// When the error occurred
+    pub at: Timestamp,
+}
+
+impl ErrorInfo {
+    /// Create error info stamped with the current time.
+    pub fn now(message: impl Into<String>, code: Option<String>) -> Self {
+        Self {
+            message: message.into(),
+            code,
+            at: Timestamp::now(),
+        }
+    }
+}
+
+// ============================================================================
+// STORAGE
+// ============================================================================
+
+/// The daemon's clip storage situation, pushed via `StorageStatus`.
+///
+/// Advisory: packs may use it to stop emitting low-priority moments while
+/// the budget is exhausted, but the daemon enforces its own limits either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageStatus {
+    /// Free space available for clips, in bytes
+    pub free_bytes: u64,
+    /// Number of clips currently stored
+    pub clip_count: u32,
+    /// Whether the clip budget is used up (new clips may be refused)
+    pub budget_exhausted: bool,
+}
+
+// ============================================================================
+// TRIGGER CONFIG
+// ============================================================================
+
+/// One trigger in a user's proposed trigger configuration.
+///
+/// Sent by the daemon in `ValidateTriggerConfig`. Unset capture times mean
+/// the pack's defaults for the moment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriggerConfig {
+    /// Moment that fires the trigger
+    pub moment_id: String,
+    /// Seconds to capture before the moment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_capture_secs: Option<f64>,
+    /// Seconds to capture after the moment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_capture_secs: Option<f64>,
+}
+
+impl TriggerConfig {
+    /// Create a trigger using the default capture window.
+    pub fn new(moment_id: impl Into<String>) -> Self {
+        Self {
+            moment_id: moment_id.into(),
+            pre_capture_secs: None,
+            post_capture_secs: None,
+        }
+    }
+
+    /// Set the capture window.
+    pub fn with_window(mut self, pre_secs: f64, post_secs: f64) -> Self {
+        self.pre_capture_secs = Some(pre_secs);
+        self.post_capture_secs = Some(post_secs);
+        self
+    }
+
+    /// Problems with this trigger for a pack defining `known_moment_ids`.
+    ///
+    /// Capture times are held to the same bounds as [`CaptureWindow::new`].
+    pub fn issues(&self, known_moment_ids: &[&str]) -> Vec<TriggerIssue> {
+        let mut issues = Vec::new();
+        if !known_moment_ids.contains(&self.moment_id.as_str()) {
+            issues.push(TriggerIssue::UnknownMomentId {
+                moment_id: self.moment_id.clone(),
+            });
+        }
+        let (pre, post) = (self.pre_capture_secs, self.post_capture_secs);
+        if let Err(e) = CaptureWindow::new(pre.unwrap_or(0.0), post.unwrap_or(0.0)) {
+            issues.push(TriggerIssue::WindowOutOfRange {
+                moment_id: self.moment_id.clone(),
+                reason: e.message,
+            });
+        }
+        issues
+    }
+}
+
+/// A problem the pack found in a proposed trigger configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TriggerIssue {
+    /// The pack defines no moment with this id
+    UnknownMomentId { moment_id: String },
+    /// A capture time is negative, non-finite or too long
+    WindowOutOfRange {
+        moment_id: String,
+        /// Human-readable explanation, shown in the UI
+        reason: String,
+    },
+}
+
+impl TriggerIssue {
+    /// Moment id of the trigger the issue is about.
+    pub fn moment_id(&self) -> &str {
+        match self {
+            Self::UnknownMomentId { moment_id } => moment_id,
+            Self::WindowOutOfRange { moment_id, .. } => moment_id,
+        }
+    }
+}
+
+/// The pack's answer to `ShouldRecord`, returned by `should_record`.
+///
+/// The daemon's trigger configuration goes first: it only asks about
+/// moments that have an enabled trigger, so a pack cannot record a moment
+/// the user turned off. The pack can then veto the recording, or replace
+/// the trigger's capture window with its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordDecision {
+    /// Whether to record the moment
+    pub record: bool,
+    /// Why, for the daemon's logs (mostly useful when skipping)
+    pub reason: Option<String>,
+    /// Capture window to use instead of the trigger's configured one
+    pub window: Option<CaptureWindow>,
+}
+
+impl RecordDecision {
+    /// Record with the trigger's configured window.
+    pub fn record() -> Self {
         Self {
-            entry_type: EntryType::Statistic,
-            entry_key: "stats".to_string(),
-            game_time_secs,
-            captured_at: captured_at.into(),
-            data: changed_fields,
-            trigger_fired: None,
+            record: true,
+            reason: None,
+            window: None,
         }
     }
 
-    /// Create a moment entry.
-    pub fn moment(
-        moment_id: impl Into<String>,
-        game_time_secs: f64,
-        captured_at: impl Into<String>,
-        data: serde_json::Value,
-        trigger_fired: bool,
-    ) -> Self {
+    /// Don't record, explaining why.
+    pub fn skip(reason: impl Into<String>) -> Self {
         Self {
-            entry_type: EntryType::Moment,
-            entry_key: moment_id.into(),
-            game_time_secs,
-            captured_at: captured_at.into(),
-            data,
-            trigger_fired: Some(trigger_fired),
+            record: false,
+            reason: Some(reason.into()),
+            window: None,
         }
     }
+
+    /// Set the reason.
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Capture `window` instead of the trigger's configured window.
+    pub fn with_window(mut self, window: CaptureWindow) -> Self {
+        self.window = Some(window);
+        self
+    }
 }
 
-/// Daemon → Gamepack: Request match timeline data.
+impl Default for RecordDecision {
+    fn default() -> Self {
+        Self::record()
+    }
+}
+
+/// One of the pack's rules turning a game event into a moment, returned by
+/// `get_trigger_rules`.
 ///
-/// Used for recovery when a gamepack needs to reconstruct match state.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GetMatchTimelineRequest {
-    /// Subpack index
-    pub subpack: u8,
-    /// Game's native match ID
-    pub external_match_id: String,
-    /// Filter by entry types (None = all types)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub entry_types: Option<Vec<String>>,
-    /// Max entries to return (latest N)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub limit: Option<u32>,
+/// Lets the daemon's UI explain which events can lead to a clip. The rules
+/// are descriptive only: the pack still decides in code which moments to
+/// emit, and the daemon never evaluates them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriggerRule {
+    /// Event type the rule looks at, as in [`GameEvent::event_type`]
+    pub from_event: String,
+    /// Moment id the rule emits, as declared in config.json
+    pub produces_moment: String,
+    /// When the rule applies, e.g. `"5 kills within 10 seconds"`: a
+    /// human-readable description, not an expression. `None` means every
+    /// such event produces the moment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
 }
 
-/// Daemon → Gamepack: Response with match timeline data.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GetMatchTimelineResponse {
-    /// Whether the match was found
-    pub found: bool,
-    /// Timeline entries (empty if not found)
-    pub entries: Vec<TimelineEntry>,
+impl TriggerRule {
+    /// Create a rule that applies to every `from_event` event.
+    pub fn new(from_event: impl Into<String>, produces_moment: impl Into<String>) -> Self {
+        Self {
+            from_event: from_event.into(),
+            produces_moment: produces_moment.into(),
+            condition: None,
+        }
+    }
+
+    /// Describe when the rule applies.
+    pub fn with_condition(mut self, condition: impl Into<String>) -> Self {
+        self.condition = Some(condition.into());
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::FixedClock;
     use serde_json::json;
     use std::str::FromStr;
+    use test_case::test_case;
 
     // ========================================================================
     // EntryType Tests
@@ -606,6 +2938,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn entry_type_from_newer_version_is_unknown() {
+        let entry_type: EntryType = serde_json::from_str("\"highlight\"").unwrap();
+        assert_eq!(entry_type, EntryType::Unknown);
+        assert!(entry_type.is_unknown() && !EntryType::Event.is_unknown());
+        assert_eq!(entry_type.to_string(), "unknown");
+
+        let entry: TimelineEntry = serde_json::from_value(json!({
+            "entry_type": "highlight",
+            "entry_key": "ace",
+            "game_time_secs": 12.0,
+            "captured_at": "2024-01-15T10:30:00.000Z",
+            "data": {},
+        }))
+        .unwrap();
+        assert!(entry.entry_type.is_unknown());
+    }
+
     // ========================================================================
     // SummarySource Tests
     // ========================================================================
@@ -649,6 +2999,74 @@ mod tests {
         }
     }
 
+    #[test]
+    fn summary_source_from_newer_version_is_unknown() {
+        let message: MatchDataMessage = serde_json::from_value(json!({
+            "type": "set_complete",
+            "subpack": 0,
+            "external_match_id": "m1",
+            "summary_source": "replay_parser",
+        }))
+        .unwrap();
+
+        let MatchDataMessage::SetComplete { summary_source, .. } = message else {
+            panic!("Expected SetComplete, got {:?}", message);
+        };
+        assert_eq!(summary_source, SummarySource::Unknown);
+        assert!(summary_source.is_unknown() && !SummarySource::Api.is_unknown());
+    }
+
+    // ========================================================================
+    // MatchResult Tests
+    // ========================================================================
+
+    #[test_case(MatchResult::Win, "win")]
+    #[test_case(MatchResult::Loss, "loss")]
+    #[test_case(MatchResult::Draw, "draw")]
+    #[test_case(MatchResult::Remake, "remake")]
+    fn match_result_uses_snake_case(result: MatchResult, expected: &str) {
+        assert_eq!(result.to_string(), expected);
+        assert_eq!(
+            serde_json::to_string(&result).unwrap(),
+            format!("\"{}\"", expected)
+        );
+        assert_eq!(MatchResult::from_str(expected).unwrap(), result);
+    }
+
+    // ========================================================================
+    // Timestamp Tests
+    // ========================================================================
+
+    #[test]
+    fn timestamp_formats_unix_epoch() {
+        assert_eq!(
+            Timestamp::from_unix_millis(0).as_str(),
+            "1970-01-01T00:00:00.000Z"
+        );
+    }
+
+    #[test]
+    fn timestamp_formats_known_instant() {
+        assert_eq!(
+            Timestamp::from_unix_millis(1_705_314_600_123).as_str(),
+            "2024-01-15T10:30:00.123Z"
+        );
+        // Leap day
+        assert_eq!(
+            Timestamp::from_unix_millis(1_709_164_800_000).as_str(),
+            "2024-02-29T00:00:00.000Z"
+        );
+    }
+
+    #[test]
+    fn timestamp_serializes_as_plain_string() {
+        let ts = Timestamp::from_unix_millis(0);
+        assert_eq!(
+            serde_json::to_string(&ts).unwrap(),
+            "\"1970-01-01T00:00:00.000Z\""
+        );
+    }
+
     // ========================================================================
     // GameEvent Tests
     // ========================================================================
@@ -670,54 +3088,451 @@ mod tests {
             .with_pre_capture(15.0)
             .with_post_capture(10.0);
 
-        assert_eq!(event.pre_capture_secs, Some(15.0));
-        assert_eq!(event.post_capture_secs, Some(10.0));
+        assert_eq!(event.pre_capture_secs, Some(15.0));
+        assert_eq!(event.post_capture_secs, Some(10.0));
+    }
+
+    #[test]
+    fn game_event_serializes_correctly() {
+        let event = GameEvent::new("ChampionKill", 100.5, json!({"killer": "Player1"}));
+        let json = serde_json::to_string(&event).unwrap();
+
+        assert!(json.contains("\"event_type\":\"ChampionKill\""));
+        assert!(json.contains("\"timestamp_secs\":100.5"));
+        assert!(!json.contains("pre_capture_secs")); // None should be skipped
+    }
+
+    #[test]
+    fn game_event_round_trips() {
+        let event = GameEvent::new("ChampionKill", 100.5, json!({"killer": "Player1"}))
+            .with_pre_capture(10.0);
+        let json = serde_json::to_string(&event).unwrap();
+        let back: GameEvent = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(event.event_type, back.event_type);
+        assert!((event.timestamp_secs - back.timestamp_secs).abs() < 0.001);
+        assert_eq!(event.pre_capture_secs, back.pre_capture_secs);
+    }
+
+    #[test]
+    fn objective_event_has_standard_shape() {
+        let objective = ObjectiveEvent::new("dragon").with_team("blue").with_value(1.0);
+
+        let event = GameEvent::objective("DragonKill", 900.0, objective.clone());
+
+        assert_eq!(event.event_type, "DragonKill");
+        assert_eq!(event.data, json!({"objective": "dragon", "team": "blue", "value": 1.0}));
+        assert_eq!(event.data_as::<ObjectiveEvent>().unwrap(), objective);
+    }
+
+    #[test]
+    fn objective_event_omits_unset_fields() {
+        let event = GameEvent::objective("TowerKill", 600.0, ObjectiveEvent::new("tower"));
+
+        assert_eq!(event.data, json!({"objective": "tower"}));
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct KillData {
+        killer: String,
+        assists: Vec<String>,
+    }
+
+    #[test]
+    fn game_event_typed_data_round_trips() {
+        let kill = KillData {
+            killer: "Ahri".to_string(),
+            assists: vec!["Lee Sin".to_string()],
+        };
+
+        let event = GameEvent::with_typed_data("ChampionKill", 12.5, &kill).unwrap();
+
+        assert_eq!(event.data, json!({"killer": "Ahri", "assists": ["Lee Sin"]}));
+        assert_eq!(event.data_as::<KillData>().unwrap(), kill);
+    }
+
+    #[test]
+    fn game_event_typed_data_rejects_unencodable_payload() {
+        let data: HashMap<(u8, u8), u8> = [((1, 2), 3)].into();
+
+        let err = GameEvent::with_typed_data("Kill", 1.0, &data).unwrap_err();
+
+        assert_eq!(err.code.as_deref(), Some("data_encode_error"));
+    }
+
+    #[test]
+    fn data_as_maps_mismatch_to_decode_error() {
+        let event = GameEvent::new("ChampionKill", 12.5, json!({"killer": 7}));
+        let moment = Moment::new("pentakill", 30.0, json!(null));
+
+        for err in [
+            event.data_as::<KillData>().unwrap_err(),
+            moment.data_as::<KillData>().unwrap_err(),
+        ] {
+            assert_eq!(err.code.as_deref(), Some("data_decode_error"));
+        }
+    }
+
+    #[test]
+    fn moment_data_as_parses_payload() {
+        let moment = Moment::new("first_blood", 90.0, json!({"killer": "Jinx", "assists": []}));
+
+        let data: KillData = moment.data_as().unwrap();
+
+        assert_eq!(data.killer, "Jinx");
+        assert!(data.assists.is_empty());
+    }
+
+    // ========================================================================
+    // CaptureWindow Tests
+    // ========================================================================
+
+    #[test_case(0.0, 0.0 ; "zero")]
+    #[test_case(15.0, 10.0 ; "typical")]
+    #[test_case(MAX_CAPTURE_SECS, MAX_CAPTURE_SECS ; "at maximum")]
+    fn capture_window_accepts_valid(pre: f64, post: f64) {
+        let window = CaptureWindow::new(pre, post).unwrap();
+
+        assert_eq!(window.pre(), pre);
+        assert_eq!(window.post(), post);
+        assert_eq!(window.total(), pre + post);
+    }
+
+    #[test_case(-0.1, 10.0 ; "negative pre")]
+    #[test_case(10.0, -0.1 ; "negative post")]
+    #[test_case(MAX_CAPTURE_SECS + 0.1, 0.0 ; "pre too long")]
+    #[test_case(0.0, MAX_CAPTURE_SECS + 0.1 ; "post too long")]
+    #[test_case(f64::NAN, 0.0 ; "nan")]
+    #[test_case(0.0, f64::INFINITY ; "infinite")]
+    fn capture_window_rejects_invalid(pre: f64, post: f64) {
+        let err = CaptureWindow::new(pre, post).unwrap_err();
+
+        assert_eq!(err.code.as_deref(), Some("invalid_capture_window"));
+    }
+
+    #[test]
+    fn game_event_with_window_sets_capture_times() {
+        let window = CaptureWindow::new(12.0, 8.0).unwrap();
+        let event = GameEvent::new("DragonKill", 500.0, json!({})).with_window(window);
+
+        assert_eq!(event.pre_capture_secs, Some(12.0));
+        assert_eq!(event.post_capture_secs, Some(8.0));
+    }
+
+    #[test]
+    fn capture_window_deserializing_applies_the_same_checks() {
+        let window = CaptureWindow::new(12.0, 8.0).unwrap();
+        let json = serde_json::to_value(window).unwrap();
+
+        assert_eq!(json, json!({"pre_secs": 12.0, "post_secs": 8.0}));
+        assert_eq!(serde_json::from_value::<CaptureWindow>(json).unwrap(), window);
+        let negative = json!({"pre_secs": -1.0, "post_secs": 8.0});
+        let err = serde_json::from_value::<CaptureWindow>(negative).unwrap_err();
+        assert!(err.to_string().contains("pre-capture"), "{}", err);
+    }
+
+    // ========================================================================
+    // Moment Tests
+    // ========================================================================
+
+    #[test]
+    fn clip_group_serializes_when_set() {
+        let event = GameEvent::new("ChampionKill", 10.0, json!({})).with_clip_group("streak-1");
+        let moment = Moment::new("triple_kill", 12.0, json!({})).with_clip_group("streak-1");
+
+        assert_eq!(serde_json::to_value(&event).unwrap()["clip_group"], "streak-1");
+        assert_eq!(serde_json::to_value(&moment).unwrap()["clip_group"], "streak-1");
+
+        let back: Moment = serde_json::from_value(serde_json::to_value(&moment).unwrap()).unwrap();
+        assert_eq!(back.clip_group.as_deref(), Some("streak-1"));
+    }
+
+    #[test]
+    fn clip_group_omitted_when_none() {
+        let event = GameEvent::new("ChampionKill", 10.0, json!({}));
+        let moment = Moment::new("pentakill", 12.0, json!({}));
+
+        assert!(serde_json::to_value(&event).unwrap().get("clip_group").is_none());
+        assert!(serde_json::to_value(&moment).unwrap().get("clip_group").is_none());
+
+        let back: GameEvent = serde_json::from_str(
+            r#"{"event_type":"Kill","timestamp_secs":1.0,"data":{}}"#,
+        )
+        .unwrap();
+        assert!(back.clip_group.is_none());
+    }
+
+    #[test]
+    fn moment_new_creates_correctly() {
+        let moment = Moment::new("pentakill", 1500.0, json!({"kills": 5}));
+
+        assert_eq!(moment.moment_id, "pentakill");
+        assert_eq!(moment.game_time_secs, 1500.0);
+        assert_eq!(moment.data, json!({"kills": 5}));
+    }
+
+    #[test]
+    fn confidence_round_trips_and_is_omitted_when_unset() {
+        let event = GameEvent::new("Kill", 10.0, json!({})).with_confidence(0.75).unwrap();
+        let moment = Moment::new("pentakill", 12.0, json!({})).with_confidence(0.5).unwrap();
+
+        let event_json = serde_json::to_value(&event).unwrap();
+        assert_eq!(event_json["confidence"], json!(0.75));
+        let back: GameEvent = serde_json::from_value(event_json).unwrap();
+        assert_eq!(back.confidence, Some(0.75));
+        let back: Moment = serde_json::from_value(serde_json::to_value(&moment).unwrap()).unwrap();
+        assert_eq!(back.confidence, Some(0.5));
+
+        let plain = serde_json::to_value(Moment::new("pentakill", 12.0, json!({}))).unwrap();
+        assert!(plain.get("confidence").is_none());
+        let old: GameEvent =
+            serde_json::from_str(r#"{"event_type":"Kill","timestamp_secs":1.0,"data":{}}"#)
+                .unwrap();
+        assert_eq!(old.confidence, None);
+    }
+
+    #[test]
+    fn out_of_range_confidence_is_rejected_or_clamped() {
+        for confidence in [-0.1, 1.5, f32::NAN, f32::INFINITY] {
+            let err = Moment::new("m", 1.0, json!({})).with_confidence(confidence).unwrap_err();
+            assert_eq!(err.code.as_deref(), Some("invalid_confidence"), "{}", confidence);
+            assert!(GameEvent::new("e", 1.0, json!({})).with_confidence(confidence).is_err());
+        }
+        assert!(Moment::new("m", 1.0, json!({})).with_confidence(1.0).is_ok());
+
+        let clamped = |c| GameEvent::new("e", 1.0, json!({})).with_clamped_confidence(c).confidence;
+        assert_eq!(clamped(1.5), Some(1.0));
+        assert_eq!(clamped(-3.0), Some(0.0));
+        assert_eq!(clamped(f32::NAN), Some(0.0));
+        let moment = Moment::new("m", 1.0, json!({})).with_clamped_confidence(0.3);
+        assert_eq!(moment.confidence, Some(0.3));
+    }
+
+    #[test]
+    fn moment_round_trips() {
+        let moment = Moment::new("death", 250.0, json!({"killer": "Enemy1"}));
+        let json = serde_json::to_string(&moment).unwrap();
+        let back: Moment = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(moment.moment_id, back.moment_id);
+        assert!((moment.game_time_secs - back.game_time_secs).abs() < 0.001);
+        assert_eq!(moment.data, back.data);
+    }
+
+    #[test]
+    fn moment_without_correlation_skips_fields() {
+        let json = serde_json::to_string(&Moment::new("death", 250.0, json!({}))).unwrap();
+
+        assert!(!json.contains("correlation_id"));
+        assert!(!json.contains("supersedes"));
+    }
+
+    #[test]
+    fn moment_supersedes_round_trips() {
+        let moment = Moment::new("triple_kill", 300.0, json!({"kills": 3}))
+            .with_correlation_id("streak-2")
+            .with_supersedes("streak-1");
+        let json = serde_json::to_string(&moment).unwrap();
+
+        assert!(json.contains("\"correlation_id\":\"streak-2\""));
+        assert!(json.contains("\"supersedes\":\"streak-1\""));
+
+        let back: Moment = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.correlation_id.as_deref(), Some("streak-2"));
+        assert_eq!(back.supersedes.as_deref(), Some("streak-1"));
+    }
+
+    #[test]
+    fn moment_deserializes_without_new_fields() {
+        let back: Moment =
+            serde_json::from_str(r#"{"moment_id":"death","game_time_secs":1.0,"data":{}}"#)
+                .unwrap();
+
+        assert!(back.correlation_id.is_none());
+        assert!(back.supersedes.is_none());
+        assert!(back.title_template.is_none());
+        assert!(back.render_title().is_none());
+    }
+
+    #[test]
+    fn moment_templates_round_trip_and_render() {
+        let moment = Moment::new("pentakill", 1800.0, json!({"player": "Faker", "kills": 5}))
+            .with_title_template("{player} pentakill")
+            .with_description_template("{kills} kills by {player} on {champion}");
+
+        let json = serde_json::to_value(&moment).unwrap();
+        assert_eq!(json["title_template"], "{player} pentakill");
+        let back: Moment = serde_json::from_value(json).unwrap();
+
+        assert_eq!(back.render_title().as_deref(), Some("Faker pentakill"));
+        assert_eq!(
+            back.render_description().as_deref(),
+            Some("5 kills by Faker on {champion}")
+        );
+    }
+
+    #[test]
+    fn moment_clip_bounds_round_trip() {
+        let moment = Moment::new("siege", 900.0, json!({}))
+            .with_clip_bounds(Some(10.0), Some(30.0))
+            .unwrap();
+
+        let json = serde_json::to_value(&moment).unwrap();
+        assert_eq!(json["min_clip_secs"], 10.0);
+        assert_eq!(json["max_clip_secs"], 30.0);
+        let back: Moment = serde_json::from_value(json).unwrap();
+        assert_eq!((back.min_clip_secs, back.max_clip_secs), (Some(10.0), Some(30.0)));
+
+        let unbounded = serde_json::to_string(&Moment::new("siege", 900.0, json!({}))).unwrap();
+        assert!(!unbounded.contains("clip_secs"));
+        let max_only = Moment::new("siege", 900.0, json!({})).with_clip_bounds(None, Some(30.0));
+        assert_eq!(max_only.unwrap().min_clip_secs, None);
+    }
+
+    #[test_case(Some(40.0), Some(30.0) ; "min above max")]
+    #[test_case(Some(0.0), None ; "zero min")]
+    #[test_case(None, Some(-5.0) ; "negative max")]
+    #[test_case(None, Some(f64::INFINITY) ; "infinite max")]
+    #[test_case(Some(f64::NAN), Some(30.0) ; "nan min")]
+    fn moment_clip_bounds_rejects_invalid(min: Option<f64>, max: Option<f64>) {
+        let err = Moment::new("siege", 900.0, json!({})).with_clip_bounds(min, max).unwrap_err();
+
+        assert_eq!(err.code.as_deref(), Some("invalid_clip_bounds"));
+    }
+
+    // ========================================================================
+    // StatValue Tests
+    // ========================================================================
+
+    #[test_case(StatValue::from(3), "3" ; "int")]
+    #[test_case(StatValue::from(3u8), "3" ; "unsigned")]
+    #[test_case(StatValue::from(2.5), "2.5" ; "float")]
+    #[test_case(StatValue::from(3.0), "3.0" ; "whole float")]
+    #[test_case(StatValue::from("Jungle"), "\"Jungle\"" ; "text")]
+    #[test_case(StatValue::from(true), "true" ; "bool")]
+    fn stat_value_serializes_as_plain_json(value: StatValue, expected: &str) {
+        assert_eq!(serde_json::to_string(&value).unwrap(), expected);
+        assert_eq!(serde_json::from_str::<StatValue>(expected).unwrap(), value);
     }
 
     #[test]
-    fn game_event_serializes_correctly() {
-        let event = GameEvent::new("ChampionKill", 100.5, json!({"killer": "Player1"}));
-        let json = serde_json::to_string(&event).unwrap();
+    fn stat_value_mixed_numbers_aggregate_consistently() {
+        let values = [
+            StatValue::from(3),
+            StatValue::from(3.0),
+            StatValue::from(3.0f32),
+            StatValue::from(3u32),
+        ];
 
-        assert!(json.contains("\"event_type\":\"ChampionKill\""));
-        assert!(json.contains("\"timestamp_secs\":100.5"));
-        assert!(!json.contains("pre_capture_secs")); // None should be skipped
+        for value in &values {
+            assert_eq!(value.as_f64(), Some(3.0), "{:?}", value);
+            assert_eq!(value.as_i64(), Some(3), "{:?}", value);
+        }
+        let total: f64 = values.iter().filter_map(StatValue::as_f64).sum();
+        assert_eq!(total, 12.0);
+    }
+
+    #[test_case(StatValue::from(3.5) ; "fractional")]
+    #[test_case(StatValue::from(f64::NAN) ; "nan")]
+    #[test_case(StatValue::from(f64::INFINITY) ; "infinite")]
+    #[test_case(StatValue::from(1e19) ; "too large")]
+    #[test_case(StatValue::from("3") ; "text")]
+    #[test_case(StatValue::from(true) ; "bool")]
+    fn stat_value_as_i64_rejects_lossy_values(value: StatValue) {
+        assert_eq!(value.as_i64(), None);
     }
 
     #[test]
-    fn game_event_round_trips() {
-        let event = GameEvent::new("ChampionKill", 100.5, json!({"killer": "Player1"}))
-            .with_pre_capture(10.0);
-        let json = serde_json::to_string(&event).unwrap();
-        let back: GameEvent = serde_json::from_str(&json).unwrap();
+    fn write_typed_statistics_matches_untyped_wire_format() {
+        let typed = MatchDataMessage::write_typed_statistics(
+            0,
+            "m1",
+            60.0,
+            [
+                ("kills".to_string(), StatValue::from(5)),
+                ("kda".to_string(), StatValue::from(2.5)),
+                ("role".to_string(), StatValue::from("Jungle")),
+            ]
+            .into(),
+        );
+        let untyped = MatchDataMessage::write_statistics(
+            0,
+            "m1",
+            60.0,
+            [
+                ("kills".to_string(), json!(5)),
+                ("kda".to_string(), json!(2.5)),
+                ("role".to_string(), json!("Jungle")),
+            ]
+            .into(),
+        );
 
-        assert_eq!(event.event_type, back.event_type);
-        assert!((event.timestamp_secs - back.timestamp_secs).abs() < 0.001);
-        assert_eq!(event.pre_capture_secs, back.pre_capture_secs);
+        assert_eq!(
+            serde_json::to_value(&typed).unwrap(),
+            serde_json::to_value(&untyped).unwrap()
+        );
     }
 
     // ========================================================================
-    // Moment Tests
+    // Scoreboard Tests
     // ========================================================================
 
+    fn two_team_scoreboard() -> Scoreboard {
+        Scoreboard::new()
+            .with_team(Team::new("blue").with_name("Blue Side").with_score(12).local())
+            .with_team(Team::new("red").with_score(9).with_extra("towers", 3))
+            .with_player(
+                PlayerRow::new("Faker")
+                    .with_team("blue")
+                    .with_kda(5, 1, 7)
+                    .local()
+                    .with_extra("champion", "Ahri"),
+            )
+            .with_player(PlayerRow::new("Chovy").with_team("red").with_kda(3, 5, 2))
+    }
+
     #[test]
-    fn moment_new_creates_correctly() {
-        let moment = Moment::new("pentakill", 1500.0, json!({"kills": 5}));
+    fn scoreboard_round_trips_through_json() {
+        let scoreboard = two_team_scoreboard();
 
-        assert_eq!(moment.moment_id, "pentakill");
-        assert_eq!(moment.game_time_secs, 1500.0);
-        assert_eq!(moment.data, json!({"kills": 5}));
+        let value = serde_json::Value::from(scoreboard.clone());
+        assert_eq!(
+            value,
+            json!({
+                "teams": [
+                    {"team_id": "blue", "name": "Blue Side", "score": 12, "is_local": true},
+                    {"team_id": "red", "score": 9, "towers": 3},
+                ],
+                "players": [
+                    {"name": "Faker", "team_id": "blue", "kills": 5, "deaths": 1, "assists": 7,
+                     "is_local": true, "champion": "Ahri"},
+                    {"name": "Chovy", "team_id": "red", "kills": 3, "deaths": 5, "assists": 2},
+                ],
+            })
+        );
+        assert_eq!(Scoreboard::from_value(&value).unwrap(), scoreboard);
+
+        let names: Vec<&str> = scoreboard.players_on("red").map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Chovy"]);
     }
 
     #[test]
-    fn moment_round_trips() {
-        let moment = Moment::new("death", 250.0, json!({"killer": "Enemy1"}));
-        let json = serde_json::to_string(&moment).unwrap();
-        let back: Moment = serde_json::from_str(&json).unwrap();
+    fn scoreboard_lives_alongside_pack_live_data() {
+        let scoreboard = two_team_scoreboard();
+        let mut live = json!({"game_clock": 812.5, "dragons": ["infernal"]});
 
-        assert_eq!(moment.moment_id, back.moment_id);
-        assert!((moment.game_time_secs - back.game_time_secs).abs() < 0.001);
-        assert_eq!(moment.data, back.data);
+        scoreboard.insert_into(&mut live);
+
+        assert_eq!(live["game_clock"], 812.5);
+        assert_eq!(Scoreboard::from_live_data(&live).unwrap(), Some(scoreboard.clone()));
+        assert_eq!(Scoreboard::from_live_data(&json!({"game_clock": 1.0})).unwrap(), None);
+
+        let err = Scoreboard::from_live_data(&json!({"scoreboard": {"teams": "blue"}}));
+        assert_eq!(err.unwrap_err().code.as_deref(), Some("data_decode_error"));
+
+        let mut empty = serde_json::Value::Null;
+        scoreboard.insert_into(&mut empty);
+        assert_eq!(empty.as_object().unwrap().len(), 1);
     }
 
     // ========================================================================
@@ -769,6 +3584,27 @@ mod tests {
         assert!(json.contains("\"summary_source\":\"api\""));
     }
 
+    #[test]
+    fn set_abandoned_round_trips() {
+        let msg = MatchDataMessage::set_abandoned(1, "match123", 312.5);
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "type": "set_abandoned",
+                "subpack": 1,
+                "external_match_id": "match123",
+                "at_game_time_secs": 312.5,
+            })
+        );
+
+        let back: MatchDataMessage = serde_json::from_value(json).unwrap();
+        assert!(back.ends_match());
+        assert_eq!((back.subpack(), back.external_match_id()), (1, "match123"));
+        assert_eq!(back.describe(), "SetAbandoned[subpack=1, match=match123 @ 312.5s]");
+        assert!(MatchDataMessage::set_abandoned(0, "m1", f64::NAN).check_finite().is_err());
+    }
+
     #[test]
     fn match_data_message_round_trips_all_variants() {
         let messages: Vec<MatchDataMessage> = vec![
@@ -786,6 +3622,7 @@ mod tests {
                 SummarySource::LiveFallback,
                 [("kills".to_string(), json!(10))].into_iter().collect(),
             ),
+            MatchDataMessage::set_abandoned(0, "m1", 312.5),
         ];
 
         for msg in messages {
@@ -797,6 +3634,142 @@ mod tests {
         }
     }
 
+    #[test]
+    fn player_slot_round_trips() {
+        let messages = [
+            MatchDataMessage::write_player_statistics(0, "m1", 2, 10.0, HashMap::new()),
+            MatchDataMessage::write_player_game_events(
+                0,
+                "m1",
+                4,
+                vec![GameEvent::new("Kill", 5.0, json!({}))],
+            ),
+        ];
+
+        for (msg, slot) in messages.into_iter().zip([2, 4]) {
+            let json = serde_json::to_value(&msg).unwrap();
+            assert_eq!(json["player_slot"], json!(slot));
+            let back: MatchDataMessage = serde_json::from_value(json).unwrap();
+            assert_eq!(back.player_slot(), slot);
+        }
+    }
+
+    #[test]
+    fn player_slot_defaults_to_none() {
+        let msg = MatchDataMessage::write_statistics(0, "m1", 10.0, HashMap::new());
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(!json.contains("player_slot"));
+
+        // Messages from packs that predate the field still parse
+        let old = r#"{"type":"write_game_events","subpack":0,"external_match_id":"m1",
+                      "events":[]}"#;
+        match serde_json::from_str(old).unwrap() {
+            MatchDataMessage::WriteGameEvents { player_slot, .. } => assert!(player_slot.is_none()),
+            other => panic!("Expected WriteGameEvents, got {:?}", other),
+        }
+        assert_eq!(msg.player_slot(), 0);
+    }
+
+    #[test]
+    fn server_time_is_optional_on_statistics() {
+        let msg = MatchDataMessage::write_statistics(0, "m1", 10.0, HashMap::new())
+            .with_server_time(12.5);
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["game_time_secs"], json!(10.0));
+        assert_eq!(json["server_time_secs"], json!(12.5));
+
+        let plain = MatchDataMessage::write_statistics(0, "m1", 10.0, HashMap::new());
+        let json = serde_json::to_value(&plain).unwrap();
+        assert!(json.get("server_time_secs").is_none());
+        match serde_json::from_value(json).unwrap() {
+            MatchDataMessage::WriteStatistics {
+                server_time_secs, ..
+            } => assert!(server_time_secs.is_none()),
+            other => panic!("Expected WriteStatistics, got {:?}", other),
+        }
+
+        // Only statistics carry it
+        let moments = MatchDataMessage::write_moments(0, "m1", vec![]).with_server_time(1.0);
+        assert!(!serde_json::to_string(&moments).unwrap().contains("server_time_secs"));
+    }
+
+    #[test]
+    fn server_time_is_optional_on_events() {
+        let event = GameEvent::new("Kill", 100.0, json!({})).with_server_time(101.25);
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["server_time_secs"], json!(101.25));
+        let back: GameEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(back.server_time_secs, Some(101.25));
+        assert_eq!(back.timestamp_secs, 100.0);
+
+        let json = serde_json::to_value(GameEvent::new("Kill", 100.0, json!({}))).unwrap();
+        assert!(json.get("server_time_secs").is_none());
+        let back: GameEvent = serde_json::from_value(json).unwrap();
+        assert!(back.server_time_secs.is_none());
+
+        let nan = GameEvent::new("Kill", 100.0, json!({})).with_server_time(f64::NAN);
+        assert!(nan.check_finite().is_err());
+    }
+
+    #[test]
+    fn cleared_stats_are_sent_as_null_and_untouched_ones_omitted() {
+        let stats: HashMap<String, serde_json::Value> =
+            [("respawn_secs".to_string(), json!(null)), ("gold".to_string(), json!(900))].into();
+        let message = MatchDataMessage::write_statistics(0, "m1", 60.0, stats);
+        assert_eq!(message.cleared_stats(), ["respawn_secs"]);
+
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["stats"], json!({"respawn_secs": null, "gold": 900}));
+        assert!(json["stats"].get("kills").is_none());
+
+        let back: MatchDataMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(back.cleared_stats(), ["respawn_secs"]);
+        let complete = MatchDataMessage::set_complete(0, "m1", SummarySource::Api);
+        assert!(complete.cleared_stats().is_empty());
+    }
+
+    #[test]
+    fn match_data_message_describe() {
+        let stats: HashMap<String, serde_json::Value> = [
+            ("kills".to_string(), json!(5)),
+            ("deaths".to_string(), json!(2)),
+            ("cs".to_string(), json!(150)),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            MatchDataMessage::write_statistics(0, "abc", 120.5, stats.clone()).describe(),
+            "WriteStatistics[subpack=0, match=abc, 3 fields @ 120.5s]"
+        );
+        assert_eq!(
+            MatchDataMessage::write_game_events(
+                1,
+                "abc",
+                vec![GameEvent::new("Kill", 1.0, json!({}))]
+            )
+            .describe(),
+            "WriteGameEvents[subpack=1, match=abc, 1 event]"
+        );
+        assert_eq!(
+            MatchDataMessage::write_player_statistics(0, "abc", 3, 1.0, HashMap::new()).describe(),
+            "WriteStatistics[subpack=0, match=abc, player=3, 0 fields @ 1s]"
+        );
+        assert_eq!(
+            MatchDataMessage::write_moments(0, "abc", vec![]).describe(),
+            "WriteMoments[subpack=0, match=abc, 0 moments]"
+        );
+        assert_eq!(
+            MatchDataMessage::set_complete(0, "abc", SummarySource::Api).describe(),
+            "SetComplete[subpack=0, match=abc, source=api]"
+        );
+        assert_eq!(
+            MatchDataMessage::set_complete_with_stats(0, "abc", SummarySource::LiveFallback, stats)
+                .describe(),
+            "SetComplete[subpack=0, match=abc, source=live_fallback, 3 final stats]"
+        );
+    }
+
     // ========================================================================
     // TimelineEntry Tests
     // ========================================================================
@@ -815,6 +3788,70 @@ mod tests {
         assert_eq!(entry.trigger_fired, None);
     }
 
+    #[test]
+    fn timeline_entry_from_emit_types_stamps_clock_time() {
+        let clock = FixedClock::at_unix_millis(1_705_314_600_000);
+        let event = GameEvent::new("ChampionKill", 100.0, json!({"killer": "Player1"}));
+        let moment = Moment::new("pentakill", 101.0, json!({"kills": 5}));
+        let stats = [("kills".to_string(), json!(5))].into();
+
+        let from_event = TimelineEntry::from_event(&event, &clock);
+        clock.advance(std::time::Duration::from_secs(1));
+        let from_moment = TimelineEntry::from_moment(&moment, &clock);
+        let from_stats = TimelineEntry::from_stats(101.0, &stats, &clock);
+
+        assert_eq!(from_event.entry_type, EntryType::Event);
+        assert_eq!(from_event.entry_key, "ChampionKill");
+        assert_eq!(from_event.game_time_secs, 100.0);
+        assert_eq!(from_event.captured_at, "2024-01-15T10:30:00.000Z");
+        assert_eq!(from_event.data, json!({"killer": "Player1"}));
+
+        assert_eq!(from_moment.entry_type, EntryType::Moment);
+        assert_eq!(from_moment.entry_key, "pentakill");
+        assert_eq!(from_moment.captured_at, "2024-01-15T10:30:01.000Z");
+        assert_eq!(from_moment.trigger_fired, None);
+
+        assert_eq!(from_stats.entry_type, EntryType::Statistic);
+        assert_eq!(from_stats.captured_at, "2024-01-15T10:30:01.000Z");
+        assert_eq!(from_stats.data, json!({"kills": 5}));
+    }
+
+    #[test]
+    fn moments_from_results_stamps_trigger_fired() {
+        let clock = FixedClock::at_unix_millis(1_705_314_600_000);
+        let moments = [
+            Moment::new("pentakill", 101.0, json!({"kills": 5})),
+            Moment::new("ace", 102.0, json!({})),
+            Moment::new("multikill", 103.0, json!({})).with_correlation_id("mk-1"),
+            Moment::new("multikill", 104.0, json!({})).with_correlation_id("mk-2"),
+        ];
+        let fired = [
+            ("pentakill".to_string(), true),
+            ("multikill".to_string(), true),
+            ("mk-2".to_string(), true),
+        ]
+        .into();
+
+        let entries = TimelineEntry::moments_from_results(&moments, &fired, &clock);
+
+        let results: Vec<_> = entries
+            .iter()
+            .map(|e| (e.entry_key.as_str(), e.game_time_secs, e.trigger_fired))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                ("pentakill", 101.0, Some(true)),
+                ("ace", 102.0, Some(false)),
+                ("multikill", 103.0, Some(false)),
+                ("multikill", 104.0, Some(true)),
+            ]
+        );
+        assert!(entries.iter().all(|e| e.entry_type == EntryType::Moment
+            && e.captured_at == "2024-01-15T10:30:00.000Z"));
+        assert_eq!(entries[0].data, json!({"kills": 5}));
+    }
+
     #[test]
     fn timeline_entry_statistic_creates_correctly() {
         let entry = TimelineEntry::statistic(
@@ -859,6 +3896,99 @@ mod tests {
         assert_eq!(entry.data, back.data);
     }
 
+    #[test_case(&[], true, None ; "empty")]
+    #[test_case(&[42.0], true, Some(0.0) ; "single entry")]
+    #[test_case(&[30.0, 5.0, 1805.0, 900.0], true, Some(1800.0) ; "unordered")]
+    #[test_case(&[-30.0, 0.0, 600.0], true, Some(630.0) ; "with pregame")]
+    #[test_case(&[-30.0, 0.0, 600.0], false, Some(600.0) ; "without pregame")]
+    #[test_case(&[-30.0, -5.0], true, Some(25.0) ; "only pregame")]
+    #[test_case(&[-30.0, -5.0], false, None ; "only pregame excluded")]
+    fn match_duration_spans_earliest_to_latest(
+        times: &[f64],
+        include_pregame: bool,
+        expected: Option<f64>,
+    ) {
+        let at = "2024-01-15T10:30:00Z";
+        let entries: Vec<TimelineEntry> = times
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| match i % 2 {
+                0 => TimelineEntry::event("Kill", t, at, json!({})),
+                _ => TimelineEntry::statistic(t, at, json!({})),
+            })
+            .collect();
+
+        assert_eq!(match_duration_secs(&entries, include_pregame), expected);
+    }
+
+    // ========================================================================
+    // Binary Timeline Encoding Tests
+    // ========================================================================
+
+    #[cfg(feature = "binary-timeline")]
+    fn assert_same_entries(a: &[TimelineEntry], b: &[TimelineEntry]) {
+        assert_eq!(
+            serde_json::to_value(a).unwrap(),
+            serde_json::to_value(b).unwrap()
+        );
+    }
+
+    #[cfg(feature = "binary-timeline")]
+    #[test]
+    fn binary_timeline_round_trips_empty_batch() {
+        let bytes = encode_timeline(&[]);
+
+        assert_eq!(bytes[0], TIMELINE_FORMAT_VERSION);
+        assert!(decode_timeline(&bytes).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "binary-timeline")]
+    #[test]
+    fn binary_timeline_round_trips_mixed_entries() {
+        let at = "2024-01-15T10:30:00.000Z";
+        let entries = vec![
+            TimelineEntry::event("ChampionKill", 12.5, at, json!({"killer": "Me"})),
+            TimelineEntry::statistic(15.0, at, json!({"kills": 1, "kda": 1.5})),
+            TimelineEntry::moment("first_blood", 12.5, at, json!(null), true),
+            TimelineEntry::moment("ace", 99.0, at, json!({"team": "ORDER"}), false),
+        ];
+
+        assert_same_entries(&decode_timeline(&encode_timeline(&entries)).unwrap(), &entries);
+    }
+
+    #[cfg(feature = "binary-timeline")]
+    #[test]
+    fn binary_timeline_round_trips_large_batch_smaller_than_json() {
+        let entries: Vec<TimelineEntry> = (0..5_000)
+            .map(|i| {
+                TimelineEntry::statistic(
+                    i as f64,
+                    Timestamp::from_unix_millis(1_700_000_000_000 + i).as_str(),
+                    json!({"gold": i * 10}),
+                )
+            })
+            .collect();
+
+        let bytes = encode_timeline(&entries);
+
+        assert_same_entries(&decode_timeline(&bytes).unwrap(), &entries);
+        assert!(bytes.len() < serde_json::to_vec(&entries).unwrap().len());
+    }
+
+    #[cfg(feature = "binary-timeline")]
+    #[test]
+    fn binary_timeline_rejects_bad_input() {
+        let entries = vec![TimelineEntry::event("Kill", 1.0, "t", json!({}))];
+        let bytes = encode_timeline(&entries);
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = 99;
+        for bad in [&[][..], &wrong_version, &bytes[..bytes.len() - 1]] {
+            let err = decode_timeline(bad).unwrap_err();
+            assert_eq!(err.code.as_deref(), Some("invalid_timeline_encoding"));
+        }
+    }
+
     // ========================================================================
     // IsMatchInProgressResponse Tests
     // ========================================================================
@@ -914,6 +4044,23 @@ mod tests {
         assert!(status.is_in_game);
     }
 
+    #[test]
+    fn game_status_named_states() {
+        let cases = [
+            (GameStatus::client_connected(), None, false),
+            (GameStatus::in_lobby(), Some("Lobby"), false),
+            (GameStatus::in_champ_select(), Some("ChampSelect"), false),
+            (GameStatus::in_match("InProgress"), Some("InProgress"), true),
+        ];
+
+        for (status, phase, in_game) in cases {
+            assert!(status.connected, "{:?}", status);
+            assert_eq!(status.connection_status, "Connected");
+            assert_eq!(status.game_phase.as_deref(), phase);
+            assert_eq!(status.is_in_game, in_game, "{:?}", status);
+        }
+    }
+
     // ========================================================================
     // MatchData Tests
     // ========================================================================
@@ -927,4 +4074,285 @@ mod tests {
         assert_eq!(data.result, "win");
         assert_eq!(data.details, json!({"kills": 10}));
     }
+
+    #[test]
+    fn match_data_with_outcome_detail() {
+        let data = MatchData::new("league", 1, MatchResult::Loss, json!({}))
+            .with_outcome_detail("surrender_at_15");
+
+        assert_eq!(data.result, "loss");
+        assert_eq!(data.match_result(), Some(MatchResult::Loss));
+        assert_eq!(data.outcome_detail.as_deref(), Some("surrender_at_15"));
+    }
+
+    #[test]
+    fn match_data_custom_result_has_no_normalized_value() {
+        let data = MatchData::new("arena", 2, "sudden_death_win", json!({}));
+
+        assert_eq!(data.match_result(), None);
+    }
+
+    #[test]
+    fn match_data_outcome_detail_serde() {
+        let data = MatchData::new("league", 1, MatchResult::Draw, json!({}))
+            .with_outcome_detail("timeout_tie");
+        let json = serde_json::to_value(&data).unwrap();
+        assert_eq!(json["result"], "draw");
+        assert_eq!(json["outcome_detail"], "timeout_tie");
+
+        // Omitted when unset, and older payloads without it still parse
+        let plain = serde_json::to_value(MatchData::new("league", 1, "win", json!({}))).unwrap();
+        assert!(plain.get("outcome_detail").is_none());
+        let back: MatchData = serde_json::from_value(plain).unwrap();
+        assert!(back.outcome_detail.is_none());
+    }
+
+    #[test]
+    fn player_identity_serde() {
+        let player = PlayerIdentity::new()
+            .with_display_name("Faker#KR1")
+            .with_game_player_id("puuid-123")
+            .with_region("kr");
+        let json = serde_json::to_value(&player).unwrap();
+        assert_eq!(
+            json,
+            json!({"display_name": "Faker#KR1", "game_player_id": "puuid-123", "region": "kr"})
+        );
+        assert_eq!(serde_json::from_value::<PlayerIdentity>(json).unwrap(), player);
+
+        // All-None serializes to an empty object and back
+        let empty = PlayerIdentity::new();
+        assert!(empty.is_empty());
+        assert_eq!(serde_json::to_value(&empty).unwrap(), json!({}));
+        assert!(serde_json::from_value::<PlayerIdentity>(json!({})).unwrap().is_empty());
+    }
+
+    #[test]
+    fn match_data_player_serde() {
+        let player = PlayerIdentity::new().with_game_player_id("puuid-123");
+        let data = MatchData::new("league", 1, "win", json!({})).with_player(player.clone());
+        let json = serde_json::to_value(&data).unwrap();
+        assert_eq!(json["player"], json!({"game_player_id": "puuid-123"}));
+        let back: MatchData = serde_json::from_value(json).unwrap();
+        assert_eq!(back.player, Some(player));
+
+        let plain = serde_json::to_value(MatchData::new("league", 1, "win", json!({}))).unwrap();
+        assert!(plain.get("player").is_none());
+    }
+
+    // ========================================================================
+    // TriggerConfig Tests
+    // ========================================================================
+
+    #[test]
+    fn trigger_issues_flag_unknown_moments_and_bad_windows() {
+        let known = ["pentakill", "baron_steal"];
+
+        assert!(TriggerConfig::new("pentakill").issues(&known).is_empty());
+        assert!(TriggerConfig::new("baron_steal")
+            .with_window(10.0, 5.0)
+            .issues(&known)
+            .is_empty());
+
+        let issues = TriggerConfig::new("quadrakill")
+            .with_window(-1.0, 5.0)
+            .issues(&known);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(
+            issues[0],
+            TriggerIssue::UnknownMomentId {
+                moment_id: "quadrakill".into()
+            }
+        );
+        match &issues[1] {
+            TriggerIssue::WindowOutOfRange { moment_id, reason } => {
+                assert_eq!(moment_id, "quadrakill");
+                assert!(reason.contains("pre-capture"), "{}", reason);
+            }
+            other => panic!("Expected WindowOutOfRange, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn trigger_issue_wire_format() {
+        let issue = TriggerIssue::UnknownMomentId {
+            moment_id: "x".into(),
+        };
+        assert_eq!(
+            serde_json::to_value(&issue).unwrap(),
+            json!({"kind": "unknown_moment_id", "moment_id": "x"})
+        );
+        let trigger: TriggerConfig = serde_json::from_value(json!({"moment_id": "x"})).unwrap();
+        assert_eq!(trigger, TriggerConfig::new("x"));
+    }
+
+    #[test]
+    fn trigger_rule_round_trips_with_optional_condition() {
+        let rule = TriggerRule::new("ChampionKill", "pentakill").with_condition("5 kills in 10s");
+        let json = serde_json::to_value(&rule).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "from_event": "ChampionKill",
+                "produces_moment": "pentakill",
+                "condition": "5 kills in 10s",
+            })
+        );
+        assert_eq!(serde_json::from_value::<TriggerRule>(json).unwrap(), rule);
+
+        let plain: TriggerRule =
+            serde_json::from_value(json!({"from_event": "Kill", "produces_moment": "kill"}))
+                .unwrap();
+        assert_eq!(plain, TriggerRule::new("Kill", "kill"));
+        assert!(!serde_json::to_string(&plain).unwrap().contains("condition"));
+    }
+
+    // ========================================================================
+    // Clip Planning Tests
+    // ========================================================================
+
+    fn at(secs: f64) -> GameEvent {
+        GameEvent::new("Kill", secs, json!({}))
+    }
+
+    fn spans(plans: &[ClipPlan]) -> Vec<(f64, f64, Vec<usize>)> {
+        plans
+            .iter()
+            .map(|p| (p.start_secs, p.end_secs, p.event_indices.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn merge_capture_windows_coalesces_overlapping_and_adjacent() {
+        // Windows: [5,15], [10,20] overlap; [20,30] touches; [50,60] is disjoint
+        let events = [at(50.0), at(10.0), at(15.0), at(25.0)];
+
+        let plans = merge_capture_windows(&events, (5.0, 5.0));
+
+        assert_eq!(
+            spans(&plans),
+            vec![(5.0, 30.0, vec![1, 2, 3]), (45.0, 55.0, vec![0])]
+        );
+    }
+
+    #[test]
+    fn merge_capture_windows_respects_overrides_and_clamps_start() {
+        let events = [at(2.0), at(30.0).with_pre_capture(20.0), at(60.0).with_post_capture(0.0)];
+
+        let plans = merge_capture_windows(&events, (5.0, 5.0));
+
+        assert_eq!(
+            spans(&plans),
+            vec![(0.0, 7.0, vec![0]), (10.0, 35.0, vec![1]), (55.0, 60.0, vec![2])]
+        );
+    }
+
+    #[test]
+    fn merge_capture_windows_keeps_clip_groups_apart() {
+        let events = [
+            at(10.0).with_clip_group("streak"),
+            at(12.0),
+            at(14.0).with_clip_group("streak"),
+            at(40.0).with_clip_group("streak"),
+        ];
+
+        let plans = merge_capture_windows(&events, (5.0, 5.0));
+
+        assert_eq!(
+            spans(&plans),
+            vec![(5.0, 19.0, vec![0, 2]), (7.0, 17.0, vec![1]), (35.0, 45.0, vec![3])]
+        );
+    }
+
+    #[test]
+    fn merge_capture_windows_keeps_pregame_lead_in() {
+        // Countdown events keep [t-5, t+5]; the in-match one at 1s is clamped to 0
+        let events = [at(1.0), at(-30.0), at(-2.0), at(-28.0)];
+
+        let plans = merge_capture_windows(&events, (5.0, 5.0));
+
+        assert_eq!(
+            spans(&plans),
+            vec![(-35.0, -23.0, vec![1, 3]), (-7.0, 6.0, vec![0, 2])]
+        );
+    }
+
+    #[test]
+    fn negative_game_times_are_accepted_and_ordered() {
+        let mut events = vec![at(5.0), at(-30.0), at(0.0), at(-1.5)];
+        assert!(events.iter().all(|e| e.check_finite().is_ok()));
+        sort_events_chronological(&mut events);
+        let times: Vec<f64> = events.iter().map(|e| e.timestamp_secs).collect();
+        assert_eq!(times, [-30.0, -1.5, 0.0, 5.0]);
+
+        let stats = MatchDataMessage::write_statistics(0, "m1", -12.5, HashMap::new());
+        assert!(stats.check_finite().is_ok());
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["game_time_secs"], -12.5);
+
+        let entry = TimelineEntry::from_event(&events[0], &FixedClock::at_unix_millis(0));
+        let back: TimelineEntry =
+            serde_json::from_str(&serde_json::to_string(&entry).unwrap()).unwrap();
+        assert_eq!(back.game_time_secs, -30.0);
+    }
+
+    // ========================================================================
+    // Number Normalization Tests
+    // ========================================================================
+
+    #[test]
+    fn normalize_numbers_matrix() {
+        let cases = [
+            (json!(42), json!(42), 0),
+            (json!(-MAX_SAFE_INTEGER), json!(-MAX_SAFE_INTEGER), 0),
+            (json!(MAX_SAFE_INTEGER), json!(MAX_SAFE_INTEGER), 0),
+            (json!(MAX_SAFE_INTEGER + 2), json!(9007199254740992.0), 1),
+            (json!(u64::MAX), json!(18446744073709551615.0), 1),
+            (json!(i64::MIN), json!(-9223372036854775808.0), 1),
+            (json!(2.5), json!(2.5), 0),
+            (json!(1e300), json!(1e300), 0),
+            (json!("99999999999999999999"), json!("99999999999999999999"), 0),
+        ];
+        for (mut value, expected, changed) in cases {
+            let input = value.clone();
+            assert_eq!(normalize_numbers(&mut value), changed, "{}", input);
+            assert_eq!(value, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn normalize_numbers_recurses_and_survives_a_round_trip() {
+        let mut value = json!({"gold": 12500, "ids": [u64::MAX, 1], "nested": {"big": i64::MAX}});
+
+        assert_eq!(normalize_numbers(&mut value), 2);
+        let line = serde_json::to_string(&value).unwrap();
+        let reparsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(reparsed, value);
+        assert_eq!(value["gold"], json!(12500));
+        assert!(value["ids"][0].is_f64() && value["nested"]["big"].is_f64());
+    }
+
+    // ========================================================================
+    // MatchId Tests
+    // ========================================================================
+
+    #[test]
+    fn match_id_rejects_empty_and_blank_ids() {
+        for bad in ["", " ", "\t\n"] {
+            let err = MatchId::new(bad).unwrap_err();
+            assert_eq!(err.code.as_deref(), Some("invalid_match_id"), "{:?}", bad);
+            assert!(serde_json::from_value::<MatchId>(json!(bad)).is_err());
+            let message = MatchDataMessage::set_complete(0, bad, SummarySource::LiveFallback);
+            let err = message.check_match_id().unwrap_err();
+            assert_eq!(err.code.as_deref(), Some("invalid_match_id"));
+        }
+
+        let id = MatchId::new(" EUW1_123 ").unwrap();
+        assert_eq!(id.as_str(), " EUW1_123 ");
+        assert_eq!(serde_json::to_value(&id).unwrap(), json!(" EUW1_123 "));
+        assert_eq!(serde_json::from_value::<MatchId>(json!(" EUW1_123 ")).unwrap(), id);
+        let message = MatchDataMessage::write_moments(0, id, vec![]);
+        assert_eq!(message.external_match_id(), " EUW1_123 ");
+        assert!(message.check_match_id().is_ok());
+    }
 }
@@ -307,7 +307,7 @@ export interface GameDefinition {
 export interface GameEvent {
   /** Event type key (e.g., "ChampionKill", "TurretDestroyed") */
   eventKey: string;
-  /** When the event occurred (game time in seconds) */
+  /** When the event occurred (game time in seconds; negative before match start) */
   eventTime: number;
   /** Additional event data (game-specific) */
   eventData?: Record<string, unknown>;
@@ -315,6 +315,25 @@ export interface GameEvent {
   preSecs?: number;
   /** Seconds to capture after the event */
   postSecs?: number;
+  /** Events sharing a clip group are merged into one clip */
+  clipGroup?: string;
+  /** Authoritative server time in seconds (eventTime drives clip timing) */
+  serverTimeSecs?: number;
+  /** Detection confidence from 0 to 1; events without one are certain */
+  confidence?: number;
+}
+
+/**
+ * In-game identity of the tracked player. Ids are opaque tokens; compare
+ * them for equality only.
+ */
+export interface PlayerIdentity {
+  /** Name shown in game (e.g., summoner name or Riot ID) */
+  displayName?: string;
+  /** Stable account id issued by the game (e.g., PUUID) */
+  gamePlayerId?: string;
+  /** Game server region or shard (e.g., "euw1") */
+  region?: string;
 }
 
 /**
@@ -327,6 +346,10 @@ export interface GameStatus {
   phase: string;
   /** Whether actively in a game */
   isInGame: boolean;
+  /** Version or patch of the running game (game-defined format, e.g., "14.3") */
+  gameVersion?: string;
+  /** Player signed in to the game client, once known */
+  player?: PlayerIdentity;
 }
 
 /**
@@ -339,8 +362,12 @@ export interface MatchData {
   gameSlug: string;
   /** Game ID (for backwards compatibility) */
   gameId: number;
-  /** Match result */
-  result: "win" | "loss" | "remake" | "unknown";
+  /** Normalized match result (for filtering) */
+  result: "win" | "loss" | "draw" | "remake" | "unknown";
+  /** Raw game-specific outcome for display (e.g., "surrender_at_15") */
+  outcomeDetail?: string;
+  /** Player the match was played as */
+  player?: PlayerIdentity;
   /** Match duration in seconds */
   duration: number;
   /** Game-specific match details */
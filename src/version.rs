@@ -1,5 +1,115 @@
-//! Protocol version constant.
+//! Protocol version constant and changelog.
+
+use serde::Serialize;
 
 /// Current protocol version.
-/// Increment when making breaking changes to the protocol.
+/// Increment when making breaking changes to the protocol, and add an entry
+/// for the new version to [`CHANGELOG`].
 pub const PROTOCOL_VERSION: u32 = 1;
+
+/// One difference between two protocol versions.
+///
+/// Names are wire names (`snake_case` message types and field keys).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProtocolChange {
+    /// A command the daemon may send
+    AddedCommand { name: &'static str },
+    /// A response or message the pack may write
+    AddedResponse { name: &'static str },
+    /// A field on an existing command or response
+    AddedField {
+        message: &'static str,
+        field: &'static str,
+    },
+    /// Still accepted, but slated for removal
+    Deprecated {
+        item: &'static str,
+        note: &'static str,
+    },
+    /// No longer sent or accepted
+    Removed { item: &'static str },
+}
+
+/// Changes introduced by each protocol version, relative to the previous one.
+///
+/// Version 1 is the baseline and lists nothing. Additive changes negotiated
+/// through `daemon_features` (see [`SUPPORTED_FEATURES`]) do not bump the
+/// version and are not listed here.
+pub const CHANGELOG: &[(u32, &[ProtocolChange])] = &[(1, &[])];
+
+/// Everything that differs between protocol versions `from` and `to`, oldest
+/// first.
+///
+/// Order-independent: `changes(2, 1)` equals `changes(1, 2)`. A daemon can
+/// use it to check whether anything it relies on is missing from an older
+/// pack. Versions without a [`CHANGELOG`] entry contribute nothing.
+pub fn changes(from: u32, to: u32) -> Vec<ProtocolChange> {
+    let (low, high) = (from.min(to), from.max(to));
+    CHANGELOG
+        .iter()
+        .filter(|(version, _)| low < *version && *version <= high)
+        .flat_map(|(_, changes)| changes.iter().copied())
+        .collect()
+}
+
+/// Optional protocol feature: live data sent as JSON Patch deltas
+/// (`LiveDataPatch`) instead of full `LiveData` blobs.
+pub const FEATURE_LIVE_DATA_PATCH: &str = "live_data_patch";
+
+/// Optional protocol feature: oversized `SessionEnded` match data split into
+/// `SessionEndedChunk` lines. See
+/// [`GamepackResponse::chunk_session_end`](crate::GamepackResponse::chunk_session_end).
+pub const FEATURE_SESSION_END_CHUNKS: &str = "session_end_chunks";
+
+/// Optional features this runtime can enable when the daemon offers them
+/// in `Init { daemon_features }`.
+pub const SUPPORTED_FEATURES: &[&str] = &[FEATURE_LIVE_DATA_PATCH, FEATURE_SESSION_END_CHUNKS];
+
+/// The calling crate's version (`CARGO_PKG_VERSION`) as `Option<String>`.
+///
+/// Expands in the pack's crate, so it reports the pack's version rather than
+/// this runtime's. Intended for [`InitResponse::pack_version`](crate::InitResponse):
+///
+/// ```rust,ignore
+/// Ok(InitResponse {
+///     game_id: 99,
+///     slug: "my-game".to_string(),
+///     protocol_version: 1,
+///     pack_version: gamepack_runtime::pack_version!(),
+///     game_version: None,
+///     player: None,
+/// })
+/// ```
+#[macro_export]
+macro_rules! pack_version {
+    () => {
+        ::core::option::Option::Some(::std::string::String::from(env!("CARGO_PKG_VERSION")))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_version_has_no_changes() {
+        assert!(changes(1, 1).is_empty());
+        assert!(changes(PROTOCOL_VERSION, PROTOCOL_VERSION).is_empty());
+    }
+
+    #[test]
+    fn adjacent_versions_are_order_independent() {
+        for version in 0..=PROTOCOL_VERSION + 1 {
+            assert_eq!(changes(version, version + 1), changes(version + 1, version));
+        }
+        assert_eq!(changes(0, u32::MAX), changes(u32::MAX, 0));
+        assert!(changes(u32::MAX, u32::MAX).is_empty());
+    }
+
+    #[test]
+    fn changelog_covers_every_version_in_order() {
+        let versions: Vec<u32> = CHANGELOG.iter().map(|(version, _)| *version).collect();
+        assert_eq!(versions, (1..=PROTOCOL_VERSION).collect::<Vec<_>>());
+    }
+}